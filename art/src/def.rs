@@ -0,0 +1,500 @@
+//! Parsing of eduke32 DEF (definition) scripts.
+//!
+//! Only the subset of commands describing tile metadata is supported:
+//!
+//! - `define NAME VALUE`
+//! - `include "FILE"`
+//! - `tilefromtexture TILE { file "FILE" alphacut A xoffset X yoffset Y }`
+//! - `texture TILE { pal PAL { file "FILE" xscale X yscale Y } }`
+//! - `dummytile TILE WIDTH HEIGHT` & `dummytilerange FIRST LAST WIDTH HEIGHT`
+//! - `voxel "FILE" { tile TILE tile0 FIRST tile1 LAST scale S }`
+//!
+//! Any other command (and its `{ ... }` block, if any) is skipped.
+use crate::Error;
+use log::warn;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Most scripts nested by `include` commands.
+pub const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// Replacement texture (hightile) of a tile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileTexture {
+    /// Path of the image file, relative to the DEF file.
+    pub file: String,
+
+    /// Alpha values below this threshold are transparent.
+    pub alpha_cut: Option<f32>,
+
+    /// Texture scale relative to the tile size.
+    pub scale: [f32; 2],
+
+    /// Drawing offset of the tile center.
+    pub offset: [i32; 2],
+}
+
+impl TileTexture {
+    fn new(file: String) -> Self {
+        Self {
+            file,
+            alpha_cut: None,
+            scale: [1.0, 1.0],
+            offset: [0, 0],
+        }
+    }
+}
+
+/// Voxel model replacing one or more tiles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxelDef {
+    /// Path of the KVX file, relative to the DEF file.
+    pub file: String,
+
+    /// Model scale.
+    pub scale: f32,
+}
+
+/// Tile metadata defined in DEF scripts.
+///
+/// Scripts can be parsed from a string too (`include` commands are ignored in
+/// that case):
+///
+/// ```
+/// use art::def::Definitions;
+///
+/// let defs: Definitions = "dummytile 100 64 64".parse().unwrap();
+/// assert_eq!(Some([64, 64]), defs.tile_size(100));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Definitions {
+    defines: HashMap<String, i32>,
+    textures: BTreeMap<(i16, u8), TileTexture>,
+    tile_sizes: BTreeMap<i16, [u16; 2]>,
+    voxels: BTreeMap<i16, VoxelDef>,
+}
+
+impl Definitions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a DEF script from the native filesystem. Included scripts are
+    /// resolved relative to the directory of `file`.
+    ///
+    /// Includes nested deeper than [`MAX_INCLUDE_DEPTH`](MAX_INCLUDE_DEPTH), or
+    /// including a script that is being parsed, are errors.
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self, Error> {
+        let mut defs = Self::new();
+        defs.parse_file(file.as_ref(), &mut Vec::new())?;
+        Ok(defs)
+    }
+
+    /// Returns the value of a `define`d symbol.
+    pub fn define(&self, name: &str) -> Option<i32> {
+        self.defines.get(name).copied()
+    }
+
+    /// Returns the replacement texture of a tile for the given palette,
+    /// falling back to the base palette.
+    pub fn texture(&self, picnum: i16, palette: u8) -> Option<&TileTexture> {
+        self.textures
+            .get(&(picnum, palette))
+            .or_else(|| self.textures.get(&(picnum, 0)))
+    }
+
+    /// Returns all the replacement textures, indexed by picnum and palette.
+    pub fn textures(&self) -> impl Iterator<Item = ((i16, u8), &TileTexture)> {
+        self.textures.iter().map(|(key, texture)| (*key, texture))
+    }
+
+    /// Returns the size of a tile defined with `dummytile` (or
+    /// `dummytilerange`).
+    pub fn tile_size(&self, picnum: i16) -> Option<[u16; 2]> {
+        self.tile_sizes.get(&picnum).copied()
+    }
+
+    /// Returns the voxel model assigned to a tile.
+    pub fn voxel(&self, picnum: i16) -> Option<&VoxelDef> {
+        self.voxels.get(&picnum)
+    }
+
+    /// Returns all the voxel assignments, indexed by picnum.
+    pub fn voxels(&self) -> impl Iterator<Item = (i16, &VoxelDef)> {
+        self.voxels.iter().map(|(picnum, voxel)| (*picnum, voxel))
+    }
+
+    // `includes` holds the scripts being parsed (the include stack).
+    fn parse_file(&mut self, file: &Path, includes: &mut Vec<PathBuf>) -> Result<(), Error> {
+        let source = fs::read_to_string(file)?;
+        includes.push(fs::canonicalize(file)?);
+        self.parse(&source, file.parent(), includes)?;
+        includes.pop();
+        Ok(())
+    }
+
+    fn parse(
+        &mut self,
+        source: &str,
+        dir: Option<&Path>,
+        includes: &mut Vec<PathBuf>,
+    ) -> Result<(), Error> {
+        let mut tokens = Tokens::new(source);
+        while let Some(command) = tokens.next() {
+            match command {
+                "define" => {
+                    let name = tokens.word()?;
+                    let value = self.int(&mut tokens)?;
+                    self.defines.insert(name.to_string(), value);
+                }
+                "include" | "#include" => {
+                    let file = tokens.string()?;
+                    let dir = match dir {
+                        Some(dir) => dir,
+                        None => {
+                            warn!("Ignoring DEF include: {}", file);
+                            continue;
+                        }
+                    };
+                    let file = dir.join(file);
+                    if includes.len() >= MAX_INCLUDE_DEPTH {
+                        return Err(tokens.error("includes nested too deep"));
+                    }
+                    if includes.contains(&fs::canonicalize(&file)?) {
+                        return Err(tokens.error("recursive include"));
+                    }
+                    self.parse_file(&file, includes)?;
+                }
+                "tilefromtexture" => {
+                    let picnum = self.tile(&mut tokens)?;
+                    let mut texture = TileTexture::new(String::new());
+                    tokens.open()?;
+                    while let Some(token) = tokens.block_token()? {
+                        match token {
+                            "file" => texture.file = tokens.string()?.to_string(),
+                            "alphacut" => texture.alpha_cut = Some(tokens.float()?),
+                            "xoffset" | "xoff" => texture.offset[0] = self.int(&mut tokens)?,
+                            "yoffset" | "yoff" => texture.offset[1] = self.int(&mut tokens)?,
+                            _ => tokens.skip_value(token)?,
+                        }
+                    }
+                    self.textures.insert((picnum, 0), texture);
+                }
+                "texture" => {
+                    let picnum = self.tile(&mut tokens)?;
+                    tokens.open()?;
+                    while let Some(token) = tokens.block_token()? {
+                        if token != "pal" {
+                            tokens.skip_value(token)?;
+                            continue;
+                        }
+                        let palette = self.int(&mut tokens)? as u8;
+                        let mut texture = TileTexture::new(String::new());
+                        tokens.open()?;
+                        while let Some(token) = tokens.block_token()? {
+                            match token {
+                                "file" | "name" => texture.file = tokens.string()?.to_string(),
+                                "alphacut" => texture.alpha_cut = Some(tokens.float()?),
+                                "xscale" => texture.scale[0] = tokens.float()?,
+                                "yscale" => texture.scale[1] = tokens.float()?,
+                                _ => tokens.skip_value(token)?,
+                            }
+                        }
+                        self.textures.insert((picnum, palette), texture);
+                    }
+                }
+                "dummytile" => {
+                    let picnum = self.tile(&mut tokens)?;
+                    let size = [self.int(&mut tokens)? as u16, self.int(&mut tokens)? as u16];
+                    self.tile_sizes.insert(picnum, size);
+                }
+                "dummytilerange" => {
+                    let first = self.tile(&mut tokens)?;
+                    let last = self.tile(&mut tokens)?;
+                    let size = [self.int(&mut tokens)? as u16, self.int(&mut tokens)? as u16];
+                    for picnum in first..=last {
+                        self.tile_sizes.insert(picnum, size);
+                    }
+                }
+                "voxel" => {
+                    let mut voxel = VoxelDef {
+                        file: tokens.string()?.to_string(),
+                        scale: 1.0,
+                    };
+                    let mut tiles = Vec::new();
+                    let mut range = [None, None];
+                    tokens.open()?;
+                    while let Some(token) = tokens.block_token()? {
+                        match token {
+                            "tile" => tiles.push(self.tile(&mut tokens)?),
+                            "tile0" => range[0] = Some(self.tile(&mut tokens)?),
+                            "tile1" => range[1] = Some(self.tile(&mut tokens)?),
+                            "scale" => voxel.scale = tokens.float()?,
+                            _ => tokens.skip_value(token)?,
+                        }
+                    }
+                    if let [Some(first), Some(last)] = range {
+                        tiles.extend(first..=last);
+                    }
+                    for picnum in tiles {
+                        self.voxels.insert(picnum, voxel.clone());
+                    }
+                }
+                "{" => tokens.skip_block()?,
+                "}" => return Err(tokens.error("unexpected '}'")),
+                _ => {
+                    // unsupported command. Its arguments are skipped as the
+                    // following tokens are not recognized as commands.
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn int(&self, tokens: &mut Tokens) -> Result<i32, Error> {
+        let token = tokens.word()?;
+        token
+            .parse()
+            .ok()
+            .or_else(|| self.define(token))
+            .ok_or_else(|| tokens.error("expected integer"))
+    }
+
+    fn tile(&self, tokens: &mut Tokens) -> Result<i16, Error> {
+        let value = self.int(tokens)?;
+        if (0..=i32::from(i16::MAX)).contains(&value) {
+            Ok(value as i16)
+        } else {
+            Err(tokens.error("tile number out of range"))
+        }
+    }
+}
+
+impl FromStr for Definitions {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self, Error> {
+        let mut defs = Self::new();
+        defs.parse(source, None, &mut Vec::new())?;
+        Ok(defs)
+    }
+}
+
+// whitespace separated tokens, skipping comments. Quoted strings are returned
+// without the quotes.
+struct Tokens<'a> {
+    source: &'a str,
+    line: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(source: &'a str) -> Self {
+        Self { source, line: 1 }
+    }
+
+    fn error(&self, message: &'static str) -> Error {
+        Error::InvalidDef(self.line, message)
+    }
+
+    fn advance(&mut self, len: usize) {
+        self.line += self.source[..len].matches('\n').count();
+        self.source = &self.source[len..];
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        loop {
+            let trimmed = self.source.trim_start();
+            self.advance(self.source.len() - trimmed.len());
+            if self.source.starts_with("//") {
+                let len = self.source.find('\n').unwrap_or(self.source.len());
+                self.advance(len);
+            } else if self.source.starts_with("/*") {
+                let len = self.source.find("*/").map_or(self.source.len(), |i| i + 2);
+                self.advance(len);
+            } else {
+                break;
+            }
+        }
+        let source = self.source;
+        if source.is_empty() {
+            return None;
+        }
+        if let Some(rest) = source.strip_prefix('"') {
+            let len = rest.find('"').unwrap_or(rest.len());
+            self.advance((len + 2).min(source.len()));
+            return Some(&rest[..len]);
+        }
+        if source.starts_with('{') || source.starts_with('}') {
+            self.advance(1);
+            return Some(&source[..1]);
+        }
+        let len = source
+            .find(|c: char| c.is_whitespace() || c == '{' || c == '}' || c == '"')
+            .unwrap_or(source.len());
+        self.advance(len);
+        Some(&source[..len])
+    }
+
+    fn word(&mut self) -> Result<&'a str, Error> {
+        match self.next() {
+            Some("{") | Some("}") | None => Err(self.error("unexpected end of command")),
+            Some(token) => Ok(token),
+        }
+    }
+
+    fn string(&mut self) -> Result<&'a str, Error> {
+        self.word()
+    }
+
+    fn float(&mut self) -> Result<f32, Error> {
+        self.word()?
+            .parse()
+            .map_err(|_| self.error("expected number"))
+    }
+
+    fn open(&mut self) -> Result<(), Error> {
+        match self.next() {
+            Some("{") => Ok(()),
+            _ => Err(self.error("expected '{'")),
+        }
+    }
+
+    // next token inside a block, or None at the closing brace.
+    fn block_token(&mut self) -> Result<Option<&'a str>, Error> {
+        match self.next() {
+            Some("}") => Ok(None),
+            Some(token) => Ok(Some(token)),
+            None => Err(self.error("unclosed '{'")),
+        }
+    }
+
+    // skips the value of an unsupported block attribute (a single token, a
+    // nested block, or nothing if the attribute is a flag).
+    fn skip_value(&mut self, attribute: &str) -> Result<(), Error> {
+        const FLAGS: &[&str] = &["nocompress", "nodownsize", "forcefilter", "artquality"];
+        if FLAGS.contains(&attribute) || self.source.trim_start().starts_with('}') {
+            return Ok(());
+        }
+        match self.next() {
+            Some("{") => self.skip_block(),
+            Some(_) => {
+                if self.source.trim_start().starts_with('{') {
+                    self.next();
+                    self.skip_block()?;
+                }
+                Ok(())
+            }
+            None => Err(self.error("unclosed '{'")),
+        }
+    }
+
+    fn skip_block(&mut self) -> Result<(), Error> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some("{") => depth += 1,
+                Some("}") => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.error("unclosed '{'")),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Definitions, TileTexture, VoxelDef, MAX_INCLUDE_DEPTH};
+    use crate::Error;
+
+    #[test]
+    fn parse() {
+        let source = r#"
+            define PIGCOP 2000 // comment
+            /* block
+               comment */
+            tilefromtexture 100 { file "tiles/100.png" alphacut 0.5 xoffset -2 yoffset 3 }
+            texture PIGCOP {
+                pal 0 { nocompress file "pigcop.png" }
+                pal 21 { file "pigcop_21.png" xscale 2.0 yscale 2 }
+                detail { file "detail.png" scale 0.25 }
+            }
+            dummytile 200 64 32
+            dummytilerange 300 302 16 16
+            voxel "pig.kvx" { tile PIGCOP tile0 10 tile1 11 scale 0.5 }
+            model "pig.md3" { scale 1.0 frame { name "idle" tile 2000 } }
+            music { id "E1L1" file "e1l1.ogg" }
+        "#;
+        let defs = source.parse::<Definitions>().unwrap();
+        assert_eq!(Some(2000), defs.define("PIGCOP"));
+        assert_eq!(
+            Some(&TileTexture {
+                file: "tiles/100.png".to_string(),
+                alpha_cut: Some(0.5),
+                scale: [1.0, 1.0],
+                offset: [-2, 3],
+            }),
+            defs.texture(100, 0)
+        );
+        assert_eq!("pigcop.png", defs.texture(2000, 0).unwrap().file);
+        assert_eq!("pigcop_21.png", defs.texture(2000, 21).unwrap().file);
+        assert_eq!([2.0, 2.0], defs.texture(2000, 21).unwrap().scale);
+        assert_eq!("pigcop.png", defs.texture(2000, 1).unwrap().file);
+        assert_eq!(Some([64, 32]), defs.tile_size(200));
+        assert_eq!(Some([16, 16]), defs.tile_size(302));
+        assert_eq!(None, defs.tile_size(303));
+        let voxel = VoxelDef {
+            file: "pig.kvx".to_string(),
+            scale: 0.5,
+        };
+        assert_eq!(Some(&voxel), defs.voxel(2000));
+        assert_eq!(Some(&voxel), defs.voxel(11));
+        assert_eq!(3, defs.voxels().count());
+    }
+
+    #[test]
+    fn errors() {
+        let err = "dummytile 1 2\n\ntexture 3 { pal 0 {"
+            .parse::<Definitions>()
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidDef(_, _)));
+        let err = "\n\ndummytile FOO 1 1".parse::<Definitions>().unwrap_err();
+        assert!(matches!(err, Error::InvalidDef(3, "expected integer")));
+    }
+
+    #[test]
+    fn includes() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, source: &str| std::fs::write(dir.path().join(name), source);
+        write("main.def", "include \"tiles.def\"\ninclude \"tiles.def\"").unwrap();
+        write("tiles.def", "dummytile 1 8 8").unwrap();
+        let defs = Definitions::from_file(dir.path().join("main.def")).unwrap();
+        assert_eq!(Some([8, 8]), defs.tile_size(1));
+
+        write("a.def", "dummytile 1 8 8\ninclude \"b.def\"").unwrap();
+        write("b.def", "\ninclude \"a.def\"").unwrap();
+        let err = Definitions::from_file(dir.path().join("a.def")).unwrap_err();
+        assert!(matches!(err, Error::InvalidDef(2, "recursive include")));
+
+        write("self.def", "include \"./self.def\"").unwrap();
+        let err = Definitions::from_file(dir.path().join("self.def")).unwrap_err();
+        assert!(matches!(err, Error::InvalidDef(1, "recursive include")));
+
+        // chain of scripts, each including the next one.
+        for i in 0..=MAX_INCLUDE_DEPTH {
+            write(&format!("{}.def", i), &format!("include \"{}.def\"", i + 1)).unwrap();
+        }
+        write(&format!("{}.def", MAX_INCLUDE_DEPTH + 1), "").unwrap();
+        let err = Definitions::from_file(dir.path().join("0.def")).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidDef(1, "includes nested too deep")
+        ));
+        // the deepest chain accepted
+        assert!(Definitions::from_file(dir.path().join("2.def")).is_ok());
+    }
+}
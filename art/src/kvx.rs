@@ -0,0 +1,202 @@
+//! Parsing of *Build Engine* KVX voxel models.
+//!
+//! Voxel models replace flat sprites in games like Blood and Shadow Warrior.
+//! Only the first (full resolution) mip level is parsed.
+use crate::{palette::read_vga_palette, Error};
+use byteorder::{ReadBytesExt, LE};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{Cursor, Read},
+    path::Path,
+};
+
+const PALETTE_LEN: usize = 768;
+
+/// Vertical run of voxels in a single (x, y) column.
+#[derive(Debug, Clone)]
+pub struct Slab {
+    /// Z coordinate of the top voxel of the slab.
+    pub z_top: u8,
+
+    /// Visible faces bitmask (`-x`, `+x`, `-y`, `+y`, `-z`, `+z`).
+    pub faces: u8,
+
+    /// Palette indices of each voxel, from top to bottom.
+    pub colors: Vec<u8>,
+}
+
+/// KVX voxel model.
+#[derive(Debug)]
+pub struct Kvx {
+    /// Model size in voxels (x, y, z).
+    pub size: [u32; 3],
+
+    /// Rotation pivot (in voxel units, 8.8 fixed point in the file).
+    pub pivot: [f32; 3],
+
+    columns: Vec<Vec<Slab>>,
+    palette: [[u8; 3]; 256],
+}
+
+impl Kvx {
+    /// Parse KVX model from a reader.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if data.len() < PALETTE_LEN {
+            return Err(Error::InvalidKvx("missing palette"));
+        }
+        let (model, palette_data) = data.split_at(data.len() - PALETTE_LEN);
+        let palette = read_vga_palette(palette_data);
+
+        let mut reader = Cursor::new(model);
+        let num_bytes = reader.read_u32::<LE>()? as usize;
+        let xsize = reader.read_u32::<LE>()?;
+        let ysize = reader.read_u32::<LE>()?;
+        let zsize = reader.read_u32::<LE>()?;
+        if xsize > 256 || ysize > 256 || zsize > 256 {
+            return Err(Error::InvalidKvx("model size out of bounds"));
+        }
+        let pivot = [
+            reader.read_i32::<LE>()? as f32 / 256.0,
+            reader.read_i32::<LE>()? as f32 / 256.0,
+            reader.read_i32::<LE>()? as f32 / 256.0,
+        ];
+        let xoffsets = (0..=xsize)
+            .map(|_| reader.read_u32::<LE>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let xyoffsets = (0..xsize * (ysize + 1))
+            .map(|_| reader.read_u16::<LE>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // offsets are relative to the start of the x offset table.
+        let table_start = 28;
+        let table_len = 4 * (xsize as usize + 1) + 2 * xyoffsets.len();
+        if num_bytes + 4 > model.len() || xoffsets[0] as usize != table_len {
+            return Err(Error::InvalidKvx("corrupt offset tables"));
+        }
+
+        let mut columns = Vec::with_capacity((xsize * ysize) as usize);
+        for x in 0..xsize as usize {
+            let row = &xyoffsets[x * (ysize as usize + 1)..(x + 1) * (ysize as usize + 1)];
+            for y in 0..ysize as usize {
+                let start = table_start + xoffsets[x] as usize + row[y] as usize;
+                let len = row[y + 1].saturating_sub(row[y]) as usize;
+                let data = model
+                    .get(start..start + len)
+                    .ok_or(Error::InvalidKvx("slab data out of bounds"))?;
+                columns.push(read_slabs(data)?);
+            }
+        }
+
+        Ok(Self {
+            size: [xsize, ysize, zsize],
+            pivot,
+            columns,
+            palette,
+        })
+    }
+
+    /// Parse KVX model from the native filesystem.
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self, Error> {
+        let mut file = File::open(file)?;
+        Self::from_reader(&mut file)
+    }
+
+    /// Parse KVX model from a byte slice.
+    pub fn from_slice(slice: &[u8]) -> Result<Self, Error> {
+        Self::from_reader(&mut Cursor::new(slice))
+    }
+
+    /// Returns the slabs of the given (x, y) column.
+    pub fn column(&self, x: u32, y: u32) -> Option<&[Slab]> {
+        if x < self.size[0] && y < self.size[1] {
+            Some(self.columns[(x * self.size[1] + y) as usize].as_slice())
+        } else {
+            None
+        }
+    }
+
+    /// Model palette (8-bit RGB).
+    pub fn palette(&self) -> &[[u8; 3]; 256] {
+        &self.palette
+    }
+}
+
+fn read_slabs(data: &[u8]) -> Result<Vec<Slab>, Error> {
+    let mut reader = Cursor::new(data);
+    let mut slabs = Vec::new();
+    while (reader.position() as usize) < data.len() {
+        let z_top = reader.read_u8()?;
+        let len = reader.read_u8()?;
+        let faces = reader.read_u8()?;
+        let mut colors = vec![0; len as usize];
+        reader.read_exact(&mut colors)?;
+        slabs.push(Slab {
+            z_top,
+            faces,
+            colors,
+        });
+    }
+    Ok(slabs)
+}
+
+/// Voxel models registered to replace sprite tiles.
+#[derive(Debug, Default)]
+pub struct Voxels {
+    models: BTreeMap<i16, Kvx>,
+}
+
+impl Voxels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a voxel model for the given picnum, returning the previously
+    /// registered model (if any).
+    pub fn register(&mut self, picnum: i16, model: Kvx) -> Option<Kvx> {
+        self.models.insert(picnum, model)
+    }
+
+    /// Returns the voxel model registered for the given picnum.
+    pub fn get(&self, picnum: i16) -> Option<&Kvx> {
+        self.models.get(&picnum)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Kvx;
+
+    #[test]
+    fn parse() {
+        #[rustfmt::skip]
+        let mut data = vec![
+            0, 0, 0, 0, // numbytes (patched below)
+            1, 0, 0, 0, // xsiz
+            2, 0, 0, 0, // ysiz
+            4, 0, 0, 0, // zsiz
+            0, 0x80, 0, 0, // xpivot
+            0, 1, 0, 0, // ypivot
+            0, 2, 0, 0, // zpivot
+            14, 0, 0, 0, // xoffset[0]
+            19, 0, 0, 0, // xoffset[1]
+            0, 0, 5, 0, 5, 0, // xyoffset
+            1, 2, 0b11, 7, 8, // slab
+        ];
+        let num_bytes = (data.len() - 4) as u8;
+        data[0] = num_bytes;
+        data.extend_from_slice(&[63; 768]);
+        let kvx = Kvx::from_slice(&data).unwrap();
+        assert_eq!([1, 2, 4], kvx.size);
+        assert_eq!([128.0, 1.0, 2.0], kvx.pivot);
+        let slabs = kvx.column(0, 0).unwrap();
+        assert_eq!(1, slabs.len());
+        assert_eq!(1, slabs[0].z_top);
+        assert_eq!(vec![7, 8], slabs[0].colors);
+        assert!(kvx.column(0, 1).unwrap().is_empty());
+        assert!(kvx.column(1, 0).is_none());
+        assert_eq!([255, 255, 255], kvx.palette()[0]);
+    }
+}
@@ -1,7 +1,211 @@
+//! Parsing of *Build Engine* ART files.
+//!
+//! ART files contain the tiles (wall & floor textures, sprites, etc...) used
+//! by *Build Engine* games. A game ships with several ART files
+//! (`TILES000.ART`, `TILES001.ART`, ...), each one holding a contiguous range
+//! of tiles.
+//!
+//! # Example
+//! ```no_run
+//! use art::Art;
+//!
+//! let mut art = Art::from_file("TILES000.ART").unwrap();
+//! art.extend(Art::from_file("TILES001.ART").unwrap());
+//!
+//! // animated tile frame after one second.
+//! let picnum = art.animate(1, art::tile::CLOCK_RATE);
+//! ```
+
+use crate::tile::{Picanm, Tile};
+use byteorder::{ReadBytesExt, LE};
+use log::info;
+use std::{
+    fs::File,
+    io::{Cursor, Read},
+    path::Path,
+};
+use thiserror::Error;
+
+pub mod def;
+pub mod kvx;
+pub mod palette;
+pub mod tile;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unsupported ART file version: {0}")]
+    UnsupportedVersion(i32),
+
+    #[error("Invalid ART tile range: {0}..={1}")]
+    InvalidTileRange(i32, i32),
+
+    #[error("Invalid DEF script (line {0}): {1}")]
+    InvalidDef(usize, &'static str),
+
+    #[error("Invalid KVX voxel data: {0}")]
+    InvalidKvx(&'static str),
+
+    /// IO error.
+    #[error("ART IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Collection of ART tiles, indexed by picnum.
+#[derive(Debug, Default)]
+pub struct Art {
+    tiles: Vec<Tile>,
+}
+
+impl Art {
+    /// Parse ART file from a reader.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let version = reader.read_i32::<LE>()?;
+        info!("ART file version: {}", version);
+        if version != 1 {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        // number of tiles (obsolete, not used).
+        let _ = reader.read_i32::<LE>()?;
+        let start = reader.read_i32::<LE>()?;
+        let end = reader.read_i32::<LE>()?;
+        if start < 0 || end < start || end > i32::from(i16::MAX) {
+            return Err(Error::InvalidTileRange(start, end));
+        }
+        let len = (end - start + 1) as usize;
+        let widths = read_array(reader, len, |r| r.read_u16::<LE>())?;
+        let heights = read_array(reader, len, |r| r.read_u16::<LE>())?;
+        let picanms = read_array(reader, len, |r| r.read_u32::<LE>())?;
+
+        let mut tiles = Vec::with_capacity(end as usize + 1);
+        tiles.resize_with(start as usize, Tile::default);
+        for i in 0..len {
+            let mut pixels = vec![0; usize::from(widths[i]) * usize::from(heights[i])];
+            reader.read_exact(&mut pixels)?;
+            tiles.push(Tile::new(widths[i], heights[i], Picanm(picanms[i]), pixels));
+        }
+        Ok(Self { tiles })
+    }
+
+    /// Parse ART file from the native filesystem.
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self, Error> {
+        let mut file = File::open(file)?;
+        Self::from_reader(&mut file)
+    }
+
+    /// Parse ART file from a byte slice.
+    pub fn from_slice(slice: &[u8]) -> Result<Self, Error> {
+        Self::from_reader(&mut Cursor::new(slice))
+    }
+
+    /// Merge tiles from another ART file. Non-empty tiles from `other` replace
+    /// any existing tiles with the same picnum.
+    pub fn extend(&mut self, other: Art) {
+        if other.tiles.len() > self.tiles.len() {
+            self.tiles.resize_with(other.tiles.len(), Tile::default);
+        }
+        for (i, tile) in other.tiles.into_iter().enumerate() {
+            if !tile.is_empty() {
+                self.tiles[i] = tile;
+            }
+        }
+    }
+
+    /// Returns the tile with the given picnum.
+    pub fn get(&self, picnum: i16) -> Option<&Tile> {
+        if picnum < 0 {
+            None
+        } else {
+            self.tiles.get(picnum as usize)
+        }
+    }
+
+    /// Returns all tiles, indexed by picnum.
+    pub fn tiles(&self) -> &[Tile] {
+        self.tiles.as_slice()
+    }
+
+    /// Returns the picnum of the animation frame displayed at the given clock
+    /// time (see [`Picanm::frame_offset`](Picanm::frame_offset)).
+    /// Frames out of the range of tiles are clamped to it.
+    pub fn animate(&self, picnum: i16, clock: u32) -> i16 {
+        if self.tiles.is_empty() {
+            return picnum;
+        }
+        // tiles are indexed by (non-negative) i16 picnums
+        let last = (self.tiles.len() - 1).min(i16::MAX as usize) as i16;
+        self.get(picnum)
+            .map(|tile| {
+                let offset = tile.picanm.frame_offset(clock);
+                picnum.saturating_add(offset).clamp(0, last)
+            })
+            .unwrap_or(picnum)
+    }
+}
+
+fn read_array<R, T, F>(reader: &mut R, len: usize, mut read: F) -> Result<Vec<T>, Error>
+where
+    R: Read,
+    F: FnMut(&mut R) -> std::io::Result<T>,
+{
+    (0..len)
+        .map(|_| read(reader).map_err(Error::from))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
+    use super::Art;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn parse() {
+        #[rustfmt::skip]
+        let data = [
+            1, 0, 0, 0, // version
+            0, 0, 0, 0, // numtiles
+            2, 0, 0, 0, // localtilestart
+            3, 0, 0, 0, // localtileend
+            2, 0, 0, 0, // tilesizx
+            1, 0, 0, 0, // tilesizy
+            0x81, 0, 0, 0, 0, 0, 0, 0, // picanm
+            1, 2, // pixels
+        ];
+        let art = Art::from_slice(&data).unwrap();
+        assert_eq!(4, art.tiles().len());
+        assert!(art.get(0).unwrap().is_empty());
+        assert_eq!(Some(2), art.get(2).unwrap().pixel(1, 0));
+        assert!(art.get(3).unwrap().is_empty());
+        assert_eq!(3, art.animate(2, 1));
+        assert_eq!(2, art.animate(2, 2));
+    }
+
+    // animations running past the first or last tile
+    #[test]
+    fn animate_clamped() {
+        #[rustfmt::skip]
+        let data = [
+            1, 0, 0, 0, // version
+            0, 0, 0, 0, // numtiles
+            0, 0, 0, 0, // localtilestart
+            1, 0, 0, 0, // localtileend
+            1, 0, 1, 0, // tilesizx
+            1, 0, 1, 0, // tilesizy
+            0xff, 0, 0, 0, 0xbf, 0, 0, 0, // picanm (backward & forward)
+            1, 2, // pixels
+        ];
+        let art = Art::from_slice(&data).unwrap();
+        assert_eq!(0, art.animate(0, 5));
+        assert_eq!(1, art.animate(1, 5));
+        assert_eq!(1, art.animate(1, 0));
+    }
+
+    #[test]
+    fn animate_empty() {
+        assert_eq!(0, Art::default().animate(0, 10));
+        assert_eq!(12, Art::default().animate(12, 10));
+    }
 }
@@ -0,0 +1,242 @@
+//! Parsing of *Build Engine* palette files (`PALETTE.DAT`).
+//!
+//! Besides the 256 color palette, the file holds the shade tables (used for
+//! distance & sector shading) and the translucency table used to blend two
+//! palette indices together.
+//!
+//! Palette swaps (the `pal` of walls, floors, ceilings & sprites) are
+//! remapping tables stored in a separate file (`LOOKUP.DAT`), parsed by
+//! [`Lookups`](Lookups).
+use crate::Error;
+use byteorder::{ReadBytesExt, LE};
+use std::{
+    fs::File,
+    io::{self, Cursor, ErrorKind, Read},
+    path::Path,
+};
+
+const TRANSLUCENCY_LEN: usize = 256 * 256;
+
+/// Converts 6-bit VGA palette colors into 8-bit RGB.
+pub(crate) fn read_vga_palette(data: &[u8]) -> [[u8; 3]; 256] {
+    let mut palette = [[0; 3]; 256];
+    for (color, rgb) in palette.iter_mut().zip(data.chunks(3)) {
+        color.copy_from_slice(rgb);
+        color.iter_mut().for_each(|c| *c = (*c << 2) | (*c >> 4));
+    }
+    palette
+}
+
+/// Palette, shade & translucency tables.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    colors: [[u8; 3]; 256],
+    shades: Vec<[u8; 256]>,
+    translucency: Option<Vec<u8>>,
+}
+
+impl Palette {
+    /// Parse palette from a reader.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let mut colors = [0; 768];
+        reader.read_exact(&mut colors)?;
+        let num_shades = reader.read_u16::<LE>()?;
+        let mut shades = vec![[0; 256]; usize::from(num_shades)];
+        for table in &mut shades {
+            reader.read_exact(table)?;
+        }
+        let mut translucency = Vec::with_capacity(TRANSLUCENCY_LEN);
+        reader
+            .take(TRANSLUCENCY_LEN as u64)
+            .read_to_end(&mut translucency)?;
+        Ok(Self {
+            colors: read_vga_palette(&colors),
+            shades,
+            translucency: if translucency.len() == TRANSLUCENCY_LEN {
+                Some(translucency)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Parse palette from the native filesystem.
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self, Error> {
+        let mut file = File::open(file)?;
+        Self::from_reader(&mut file)
+    }
+
+    /// Parse palette from a byte slice.
+    pub fn from_slice(slice: &[u8]) -> Result<Self, Error> {
+        Self::from_reader(&mut Cursor::new(slice))
+    }
+
+    /// Palette colors (8-bit RGB).
+    pub fn colors(&self) -> &[[u8; 3]; 256] {
+        &self.colors
+    }
+
+    /// Number of shade levels.
+    pub fn num_shades(&self) -> usize {
+        self.shades.len()
+    }
+
+    /// Returns the palette index of a color at the given shade level. Shades
+    /// are clamped to the range of the shade tables (0 is fully lit).
+    pub fn shade(&self, index: u8, shade: i32) -> u8 {
+        match self.shades.len() {
+            0 => index,
+            len => self.shades[shade.clamp(0, len as i32 - 1) as usize][usize::from(index)],
+        }
+    }
+
+    /// Returns the palette index resulting of drawing a translucent color
+    /// (`src`) over another (`dst`). Without a translucency table the source
+    /// color is returned.
+    pub fn blend(&self, src: u8, dst: u8) -> u8 {
+        match &self.translucency {
+            Some(table) => table[usize::from(src) * 256 + usize::from(dst)],
+            None => src,
+        }
+    }
+}
+
+/// Palette swap tables (`LOOKUP.DAT`), and the alternative palettes stored
+/// after them (underwater, night vision, etc).
+#[derive(Debug, Default, Clone)]
+pub struct Lookups {
+    tables: Vec<Option<Box<[u8; 256]>>>,
+    palettes: Vec<[[u8; 3]; 256]>,
+}
+
+impl Lookups {
+    /// Parse lookups from a reader.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let count = reader.read_u8()?;
+        let mut tables = vec![None; 256];
+        for _ in 0..count {
+            let pal = reader.read_u8()?;
+            let mut table = Box::new([0; 256]);
+            reader.read_exact(&mut table[..])?;
+            tables[usize::from(pal)] = Some(table);
+        }
+        let mut palettes = Vec::new();
+        let mut colors = [0; 768];
+        // the number of alternative palettes varies between games.
+        while read_palette(reader, &mut colors)? {
+            palettes.push(read_vga_palette(&colors));
+        }
+        Ok(Self { tables, palettes })
+    }
+
+    /// Parse lookups from the native filesystem.
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self, Error> {
+        let mut file = File::open(file)?;
+        Self::from_reader(&mut file)
+    }
+
+    /// Parse lookups from a byte slice.
+    pub fn from_slice(slice: &[u8]) -> Result<Self, Error> {
+        Self::from_reader(&mut Cursor::new(slice))
+    }
+
+    /// Returns true if there is a swap table for the given pal.
+    pub fn contains(&self, pal: u8) -> bool {
+        matches!(self.tables.get(usize::from(pal)), Some(Some(_)))
+    }
+
+    /// Returns the palette index of a color with the given palette swap.
+    /// Pals without a swap table (including 0) leave colors unchanged.
+    pub fn swap(&self, pal: u8, index: u8) -> u8 {
+        match self.tables.get(usize::from(pal)) {
+            Some(Some(table)) => table[usize::from(index)],
+            _ => index,
+        }
+    }
+
+    /// Alternative palettes (8-bit RGB).
+    pub fn palettes(&self) -> &[[[u8; 3]; 256]] {
+        &self.palettes
+    }
+}
+
+// reads a whole palette, returning false if the reader ended right before
+// it. Palettes cut short are an error.
+fn read_palette<R: Read>(reader: &mut R, colors: &mut [u8; 768]) -> Result<bool, Error> {
+    let mut len = 0;
+    while len < colors.len() {
+        match reader.read(&mut colors[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    match len {
+        0 => Ok(false),
+        768 => Ok(true),
+        _ => Err(io::Error::from(ErrorKind::UnexpectedEof).into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Lookups, Palette};
+    use std::io::{self, Read};
+
+    #[test]
+    fn parse() {
+        let mut data = vec![0; 768];
+        data[3..6].copy_from_slice(&[63, 32, 0]);
+        data.extend_from_slice(&[2, 0]);
+        data.extend((0..=255).map(|i: u8| i));
+        data.extend((0..=255).map(|i: u8| i / 2));
+        let palette = Palette::from_slice(&data).unwrap();
+        assert_eq!([255, 130, 0], palette.colors()[1]);
+        assert_eq!(2, palette.num_shades());
+        assert_eq!(100, palette.shade(100, -4));
+        assert_eq!(50, palette.shade(100, 10));
+        assert_eq!(7, palette.blend(7, 3));
+
+        data.extend((0..256 * 256).map(|i| (i % 256) as u8));
+        let palette = Palette::from_slice(&data).unwrap();
+        assert_eq!(3, palette.blend(7, 3));
+    }
+
+    #[test]
+    fn lookups() {
+        let mut data = vec![1, 21];
+        data.extend((0..=255).map(|i: u8| i.wrapping_add(1)));
+        data.extend(vec![0; 768]);
+        data[2 + 256 + 3] = 63;
+        let lookups = Lookups::from_slice(&data).unwrap();
+        assert!(lookups.contains(21));
+        assert!(!lookups.contains(0));
+        assert_eq!(11, lookups.swap(21, 10));
+        assert_eq!(0, lookups.swap(21, 255));
+        assert_eq!(10, lookups.swap(2, 10));
+        assert_eq!(1, lookups.palettes().len());
+        assert_eq!([255, 0, 0], lookups.palettes()[0][1]);
+        assert!(Lookups::from_slice(&data[..100]).is_err());
+
+        // incomplete trailing palette
+        data.extend(vec![0; 10]);
+        assert!(Lookups::from_slice(&data).is_err());
+    }
+
+    // IO errors after the swap tables aren't mistaken for the end of file
+    #[test]
+    fn lookups_io_error() {
+        struct Broken;
+
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken"))
+            }
+        }
+
+        let data = [0];
+        assert!(Lookups::from_reader(&mut data.chain(Broken)).is_err());
+        assert!(Lookups::from_reader(&mut data.as_ref()).is_ok());
+    }
+}
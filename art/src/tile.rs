@@ -0,0 +1,159 @@
+/// Number of clock ticks per second (Build's `totalclock` rate).
+pub const CLOCK_RATE: u32 = 120;
+
+/// Tile animation type (bits 6-7 of the `picanm` field).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum AnimationType {
+    /// Static tile.
+    None,
+
+    /// Ping-pong between the first and last frames.
+    Oscillating,
+
+    /// Loop through tiles `picnum..=picnum + frames`.
+    Forward,
+
+    /// Loop through tiles `picnum..=picnum - frames`.
+    Backward,
+}
+
+/// Tile animation & offset metadata (the `picanm` array of an ART file).
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+#[repr(transparent)]
+pub struct Picanm(pub u32);
+
+impl Picanm {
+    /// Number of animation frames (not counting the first one).
+    pub fn frames(&self) -> u8 {
+        (self.0 & 0x3f) as u8
+    }
+
+    pub fn animation_type(&self) -> AnimationType {
+        match (self.0 >> 6) & 0b11 {
+            0b00 => AnimationType::None,
+            0b01 => AnimationType::Oscillating,
+            0b10 => AnimationType::Forward,
+            0b11 => AnimationType::Backward,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Horizontal drawing offset of the tile center.
+    pub fn x_offset(&self) -> i8 {
+        (self.0 >> 8) as u8 as i8
+    }
+
+    /// Vertical drawing offset of the tile center.
+    pub fn y_offset(&self) -> i8 {
+        (self.0 >> 16) as u8 as i8
+    }
+
+    /// Animation speed. Frames advance every `2^speed` clock ticks.
+    pub fn speed(&self) -> u8 {
+        ((self.0 >> 24) & 0xf) as u8
+    }
+
+    /// Returns the picnum offset of the animation frame to be displayed at the
+    /// given clock time (in [`CLOCK_RATE`](CLOCK_RATE) ticks), as computed by
+    /// the engine's `animateoffs`.
+    pub fn frame_offset(&self, clock: u32) -> i16 {
+        let frames = u32::from(self.frames());
+        if frames == 0 {
+            return 0;
+        }
+        let i = clock >> self.speed();
+        match self.animation_type() {
+            AnimationType::None => 0,
+            AnimationType::Oscillating => {
+                let k = i % (frames * 2);
+                if k >= frames {
+                    (frames * 2 - k) as i16
+                } else {
+                    k as i16
+                }
+            }
+            AnimationType::Forward => (i % (frames + 1)) as i16,
+            AnimationType::Backward => -((i % (frames + 1)) as i16),
+        }
+    }
+}
+
+/// A single ART tile.
+///
+/// Pixels are palette indices stored in column-major order.
+#[derive(Debug, Default)]
+pub struct Tile {
+    pub width: u16,
+    pub height: u16,
+
+    /// Animation and offset metadata.
+    pub picanm: Picanm,
+
+    pixels: Vec<u8>,
+}
+
+impl Tile {
+    pub(crate) fn new(width: u16, height: u16, picanm: Picanm, pixels: Vec<u8>) -> Self {
+        assert_eq!(usize::from(width) * usize::from(height), pixels.len());
+        Self {
+            width,
+            height,
+            picanm,
+            pixels,
+        }
+    }
+
+    /// Returns true if the tile has no pixel data.
+    pub fn is_empty(&self) -> bool {
+        self.pixels.is_empty()
+    }
+
+    /// Palette indices in column-major order.
+    pub fn pixels(&self) -> &[u8] {
+        self.pixels.as_slice()
+    }
+
+    /// Returns the palette index of the pixel at the given tile coordinates.
+    pub fn pixel(&self, x: u16, y: u16) -> Option<u8> {
+        if x < self.width && y < self.height {
+            let idx = usize::from(x) * usize::from(self.height) + usize::from(y);
+            Some(self.pixels[idx])
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AnimationType, Picanm};
+
+    #[test]
+    fn picanm_fields() {
+        let picanm = Picanm(0x03_fe_02_80 | 5);
+        assert_eq!(5, picanm.frames());
+        assert_eq!(AnimationType::Forward, picanm.animation_type());
+        assert_eq!(2, picanm.x_offset());
+        assert_eq!(-2, picanm.y_offset());
+        assert_eq!(3, picanm.speed());
+    }
+
+    #[test]
+    fn frame_offset() {
+        let forward = Picanm(0b10 << 6 | 3);
+        let offsets: Vec<_> = (0..6).map(|c| forward.frame_offset(c)).collect();
+        assert_eq!(vec![0, 1, 2, 3, 0, 1], offsets);
+
+        let backward = Picanm(0b11 << 6 | 3);
+        let offsets: Vec<_> = (0..6).map(|c| backward.frame_offset(c)).collect();
+        assert_eq!(vec![0, -1, -2, -3, 0, -1], offsets);
+
+        let oscillating = Picanm(0b01 << 6 | 3);
+        let offsets: Vec<_> = (0..8).map(|c| oscillating.frame_offset(c)).collect();
+        assert_eq!(vec![0, 1, 2, 3, 2, 1, 0, 1], offsets);
+
+        let slow = Picanm(1 << 24 | 0b10 << 6 | 3);
+        let offsets: Vec<_> = (0..6).map(|c| slow.frame_offset(c)).collect();
+        assert_eq!(vec![0, 0, 1, 1, 2, 2], offsets);
+    }
+}
@@ -0,0 +1,29 @@
+/// `buildmap downgrade INPUT OUTPUT`
+pub fn run(args: &[String]) {
+    let (input, output) = match args {
+        [input, output] => (input, output),
+        _ => panic!("Expected INPUT and OUTPUT MAP files."),
+    };
+    let map = crate::load_map(input);
+    let mut bytes = Vec::new();
+    let report = map
+        .to_writer_v7(&mut bytes)
+        .unwrap_or_else(|err| panic!("Error downgrading {:?}: {}", input, err));
+    std::fs::write(output, bytes)
+        .unwrap_or_else(|err| panic!("Error writing {:?}: {}", output, err));
+
+    if report.is_lossless() {
+        eprintln!("Lossless conversion.");
+        return;
+    }
+    let lost = [
+        ("TROR ceilings & floors", report.tror_surfaces),
+        ("TROR walls", report.tror_walls),
+        ("sectors with unsupported flags", report.sector_flags),
+        ("walls with unsupported flags", report.wall_flags),
+        ("sprites with unsupported flags", report.sprite_flags),
+    ];
+    for (what, count) in lost.iter().filter(|(_, count)| *count > 0) {
+        eprintln!("{}\t{}", count, what);
+    }
+}
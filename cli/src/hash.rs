@@ -0,0 +1,16 @@
+use map::Map;
+
+/// `buildmap hash MAP...`
+///
+/// MAPs that fail to load are reported and skipped.
+pub fn run(args: &[String]) {
+    if args.is_empty() {
+        panic!("Missing MAP input file(s).");
+    }
+    for path in args {
+        match Map::from_file(path) {
+            Ok(map) => println!("{}\t{}", map.fingerprint(), path),
+            Err(err) => eprintln!("Error loading {:?}: {}", path, err),
+        }
+    }
+}
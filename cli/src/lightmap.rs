@@ -0,0 +1,54 @@
+use map::lightmap::{self, Channel};
+use std::{
+    fs,
+    io::{self, BufWriter},
+    path::Path,
+};
+
+// Size of the largest side of the heatmap, in pixels.
+const SIZE: u32 = 1024;
+
+// Neighbouring sectors whose shades differ by more than this are reported.
+const THRESHOLD: i32 = 8;
+
+/// `buildmap lightmap MAP OUTPUT [floor|ceiling|visibility]`
+///
+/// OUTPUT is written as PNG or SVG depending on its extension.
+pub fn run(args: &[String]) {
+    let (input, output) = match args {
+        [input, output] | [input, output, _] => (input, Path::new(output)),
+        _ => panic!("Expected MAP and OUTPUT files."),
+    };
+    let channel = match args.get(2).map(String::as_str) {
+        None | Some("floor") => Channel::FloorShade,
+        Some("ceiling") => Channel::CeilingShade,
+        Some("visibility") => Channel::Visibility,
+        Some(other) => panic!("Unknown channel {:?}.", other),
+    };
+    let map = crate::load_map(input);
+    let svg = output
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+    if svg {
+        fs::write(output, lightmap::svg(&map, channel, f64::from(SIZE)))
+    } else {
+        write_png(&lightmap::raster(&map, channel, SIZE), output)
+    }
+    .unwrap_or_else(|err| panic!("Error writing {:?}: {}", output, err));
+
+    for (a, b, diff) in lightmap::inconsistencies(&map, channel, THRESHOLD) {
+        println!("sectors {} and {} differ by {}", a, b, diff);
+    }
+}
+
+fn write_png(image: &lightmap::Lightmap, path: &Path) -> io::Result<()> {
+    let file = BufWriter::new(fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer
+        .write_image_data(&image.pixels)
+        .map_err(io::Error::other)
+}
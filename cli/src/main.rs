@@ -0,0 +1,47 @@
+use map::Map;
+use std::{env, path::Path};
+
+mod downgrade;
+mod hash;
+mod lightmap;
+mod textures;
+mod thumbnails;
+
+fn print_usage() {
+    eprintln!("Usage: buildmap COMMAND [ARGS...]");
+    eprintln!();
+    eprintln!("Commands:");
+    eprintln!("    downgrade INPUT OUTPUT   Convert MAP to version 7, reporting lost features");
+    eprintln!("    hash MAP...              Print content hashes, to find duplicate MAPs");
+    eprintln!("    lightmap MAP OUTPUT      Draw a sector shade heatmap (PNG or SVG)");
+    eprintln!("    textures MAP [ART...]    Report tiles referenced by MAP");
+    eprintln!("    thumbnails INPUT DIR     Render thumbnails of every MAP in a directory or GRP");
+}
+
+fn main() {
+    pretty_env_logger::init();
+
+    let mut args = env::args().skip(1);
+    let command = args.next();
+    let args: Vec<_> = args.collect();
+    if args.iter().any(|arg| arg == "--help") {
+        print_usage();
+        return;
+    }
+    match command.as_deref() {
+        Some("downgrade") => downgrade::run(&args),
+        Some("hash") => hash::run(&args),
+        Some("lightmap") => lightmap::run(&args),
+        Some("textures") => textures::run(&args),
+        Some("thumbnails") => thumbnails::run(&args),
+        _ => {
+            print_usage();
+            std::process::exit(1);
+        }
+    }
+}
+
+fn load_map<P: AsRef<Path>>(path: P) -> Map {
+    let path = path.as_ref();
+    Map::from_file(path).unwrap_or_else(|err| panic!("Error loading {:?}: {}", path, err))
+}
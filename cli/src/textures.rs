@@ -0,0 +1,36 @@
+use art::Art;
+use map::names::NameRegistry;
+
+/// `buildmap textures MAP [ART...]`
+pub fn run(args: &[String]) {
+    let (map, art_files) = args.split_first().expect("Missing MAP input file.");
+    let map = crate::load_map(map);
+    let usage = map.stats().textures;
+    let names = NameRegistry::duke();
+
+    println!("picnum\tname\twalls\tmasked\tfloors\tceilings\tsprites\ttotal");
+    for (picnum, tile) in usage.iter() {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            picnum,
+            names.name(*picnum).unwrap_or("-"),
+            tile.walls,
+            tile.masked_walls,
+            tile.floors,
+            tile.ceilings,
+            tile.sprites,
+            tile.total()
+        );
+    }
+
+    if !art_files.is_empty() {
+        let art = art_files.iter().fold(Art::default(), |mut art, file| {
+            let other = Art::from_file(file)
+                .unwrap_or_else(|err| panic!("Error loading {:?}: {}", file, err));
+            art.extend(other);
+            art
+        });
+        let missing = usage.missing(|picnum| art.get(picnum).is_some_and(|t| !t.is_empty()));
+        eprintln!("{} missing tile(s): {:?}", missing.len(), missing);
+    }
+}
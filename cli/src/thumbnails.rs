@@ -0,0 +1,144 @@
+use map::{batch, Map};
+use render::{camera::Camera, d3, frame};
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, BufWriter},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+// Width of the SVG thumbnails, in pixels.
+const SVG_SIZE: f64 = 256.0;
+
+/// `buildmap thumbnails INPUT OUTPUT_DIR`
+///
+/// INPUT is either a directory (searched recursively) or a GRP archive.
+pub fn run(args: &[String]) {
+    let input = args.first().expect("Missing INPUT directory or GRP file.");
+    let output = PathBuf::from(args.get(1).expect("Missing OUTPUT directory."));
+    fs::create_dir_all(&output)
+        .unwrap_or_else(|err| panic!("Error creating {:?}: {}", output, err));
+
+    let input = Path::new(input);
+    let batch = if input.is_dir() {
+        batch::load_dir(input).unwrap_or_else(|err| panic!("Error reading {:?}: {}", input, err))
+    } else {
+        batch::load_grp(input).unwrap_or_else(|err| panic!("Error reading {:?}: {}", input, err))
+    };
+
+    let mut index = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>MAP index</title></head>\n<body>\n<table>\n",
+    );
+    for entry in batch.entries {
+        let name = entry.name;
+        let map = match entry.result {
+            Ok(map) => map,
+            Err(err) => {
+                eprintln!("Skipping {}: {}", name, err);
+                continue;
+            }
+        };
+        let stem = name.replace(['/', '\\'], "_");
+        let svg = format!("{}.svg", stem);
+        let png = format!("{}.png", stem);
+        fs::write(output.join(&svg), map::svg::top_down(&map, SVG_SIZE))
+            .unwrap_or_else(|err| panic!("Error writing {:?}: {}", svg, err));
+        write_screenshot(&map, &output.join(&png))
+            .unwrap_or_else(|err| panic!("Error writing {:?}: {}", png, err));
+        writeln!(
+            index,
+            "<tr><td>{}</td><td><img src=\"{}\"></td><td><img src=\"{}\"></td><td>{} sectors, {} walls, {} sprites</td></tr>",
+            escape(&name),
+            escape(&svg),
+            escape(&png),
+            map.sectors.sectors().len(),
+            map.sectors.walls().len(),
+            map.sprites.len(),
+        )
+        .unwrap();
+        println!("{}", name);
+    }
+    index.push_str("</table>\n</body>\n</html>\n");
+    fs::write(output.join("index.html"), index)
+        .unwrap_or_else(|err| panic!("Error writing index.html: {}", err));
+}
+
+// escapes text for use in HTML elements and (quoted) attributes.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// First-person view from the player start, as a PNG image.
+fn write_screenshot(map: &Map, path: &Path) -> io::Result<()> {
+    let mut frame = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+    if map.sectors.get(map.player.sector).is_some() {
+        // a renderer bug on one MAP shouldn't abort the whole batch.
+        let render = panic::catch_unwind(AssertUnwindSafe(|| {
+            d3::Renderer::new().render(map, &Camera::from(&map.player), &mut frame)
+        }));
+        if render.is_err() {
+            *frame = [[0; frame::WIDTH]; frame::HEIGHT];
+        }
+    }
+    let mut data = Vec::new();
+    frame::encode(&frame, frame::PixelFormat::Rgba, &mut data);
+
+    let file = BufWriter::new(fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, frame::WIDTH as u32, frame::HEIGHT as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&data).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;",
+            super::escape("<a href=\"x\">Tom & Jerry's</a>")
+        );
+    }
+
+    #[test]
+    fn run() {
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        fs::create_dir(input.path().join("maps")).unwrap();
+        fs::write(
+            input.path().join("maps").join("<B&B>.map"),
+            include_bytes!("../../map/tests/maps/SIMPLE0.MAP"),
+        )
+        .unwrap();
+        fs::write(input.path().join("BROKEN.MAP"), b"not a MAP").unwrap();
+        fs::write(input.path().join("README.TXT"), b"not a MAP either").unwrap();
+
+        let args = [input.path(), output.path()].map(|p| p.display().to_string());
+        super::run(&args);
+
+        let stem = "maps_<B&B>.map";
+        assert!(output.path().join(format!("{}.svg", stem)).is_file());
+        assert!(output.path().join(format!("{}.png", stem)).is_file());
+        assert!(!output.path().join("BROKEN.MAP.svg").exists());
+        let index = fs::read_to_string(output.path().join("index.html")).unwrap();
+        assert_eq!(1, index.matches("<tr>").count());
+        assert!(index.contains("<td>maps/&lt;B&amp;B&gt;.map</td>"));
+        assert!(index.contains("<img src=\"maps_&lt;B&amp;B&gt;.map.svg\">"));
+        assert!(!index.contains("<B&B>"));
+    }
+}
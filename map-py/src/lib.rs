@@ -0,0 +1,311 @@
+//! Python bindings of the `map` crate.
+//!
+//! ```python
+//! import buildmap
+//! import numpy as np
+//!
+//! m = buildmap.load("E1L1.MAP")
+//! walls = np.array(m.wall_segments())  # shape (walls, 4): x0, y0, x1, y1
+//! open("E1L1.svg", "w").write(m.to_svg())
+//! ```
+//!
+//! Besides per-element objects ([`Sector`], [`Wall`], [`Sprite`]), `Map`
+//! has bulk accessors returning lists of tuples, which convert directly to
+//! numpy arrays.
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+use std::path::PathBuf;
+
+// Default size of SVG exports, in pixels.
+const SVG_SIZE: f64 = 1024.0;
+
+fn to_py_err(err: map::Error) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+/// Parses a MAP file from bytes.
+#[pyfunction]
+fn parse(data: &[u8]) -> PyResult<Map> {
+    map::Map::from_slice(data).map(Map).map_err(to_py_err)
+}
+
+/// Parses a MAP file from the filesystem.
+#[pyfunction]
+fn load(path: PathBuf) -> PyResult<Map> {
+    map::Map::from_file(path).map(Map).map_err(to_py_err)
+}
+
+#[pyclass]
+pub struct Map(map::Map);
+
+#[pymethods]
+impl Map {
+    #[getter]
+    fn version(&self) -> i32 {
+        self.0.version
+    }
+
+    /// Player start as `(x, y, z, angle, sector)`.
+    #[getter]
+    fn player(&self) -> (i32, i32, i32, i16, i16) {
+        let p = &self.0.player;
+        (p.pos_x, p.pos_y, p.pos_z, p.angle.0, p.sector)
+    }
+
+    fn sectors(&self) -> Vec<Sector> {
+        self.0.sectors.sectors().iter().map(Sector::from).collect()
+    }
+
+    fn walls(&self) -> Vec<Wall> {
+        self.0.sectors.walls().iter().map(Wall::from).collect()
+    }
+
+    fn sprites(&self) -> Vec<Sprite> {
+        self.0.sprites.iter().map(Sprite::from).collect()
+    }
+
+    /// `(ceiling_z, floor_z)` of each sector.
+    fn sector_heights(&self) -> Vec<(i32, i32)> {
+        let sectors = self.0.sectors.sectors().iter();
+        sectors.map(|s| (s.ceiling_z, s.floor_z)).collect()
+    }
+
+    /// `(first_wall, wall_count)` of each sector.
+    fn sector_walls(&self) -> Vec<(i16, usize)> {
+        let sectors = self.0.sectors.sectors().iter();
+        sectors.map(|s| (s.first_wall(), s.wall_count())).collect()
+    }
+
+    /// `(x, y)` left vertex of each wall.
+    fn wall_positions(&self) -> Vec<(i32, i32)> {
+        self.0.sectors.walls().iter().map(|w| (w.x, w.y)).collect()
+    }
+
+    /// `(x0, y0, x1, y1)` of each wall, from its left to its right vertex.
+    fn wall_segments(&self) -> Vec<(i32, i32, i32, i32)> {
+        let walls = self.0.sectors.walls();
+        walls
+            .iter()
+            .map(|w| match walls.get(w.point2 as usize) {
+                Some(r) => (w.x, w.y, r.x, r.y),
+                None => (w.x, w.y, w.x, w.y),
+            })
+            .collect()
+    }
+
+    /// Sector owning each wall (-1 if none).
+    fn wall_sectors(&self) -> Vec<i16> {
+        self.0.sectors.wall_owners()
+    }
+
+    /// `(x, y, z)` of each sprite.
+    fn sprite_positions(&self) -> Vec<(i32, i32, i32)> {
+        self.0.sprites.iter().map(|s| (s.x, s.y, s.z)).collect()
+    }
+
+    /// Top-down SVG drawing of the walls.
+    #[pyo3(signature = (size = SVG_SIZE))]
+    fn to_svg(&self, size: f64) -> String {
+        map::svg::top_down(&self.0, size)
+    }
+
+    /// Serializes the MAP back to bytes.
+    fn to_bytes<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let bytes = self.0.to_vec().map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &bytes))
+    }
+
+    fn save(&self, path: PathBuf) -> PyResult<()> {
+        self.0.to_file(path).map_err(to_py_err)
+    }
+}
+
+/// Copy of a MAP sector.
+#[pyclass(get_all)]
+#[derive(Clone)]
+pub struct Sector {
+    first_wall: i16,
+    wall_count: usize,
+    ceiling_z: i32,
+    floor_z: i32,
+    ceiling_picnum: i16,
+    floor_picnum: i16,
+    ceiling_heinum: i16,
+    floor_heinum: i16,
+    ceiling_shade: i8,
+    floor_shade: i8,
+    lotag: i16,
+    hitag: i16,
+    extra: i16,
+}
+
+impl From<&map::sector::Sector> for Sector {
+    fn from(s: &map::sector::Sector) -> Self {
+        Self {
+            first_wall: s.first_wall(),
+            wall_count: s.wall_count(),
+            ceiling_z: s.ceiling_z,
+            floor_z: s.floor_z,
+            ceiling_picnum: s.ceiling_picnum,
+            floor_picnum: s.floor_picnum,
+            ceiling_heinum: s.ceiling_heinum,
+            floor_heinum: s.floor_heinum,
+            ceiling_shade: s.ceiling_shade,
+            floor_shade: s.floor_shade,
+            lotag: s.lotag,
+            hitag: s.hitag,
+            extra: s.extra,
+        }
+    }
+}
+
+/// Copy of a MAP wall.
+#[pyclass(get_all)]
+#[derive(Clone)]
+pub struct Wall {
+    x: i32,
+    y: i32,
+    point2: i16,
+    next_wall: i16,
+    next_sector: i16,
+    picnum: i16,
+    over_picnum: i16,
+    shade: i8,
+    pal: u8,
+    lotag: i16,
+    hitag: i16,
+    extra: i16,
+}
+
+impl From<&map::sector::Wall> for Wall {
+    fn from(w: &map::sector::Wall) -> Self {
+        Self {
+            x: w.x,
+            y: w.y,
+            point2: w.point2,
+            next_wall: w.next_wall,
+            next_sector: w.next_sector,
+            picnum: w.picnum,
+            over_picnum: w.over_picnum,
+            shade: w.shade,
+            pal: w.pal,
+            lotag: w.lotag,
+            hitag: w.hitag,
+            extra: w.extra,
+        }
+    }
+}
+
+/// Copy of a MAP sprite.
+#[pyclass(get_all)]
+#[derive(Clone)]
+pub struct Sprite {
+    x: i32,
+    y: i32,
+    z: i32,
+    picnum: i16,
+    shade: i8,
+    pal: u8,
+    angle: i16,
+    sectnum: i16,
+    statnum: i16,
+    lotag: i16,
+    hitag: i16,
+    extra: i16,
+}
+
+impl From<&map::sprite::Sprite> for Sprite {
+    fn from(s: &map::sprite::Sprite) -> Self {
+        Self {
+            x: s.x,
+            y: s.y,
+            z: s.z,
+            picnum: s.picnum,
+            shade: s.shade,
+            pal: s.pal,
+            angle: s.angle.0,
+            sectnum: s.sectnum,
+            statnum: s.statnum,
+            lotag: s.lotag,
+            hitag: s.hitag,
+            extra: s.extra,
+        }
+    }
+}
+
+#[pymodule]
+fn buildmap(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(load, m)?)?;
+    m.add_class::<Map>()?;
+    m.add_class::<Sector>()?;
+    m.add_class::<Wall>()?;
+    m.add_class::<Sprite>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Sector, Sprite, Wall};
+
+    const E1L1: &[u8] = include_bytes!("../../map/tests/maps/E1L1.MAP");
+
+    #[test]
+    fn conversions() {
+        let expected = map::Map::from_slice(E1L1).unwrap();
+        let map = parse(E1L1).unwrap();
+        assert_eq!(expected.version, map.version());
+        assert_eq!(expected.player.pos_x, map.player().0);
+        assert_eq!(expected.player.sector, map.player().4);
+
+        let sector = Sector::from(&expected.sectors.sectors()[3]);
+        let s = &expected.sectors.sectors()[3];
+        assert_eq!(
+            (s.first_wall(), s.wall_count()),
+            (sector.first_wall, sector.wall_count)
+        );
+        assert_eq!((s.ceiling_z, s.floor_z), (sector.ceiling_z, sector.floor_z));
+        assert_eq!((s.lotag, s.hitag), (sector.lotag, sector.hitag));
+
+        let w = &expected.sectors.walls()[10];
+        let wall = Wall::from(w);
+        assert_eq!((w.x, w.y, w.point2), (wall.x, wall.y, wall.point2));
+        assert_eq!(
+            (w.next_wall, w.next_sector),
+            (wall.next_wall, wall.next_sector)
+        );
+
+        let s = &expected.sprites[5];
+        let sprite = Sprite::from(s);
+        assert_eq!((s.x, s.y, s.z), (sprite.x, sprite.y, sprite.z));
+        assert_eq!(
+            (s.picnum, s.angle.0, s.sectnum),
+            (sprite.picnum, sprite.angle, sprite.sectnum)
+        );
+    }
+
+    #[test]
+    fn bulk_accessors() {
+        let expected = map::Map::from_slice(E1L1).unwrap();
+        let walls = expected.sectors.walls();
+        let map = parse(E1L1).unwrap();
+        assert_eq!(expected.sectors.sectors().len(), map.sectors().len());
+        assert_eq!(walls.len(), map.walls().len());
+        assert_eq!(expected.sprites.len(), map.sprites().len());
+        assert_eq!(expected.sectors.sectors().len(), map.sector_heights().len());
+        assert_eq!(expected.sectors.wall_owners(), map.wall_sectors());
+
+        // segments go from the left vertex of each wall to its point2
+        let segments = map.wall_segments();
+        assert_eq!(walls.len(), segments.len());
+        for ((x0, y0, x1, y1), wall) in segments.into_iter().zip(walls) {
+            let right = &walls[wall.point2 as usize];
+            assert_eq!((wall.x, wall.y, right.x, right.y), (x0, y0, x1, y1));
+        }
+        assert!(map.to_svg(256.0).starts_with("<svg"));
+    }
+
+    #[test]
+    fn parse_error() {
+        assert!(parse(&[0; 4]).is_err());
+    }
+}
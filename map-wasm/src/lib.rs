@@ -0,0 +1,276 @@
+//! JavaScript bindings of the `map` crate, for web tools that inspect MAP
+//! files without the renderer.
+//!
+//! ```js
+//! import { parseMap } from "map-wasm";
+//!
+//! const map = parseMap(new Uint8Array(buffer));
+//! console.log(map.sectorCount, map.wallCount, map.spriteCount);
+//! const xy = map.wallPositions(); // Int32Array [x0, y0, x1, y1, ...]
+//! ```
+//!
+//! Bulk accessors return typed arrays indexed by sector, wall or sprite id.
+use wasm_bindgen::prelude::*;
+
+/// Parses a MAP file.
+#[wasm_bindgen(js_name = parseMap)]
+pub fn parse_map(bytes: &[u8]) -> Result<Map, JsValue> {
+    map::Map::from_slice(bytes)
+        .map(|map| Map { map })
+        .map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+/// Handle to a parsed MAP.
+#[wasm_bindgen]
+pub struct Map {
+    map: map::Map,
+}
+
+#[wasm_bindgen]
+impl Map {
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> i32 {
+        self.map.version
+    }
+
+    #[wasm_bindgen(getter, js_name = sectorCount)]
+    pub fn sector_count(&self) -> usize {
+        self.map.sectors.sectors().len()
+    }
+
+    #[wasm_bindgen(getter, js_name = wallCount)]
+    pub fn wall_count(&self) -> usize {
+        self.map.sectors.walls().len()
+    }
+
+    #[wasm_bindgen(getter, js_name = spriteCount)]
+    pub fn sprite_count(&self) -> usize {
+        self.map.sprites.len()
+    }
+
+    /// Player start as `[x, y, z, angle, sector]`.
+    #[wasm_bindgen(getter)]
+    pub fn player(&self) -> Vec<i32> {
+        let p = &self.map.player;
+        vec![
+            p.pos_x,
+            p.pos_y,
+            p.pos_z,
+            i32::from(p.angle.0),
+            i32::from(p.sector),
+        ]
+    }
+
+    /// First wall and number of walls of each sector, as
+    /// `[first0, count0, first1, count1, ...]`.
+    #[wasm_bindgen(js_name = sectorWalls)]
+    pub fn sector_walls(&self) -> Vec<u32> {
+        self.sectors()
+            .flat_map(|s| [s.first_wall() as u32, s.wall_count() as u32])
+            .collect()
+    }
+
+    /// Ceiling & floor heights of each sector, as
+    /// `[ceiling0, floor0, ceiling1, floor1, ...]`.
+    #[wasm_bindgen(js_name = sectorHeights)]
+    pub fn sector_heights(&self) -> Vec<i32> {
+        self.sectors()
+            .flat_map(|s| [s.ceiling_z, s.floor_z])
+            .collect()
+    }
+
+    /// Ceiling & floor picnums of each sector, as
+    /// `[ceiling0, floor0, ceiling1, floor1, ...]`.
+    #[wasm_bindgen(js_name = sectorPicnums)]
+    pub fn sector_picnums(&self) -> Vec<i16> {
+        self.sectors()
+            .flat_map(|s| [s.ceiling_picnum, s.floor_picnum])
+            .collect()
+    }
+
+    /// Low & high tags of each sector, as `[lotag0, hitag0, ...]`.
+    #[wasm_bindgen(js_name = sectorTags)]
+    pub fn sector_tags(&self) -> Vec<i16> {
+        self.sectors().flat_map(|s| [s.lotag, s.hitag]).collect()
+    }
+
+    /// Left vertex of each wall, as `[x0, y0, x1, y1, ...]`.
+    #[wasm_bindgen(js_name = wallPositions)]
+    pub fn wall_positions(&self) -> Vec<i32> {
+        self.walls().flat_map(|w| [w.x, w.y]).collect()
+    }
+
+    /// Right vertex (next wall of the loop) of each wall.
+    #[wasm_bindgen(js_name = wallPoint2)]
+    pub fn wall_point2(&self) -> Vec<i16> {
+        self.walls().map(|w| w.point2).collect()
+    }
+
+    /// Wall on the other side of each wall (-1 if solid).
+    #[wasm_bindgen(js_name = wallNextWall)]
+    pub fn wall_next_wall(&self) -> Vec<i16> {
+        self.walls().map(|w| w.next_wall).collect()
+    }
+
+    /// Sector on the other side of each wall (-1 if solid).
+    #[wasm_bindgen(js_name = wallNextSector)]
+    pub fn wall_next_sector(&self) -> Vec<i16> {
+        self.walls().map(|w| w.next_sector).collect()
+    }
+
+    #[wasm_bindgen(js_name = wallPicnums)]
+    pub fn wall_picnums(&self) -> Vec<i16> {
+        self.walls().map(|w| w.picnum).collect()
+    }
+
+    /// Sector owning each wall (-1 if none).
+    #[wasm_bindgen(js_name = wallSectors)]
+    pub fn wall_sectors(&self) -> Vec<i16> {
+        self.map.sectors.wall_owners()
+    }
+
+    /// Position of each sprite, as `[x0, y0, z0, x1, y1, z1, ...]`.
+    #[wasm_bindgen(js_name = spritePositions)]
+    pub fn sprite_positions(&self) -> Vec<i32> {
+        self.map
+            .sprites
+            .iter()
+            .flat_map(|s| [s.x, s.y, s.z])
+            .collect()
+    }
+
+    #[wasm_bindgen(js_name = spritePicnums)]
+    pub fn sprite_picnums(&self) -> Vec<i16> {
+        self.map.sprites.iter().map(|s| s.picnum).collect()
+    }
+
+    #[wasm_bindgen(js_name = spriteAngles)]
+    pub fn sprite_angles(&self) -> Vec<i16> {
+        self.map.sprites.iter().map(|s| s.angle.0).collect()
+    }
+
+    #[wasm_bindgen(js_name = spriteSectors)]
+    pub fn sprite_sectors(&self) -> Vec<i16> {
+        self.map.sprites.iter().map(|s| s.sectnum).collect()
+    }
+
+    /// Low & high tags of each sprite, as `[lotag0, hitag0, ...]`.
+    #[wasm_bindgen(js_name = spriteTags)]
+    pub fn sprite_tags(&self) -> Vec<i16> {
+        self.map
+            .sprites
+            .iter()
+            .flat_map(|s| [s.lotag, s.hitag])
+            .collect()
+    }
+
+    /// Simplified building outlines (see [`map::outline`]), as a sequence of
+    /// contours `[len, hole, x0, y0, x1, y1, ...]`, where `hole` is 0 for the
+    /// exterior contour of each polygon (which comes first) and 1 for its
+    /// holes.
+    pub fn outline(&self) -> Vec<i32> {
+        let mut data = Vec::new();
+        for polygon in map::outline(&self.map) {
+            let holes = polygon.holes.iter().map(|h| (1, h));
+            for (hole, contour) in std::iter::once((0, &polygon.exterior)).chain(holes) {
+                data.extend_from_slice(&[contour.len() as i32, hole]);
+                data.extend(contour.iter().flatten());
+            }
+        }
+        data
+    }
+
+    /// Serializes the MAP back to bytes.
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsValue> {
+        self.map
+            .to_vec()
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+}
+
+impl Map {
+    fn sectors(&self) -> impl Iterator<Item = &map::sector::Sector> {
+        self.map.sectors.sectors().iter()
+    }
+
+    fn walls(&self) -> impl Iterator<Item = &map::sector::Wall> {
+        self.map.sectors.walls().iter()
+    }
+}
+
+// JsValue can't be created outside of wasm, so error paths aren't tested.
+#[cfg(test)]
+mod test {
+    const E1L1: &[u8] = include_bytes!("../../map/tests/maps/E1L1.MAP");
+
+    #[test]
+    fn accessors() {
+        let map = super::parse_map(E1L1).unwrap();
+        let expected = map::Map::from_slice(E1L1).unwrap();
+        let sectors = expected.sectors.sectors();
+        let walls = expected.sectors.walls();
+
+        assert_eq!(expected.version, map.version());
+        assert_eq!(sectors.len(), map.sector_count());
+        assert_eq!(walls.len(), map.wall_count());
+        assert_eq!(expected.sprites.len(), map.sprite_count());
+        assert_eq!(expected.player.pos_x, map.player()[0]);
+        assert_eq!(i32::from(expected.player.sector), map.player()[4]);
+
+        // flattened arrays, one tuple per element
+        assert_eq!(2 * sectors.len(), map.sector_walls().len());
+        assert_eq!(2 * sectors.len(), map.sector_heights().len());
+        assert_eq!(2 * walls.len(), map.wall_positions().len());
+        assert_eq!(walls.len(), map.wall_point2().len());
+        assert_eq!(3 * expected.sprites.len(), map.sprite_positions().len());
+        assert_eq!(2 * expected.sprites.len(), map.sprite_tags().len());
+
+        let sector = &sectors[3];
+        assert_eq!(
+            [sector.first_wall() as u32, sector.wall_count() as u32],
+            map.sector_walls()[6..8]
+        );
+        assert_eq!(
+            [sector.ceiling_z, sector.floor_z],
+            map.sector_heights()[6..8]
+        );
+        let wall = &walls[10];
+        assert_eq!([wall.x, wall.y], map.wall_positions()[20..22]);
+        assert_eq!(wall.next_sector, map.wall_next_sector()[10]);
+        assert_eq!(expected.sectors.wall_owners(), map.wall_sectors());
+        let sprite = &expected.sprites[5];
+        assert_eq!(
+            [sprite.x, sprite.y, sprite.z],
+            map.sprite_positions()[15..18]
+        );
+        assert_eq!(sprite.picnum, map.sprite_picnums()[5]);
+    }
+
+    #[test]
+    fn outline() {
+        let map = super::parse_map(E1L1).unwrap();
+        let data = map.outline();
+        assert!(!data.is_empty());
+        // walk the contours: [len, hole, x0, y0, ...]
+        let (mut i, mut exteriors) = (0, 0);
+        while i < data.len() {
+            let (len, hole) = (data[i] as usize, data[i + 1]);
+            assert!(len >= 3);
+            assert!(hole == 0 || (hole == 1 && exteriors > 0));
+            exteriors += (hole == 0) as usize;
+            i += 2 + 2 * len;
+        }
+        assert_eq!(data.len(), i);
+        assert_eq!(
+            map::outline(&map::Map::from_slice(E1L1).unwrap()).len(),
+            exteriors
+        );
+    }
+
+    #[test]
+    fn to_bytes() {
+        let map = super::parse_map(E1L1).unwrap();
+        assert_eq!(E1L1, &map.to_bytes().unwrap()[..]);
+    }
+}
@@ -0,0 +1,78 @@
+//! Wall texture alignment.
+use crate::{
+    sector::{x_repeat_for_density, WallId},
+    Map,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Aligns the textures of the walls connected to `start` (reproducing the
+/// Build editor's `.` key), assuming tile widths are powers of two no larger
+/// than 256 texels. Returns the number of walls that were modified.
+///
+/// See [`align_walls_with`](align_walls_with).
+pub fn align_walls(map: &mut Map, start: WallId) -> usize {
+    align_walls_with(map, start, |_| 256)
+}
+
+/// Aligns the textures of the walls connected to `start`, using `tile_width`
+/// to look up the width (in texels) of each picnum. Returns the number of
+/// walls that were modified.
+///
+/// Starting from `start`, the texture is propagated to every wall that begins
+/// where the previous one ends and uses the same picnum. Each wall continues
+/// the horizontal panning of the previous one, its `x_repeat` is adjusted to
+/// keep the same texel density, and the vertical repeat & panning are copied.
+pub fn align_walls_with<F>(map: &mut Map, start: WallId, tile_width: F) -> usize
+where
+    F: Fn(i16) -> u16,
+{
+    let walls = &mut map.sectors.walls;
+    if start < 0 || start as usize >= walls.len() {
+        return 0;
+    }
+    // walls indexed by their left vertex
+    let mut vertices: HashMap<_, Vec<usize>> = HashMap::new();
+    for (i, wall) in walls.iter().enumerate() {
+        vertices.entry((wall.x, wall.y)).or_default().push(i);
+    }
+    let picnum = walls[start as usize].picnum;
+    let mut visited = HashSet::new();
+    let mut stack = vec![start as usize];
+    visited.insert(start as usize);
+    let mut aligned = 0;
+    while let Some(curr) = stack.pop() {
+        let right = match walls.get(walls[curr].point2 as usize) {
+            Some(right) => right,
+            None => continue,
+        };
+        let length = walls[curr].length(right);
+        let [density, _] = walls[curr].texel_density(right);
+        let next_candidates = vertices
+            .get(&(right.x, right.y))
+            .cloned()
+            .unwrap_or_default();
+        let back = walls[curr].next_wall;
+        for next in next_candidates {
+            if next as i16 == back || walls[next].picnum != picnum || !visited.insert(next) {
+                continue;
+            }
+            let width = u32::from(tile_width(picnum).max(1));
+            let curr_wall = &walls[curr];
+            let x_panning =
+                (u32::from(curr_wall.x_panning) + 8 * u32::from(curr_wall.x_repeat)) % width;
+            let (y_repeat, y_panning) = (curr_wall.y_repeat, curr_wall.y_panning);
+            let next_length = walls
+                .get(walls[next].point2 as usize)
+                .map(|r| walls[next].length(r))
+                .unwrap_or(length);
+            let next_wall = &mut walls[next];
+            next_wall.x_panning = x_panning as u8;
+            next_wall.x_repeat = x_repeat_for_density(next_length, density);
+            next_wall.y_repeat = y_repeat;
+            next_wall.y_panning = y_panning;
+            aligned += 1;
+            stack.push(next);
+        }
+    }
+    aligned
+}
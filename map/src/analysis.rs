@@ -0,0 +1,255 @@
+//! Sector connectivity.
+//!
+//! Sectors are connected through portals (walls with a `next_sector`). These
+//! helpers answer which parts of a MAP the player can get to, to validate the
+//! playability of hand-made or generated MAPs.
+use crate::{
+    sector::{SectorId, WallStat},
+    tags, Map,
+};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Rules of movement between sectors.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Reachability {
+    /// Don't cross portals flagged as blocking (on either side).
+    pub respect_blocking: bool,
+
+    /// Don't enter locked doors (see [`locked_sectors`]).
+    pub respect_locked: bool,
+
+    /// Move between the sectors of paired teleporter and warp elevator
+    /// effectors (SE 7 & SE 17 sharing a hitag), as if they were connected
+    /// by portals.
+    pub follow_transports: bool,
+}
+
+impl Reachability {
+    /// Rules of a player walking around: blocking walls and locked doors
+    /// can't be crossed, but transports can be used.
+    pub fn walking() -> Self {
+        Self {
+            respect_blocking: true,
+            respect_locked: true,
+            follow_transports: true,
+        }
+    }
+}
+
+/// Returns the sectors reachable from `sector` through portals (including
+/// `sector` itself), in ascending order. Returns an empty list if `sector`
+/// is not a valid index.
+pub fn reachable_from(map: &Map, sector: SectorId, rules: &Reachability) -> Vec<SectorId> {
+    visit(map, sector, rules, |_| false)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, reached)| *reached)
+        .map(|(sector, _)| sector as SectorId)
+        .collect()
+}
+
+/// Returns the sectors that can't be reached from the player start sector,
+/// in ascending order. If the player start is outside of the MAP, every
+/// sector is orphaned.
+pub fn orphaned_sectors(map: &Map, rules: &Reachability) -> Vec<SectorId> {
+    visit(map, map.player.sector, rules, |_| false)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, reached)| !*reached)
+        .map(|(sector, _)| sector as SectorId)
+        .collect()
+}
+
+/// Returns the locked doors of the MAP, in ascending order: sectors tagged
+/// as [`SECTOR_LOCKED`](tags::duke::SECTOR_LOCKED), or holding an
+/// `ACTIVATORLOCKED` sprite (which locks its sector when the level starts).
+pub fn locked_sectors(map: &Map) -> Vec<SectorId> {
+    locked(map)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, locked)| *locked)
+        .map(|(sector, _)| sector as SectorId)
+        .collect()
+}
+
+fn locked(map: &Map) -> Vec<bool> {
+    use tags::duke;
+    let mut locked: Vec<_> = map
+        .sectors
+        .sectors()
+        .iter()
+        .map(|s| {
+            s.lotag != duke::ST_SECRET_PLACE
+                && s.lotag != duke::ST_END_OF_LEVEL
+                && s.lotag & duke::SECTOR_LOCKED != 0
+        })
+        .collect();
+    for sprite in &map.sprites {
+        if sprite.picnum == duke::ACTIVATORLOCKED && sprite.sectnum >= 0 {
+            if let Some(locked) = locked.get_mut(sprite.sectnum as usize) {
+                *locked = true;
+            }
+        }
+    }
+    locked
+}
+
+/// Groups of sectors linked by transport effectors.
+fn transports(map: &Map) -> Vec<Vec<usize>> {
+    use tags::duke;
+    let mut groups: BTreeMap<(i16, i16), Vec<usize>> = BTreeMap::new();
+    for sprite in &map.sprites {
+        let transport =
+            sprite.lotag == duke::SE_7_TELEPORT || sprite.lotag == duke::SE_17_WARP_ELEVATOR;
+        if sprite.picnum == duke::SECTOREFFECTOR && transport && sprite.sectnum >= 0 {
+            groups
+                .entry((sprite.lotag, sprite.hitag))
+                .or_default()
+                .push(sprite.sectnum as usize);
+        }
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+/// Sector adjacency lists, through portals. Each neighbour is listed once,
+/// in ascending order, regardless of the number of portals in between.
+pub fn adjacency(map: &Map) -> Vec<Vec<SectorId>> {
+    let sectors = map.sectors.sectors();
+    let walls = map.sectors.walls();
+    sectors
+        .iter()
+        .enumerate()
+        .map(|(sector, s)| {
+            let mut neighbours: Vec<_> = walls
+                .get(s.wall_range())
+                .unwrap_or(&[])
+                .iter()
+                .map(|w| w.next_sector)
+                .filter(|&n| n >= 0 && (n as usize) < sectors.len() && n as usize != sector)
+                .collect();
+            neighbours.sort_unstable();
+            neighbours.dedup();
+            neighbours
+        })
+        .collect()
+}
+
+/// Breadth-first search over portals. Returns a flag per sector. Locked
+/// sectors for which `unlocked` returns true can be entered.
+pub(crate) fn visit<F>(map: &Map, start: SectorId, rules: &Reachability, unlocked: F) -> Vec<bool>
+where
+    F: Fn(SectorId) -> bool,
+{
+    let sectors = map.sectors.sectors();
+    let walls = map.sectors.walls();
+    let mut reached = vec![false; sectors.len()];
+    if start < 0 || start as usize >= sectors.len() {
+        return reached;
+    }
+    let blocking = |wall: usize| {
+        rules.respect_blocking
+            && walls
+                .get(wall)
+                .is_some_and(|w| w.wall_stat.contains(WallStat::BLOCKING_CLIPMOVE_GETZRANGE))
+    };
+
+    let locked = if rules.respect_locked {
+        locked(map)
+    } else {
+        Vec::new()
+    };
+
+    let transports = if rules.follow_transports {
+        transports(map)
+    } else {
+        Vec::new()
+    };
+
+    let mut queue = VecDeque::new();
+    reached[start as usize] = true;
+    queue.push_back(start as usize);
+    while let Some(sector) = queue.pop_front() {
+        let portals = sectors[sector].wall_range().filter_map(|wall| {
+            let w = walls.get(wall)?;
+            let blocked = blocking(wall) || (w.next_wall >= 0 && blocking(w.next_wall as usize));
+            if w.next_sector < 0 || blocked {
+                None
+            } else {
+                Some(w.next_sector as usize)
+            }
+        });
+        let warps = transports
+            .iter()
+            .filter(|group| group.contains(&sector))
+            .flatten()
+            .copied();
+        for next in portals.chain(warps) {
+            if next >= sectors.len() || reached[next] {
+                continue;
+            }
+            if locked.get(next) == Some(&true) && !unlocked(next as SectorId) {
+                continue;
+            }
+            reached[next] = true;
+            queue.push_back(next);
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod test {
+    use super::{orphaned_sectors, reachable_from, Reachability};
+    use crate::{sector::WallStat, Map};
+
+    #[test]
+    fn reachable() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/SIMPLE0.MAP")).unwrap();
+        let rules = Reachability::default();
+        let sectors = map.sectors.sectors().len();
+        assert_eq!(
+            sectors,
+            reachable_from(&map, map.player.sector, &rules).len()
+        );
+        assert!(orphaned_sectors(&map, &rules).is_empty());
+        assert!(reachable_from(&map, -1, &rules).is_empty());
+    }
+
+    #[test]
+    fn transports() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let portals = Reachability::default();
+        let transports = Reachability {
+            follow_transports: true,
+            ..portals
+        };
+        let start = map.player.sector;
+        assert!(
+            reachable_from(&map, start, &portals).len()
+                < reachable_from(&map, start, &transports).len()
+        );
+    }
+
+    #[test]
+    fn blocking() {
+        let mut map = Map::from_slice(include_bytes!("../tests/maps/SIMPLE0.MAP")).unwrap();
+        // block every portal out of the player sector.
+        let start = map.player.sector;
+        let range = map.sectors.sectors()[start as usize].wall_range();
+        for wall in &mut map.sectors.walls[range] {
+            if wall.next_sector != -1 {
+                wall.wall_stat |= WallStat::BLOCKING_CLIPMOVE_GETZRANGE;
+            }
+        }
+        assert_eq!(
+            vec![start],
+            reachable_from(&map, start, &Reachability::walking())
+        );
+        let orphans = orphaned_sectors(&map, &Reachability::walking());
+        assert_eq!(map.sectors.sectors().len() - 1, orphans.len());
+        assert!(orphaned_sectors(&map, &Reachability::default()).is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+//! Positional audio helpers.
+//!
+//! Sound travels between sectors through portals. Comparing the length of
+//! the shortest path through portals with the straight-line distance gives
+//! a cheap occlusion estimate, consistent with the MAP geometry.
+use crate::{
+    sector::{SectorId, Wall},
+    Map,
+};
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// How sound travels between two points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Propagation {
+    /// Straight-line distance (in map XY units).
+    pub direct: f64,
+
+    /// Length of the shortest path through portals, going through their
+    /// midpoints. Never shorter than the direct distance.
+    pub path: f64,
+
+    /// Number of portals crossed along the path.
+    pub portals: usize,
+
+    /// From 0 (nothing in between) to 1 (fully occluded): how much longer
+    /// the path is than the direct distance (`1 - direct / path`).
+    pub occlusion: f64,
+}
+
+/// Estimates the propagation of a sound emitted at `from` and heard at `to`
+/// (XY map coordinates). Returns `None` if either point is outside of the
+/// MAP, or there is no path through portals between them.
+pub fn propagation(map: &Map, from: [i32; 2], to: [i32; 2]) -> Option<Propagation> {
+    let from_sector = map.sectors.sector_at(from[0], from[1])?;
+    let to_sector = map.sectors.sector_at(to[0], to[1])?;
+    let direct = distance(point(from), point(to));
+    let (path, portals) = if from_sector == to_sector {
+        (direct, 0)
+    } else {
+        shortest_path(map, from, from_sector, to, to_sector)?
+    };
+    let path = path.max(direct);
+    let occlusion = if path == 0.0 { 0.0 } else { 1.0 - direct / path };
+    Some(Propagation {
+        direct,
+        path,
+        portals,
+        occlusion,
+    })
+}
+
+// Dijkstra over portals, from the portals of the first sector to the point in
+// the last sector. Costs are rounded to integer map units.
+fn shortest_path(
+    map: &Map,
+    from: [i32; 2],
+    from_sector: SectorId,
+    to: [i32; 2],
+    to_sector: SectorId,
+) -> Option<(f64, usize)> {
+    let walls = map.sectors.walls();
+    let midpoint = |wall: usize| {
+        let left = &walls[wall];
+        let right = &walls[left.point2 as usize];
+        [
+            (f64::from(left.x) + f64::from(right.x)) / 2.0,
+            (f64::from(left.y) + f64::from(right.y)) / 2.0,
+        ]
+    };
+    // portals out of a sector, with the sector on their other side.
+    let portals = |sector: SectorId| -> Vec<(usize, SectorId)> {
+        match map.sectors.get(sector) {
+            Some((_, sector_walls)) => sector_walls
+                .filter(|(_, left, _)| is_portal(map, left))
+                .map(|(wall, left, _)| (wall as usize, left.next_sector))
+                .collect(),
+            None => Vec::new(),
+        }
+    };
+    // `None` is the end point.
+    let mut heap = BinaryHeap::new();
+    let mut done = vec![false; walls.len()];
+    for (wall, next) in portals(from_sector) {
+        let cost = distance(point(from), midpoint(wall));
+        heap.push(Reverse((cost.round() as u64, 1, Some((wall, next)))));
+    }
+    while let Some(Reverse((cost, crossed, node))) = heap.pop() {
+        let (wall, sector) = match node {
+            Some(node) => node,
+            None => return Some((cost as f64, crossed)),
+        };
+        if std::mem::replace(&mut done[wall], true) {
+            continue;
+        }
+        let p = midpoint(wall);
+        if sector == to_sector {
+            let cost = cost + distance(p, point(to)).round() as u64;
+            heap.push(Reverse((cost, crossed, None)));
+            continue;
+        }
+        let back = walls[wall].next_wall;
+        for (next_wall, next_sector) in portals(sector) {
+            if next_wall as i16 == back || done[next_wall] {
+                continue;
+            }
+            let cost = cost + distance(p, midpoint(next_wall)).round() as u64;
+            heap.push(Reverse((cost, crossed + 1, Some((next_wall, next_sector)))));
+        }
+    }
+    None
+}
+
+fn is_portal(map: &Map, wall: &Wall) -> bool {
+    let sectors = map.sectors.sectors().len();
+    wall.next_sector >= 0
+        && (wall.next_sector as usize) < sectors
+        && (wall.point2 as usize) < map.sectors.walls().len()
+}
+
+fn point([x, y]: [i32; 2]) -> [f64; 2] {
+    [f64::from(x), f64::from(y)]
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+#[cfg(test)]
+mod test {
+    use super::propagation;
+    use crate::Map;
+
+    #[test]
+    fn same_sector() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/SIMPLE0.MAP")).unwrap();
+        let p = [map.player.pos_x, map.player.pos_y];
+        let prop = propagation(&map, p, p).unwrap();
+        assert_eq!((0.0, 0, 0.0), (prop.path, prop.portals, prop.occlusion));
+        assert_eq!(None, propagation(&map, p, [i32::MAX, i32::MAX]));
+    }
+
+    #[test]
+    fn through_portal() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/SIMPLE0.MAP")).unwrap();
+        let from = [map.player.pos_x, map.player.pos_y];
+        let (_, mut walls) = map.sectors.get(map.player.sector).unwrap();
+        let (_, left, right) = walls.find(|(_, l, _)| l.next_sector >= 0).unwrap();
+        // a point just across the portal.
+        let mid = [(left.x + right.x) / 2, (left.y + right.y) / 2];
+        let [nx, ny] = left.normal(right);
+        let to = [-16.0, 16.0]
+            .iter()
+            .map(|d| [mid[0] + (nx * d) as i32, mid[1] + (ny * d) as i32])
+            .find(|p| map.sectors.sector_at(p[0], p[1]) == Some(left.next_sector))
+            .unwrap();
+        let prop = propagation(&map, from, to).unwrap();
+        assert!(prop.portals >= 1);
+        assert!(prop.path >= prop.direct);
+        assert!((0.0..1.0).contains(&prop.occlusion));
+    }
+}
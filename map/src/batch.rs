@@ -0,0 +1,129 @@
+//! Parallel loading of many MAP files.
+//!
+//! Requires the `rayon` feature.
+use crate::{
+    grp::{self, Grp},
+    Error, Map,
+};
+use rayon::prelude::*;
+use std::{fmt, fs, io, path::Path};
+
+/// Result of loading a single MAP.
+#[derive(Debug)]
+pub struct BatchEntry {
+    /// Path relative to the loaded directory, or name within the GRP.
+    pub name: String,
+    pub result: Result<Map, Error>,
+}
+
+/// Loaded MAP files, sorted by name.
+#[derive(Debug, Default)]
+pub struct Batch {
+    pub entries: Vec<BatchEntry>,
+}
+
+/// Summary of a [`Batch`](Batch).
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Summary {
+    pub loaded: usize,
+    pub failed: usize,
+    pub sectors: usize,
+    pub walls: usize,
+    pub sprites: usize,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} MAP(s) loaded, {} failed ({} sectors, {} walls, {} sprites)",
+            self.loaded, self.failed, self.sectors, self.walls, self.sprites
+        )
+    }
+}
+
+impl Batch {
+    fn parse(files: Vec<(String, Result<Vec<u8>, Error>)>) -> Self {
+        let mut entries: Vec<_> = files
+            .into_par_iter()
+            .map(|(name, bytes)| BatchEntry {
+                name,
+                result: bytes.and_then(|b| Map::from_slice(&b)),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Self { entries }
+    }
+
+    /// Successfully loaded MAPs.
+    pub fn maps(&self) -> impl Iterator<Item = (&str, &Map)> {
+        self.entries
+            .iter()
+            .filter_map(|e| e.result.as_ref().ok().map(|m| (e.name.as_str(), m)))
+    }
+
+    pub fn summary(&self) -> Summary {
+        self.entries
+            .iter()
+            .fold(Summary::default(), |mut summary, entry| {
+                match &entry.result {
+                    Ok(map) => {
+                        summary.loaded += 1;
+                        summary.sectors += map.sectors.sectors().len();
+                        summary.walls += map.sectors.walls().len();
+                        summary.sprites += map.sprites.len();
+                    }
+                    Err(_) => summary.failed += 1,
+                }
+                summary
+            })
+    }
+}
+
+/// Loads, in parallel, all the MAP files (`.MAP` extension, case insensitive)
+/// in a directory and its subdirectories.
+pub fn load_dir<P: AsRef<Path>>(path: P) -> io::Result<Batch> {
+    let root = path.as_ref();
+    let mut paths = Vec::new();
+    find_maps(root, &mut paths)?;
+    let files = paths
+        .into_par_iter()
+        .map(|path| {
+            let name = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            (name, fs::read(&path).map_err(Error::from))
+        })
+        .collect();
+    Ok(Batch::parse(files))
+}
+
+/// Loads, in parallel, all the MAP files in a GRP archive.
+pub fn load_grp<P: AsRef<Path>>(path: P) -> Result<Batch, Error> {
+    let grp = Grp::from_file(path)?;
+    let files = grp
+        .entries
+        .into_iter()
+        .filter(|e| e.is_map())
+        .map(|e| (e.name, Ok(e.data)))
+        .collect();
+    Ok(Batch::parse(files))
+}
+
+fn find_maps(dir: &Path, paths: &mut Vec<std::path::PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_maps(&path, paths)?;
+        } else if path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(grp::is_map_name)
+        {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! Conversion of MAPs saved by modern editors (versions 8 & 9) to version 7,
+//! so they can be loaded by classic ports.
+//!
+//! Versions 8 & 9 share the layout of version 7, but allow more elements, and
+//! EDuke32 uses some flags reserved in version 7 (most notably for TROR, its
+//! *true room over room* extension). Downgrading clears those flags and
+//! reports what was lost.
+use crate::{
+    sector::{SectorStat, WallStat},
+    sprite::SpriteStat,
+    Error, Map,
+};
+use std::io::Write;
+
+/// Most sectors supported by version 7.
+pub const V7_MAX_SECTORS: usize = 1024;
+
+/// Most walls supported by version 7.
+pub const V7_MAX_WALLS: usize = 8192;
+
+/// Most sprites supported by version 7.
+pub const V7_MAX_SPRITES: usize = 4096;
+
+// ceiling or floor linked to the sector above or below (TROR).
+const YAX_SECTOR: u16 = 1 << 10;
+
+// wall linked to a wall of the sector above (bit 10) or below (bit 11).
+const YAX_WALL: u16 = 0b11 << 10;
+
+/// Data lost when downgrading a MAP.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct DowngradeReport {
+    /// TROR ceilings & floors (now regular, unlinked surfaces).
+    pub tror_surfaces: usize,
+
+    /// Walls with TROR links.
+    pub tror_walls: usize,
+
+    /// Ceilings & floors with other flags reserved in version 7.
+    pub sector_flags: usize,
+
+    /// Walls with other flags reserved in version 7.
+    pub wall_flags: usize,
+
+    /// Sprites with flags reserved in version 7.
+    pub sprite_flags: usize,
+}
+
+impl DowngradeReport {
+    /// True if the MAP didn't use any feature missing from version 7.
+    pub fn is_lossless(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Map {
+    /// Converts the MAP to version 7, clearing the flags the version doesn't
+    /// support.
+    ///
+    /// Fails with [`Error::LimitExceeded`] (leaving the MAP untouched) if the
+    /// MAP has more elements than version 7 supports.
+    pub fn downgrade_to_v7(&mut self) -> Result<DowngradeReport, Error> {
+        if self.sectors.sectors.len() > V7_MAX_SECTORS {
+            return Err(Error::LimitExceeded("sectors"));
+        }
+        if self.sectors.walls.len() > V7_MAX_WALLS {
+            return Err(Error::LimitExceeded("walls"));
+        }
+        if self.sprites.len() > V7_MAX_SPRITES {
+            return Err(Error::LimitExceeded("sprites"));
+        }
+        let mut report = DowngradeReport::default();
+        for sector in &mut self.sectors.sectors {
+            let planes = [
+                (&mut sector.ceiling_stat, &mut sector.ceiling_xpanning),
+                (&mut sector.floor_stat, &mut sector.floor_xpanning),
+            ];
+            for (stat, xpanning) in planes {
+                if stat.bits() & YAX_SECTOR != 0 {
+                    // EDuke32 keeps the TROR bunch number in the panning.
+                    *xpanning = 0;
+                    report.tror_surfaces += 1;
+                }
+                if stat.bits() & !YAX_SECTOR & SectorStat::RESERVED.bits() != 0 {
+                    report.sector_flags += 1;
+                }
+                stat.remove(SectorStat::RESERVED);
+            }
+        }
+        for wall in &mut self.sectors.walls {
+            let stat = &mut wall.wall_stat;
+            if stat.bits() & YAX_WALL != 0 {
+                report.tror_walls += 1;
+            }
+            if stat.bits() & !YAX_WALL & WallStat::RESERVED.bits() != 0 {
+                report.wall_flags += 1;
+            }
+            stat.remove(WallStat::RESERVED);
+        }
+        for sprite in &mut self.sprites {
+            if sprite.sprite_stat.intersects(SpriteStat::RESERVED) {
+                report.sprite_flags += 1;
+            }
+            sprite.sprite_stat.remove(SpriteStat::RESERVED);
+        }
+        self.version = 7;
+        Ok(report)
+    }
+
+    /// Writes a version 7 copy of the MAP (see
+    /// [`downgrade_to_v7`](Self::downgrade_to_v7)) to a writer.
+    pub fn to_writer_v7<W: Write>(&self, writer: &mut W) -> Result<DowngradeReport, Error> {
+        let mut map = self.clone();
+        let report = map.downgrade_to_v7()?;
+        map.to_writer(writer)?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DowngradeReport;
+    use crate::{
+        sector::{SectorStat, WallStat},
+        sprite::SpriteStat,
+        Error, Map,
+    };
+
+    #[test]
+    fn downgrade() {
+        let mut map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        map.version = 9;
+        let original = map.clone();
+        let sector = &mut map.sectors.sectors[0];
+        sector.ceiling_stat |= SectorStat::from_bits(1 << 10).unwrap();
+        sector.ceiling_xpanning = 3;
+        sector.floor_stat |= SectorStat::from_bits(1 << 8).unwrap();
+        map.sectors.walls[0].wall_stat |= WallStat::from_bits(1 << 11).unwrap();
+        map.sprites[0].sprite_stat |= SpriteStat::from_bits(1 << 12).unwrap();
+
+        let mut bytes = Vec::new();
+        let report = map.to_writer_v7(&mut bytes).unwrap();
+        assert_eq!(
+            DowngradeReport {
+                tror_surfaces: 1,
+                tror_walls: 1,
+                sector_flags: 1,
+                wall_flags: 0,
+                sprite_flags: 1,
+            },
+            report
+        );
+        let mut downgraded = Map::from_slice(&bytes).unwrap();
+        assert_eq!(7, downgraded.version);
+        assert_eq!(0, downgraded.sectors.sectors[0].ceiling_xpanning);
+        assert!(downgraded.downgrade_to_v7().unwrap().is_lossless());
+
+        let mut expected = original;
+        expected.version = 7;
+        expected.sectors.sectors[0].ceiling_xpanning = 0;
+        assert_eq!(expected.to_vec().unwrap(), bytes);
+    }
+
+    #[test]
+    fn limits() {
+        let mut map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let sprite = map.sprites[0].clone();
+        map.sprites.resize(super::V7_MAX_SPRITES + 1, sprite);
+        assert!(matches!(
+            map.downgrade_to_v7(),
+            Err(Error::LimitExceeded("sprites"))
+        ));
+        assert_eq!(super::V7_MAX_SPRITES + 1, map.sprites.len());
+    }
+}
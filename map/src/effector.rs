@@ -0,0 +1,142 @@
+//! Engine plumbing sprites.
+//!
+//! Besides enemies, items and decorations, MAPs are full of invisible
+//! sprites that drive game logic: sector effectors, activators, sound
+//! emitters... These are identified by picnum (and tags), following the
+//! conventions of each game.
+use crate::{
+    sprite::{Sprite, SpriteId},
+    tags::{self, GameProfile},
+    Map,
+};
+
+/// Kind of an effector sprite.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum EffectorKind {
+    /// Sector effector (`SECTOREFFECTOR`), with its effect number (lotag).
+    SectorEffector(i16),
+
+    /// Triggers effectors with a matching lotag (`ACTIVATOR` &
+    /// `ACTIVATORLOCKED`).
+    Activator { locked: bool },
+
+    /// Triggers effectors when the player enters its sector (`TOUCHPLATE`).
+    Touchplate,
+
+    /// Ambient sound or music (see [`AmbientSound`](crate::sound::AmbientSound)).
+    Sound,
+
+    /// Path node of subways & trains (`LOCATORS`).
+    Locator,
+
+    /// Sector shade cycler (`CYCLER`).
+    Cycler,
+
+    /// Triggers effectors when destroyed (`MASTERSWITCH`).
+    MasterSwitch,
+
+    /// Respawns sprites (`RESPAWN`).
+    Respawn,
+
+    /// Effector speed modifier (`GPSPEED`).
+    Speed,
+
+    /// Shadow Warrior sprite tag marker (`ST1` & `ST2`), with its hitag.
+    Marker(i16),
+}
+
+impl Sprite {
+    /// Decodes the effector kind of the sprite, following the conventions of
+    /// the given game. Returns `None` for sprites that aren't effectors.
+    pub fn effector_kind(&self, profile: GameProfile) -> Option<EffectorKind> {
+        use tags::duke;
+        match profile {
+            GameProfile::Duke3D | GameProfile::RedneckRampage => match self.picnum {
+                duke::SECTOREFFECTOR => Some(EffectorKind::SectorEffector(self.lotag)),
+                duke::ACTIVATOR => Some(EffectorKind::Activator { locked: false }),
+                duke::ACTIVATORLOCKED => Some(EffectorKind::Activator { locked: true }),
+                duke::TOUCHPLATE => Some(EffectorKind::Touchplate),
+                duke::MUSICANDSFX => Some(EffectorKind::Sound),
+                duke::LOCATORS => Some(EffectorKind::Locator),
+                duke::CYCLER => Some(EffectorKind::Cycler),
+                duke::MASTERSWITCH => Some(EffectorKind::MasterSwitch),
+                duke::RESPAWN => Some(EffectorKind::Respawn),
+                duke::GPSPEED => Some(EffectorKind::Speed),
+                _ => None,
+            },
+            GameProfile::ShadowWarrior => match self.picnum {
+                tags::sw::ST1 if self.hitag == tags::sw::SOUND_SPOT => Some(EffectorKind::Sound),
+                tags::sw::ST1 | tags::sw::ST2 => Some(EffectorKind::Marker(self.hitag)),
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns true if the sprite is an effector in the given game (see
+    /// [`effector_kind`](Self::effector_kind)).
+    pub fn is_effector(&self, profile: GameProfile) -> bool {
+        self.effector_kind(profile).is_some()
+    }
+}
+
+impl Map {
+    /// Returns the effector sprites of the map, along with their index and
+    /// kind.
+    pub fn effectors(
+        &self,
+        profile: GameProfile,
+    ) -> impl Iterator<Item = (SpriteId, &Sprite, EffectorKind)> {
+        self.sprites
+            .iter()
+            .enumerate()
+            .filter_map(move |(id, sprite)| {
+                let kind = sprite.effector_kind(profile)?;
+                Some((id as SpriteId, sprite, kind))
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EffectorKind;
+    use crate::{
+        sprite::Sprite,
+        tags::{self, GameProfile},
+        Map,
+    };
+
+    #[test]
+    fn classify() {
+        let mut sprite = Sprite::new(0, 0, 0, 0);
+        sprite.picnum = tags::duke::SECTOREFFECTOR;
+        sprite.lotag = 7;
+        assert_eq!(
+            Some(EffectorKind::SectorEffector(7)),
+            sprite.effector_kind(GameProfile::Duke3D)
+        );
+        assert!(!sprite.is_effector(GameProfile::ShadowWarrior));
+        sprite.picnum = tags::sw::ST1;
+        sprite.hitag = tags::sw::SOUND_SPOT;
+        assert_eq!(
+            Some(EffectorKind::Sound),
+            sprite.effector_kind(GameProfile::ShadowWarrior)
+        );
+        sprite.picnum = 1405;
+        assert!(!sprite.is_effector(GameProfile::Duke3D));
+    }
+
+    #[test]
+    fn effectors() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let effectors: Vec<_> = map.effectors(GameProfile::Duke3D).collect();
+        assert!(!effectors.is_empty());
+        assert!(effectors
+            .iter()
+            .all(|(_, s, _)| s.picnum <= tags::duke::GPSPEED));
+        let sounds = effectors
+            .iter()
+            .filter(|(_, _, kind)| *kind == EffectorKind::Sound)
+            .count();
+        assert_eq!(map.ambient_sounds(GameProfile::Duke3D).len(), sounds);
+    }
+}
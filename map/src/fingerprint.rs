@@ -0,0 +1,137 @@
+//! Content hashes of MAPs, to find duplicates in map archives.
+//!
+//! The [`Fingerprint`](Fingerprint) of a MAP only depends on what the MAP
+//! looks and plays like: the file version, the padding bytes, the order of
+//! the sprites, the (index based) sprite `owner` field and the
+//! [trailing data](crate::Map::trailing_data) are ignored. The hash (64-bit
+//! FNV-1a) is stable across platforms and releases.
+use crate::Map;
+use std::{convert::TryFrom, fmt, io::Write};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Content hash of a MAP.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(pub u64);
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(FNV_OFFSET)
+    }
+}
+
+impl Write for Fnv {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for byte in buf {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(FNV_PRIME);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Map {
+    /// Computes the content hash of the MAP.
+    pub fn fingerprint(&self) -> Fingerprint {
+        // writing to the hasher never fails. Sectors & walls are hashed one
+        // by one rather than with Sectors::to_writer, which fails on MAPs
+        // exceeding the format limits.
+        let mut hasher = Fnv::default();
+        self.player.to_writer(&mut hasher).unwrap();
+        write_len(&mut hasher, self.sectors.sectors.len());
+        for sector in &self.sectors.sectors {
+            let mut sector = sector.clone();
+            sector.filler = [0];
+            sector.to_writer(&mut hasher).unwrap();
+        }
+        write_len(&mut hasher, self.sectors.walls.len());
+        for wall in &self.sectors.walls {
+            wall.to_writer(&mut hasher).unwrap();
+        }
+
+        // sprites as a multiset
+        let mut sprites: Vec<u64> = self
+            .sprites
+            .iter()
+            .map(|sprite| {
+                let mut sprite = sprite.clone();
+                sprite.filler = [0];
+                sprite.owner = -1;
+                let mut hasher = Fnv::default();
+                sprite.to_writer(&mut hasher).unwrap();
+                hasher.0
+            })
+            .collect();
+        sprites.sort_unstable();
+        hasher
+            .write_all(&(sprites.len() as u64).to_le_bytes())
+            .unwrap();
+        for sprite in sprites {
+            hasher.write_all(&sprite.to_le_bytes()).unwrap();
+        }
+        Fingerprint(hasher.0)
+    }
+}
+
+// Hashes a sector or wall count like the MAP format stores it (u16).
+// Counts beyond the format limits are followed by the full count.
+fn write_len(hasher: &mut Fnv, len: usize) {
+    match u16::try_from(len) {
+        Ok(len) => hasher.write_all(&len.to_le_bytes()).unwrap(),
+        Err(_) => {
+            hasher.write_all(&u16::MAX.to_le_bytes()).unwrap();
+            hasher.write_all(&(len as u64).to_le_bytes()).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Map;
+
+    #[test]
+    fn fingerprint() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let fingerprint = map.fingerprint();
+        // hashes must not change between releases
+        assert_eq!("c57e568ca5d64bcf", fingerprint.to_string());
+
+        let mut other = map.clone();
+        other.version = 9;
+        other.sprites.reverse();
+        other.sectors.sectors[3].filler = [42];
+        other.sprites[7].owner = 12;
+        assert_eq!(fingerprint, other.fingerprint());
+
+        other.sprites[7].x += 1;
+        assert_ne!(fingerprint, other.fingerprint());
+        let mut other = map;
+        other.sectors.sectors[3].floor_z += 1;
+        assert_ne!(fingerprint, other.fingerprint());
+    }
+
+    #[test]
+    fn exceeding_limits() {
+        let mut map = Map::from_slice(include_bytes!("../tests/maps/SIMPLE0.MAP")).unwrap();
+        let wall = map.sectors.walls[0].clone();
+        map.sectors
+            .walls
+            .resize(usize::from(u16::MAX) + 1, wall.clone());
+        assert!(map.to_vec().is_err());
+        let fingerprint = map.fingerprint();
+        map.sectors.walls.push(wall);
+        assert_ne!(fingerprint, map.fingerprint());
+    }
+}
@@ -0,0 +1,102 @@
+//! GRP archives.
+//!
+//! GRP files bundle the resources of Build games (MAPs, ART, palettes...). They
+//! start with a `KenSilverman` signature, followed by the number of files, a
+//! table of 12-byte names and sizes, and the contents of all files, in order.
+use crate::Error;
+use byteorder::{ReadBytesExt, LE};
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+const MAGIC: &[u8; 12] = b"KenSilverman";
+
+/// File stored in a GRP archive.
+#[derive(Debug, Clone)]
+pub struct GrpEntry {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl GrpEntry {
+    /// Returns true if the file has a `.MAP` extension (case insensitive).
+    pub fn is_map(&self) -> bool {
+        is_map_name(&self.name)
+    }
+}
+
+/// Contents of a GRP archive.
+#[derive(Debug, Clone, Default)]
+pub struct Grp {
+    pub entries: Vec<GrpEntry>,
+}
+
+impl Grp {
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let mut magic = [0; 12];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::InvalidGrp);
+        }
+        let count = reader.read_u32::<LE>()?;
+        let mut table = Vec::new();
+        for _ in 0..count {
+            let mut name = [0; 12];
+            reader.read_exact(&mut name)?;
+            let name = name.split(|b| *b == 0).next().unwrap_or_default();
+            let size = reader.read_u32::<LE>()?;
+            table.push((String::from_utf8_lossy(name).into_owned(), size));
+        }
+        let entries = table
+            .into_iter()
+            .map(|(name, size)| {
+                let mut data = Vec::new();
+                reader.take(u64::from(size)).read_to_end(&mut data)?;
+                if data.len() != size as usize {
+                    return Err(Error::InvalidGrp);
+                }
+                Ok(GrpEntry { name, data })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { entries })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self, Error> {
+        Self::from_reader(&mut BufReader::new(File::open(file)?))
+    }
+
+    /// Returns the MAP files of the archive.
+    pub fn maps(&self) -> impl Iterator<Item = &GrpEntry> {
+        self.entries.iter().filter(|e| e.is_map())
+    }
+}
+
+pub(crate) fn is_map_name(name: &str) -> bool {
+    name.to_ascii_uppercase().ends_with(".MAP")
+}
+
+#[cfg(test)]
+mod test {
+    use super::Grp;
+
+    #[test]
+    fn parse() {
+        let mut bytes = b"KenSilverman".to_vec();
+        bytes.extend(&2u32.to_le_bytes());
+        bytes.extend(b"E1L1.MAP\0\0\0\0");
+        bytes.extend(&3u32.to_le_bytes());
+        bytes.extend(b"TILES000.ART");
+        bytes.extend(&1u32.to_le_bytes());
+        bytes.extend(b"abcd");
+        let grp = Grp::from_reader(&mut &bytes[..]).unwrap();
+        assert_eq!(2, grp.entries.len());
+        assert_eq!("TILES000.ART", grp.entries[1].name);
+        assert_eq!(b"d", &grp.entries[1].data[..]);
+        let maps: Vec<_> = grp.maps().map(|e| e.name.as_str()).collect();
+        assert_eq!(vec!["E1L1.MAP"], maps);
+        assert!(Grp::from_reader(&mut &bytes[..bytes.len() - 1]).is_err());
+        assert!(Grp::from_reader(&mut &b"KenSilverma"[..]).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Lock & key dependencies.
+//!
+//! In Duke Nukem 3D, locked doors are sectors holding an `ACTIVATORLOCKED`
+//! sprite. Using a key card switch (a wall, or a wall-aligned sprite) with a
+//! matching lotag unlocks them, once the player holds the key card of the
+//! same color (its palette).
+//!
+//! Only Duke Nukem 3D conventions are known. The graphs of other games are
+//! empty.
+use crate::{
+    analysis::{self, Reachability},
+    sector::{SectorId, WallId},
+    sprite::SpriteId,
+    tags::{self, GameProfile},
+    Map,
+};
+
+/// Key card colors.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum KeyColor {
+    Blue,
+    Red,
+    Yellow,
+}
+
+impl KeyColor {
+    /// Color of a key card or key card switch of the given palette.
+    pub fn from_pal(pal: u8) -> Option<Self> {
+        let colors = [Self::Blue, Self::Red, Self::Yellow];
+        let index = tags::duke::KEY_PALS.iter().position(|p| *p == pal)?;
+        Some(colors[index])
+    }
+}
+
+/// Key card sprite.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Key {
+    pub sprite: SpriteId,
+    pub sector: SectorId,
+    pub color: KeyColor,
+}
+
+/// Element of the MAP used as a key card switch.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Switch {
+    Wall(WallId),
+    Sprite(SpriteId),
+}
+
+/// Key card switch, and the doors it unlocks.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Lock {
+    pub switch: Switch,
+
+    /// Sector the switch is used from.
+    pub sector: SectorId,
+    pub color: KeyColor,
+
+    /// Locked sectors unlocked by the switch, in ascending order.
+    pub doors: Vec<SectorId>,
+}
+
+/// Keys and locks of a MAP.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct KeyGraph {
+    pub keys: Vec<Key>,
+    pub locks: Vec<Lock>,
+}
+
+/// Keys picked up and locks opened in one round of exploration.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Step {
+    /// Indices of [`KeyGraph::keys`].
+    pub keys: Vec<usize>,
+
+    /// Indices of [`KeyGraph::locks`], opened with keys of this or previous
+    /// steps.
+    pub locks: Vec<usize>,
+}
+
+/// Outcome of [`KeyGraph::solve`].
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Solution {
+    /// Rounds of exploration from the player start. The keys of a step
+    /// depend on the locks of the previous ones.
+    pub steps: Vec<Step>,
+
+    /// Locks that can't be opened (their switch is unreachable, or there is
+    /// no reachable key of their color).
+    pub sealed: Vec<usize>,
+
+    /// Whether an end of level button can be reached. `None` if the MAP has
+    /// none.
+    pub exit: Option<bool>,
+}
+
+impl Solution {
+    /// Returns true if every lock can be opened and the exit (if any) can be
+    /// reached.
+    pub fn is_solvable(&self) -> bool {
+        self.sealed.is_empty() && self.exit != Some(false)
+    }
+}
+
+impl KeyGraph {
+    /// Extracts the keys and locks of the map, following the conventions of
+    /// the given game.
+    pub fn new(map: &Map, profile: GameProfile) -> Self {
+        if profile != GameProfile::Duke3D {
+            return Self::default();
+        }
+        let keys = map
+            .sprites
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.picnum == tags::duke::ACCESSCARD)
+            .filter_map(|(id, s)| {
+                Some(Key {
+                    sprite: id as SpriteId,
+                    sector: s.sectnum,
+                    color: KeyColor::from_pal(s.pal)?,
+                })
+            })
+            .collect();
+
+        let is_switch =
+            |picnum| picnum == tags::duke::ACCESSSWITCH || picnum == tags::duke::ACCESSSWITCH2;
+        let doors = |lotag: i16| {
+            let mut doors: Vec<_> = map
+                .sprites
+                .iter()
+                .filter(|s| s.picnum == tags::duke::ACTIVATORLOCKED && s.lotag == lotag)
+                .map(|s| s.sectnum)
+                .filter(|&s| s >= 0 && (s as usize) < map.sectors.sectors().len())
+                .collect();
+            doors.sort_unstable();
+            doors.dedup();
+            doors
+        };
+        let walls = map
+            .sectors
+            .walls()
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| is_switch(w.picnum))
+            .filter_map(|(id, w)| {
+                let wall = id as WallId;
+                Some(Lock {
+                    switch: Switch::Wall(wall),
+                    sector: map.sectors.sector_of_wall(wall)?,
+                    color: KeyColor::from_pal(w.pal)?,
+                    doors: doors(w.lotag),
+                })
+            });
+        let sprites = map
+            .sprites
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| is_switch(s.picnum))
+            .filter_map(|(id, s)| {
+                Some(Lock {
+                    switch: Switch::Sprite(id as SpriteId),
+                    sector: s.sectnum,
+                    color: KeyColor::from_pal(s.pal)?,
+                    doors: doors(s.lotag),
+                })
+            });
+        let locks = walls.chain(sprites).collect();
+        Self { keys, locks }
+    }
+
+    /// Explores the map from the player start, picking up keys and opening
+    /// locks until no more progress can be made.
+    pub fn solve(&self, map: &Map) -> Solution {
+        // blocking walls are often breakable (windows, vents), so they are
+        // ignored.
+        let rules = Reachability {
+            respect_blocking: false,
+            ..Reachability::walking()
+        };
+        let mut held = Vec::new();
+        let mut opened = vec![false; self.locks.len()];
+        let mut picked = vec![false; self.keys.len()];
+        let mut steps = Vec::new();
+        let reached = loop {
+            let unlocked = |sector: SectorId| {
+                self.locks
+                    .iter()
+                    .zip(&opened)
+                    .any(|(lock, open)| *open && lock.doors.contains(&sector))
+            };
+            let reached = analysis::visit(map, map.player.sector, &rules, unlocked);
+            let is_reached = |s: SectorId| s >= 0 && reached.get(s as usize) == Some(&true);
+
+            let mut step = Step::default();
+            for (index, key) in self.keys.iter().enumerate() {
+                if !picked[index] && is_reached(key.sector) {
+                    picked[index] = true;
+                    held.push(key.color);
+                    step.keys.push(index);
+                }
+            }
+            for (index, lock) in self.locks.iter().enumerate() {
+                if !opened[index] && is_reached(lock.sector) && held.contains(&lock.color) {
+                    opened[index] = true;
+                    step.locks.push(index);
+                }
+            }
+            if step == Step::default() {
+                break reached;
+            }
+            steps.push(step);
+        };
+
+        let buttons: Vec<_> = map
+            .sprites
+            .iter()
+            .filter(|s| s.picnum == tags::duke::NUKEBUTTON)
+            .map(|s| s.sectnum)
+            .collect();
+        let exit = if buttons.is_empty() {
+            None
+        } else {
+            Some(
+                buttons
+                    .iter()
+                    .any(|&s| s >= 0 && reached.get(s as usize) == Some(&true)),
+            )
+        };
+        Solution {
+            steps,
+            sealed: (0..self.locks.len()).filter(|i| !opened[*i]).collect(),
+            exit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{KeyColor, KeyGraph};
+    use crate::{tags::GameProfile, Map};
+
+    #[test]
+    fn key_colors() {
+        assert_eq!(Some(KeyColor::Blue), KeyColor::from_pal(0));
+        assert_eq!(Some(KeyColor::Yellow), KeyColor::from_pal(23));
+        assert_eq!(None, KeyColor::from_pal(1));
+    }
+
+    #[test]
+    fn e1l1() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let graph = KeyGraph::new(&map, GameProfile::Duke3D);
+        assert!(!graph.keys.is_empty());
+        assert_eq!(KeyColor::Red, graph.locks[0].color);
+        assert_eq!(2, graph.locks[0].doors.len());
+        let solution = graph.solve(&map);
+        assert_eq!(vec![0], solution.steps.last().unwrap().locks);
+        assert!(solution.is_solvable(), "{:?}", solution);
+        assert!(KeyGraph::new(&map, GameProfile::ShadowWarrior)
+            .keys
+            .is_empty());
+    }
+}
@@ -12,35 +12,158 @@
 //!
 //! println!("Player = {:?}", player);
 //! ```
+//!
+//! # Features
+//! - `rayon`: parallel loading of MAP collections (the `batch` module).
+//! - `tracing`: structured [`tracing`](https://docs.rs/tracing) spans
+//!   around parsing, one per file section, instead of `log` messages.
+//! - `std` (default): parsing & writing MAPs, and every module other than
+//!   the MAP types ([`player`](player), [`sector`](sector),
+//!   [`sprite`](sprite)) and tag decoding. Without it the crate is `no_std`
+//!   (it only needs `alloc`), and geometry helpers needing floating point
+//!   functions (`sqrt`, `round`...) are left out.
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
 
 #[cfg(feature = "v6")]
 compile_error!("Feature flag 'v6' is not yet implemented.");
 
+#[cfg(feature = "std")]
+use crate::{
+    cancel::CancellationToken,
+    progress::{Progress, Reporter, Section},
+};
 use crate::{player::Player, sector::Sectors, sprite::Sprite};
-use byteorder::{ReadBytesExt, LE};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+#[cfg(all(feature = "std", not(feature = "tracing")))]
 use log::info;
+#[cfg(feature = "std")]
 use std::{
     fs::File,
-    io::{Cursor, Read},
+    io::{BufWriter, Cursor, Read, Write},
     path::Path,
 };
 use thiserror::Error;
 
+#[cfg(feature = "std")]
+pub use outline::{outline, Polygon};
+#[cfg(feature = "std")]
+pub use similarity::similarity;
+
+#[cfg(feature = "std")]
+pub mod align;
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod audio;
+#[cfg(feature = "rayon")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod cancel;
+#[cfg(feature = "std")]
+pub mod downgrade;
+pub mod effector;
+#[cfg(feature = "std")]
+pub mod fingerprint;
+#[cfg(feature = "std")]
+pub mod grp;
+#[cfg(feature = "std")]
+pub mod keys;
+#[cfg(feature = "std")]
+pub mod lightmap;
+#[cfg(feature = "std")]
+pub mod maphack;
+#[cfg(feature = "std")]
+pub mod math;
+#[cfg(feature = "std")]
+pub mod merge;
+#[cfg(feature = "std")]
+pub mod names;
+#[cfg(feature = "std")]
+pub mod navmesh;
+#[cfg(feature = "std")]
+pub mod options;
+#[cfg(feature = "std")]
+mod outline;
 pub mod player;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "std")]
+pub mod pvs;
+#[cfg(feature = "std")]
+pub mod quirks;
+#[cfg(feature = "std")]
+pub mod renumber;
+#[cfg(feature = "std")]
+pub mod repair;
 pub mod sector;
+#[cfg(feature = "std")]
+pub mod select;
+#[cfg(feature = "std")]
+mod similarity;
+#[cfg(feature = "std")]
+pub mod snapshot;
+pub mod sound;
 pub mod sprite;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod svg;
+pub mod tags;
+#[cfg(feature = "std")]
+pub mod tracked;
+#[cfg(feature = "std")]
+pub mod transform;
+#[cfg(feature = "std")]
+pub mod triangulate;
+pub mod units;
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Unsupported MAP file version: {0}")]
     UnsupportedVersion(i32),
 
+    /// Too many elements of the given kind to be indexed.
+    #[error("MAP {0} limit exceeded")]
+    LimitExceeded(&'static str),
+
+    /// Not a GRP archive, or a truncated one.
+    #[error("Invalid GRP file")]
+    InvalidGrp,
+
+    /// Reference to a sector that doesn't exist.
+    #[error("Invalid sector: {0}")]
+    InvalidSector(sector::SectorId),
+
+    /// Reference to a sprite that doesn't exist.
+    #[error("Invalid sprite: {0}")]
+    InvalidSprite(sprite::SpriteId),
+
+    /// Snapshot taken before sectors or walls were added or removed.
+    #[error("Snapshot doesn't match the MAP geometry")]
+    SnapshotMismatch,
+
+    /// MAP rejected by strict parsing (see [`options`](options)).
+    #[error("Malformed MAP: {0}")]
+    Malformed(&'static str),
+
+    /// Malformed maphack file, at the given line.
+    #[error("Invalid maphack at line {0}")]
+    InvalidMaphack(usize),
+
+    /// Parsing was cancelled from a progress callback or cancellation token.
+    #[error("MAP parsing cancelled")]
+    Cancelled,
+
     /// IO error.
+    #[cfg(feature = "std")]
     #[error("MAP IO error: {0}")]
     Io(#[from] std::io::Error),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Map {
     /// MAP file version.
     pub version: i32,
@@ -53,26 +176,107 @@ pub struct Map {
 
     /// MAP sprites.
     pub sprites: Vec<Sprite>,
+
+    // unknown data after the sprites (e.g. editor metadata).
+    trailing: Vec<u8>,
+
+    // model overrides applied from a maphack (render only).
+    #[cfg(feature = "std")]
+    hacks: std::collections::BTreeMap<sprite::SpriteId, maphack::SpriteHack>,
+}
+
+impl Map {
+    /// MAP of the given version, without trailing data.
+    pub fn new(version: i32, player: Player, sectors: Sectors, sprites: Vec<Sprite>) -> Self {
+        Self {
+            version,
+            player,
+            sectors,
+            sprites,
+            trailing: Vec::new(),
+            #[cfg(feature = "std")]
+            hacks: Default::default(),
+        }
+    }
+
+    /// Data found after the sprites when the MAP was parsed (e.g. metadata
+    /// from third-party editors), written back as is by
+    /// [`to_writer`](Self::to_writer).
+    pub fn trailing_data(&self) -> &[u8] {
+        &self.trailing
+    }
+
+    /// Replaces the data written after the sprites.
+    pub fn set_trailing_data(&mut self, data: Vec<u8>) {
+        self.trailing = data;
+    }
 }
 
+#[cfg(feature = "std")]
 impl Map {
     /// Parse MAP file from a reader.
     pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
-        // crate supports versions from 7 to 9.
-        // according to some wiki, 8 and 9 are the same as version 7.
-        let version = reader.read_i32::<LE>()?;
-        info!("MAP file version: {}", version);
-        match version {
-            7 | 8 | 9 => {}
-            version => return Err(Error::UnsupportedVersion(version)),
+        Self::from_reader_with_progress(reader, |_| true)
+    }
+
+    /// Parse MAP file from a reader, reporting progress to a callback. Parsing
+    /// is cancelled (with [`Error::Cancelled`]) if the callback returns false.
+    pub fn from_reader_with_progress<R, F>(
+        reader: &mut R,
+        mut on_progress: F,
+    ) -> Result<Self, Error>
+    where
+        R: Read,
+        F: FnMut(Progress) -> bool,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("parse_map").entered();
+        let mut reporter = Reporter(&mut on_progress);
+        let (version, player) = {
+            let section = Section::Header;
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!("section", ?section).entered();
+            reporter.report(Progress::Started { section, count: 1 })?;
+            // crate supports versions from 7 to 9.
+            // according to some wiki, 8 and 9 are the same as version 7.
+            let version = reader.read_i32::<LE>()?;
+            #[cfg(feature = "tracing")]
+            tracing::info!(version, "MAP file version");
+            #[cfg(not(feature = "tracing"))]
+            info!("MAP file version: {}", version);
+            match version {
+                7..=9 => {}
+                version => return Err(Error::UnsupportedVersion(version)),
+            }
+
+            let player = Player::from_reader(reader)?;
+            reporter.report(Progress::Completed { section })?;
+            (version, player)
+        };
+        let sectors = Sectors::from_reader(reader, &mut reporter)?;
+        let sprites = sprite::from_reader(reader, &mut reporter)?;
+        let mut trailing = Vec::new();
+        reader.read_to_end(&mut trailing)?;
+        if !trailing.is_empty() {
+            #[cfg(feature = "tracing")]
+            tracing::info!(bytes = trailing.len(), "trailing data");
+            #[cfg(not(feature = "tracing"))]
+            info!("{} bytes of trailing data", trailing.len());
         }
+        let mut map = Self::new(version, player, sectors, sprites);
+        map.trailing = trailing;
+        Ok(map)
+    }
 
-        Ok(Self {
-            version,
-            player: Player::from_reader(reader)?,
-            sectors: Sectors::from_reader(reader)?,
-            sprites: sprite::from_reader(reader)?,
-        })
+    /// Parse MAP file from a reader. Parsing stops with
+    /// [`Error::Cancelled`](Error::Cancelled) once the token is cancelled
+    /// (checked between sections and every
+    /// [`REPORT_INTERVAL`](progress::REPORT_INTERVAL) elements).
+    pub fn from_reader_cancellable<R: Read>(
+        reader: &mut R,
+        token: &CancellationToken,
+    ) -> Result<Self, Error> {
+        Self::from_reader_with_progress(reader, |_| !token.is_cancelled())
     }
 
     /// Parse MAP file from the native filesystem.
@@ -85,4 +289,38 @@ impl Map {
     pub fn from_slice(slice: &[u8]) -> Result<Self, Error> {
         Self::from_reader(&mut Cursor::new(slice))
     }
+
+    /// Parse MAP file from a byte slice, reporting progress to a callback.
+    /// See [`from_reader_with_progress`](Self::from_reader_with_progress).
+    pub fn from_slice_with_progress<F>(slice: &[u8], on_progress: F) -> Result<Self, Error>
+    where
+        F: FnMut(Progress) -> bool,
+    {
+        Self::from_reader_with_progress(&mut Cursor::new(slice), on_progress)
+    }
+
+    /// Write MAP file (in the same version it was read as) to a writer.
+    pub fn to_writer<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_i32::<LE>(self.version)?;
+        self.player.to_writer(writer)?;
+        self.sectors.to_writer(writer)?;
+        sprite::to_writer(&self.sprites, writer)?;
+        writer.write_all(&self.trailing)?;
+        Ok(())
+    }
+
+    /// Write MAP file to the native filesystem.
+    pub fn to_file<P: AsRef<Path>>(&self, file: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(file)?);
+        self.to_writer(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write MAP file to a byte vector.
+    pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
+        let mut vec = Vec::new();
+        self.to_writer(&mut vec)?;
+        Ok(vec)
+    }
 }
@@ -0,0 +1,265 @@
+//! Top-down lighting heatmaps.
+//!
+//! Sectors are filled with a color ramp of their shade (or visibility), from
+//! dark blue (dark) to yellow (bright), so lighting inconsistencies between
+//! neighbouring sectors stand out.
+use crate::{
+    sector::{Sector, SectorId},
+    Map,
+};
+use std::fmt::Write;
+
+/// Brightest to darkest range of sector shades.
+const SHADE_RANGE: i32 = 31;
+
+/// Sector lighting value to visualize.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Channel {
+    #[default]
+    FloorShade,
+    CeilingShade,
+    Visibility,
+}
+
+impl Channel {
+    /// Raw channel value of the sector.
+    pub fn value(self, sector: &Sector) -> i32 {
+        match self {
+            Self::FloorShade => i32::from(sector.floor_shade),
+            Self::CeilingShade => i32::from(sector.ceiling_shade),
+            Self::Visibility => i32::from(sector.visibility),
+        }
+    }
+
+    /// Channel value of the sector, normalized to `[0, 1]`, where 1 is the
+    /// brightest (lowest shade, or highest visibility).
+    pub fn intensity(self, sector: &Sector) -> f64 {
+        let value = self.value(sector);
+        match self {
+            Self::FloorShade | Self::CeilingShade => {
+                1.0 - f64::from(value.clamp(0, SHADE_RANGE)) / f64::from(SHADE_RANGE)
+            }
+            Self::Visibility => f64::from(value) / 255.0,
+        }
+    }
+}
+
+/// Maps an intensity in `[0, 1]` to a heatmap RGB color.
+pub fn heat(intensity: f64) -> [u8; 3] {
+    const RAMP: [[f64; 3]; 4] = [
+        [16.0, 16.0, 96.0],
+        [160.0, 32.0, 128.0],
+        [240.0, 96.0, 32.0],
+        [255.0, 240.0, 96.0],
+    ];
+    let t = intensity.clamp(0.0, 1.0) * (RAMP.len() - 1) as f64;
+    let i = (t as usize).min(RAMP.len() - 2);
+    let f = t - i as f64;
+    let mut color = [0; 3];
+    for (c, color) in color.iter_mut().enumerate() {
+        *color = (RAMP[i][c] + (RAMP[i + 1][c] - RAMP[i][c]) * f).round() as u8;
+    }
+    color
+}
+
+/// Pairs of adjacent sectors whose channel values differ by more than
+/// `threshold`, with the absolute difference. Each pair is reported once,
+/// lowest sector first.
+pub fn inconsistencies(
+    map: &Map,
+    channel: Channel,
+    threshold: i32,
+) -> Vec<(SectorId, SectorId, i32)> {
+    let sectors = map.sectors.sectors();
+    let walls = map.sectors.walls();
+    let mut pairs = Vec::new();
+    for (id, sector) in sectors.iter().enumerate() {
+        let id = id as SectorId;
+        for wall in walls.get(sector.wall_range()).unwrap_or(&[]) {
+            let next = wall.next_sector;
+            if next <= id {
+                continue;
+            }
+            let other = match sectors.get(next as usize) {
+                Some(other) => other,
+                None => continue,
+            };
+            let diff = (channel.value(sector) - channel.value(other)).abs();
+            if diff > threshold && !pairs.iter().any(|&(a, b, _)| a == id && b == next) {
+                pairs.push((id, next, diff));
+            }
+        }
+    }
+    pairs
+}
+
+/// An RGB heatmap image.
+#[derive(Debug, Clone)]
+pub struct Lightmap {
+    pub width: u32,
+    pub height: u32,
+
+    /// Row-major RGB pixels. Pixels outside of any sector are black.
+    pub pixels: Vec<u8>,
+}
+
+/// World-to-image transformation, fitting the MAP in `size` pixels.
+struct Frame {
+    min: [i32; 2],
+    scale: f64,
+    width: u32,
+    height: u32,
+}
+
+impl Frame {
+    fn new(map: &Map, size: f64) -> Self {
+        let walls = map.sectors.walls();
+        let min_x = walls.iter().map(|w| w.x).min().unwrap_or(0);
+        let max_x = walls.iter().map(|w| w.x).max().unwrap_or(0);
+        let min_y = walls.iter().map(|w| w.y).min().unwrap_or(0);
+        let max_y = walls.iter().map(|w| w.y).max().unwrap_or(0);
+        let extent = f64::from((max_x - min_x).max(max_y - min_y).max(1));
+        let scale = size / extent;
+        Self {
+            min: [min_x, min_y],
+            scale,
+            width: (f64::from(max_x - min_x) * scale).ceil().max(1.0) as u32,
+            height: (f64::from(max_y - min_y) * scale).ceil().max(1.0) as u32,
+        }
+    }
+
+    fn to_image(&self, x: i32, y: i32) -> [f64; 2] {
+        [
+            f64::from(x - self.min[0]) * self.scale,
+            f64::from(y - self.min[1]) * self.scale,
+        ]
+    }
+
+    fn to_world(&self, x: f64, y: f64) -> [i32; 2] {
+        [
+            (x / self.scale) as i32 + self.min[0],
+            (y / self.scale) as i32 + self.min[1],
+        ]
+    }
+}
+
+/// Rasterizes the heatmap, scaled so the largest side of the MAP spans
+/// `size` pixels.
+pub fn raster(map: &Map, channel: Channel, size: u32) -> Lightmap {
+    let frame = Frame::new(map, f64::from(size));
+    let (width, height) = (frame.width, frame.height);
+    let mut pixels = vec![0; (width * height * 3) as usize];
+    let walls = map.sectors.walls();
+    for (id, sector) in map.sectors.sectors().iter().enumerate() {
+        let loop_walls = walls.get(sector.wall_range()).unwrap_or(&[]);
+        if loop_walls.is_empty() {
+            continue;
+        }
+        let color = heat(channel.intensity(sector));
+        // bounding box of the sector, in pixels.
+        let [min_x, min_y] = frame.to_image(
+            loop_walls.iter().map(|w| w.x).min().unwrap(),
+            loop_walls.iter().map(|w| w.y).min().unwrap(),
+        );
+        let [max_x, max_y] = frame.to_image(
+            loop_walls.iter().map(|w| w.x).max().unwrap(),
+            loop_walls.iter().map(|w| w.y).max().unwrap(),
+        );
+        let (min_x, max_x) = (min_x as u32, (max_x.ceil() as u32).min(width));
+        let (min_y, max_y) = (min_y as u32, (max_y.ceil() as u32).min(height));
+        for py in min_y..max_y {
+            for px in min_x..max_x {
+                let [x, y] = frame.to_world(f64::from(px) + 0.5, f64::from(py) + 0.5);
+                if map.sectors.inside(id as SectorId, x, y) {
+                    let offset = ((py * width + px) * 3) as usize;
+                    pixels[offset..offset + 3].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+    Lightmap {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// Returns the heatmap as an SVG drawing, scaled so the largest side of the
+/// MAP spans `size` pixels (see [`svg::top_down`](crate::svg::top_down)).
+/// Each sector is a path titled with its index and channel value.
+pub fn svg(map: &Map, channel: Channel, size: f64) -> String {
+    let frame = Frame::new(map, size);
+    let walls = map.sectors.walls();
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n<rect width=\"100%\" height=\"100%\" fill=\"black\"/>\n",
+        frame.width, frame.height
+    );
+    for (id, sector) in map.sectors.sectors().iter().enumerate() {
+        let mut path = String::new();
+        let range = sector.wall_range();
+        for (index, wall) in walls.get(range.clone()).unwrap_or(&[]).iter().enumerate() {
+            let [x, y] = frame.to_image(wall.x, wall.y);
+            // a wall starts a new loop unless the previous one points to it.
+            let prev = index
+                .checked_sub(1)
+                .map(|prev| walls[range.start + prev].point2 as usize);
+            let command = match prev {
+                Some(prev) if prev == range.start + index => "L",
+                Some(_) => "Z M",
+                None => "M",
+            };
+            write!(path, "{}{:.1} {:.1} ", command, x, y).unwrap();
+        }
+        if path.is_empty() {
+            continue;
+        }
+        let [r, g, b] = heat(channel.intensity(sector));
+        writeln!(
+            svg,
+            "<path d=\"{}Z\" fill=\"#{:02x}{:02x}{:02x}\" fill-rule=\"evenodd\" stroke=\"black\" stroke-width=\"0.5\"><title>sector {}: {}</title></path>",
+            path.trim_end(),
+            r,
+            g,
+            b,
+            id,
+            channel.value(sector),
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod test {
+    use super::{heat, inconsistencies, raster, svg, Channel};
+    use crate::Map;
+
+    #[test]
+    fn heat_ramp() {
+        assert_eq!([16, 16, 96], heat(0.0));
+        assert_eq!([255, 240, 96], heat(1.0));
+        assert_eq!(heat(1.0), heat(2.0));
+    }
+
+    #[test]
+    fn lightmap() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let image = raster(&map, Channel::FloorShade, 128);
+        assert_eq!(128, image.width.max(image.height));
+        assert_eq!(
+            (image.width * image.height * 3) as usize,
+            image.pixels.len()
+        );
+        assert!(image.pixels.iter().any(|p| *p != 0));
+
+        let drawing = svg(&map, Channel::FloorShade, 128.0);
+        assert_eq!(
+            map.sectors.sectors().len(),
+            drawing.matches("<path").count()
+        );
+
+        let pairs = inconsistencies(&map, Channel::FloorShade, 8);
+        assert!(pairs.iter().all(|&(a, b, diff)| a < b && diff > 8));
+    }
+}
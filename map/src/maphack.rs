@@ -0,0 +1,376 @@
+//! EDuke32 maphack (`.mhk`) files.
+//!
+//! Maphacks patch the sprites of a MAP without changing the MAP file, mostly
+//! to fix the orientation and placement of 3D models. They are text files of
+//! `sprite ID` commands followed by the overrides of the sprite:
+//!
+//! ```text
+//! // E1L1.mhk
+//! sprite 12 angoff 512 mdposzoff -256
+//! sprite 40 notmd
+//! ```
+//!
+//! Lines with commands other than `sprite` (e.g. Polymer `light`s) are kept
+//! as they are, and written back unchanged in their original order.
+//!
+//! Overrides only change how sprites are drawn: [`apply_maphack`] stores them
+//! alongside the MAP, leaving the sprites themselves untouched.
+use crate::{sprite::SpriteId, Error, Map};
+use std::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fmt, fs,
+    path::Path,
+};
+
+bitflags::bitflags! {
+    /// Model flags of a sprite.
+    #[derive(Default)]
+    pub struct HackFlags: u8 {
+        /// Draw the sprite instead of its model (`notmd`).
+        const NOT_MD      = 0b0001;
+
+        /// Don't animate the model (`nomdanim`).
+        const NO_MD_ANIM  = 0b0010;
+
+        /// Push the sprite away from the wall it's on (`away1`).
+        const AWAY1       = 0b0100;
+
+        /// Push the sprite away from the wall behind it (`away2`).
+        const AWAY2       = 0b1000;
+    }
+}
+
+const FLAGS: [(&str, HackFlags); 4] = [
+    ("notmd", HackFlags::NOT_MD),
+    ("nomdanim", HackFlags::NO_MD_ANIM),
+    ("away1", HackFlags::AWAY1),
+    ("away2", HackFlags::AWAY2),
+];
+
+/// Overrides of a sprite. They only affect how its model is drawn.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct SpriteHack {
+    /// Model angle, relative to the sprite angle (`angoff`).
+    pub angle_offset: i16,
+
+    /// Model position, relative to the sprite position, in XY & Z units
+    /// (`mdposxoff`, `mdposyoff` & `mdposzoff`).
+    pub position_offset: [i32; 3],
+
+    /// Model pivot (`mdxoff`, `mdyoff` & `mdzoff`).
+    pub pivot_offset: [i32; 3],
+
+    /// Model pitch & roll (`pitch` & `roll`).
+    pub pitch: i16,
+    pub roll: i16,
+    pub flags: HackFlags,
+}
+
+/// Parsed maphack.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Maphack {
+    /// Overrides, by sprite.
+    pub sprites: BTreeMap<SpriteId, SpriteHack>,
+
+    /// Lines with unsupported commands.
+    pub unknown: Vec<String>,
+
+    // parsed sprites & unknown lines, in the order they were found.
+    order: Vec<Line>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Line {
+    Sprite(SpriteId),
+    // index into 'unknown'
+    Unknown(usize),
+}
+
+impl Maphack {
+    /// Parses a maphack. Fails with [`Error::InvalidMaphack`] (and the line
+    /// number) on missing or malformed numbers, and on angles out of the
+    /// `i16` range.
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let mut hack = Self::default();
+        let mut sprite = None;
+        for (i, line) in source.lines().enumerate() {
+            let err = || Error::InvalidMaphack(i + 1);
+            let code = line.split("//").next().unwrap_or_default();
+            let mut tokens = code.split_whitespace().peekable();
+            let known = match tokens.peek() {
+                Some(token) => is_command(token),
+                None => continue,
+            };
+            if !known {
+                hack.order.push(Line::Unknown(hack.unknown.len()));
+                hack.unknown.push(line.trim().to_string());
+                continue;
+            }
+            while let Some(token) = tokens.next() {
+                let token = token.to_ascii_lowercase();
+                if token == "sprite" {
+                    let id: SpriteId =
+                        tokens.next().and_then(|n| n.parse().ok()).ok_or_else(err)?;
+                    sprite = Some(id);
+                    if let Entry::Vacant(entry) = hack.sprites.entry(id) {
+                        entry.insert(SpriteHack::default());
+                        hack.order.push(Line::Sprite(id));
+                    }
+                    continue;
+                }
+                let id = sprite.ok_or_else(err)?;
+                let entry = hack.sprites.get_mut(&id).unwrap();
+                if let Some((_, flag)) = FLAGS.iter().find(|(name, _)| *name == token) {
+                    entry.flags |= *flag;
+                    continue;
+                }
+                if token == "mhkreset" {
+                    *entry = SpriteHack::default();
+                    continue;
+                }
+                let value: i32 = tokens.next().and_then(|n| n.parse().ok()).ok_or_else(err)?;
+                let angle = || i16::try_from(value).map_err(|_| err());
+                match token.as_str() {
+                    "angoff" | "angleoff" => entry.angle_offset = angle()?,
+                    "pitch" => entry.pitch = angle()?,
+                    "roll" => entry.roll = angle()?,
+                    "mdxoff" | "mdpivxoff" | "mdpivotxoff" => entry.pivot_offset[0] = value,
+                    "mdyoff" | "mdpivyoff" | "mdpivotyoff" => entry.pivot_offset[1] = value,
+                    "mdzoff" | "mdpivzoff" | "mdpivotzoff" => entry.pivot_offset[2] = value,
+                    "mdposxoff" | "mdpositionxoff" => entry.position_offset[0] = value,
+                    "mdposyoff" | "mdpositionyoff" => entry.position_offset[1] = value,
+                    "mdposzoff" | "mdpositionzoff" => entry.position_offset[2] = value,
+                    _ => return Err(err()),
+                }
+            }
+        }
+        Ok(hack)
+    }
+
+    /// Parses a maphack file from the native filesystem.
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(file)?)
+    }
+}
+
+// true for the tokens starting (or continuing) a sprite command.
+fn is_command(token: &str) -> bool {
+    const KEYWORDS: [&str; 21] = [
+        "sprite",
+        "mhkreset",
+        "angoff",
+        "angleoff",
+        "pitch",
+        "roll",
+        "mdxoff",
+        "mdyoff",
+        "mdzoff",
+        "mdpivxoff",
+        "mdpivyoff",
+        "mdpivzoff",
+        "mdpivotxoff",
+        "mdpivotyoff",
+        "mdpivotzoff",
+        "mdposxoff",
+        "mdposyoff",
+        "mdposzoff",
+        "mdpositionxoff",
+        "mdpositionyoff",
+        "mdpositionzoff",
+    ];
+    let token = token.to_ascii_lowercase();
+    KEYWORDS.contains(&token.as_str()) || FLAGS.iter().any(|(name, _)| *name == token)
+}
+
+impl fmt::Display for Maphack {
+    /// Writes the maphack in `.mhk` format, one line per sprite. Parsed
+    /// sprites & unknown lines keep their order, others are written last.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sprites = BTreeSet::new();
+        let mut unknown = BTreeSet::new();
+        for line in &self.order {
+            match *line {
+                Line::Sprite(id) => {
+                    if let Some(hack) = self.sprites.get(&id) {
+                        sprites.insert(id);
+                        write_sprite(f, id, hack)?;
+                    }
+                }
+                Line::Unknown(i) => {
+                    if let Some(line) = self.unknown.get(i) {
+                        unknown.insert(i);
+                        writeln!(f, "{}", line)?;
+                    }
+                }
+            }
+        }
+        for (id, hack) in &self.sprites {
+            if !sprites.contains(id) {
+                write_sprite(f, *id, hack)?;
+            }
+        }
+        for (i, line) in self.unknown.iter().enumerate() {
+            if !unknown.contains(&i) {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_sprite(f: &mut fmt::Formatter<'_>, id: SpriteId, hack: &SpriteHack) -> fmt::Result {
+    write!(f, "sprite {}", id)?;
+    let values = [
+        ("angoff", i32::from(hack.angle_offset)),
+        ("mdxoff", hack.pivot_offset[0]),
+        ("mdyoff", hack.pivot_offset[1]),
+        ("mdzoff", hack.pivot_offset[2]),
+        ("mdposxoff", hack.position_offset[0]),
+        ("mdposyoff", hack.position_offset[1]),
+        ("mdposzoff", hack.position_offset[2]),
+        ("pitch", i32::from(hack.pitch)),
+        ("roll", i32::from(hack.roll)),
+    ];
+    for (name, value) in values.iter().filter(|(_, v)| *v != 0) {
+        write!(f, " {} {}", name, value)?;
+    }
+    for (name, _) in FLAGS.iter().filter(|(_, flag)| hack.flags.contains(*flag)) {
+        write!(f, " {}", name)?;
+    }
+    writeln!(f)
+}
+
+/// Stores the overrides of a maphack alongside the MAP (see
+/// [`Map::sprite_hack`]), replacing the previous overrides of the same
+/// sprites.
+///
+/// Sprites aren't modified: the overrides only affect how models are drawn,
+/// and are left to renderers. Fails with [`Error::InvalidSprite`] (leaving
+/// the MAP untouched) if the maphack references a sprite the MAP doesn't
+/// have.
+pub fn apply_maphack(map: &mut Map, hack: &Maphack) -> Result<(), Error> {
+    if let Some(id) = hack
+        .sprites
+        .keys()
+        .find(|id| **id < 0 || **id as usize >= map.sprites.len())
+    {
+        return Err(Error::InvalidSprite(*id));
+    }
+    map.hacks
+        .extend(hack.sprites.iter().map(|(id, hack)| (*id, hack.clone())));
+    Ok(())
+}
+
+impl Map {
+    /// Overrides of a sprite, from the maphacks [applied](apply_maphack) to
+    /// the MAP. Overrides are indexed by sprite, so they don't follow sprites
+    /// that are removed or renumbered afterwards.
+    pub fn sprite_hack(&self, sprite: SpriteId) -> Option<&SpriteHack> {
+        self.hacks.get(&sprite)
+    }
+
+    /// Removes the overrides of all sprites.
+    pub fn clear_sprite_hacks(&mut self) {
+        self.hacks.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{apply_maphack, HackFlags, Maphack};
+    use crate::{Error, Map};
+
+    const SOURCE: &str = "\
+// comment
+sprite 1 angoff 512 mdposzoff -256 // trailing comment
+sprite 2 notmd
+  nomdanim pitch 10
+light 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15
+Sprite 3 mdxoff 1 mhkreset roll 4
+";
+
+    #[test]
+    fn parse() {
+        let hack = Maphack::parse(SOURCE).unwrap();
+        assert_eq!(3, hack.sprites.len());
+        assert_eq!(512, hack.sprites[&1].angle_offset);
+        assert_eq!([0, 0, -256], hack.sprites[&1].position_offset);
+        assert_eq!(
+            HackFlags::NOT_MD | HackFlags::NO_MD_ANIM,
+            hack.sprites[&2].flags
+        );
+        assert_eq!(10, hack.sprites[&2].pitch);
+        assert_eq!([0; 3], hack.sprites[&3].pivot_offset);
+        assert_eq!(4, hack.sprites[&3].roll);
+        assert_eq!(1, hack.unknown.len());
+        assert_eq!(hack, Maphack::parse(&hack.to_string()).unwrap());
+
+        assert!(matches!(
+            Maphack::parse("\n\nsprite x"),
+            Err(Error::InvalidMaphack(3))
+        ));
+        assert!(matches!(
+            Maphack::parse("sprite 1 mdxoff"),
+            Err(Error::InvalidMaphack(1))
+        ));
+        assert!(matches!(
+            Maphack::parse("sprite 1 bogus 3"),
+            Err(Error::InvalidMaphack(1))
+        ));
+        assert!(matches!(
+            Maphack::parse("sprite 1\nangoff 40000"),
+            Err(Error::InvalidMaphack(2))
+        ));
+        assert!(matches!(
+            Maphack::parse("sprite 1 roll -32769"),
+            Err(Error::InvalidMaphack(1))
+        ));
+    }
+
+    #[test]
+    fn write_order() {
+        let source = "\
+light 0 1 2
+sprite 5 notmd
+light 3 4 5
+sprite 2 pitch 1
+";
+        let mut hack = Maphack::parse(source).unwrap();
+        assert_eq!(source, hack.to_string());
+        // sprites added after parsing come last, after the unknown lines.
+        hack.sprites.entry(1).or_default().roll = 2;
+        hack.unknown.push("light 6 7 8".to_string());
+        assert_eq!(
+            format!("{}sprite 1 roll 2\nlight 6 7 8\n", source),
+            hack.to_string()
+        );
+    }
+
+    #[test]
+    fn apply() {
+        let mut map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let sprites = map.sprites.clone();
+        assert!(map.sprite_hack(1).is_none());
+        apply_maphack(&mut map, &Maphack::parse(SOURCE).unwrap()).unwrap();
+        // gameplay position & angle are left untouched.
+        assert_eq!(sprites.len(), map.sprites.len());
+        assert!(sprites
+            .iter()
+            .zip(&map.sprites)
+            .all(|(a, b)| (a.x, a.y, a.z, a.angle) == (b.x, b.y, b.z, b.angle)));
+        let hack = map.sprite_hack(1).unwrap();
+        assert_eq!(512, hack.angle_offset);
+        assert_eq!([0, 0, -256], hack.position_offset);
+        assert_eq!(4, map.sprite_hack(3).unwrap().roll);
+
+        let hack = Maphack::parse("sprite 1 angoff 1\nsprite 30000 angoff 1").unwrap();
+        assert!(matches!(
+            apply_maphack(&mut map, &hack),
+            Err(Error::InvalidSprite(30000))
+        ));
+        assert_eq!(512, map.sprite_hack(1).unwrap().angle_offset);
+        map.clear_sprite_hacks();
+        assert!(map.sprite_hack(1).is_none());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{sector::WallId, Error, Map};
+use std::collections::HashMap;
+
+// largest element count addressable with i16 indices.
+const MAX_ELEMENTS: usize = i16::MAX as usize;
+
+impl Map {
+    /// Appends the sectors, walls and sprites of another map, displaced by
+    /// the given (x, y, z) offset. The player start of `other` is ignored.
+    ///
+    /// Portals between the two maps are not created automatically; call
+    /// [`Map::stitch_portals`](Map::stitch_portals) afterwards to connect
+    /// coincident boundary walls.
+    pub fn merge(&mut self, other: &Map, [dx, dy, dz]: [i32; 3]) -> Result<(), Error> {
+        let wall_base = self.sectors.walls.len();
+        let sector_base = self.sectors.sectors.len();
+        let sprite_base = self.sprites.len();
+        if wall_base + other.sectors.walls.len() > MAX_ELEMENTS {
+            return Err(Error::LimitExceeded("walls"));
+        }
+        if sector_base + other.sectors.sectors.len() > MAX_ELEMENTS {
+            return Err(Error::LimitExceeded("sectors"));
+        }
+        if sprite_base + other.sprites.len() > MAX_ELEMENTS {
+            return Err(Error::LimitExceeded("sprites"));
+        }
+        let offset = |i: i16, base: usize| if i < 0 { i } else { i + base as i16 };
+
+        self.sectors
+            .walls
+            .extend(other.sectors.walls.iter().map(|w| {
+                let mut wall = w.clone();
+                wall.x += dx;
+                wall.y += dy;
+                wall.point2 = offset(wall.point2, wall_base);
+                wall.next_wall = offset(wall.next_wall, wall_base);
+                wall.next_sector = offset(wall.next_sector, sector_base);
+                wall
+            }));
+        self.sectors
+            .sectors
+            .extend(other.sectors.sectors.iter().map(|s| {
+                let mut sector = s.clone();
+                sector.wallptr += wall_base as u16;
+                sector.ceiling_z += dz;
+                sector.floor_z += dz;
+                sector
+            }));
+        self.sprites.extend(other.sprites.iter().map(|s| {
+            let mut sprite = s.clone();
+            sprite.x += dx;
+            sprite.y += dy;
+            sprite.z += dz;
+            sprite.sectnum = offset(sprite.sectnum, sector_base);
+            sprite.owner = offset(sprite.owner, sprite_base);
+            sprite
+        }));
+        Ok(())
+    }
+
+    /// Connects pairs of solid walls (`next_wall == -1`) from different
+    /// sectors that share the same vertices in opposite directions, turning
+    /// them into portals. Returns the number of portals created.
+    pub fn stitch_portals(&mut self) -> usize {
+        let owner = self.sectors.wall_owners();
+        let walls = &self.sectors.walls;
+        let segment = |i: usize| {
+            let left = &walls[i];
+            walls
+                .get(left.point2 as usize)
+                .map(|right| ((left.x, left.y), (right.x, right.y)))
+        };
+        // solid walls indexed by their (left, right) vertices
+        let mut solid: HashMap<_, WallId> = HashMap::new();
+        for (i, wall) in walls.iter().enumerate() {
+            if wall.next_wall == -1 && owner[i] != -1 {
+                if let Some(seg) = segment(i) {
+                    solid.insert(seg, i as WallId);
+                }
+            }
+        }
+        let mut pairs = Vec::new();
+        for (&(l, r), &a) in &solid {
+            if let Some(&b) = solid.get(&(r, l)) {
+                if a < b && owner[a as usize] != owner[b as usize] {
+                    pairs.push((a, b));
+                }
+            }
+        }
+        for &(a, b) in &pairs {
+            let walls = &mut self.sectors.walls;
+            walls[a as usize].next_wall = b;
+            walls[a as usize].next_sector = owner[b as usize];
+            walls[b as usize].next_wall = a;
+            walls[b as usize].next_sector = owner[a as usize];
+        }
+        pairs.len()
+    }
+}
@@ -0,0 +1,136 @@
+//! Symbolic tile names.
+use std::{collections::HashMap, fs, path::Path};
+
+static DUKE_NAMES: &str = include_str!("../assets/DUKE_NAMES.H");
+
+/// Mapping between picnums and symbolic names (e.g. `2000` is `PIGCOP` in
+/// Duke Nukem 3D).
+#[derive(Debug, Default, Clone)]
+pub struct NameRegistry {
+    names: HashMap<i16, String>,
+    picnums: HashMap<String, i16>,
+}
+
+impl NameRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bundled Duke Nukem 3D names (special sprites, actors & pickups).
+    pub fn duke() -> Self {
+        let mut registry = Self::new();
+        registry.parse_defines(DUKE_NAMES);
+        registry
+    }
+
+    /// Load names from a `NAMES.H` or CON file (see
+    /// [`NameRegistry::parse_defines`](NameRegistry::parse_defines)).
+    pub fn from_file<P: AsRef<Path>>(file: P) -> std::io::Result<Self> {
+        let mut registry = Self::new();
+        registry.parse_defines(&fs::read_to_string(file)?);
+        Ok(registry)
+    }
+
+    /// Parses `#define NAME PICNUM` (C headers) and `define NAME PICNUM` (CON
+    /// scripts) lines, registering each name. Other lines are ignored.
+    /// Returns the number of names registered.
+    pub fn parse_defines(&mut self, source: &str) -> usize {
+        let mut count = 0;
+        for line in source.lines() {
+            let line = line.split("//").next().unwrap_or_default();
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("#define") | Some("define") => {}
+                _ => continue,
+            }
+            if let (Some(name), Some(Ok(picnum))) = (tokens.next(), tokens.next().map(str::parse)) {
+                self.insert(picnum, name);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Registers a name, replacing any previous name of the picnum and any
+    /// previous picnum of the name.
+    pub fn insert(&mut self, picnum: i16, name: &str) {
+        if let Some(old) = self.picnums.insert(name.to_string(), picnum) {
+            if old != picnum {
+                self.names.remove(&old);
+            }
+        }
+        if let Some(old) = self.names.insert(picnum, name.to_string()) {
+            if old != name {
+                self.picnums.remove(&old);
+            }
+        }
+    }
+
+    /// Returns the name of a picnum.
+    pub fn name(&self, picnum: i16) -> Option<&str> {
+        self.names.get(&picnum).map(String::as_str)
+    }
+
+    /// Returns the picnum with the given name.
+    pub fn picnum(&self, name: &str) -> Option<i16> {
+        self.picnums.get(name).copied()
+    }
+
+    /// Returns the name of a picnum, or the number itself if it has no name.
+    pub fn display(&self, picnum: i16) -> String {
+        self.name(picnum)
+            .map(str::to_string)
+            .unwrap_or_else(|| picnum.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NameRegistry;
+    use crate::tags::duke;
+
+    #[test]
+    fn duke_names() {
+        let names = NameRegistry::duke();
+        assert_eq!(Some("PIGCOP"), names.name(2000));
+        assert_eq!(Some(duke::MUSICANDSFX), names.picnum("MUSICANDSFX"));
+        assert_eq!("1234", names.display(1234));
+    }
+
+    #[test]
+    fn parse_defines() {
+        let mut names = NameRegistry::new();
+        let source = "define FOO 12 // comment\n#define BAR 13\ndefine BAZ\ngamevar X 0 0\n";
+        assert_eq!(2, names.parse_defines(source));
+        assert_eq!(Some("FOO"), names.name(12));
+        names.insert(12, "QUX");
+        assert_eq!(None, names.picnum("FOO"));
+        assert_eq!(Some(12), names.picnum("QUX"));
+    }
+
+    #[test]
+    fn rebind() {
+        let mut names = NameRegistry::new();
+        names.insert(12, "FOO");
+        names.insert(13, "BAR");
+        // same name, different picnum
+        names.insert(14, "FOO");
+        assert_eq!(None, names.name(12));
+        assert_eq!(Some("FOO"), names.name(14));
+        assert_eq!(Some(14), names.picnum("FOO"));
+        // same picnum, different name
+        names.insert(13, "BAZ");
+        assert_eq!(None, names.picnum("BAR"));
+        assert_eq!(Some("BAZ"), names.name(13));
+        // a name taking the picnum of another one
+        names.insert(13, "FOO");
+        assert_eq!(None, names.name(14));
+        assert_eq!(None, names.picnum("BAZ"));
+        assert_eq!(Some("FOO"), names.name(13));
+        assert_eq!(Some(13), names.picnum("FOO"));
+        // rebinding to the same picnum keeps the mapping
+        names.insert(13, "FOO");
+        assert_eq!(Some(13), names.picnum("FOO"));
+        assert_eq!(Some("FOO"), names.name(13));
+    }
+}
@@ -0,0 +1,326 @@
+//! Navigation meshes for bots.
+//!
+//! Sector floors are triangulated into cells, linked through shared edges
+//! (inside a sector) and portals (between sectors). Cells and links are
+//! flagged with walkability information, derived from slopes, ceiling
+//! clearance, floor steps and blocking walls, so that external AIs can plan
+//! paths without knowing about Build.
+use crate::{
+    sector::{SectorId, WallStat},
+    triangulate::triangulate,
+    Map,
+};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+};
+
+/// Highest floor step (Z units) a player can climb.
+pub const MAX_STEP: i32 = 16 << 8;
+
+/// Lowest ceiling clearance (Z units) a player fits through.
+pub const PLAYER_HEIGHT: i32 = 38 << 8;
+
+/// Z component of the normal of the steepest walkable floor (about 45
+/// degrees).
+pub const MIN_FLOOR_NORMAL_Z: f64 = 0.7;
+
+const MAGIC: &[u8; 4] = b"NAVM";
+const VERSION: u32 = 1;
+
+bitflags::bitflags! {
+    pub struct CellFlags: u8 {
+        /// The floor is too steep to stand on.
+        const STEEP       = 0b0000_0001;
+
+        /// The ceiling is too low for a player to fit.
+        const LOW_CEILING = 0b0000_0010;
+    }
+}
+
+bitflags::bitflags! {
+    pub struct LinkFlags: u8 {
+        /// Crosses a portal into another sector.
+        const PORTAL  = 0b0000_0001;
+
+        /// Climbs a floor step (up to [`MAX_STEP`]).
+        const STEP_UP = 0b0000_0010;
+
+        /// Drops down more than [`MAX_STEP`]. Can't be walked backwards.
+        const DROP    = 0b0000_0100;
+
+        /// Can't be crossed: blocking wall, step too high or opening too
+        /// narrow.
+        const BLOCKED = 0b0000_1000;
+    }
+}
+
+/// Triangular navigation cell.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Cell {
+    pub sector: SectorId,
+
+    /// Corners, counter-clockwise (X right, Y up), with the floor height at
+    /// each corner in Z units.
+    pub vertices: [[i32; 3]; 3],
+    pub flags: CellFlags,
+}
+
+impl Cell {
+    /// Returns true if a player can stand in the cell.
+    pub fn is_walkable(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    /// Center of the cell.
+    pub fn centroid(&self) -> [i32; 3] {
+        let mut center = [0; 3];
+        for (axis, center) in center.iter_mut().enumerate() {
+            let sum: i64 = self.vertices.iter().map(|v| i64::from(v[axis])).sum();
+            *center = (sum / 3) as i32;
+        }
+        center
+    }
+}
+
+/// Directed link between cells.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Link {
+    pub from: u32,
+    pub to: u32,
+    pub flags: LinkFlags,
+}
+
+impl Link {
+    /// Returns true if the link can be walked, from one walkable cell into
+    /// another.
+    pub fn is_walkable(&self) -> bool {
+        !self.flags.contains(LinkFlags::BLOCKED)
+    }
+}
+
+/// Navigation mesh of a MAP.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct NavMesh {
+    pub cells: Vec<Cell>,
+
+    /// Links, sorted by `from` cell.
+    pub links: Vec<Link>,
+}
+
+impl NavMesh {
+    /// Links leaving the given cell.
+    pub fn links_from(&self, cell: u32) -> &[Link] {
+        let start = self.links.partition_point(|l| l.from < cell);
+        let end = self.links.partition_point(|l| l.from <= cell);
+        &self.links[start..end]
+    }
+
+    /// Serializes the mesh, as little endian:
+    ///
+    /// - `"NAVM"` magic & `u32` version (1).
+    /// - `u32` number of cells, followed by each cell's `i16` sector, `u8`
+    ///   flags and 3 `[i32; 3]` vertices.
+    /// - `u32` number of links, followed by each link's `u32` source cell,
+    ///   `u32` target cell and `u8` flags.
+    pub fn to_writer<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<LE>(VERSION)?;
+        writer.write_u32::<LE>(self.cells.len() as u32)?;
+        for cell in &self.cells {
+            writer.write_i16::<LE>(cell.sector)?;
+            writer.write_u8(cell.flags.bits())?;
+            for vertex in cell.vertices.iter().flatten() {
+                writer.write_i32::<LE>(*vertex)?;
+            }
+        }
+        writer.write_u32::<LE>(self.links.len() as u32)?;
+        for link in &self.links {
+            writer.write_u32::<LE>(link.from)?;
+            writer.write_u32::<LE>(link.to)?;
+            writer.write_u8(link.flags.bits())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a mesh written by [`to_writer`](Self::to_writer).
+    pub fn from_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let invalid = |what| io::Error::new(io::ErrorKind::InvalidData, what);
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC || reader.read_u32::<LE>()? != VERSION {
+            return Err(invalid("unsupported navmesh"));
+        }
+        let cell_count = reader.read_u32::<LE>()?;
+        let mut cells = Vec::new();
+        for _ in 0..cell_count {
+            let sector = reader.read_i16::<LE>()?;
+            let flags = CellFlags::from_bits_truncate(reader.read_u8()?);
+            let mut vertices = [[0; 3]; 3];
+            for vertex in vertices.iter_mut().flatten() {
+                *vertex = reader.read_i32::<LE>()?;
+            }
+            cells.push(Cell {
+                sector,
+                vertices,
+                flags,
+            });
+        }
+        let link_count = reader.read_u32::<LE>()?;
+        let mut links = Vec::new();
+        for _ in 0..link_count {
+            let from = reader.read_u32::<LE>()?;
+            let to = reader.read_u32::<LE>()?;
+            if from >= cell_count || to >= cell_count {
+                return Err(invalid("navmesh link out of bounds"));
+            }
+            let flags = LinkFlags::from_bits_truncate(reader.read_u8()?);
+            links.push(Link { from, to, flags });
+        }
+        Ok(Self { cells, links })
+    }
+}
+
+/// Builds the navigation mesh of the map.
+pub fn build(map: &Map) -> NavMesh {
+    let sectors = &map.sectors;
+    let walls = sectors.walls();
+
+    let mut cells = Vec::new();
+    for (id, sector) in sectors.sectors().iter().enumerate() {
+        let id = id as SectorId;
+        let steep = sector.floor_plane(sectors).normal[2].abs() < MIN_FLOOR_NORMAL_Z;
+        for triangle in triangulate(map, id) {
+            let mut vertices = [[0; 3]; 3];
+            for (vertex, wall) in vertices.iter_mut().zip(&triangle) {
+                let wall = &walls[*wall as usize];
+                let z = sectors.floor_z_at(id, wall.x, wall.y).unwrap_or(0);
+                *vertex = [wall.x, wall.y, z];
+            }
+            let mut cell = Cell {
+                sector: id,
+                vertices,
+                flags: CellFlags::empty(),
+            };
+            let [x, y, z] = cell.centroid();
+            let ceiling = sectors.ceiling_z_at(id, x, y).unwrap_or(z);
+            cell.flags.set(CellFlags::STEEP, steep);
+            cell.flags
+                .set(CellFlags::LOW_CEILING, z - ceiling < PLAYER_HEIGHT);
+            cells.push(cell);
+        }
+    }
+
+    // cells sharing each (undirected) edge.
+    let mut edges: HashMap<([i32; 2], [i32; 2]), Vec<u32>> = HashMap::new();
+    for (index, cell) in cells.iter().enumerate() {
+        for i in 0..3 {
+            let [ax, ay, _] = cell.vertices[i];
+            let [bx, by, _] = cell.vertices[(i + 1) % 3];
+            let key = ([ax, ay].min([bx, by]), [ax, ay].max([bx, by]));
+            edges.entry(key).or_default().push(index as u32);
+        }
+    }
+
+    let mut links = Vec::new();
+    for ((a, b), shared) in edges {
+        for &from in &shared {
+            for &to in &shared {
+                let (src, dst) = (&cells[from as usize], &cells[to as usize]);
+                if from == to {
+                    continue;
+                }
+                let flags = if src.sector == dst.sector {
+                    LinkFlags::empty()
+                } else {
+                    match portal_flags(map, src.sector, dst.sector, a, b) {
+                        Some(flags) => flags,
+                        // overlapping sectors touching without a portal.
+                        None => continue,
+                    }
+                };
+                links.push(Link { from, to, flags });
+            }
+        }
+    }
+    links.sort_unstable_by_key(|l| (l.from, l.to));
+    links.dedup_by_key(|l| (l.from, l.to));
+    NavMesh { cells, links }
+}
+
+/// Flags of a link crossing the portal between `a` and `b`, from sector
+/// `from` into sector `to`. Returns `None` if there is no such portal.
+fn portal_flags(
+    map: &Map,
+    from: SectorId,
+    to: SectorId,
+    a: [i32; 2],
+    b: [i32; 2],
+) -> Option<LinkFlags> {
+    let sectors = &map.sectors;
+    let walls = sectors.walls();
+    let range = sectors.sectors()[from as usize].wall_range();
+    let wall = walls.get(range)?.iter().find(|w| {
+        let right = match walls.get(w.point2.max(0) as usize) {
+            Some(right) => right,
+            None => return false,
+        };
+        let (p, q) = ([w.x, w.y], [right.x, right.y]);
+        w.next_sector == to && ((p, q) == (a, b) || (p, q) == (b, a))
+    })?;
+
+    let mut flags = LinkFlags::PORTAL;
+    let blocking =
+        |w: &crate::sector::Wall| w.wall_stat.contains(WallStat::BLOCKING_CLIPMOVE_GETZRANGE);
+    let back = walls.get(wall.next_wall.max(0) as usize);
+    if blocking(wall) || back.is_some_and(blocking) {
+        flags |= LinkFlags::BLOCKED;
+    }
+
+    // heights at the middle of the portal (Z points down).
+    let (x, y) = ((a[0] + b[0]) / 2, (a[1] + b[1]) / 2);
+    let src_floor = sectors.floor_z_at(from, x, y)?;
+    let dst_floor = sectors.floor_z_at(to, x, y)?;
+    let ceiling = sectors
+        .ceiling_z_at(from, x, y)?
+        .max(sectors.ceiling_z_at(to, x, y)?);
+    let rise = src_floor - dst_floor;
+    if rise > MAX_STEP {
+        flags |= LinkFlags::BLOCKED;
+    } else if rise > 0 {
+        flags |= LinkFlags::STEP_UP;
+    } else if -rise > MAX_STEP {
+        flags |= LinkFlags::DROP;
+    }
+    if src_floor.min(dst_floor) - ceiling < PLAYER_HEIGHT {
+        flags |= LinkFlags::BLOCKED;
+    }
+    Some(flags)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{build, LinkFlags, NavMesh};
+    use crate::Map;
+
+    #[test]
+    fn navmesh() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let mesh = build(&map);
+        assert!(!mesh.cells.is_empty());
+        assert!(mesh.cells.iter().any(|c| c.is_walkable()));
+        assert!(mesh
+            .links
+            .iter()
+            .any(|l| l.flags.contains(LinkFlags::PORTAL) && l.is_walkable()));
+        assert!(mesh.links.iter().any(|l| !l.is_walkable()));
+        for cell in 0..mesh.cells.len() as u32 {
+            assert!(mesh.links_from(cell).iter().all(|l| l.from == cell));
+        }
+
+        let mut bytes = Vec::new();
+        mesh.to_writer(&mut bytes).unwrap();
+        assert_eq!(mesh, NavMesh::from_reader(&mut &bytes[..]).unwrap());
+    }
+}
@@ -0,0 +1,339 @@
+//! Strict & lenient parsing.
+//!
+//! [`Map::from_reader`](Map::from_reader) reads MAPs as they are, without
+//! checking them. Archivists want to know about anything suspicious, while
+//! engines want to load as much of a MAP as possible. [`ParseOptions`]
+//! picks one of the two behaviors:
+//!
+//! - Strict parsing rejects MAPs that would need any
+//!   [repairs](crate::repair).
+//! - Lenient parsing repairs MAPs, and keeps the sprites of MAPs truncated in
+//!   the middle of the sprites. The repairs are
+//!   [reported](Map::from_reader_with_report) to the caller.
+//!
+//! Both reject MAPs exceeding the limits of the options, as soon as the
+//! number of sectors, walls or sprites is read.
+//!
+//! Parsed MAPs are fixed up by the [quirks](crate::quirks) handler of their
+//! game.
+//!
+//! ```
+//! use map::{options::ParseOptions, Map};
+//!
+//! let bytes = include_bytes!("../tests/maps/E1L1.MAP");
+//! let map = Map::from_slice_with_options(bytes, &ParseOptions::strict()).unwrap();
+//! let truncated = Map::from_slice_with_options(&bytes[..bytes.len() - 50], &Default::default());
+//! assert_eq!(map.sprites.len() - 2, truncated.unwrap().sprites.len());
+//! ```
+use crate::{
+    player::Player,
+    progress::{Progress, Reporter, Section},
+    quirks::QuirksRegistry,
+    repair::RepairReport,
+    sector::{SectorStat, Sectors, WallStat},
+    sprite::{Sprite, SpriteStat},
+    tags::GameProfile,
+    Error, Map,
+};
+use byteorder::{ReadBytesExt, LE};
+use std::io::{Cursor, ErrorKind, Read};
+
+// size of a sprite in the file, in bytes.
+const SPRITE_SIZE: usize = 44;
+
+/// MAP parsing options.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseOptions {
+    /// Reject MAPs that would need repairs, instead of repairing them.
+    pub strict: bool,
+
+    /// Most sectors accepted.
+    pub max_sectors: usize,
+
+    /// Most walls accepted.
+    pub max_walls: usize,
+
+    /// Most sprites accepted.
+    pub max_sprites: usize,
+
+    /// Accept flags reserved in version 7 (used by some games & editors).
+    pub allow_unknown_bits: bool,
+
+    /// Game the MAP was made for, or `None` to
+    /// [detect it](GameProfile::detect).
+    pub game: Option<GameProfile>,
+}
+
+impl Default for ParseOptions {
+    /// Lenient parsing, without limits.
+    fn default() -> Self {
+        Self {
+            strict: false,
+            max_sectors: usize::from(u16::MAX),
+            max_walls: usize::from(u16::MAX),
+            max_sprites: usize::from(u16::MAX),
+            allow_unknown_bits: true,
+            game: None,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Strict parsing, with the limits of versions 8 & 9 (4096 sectors,
+    /// 16384 walls and 16384 sprites) and no unknown flags.
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            max_sectors: 4096,
+            max_walls: 16384,
+            max_sprites: 16384,
+            allow_unknown_bits: false,
+            game: None,
+        }
+    }
+
+    // Error if the number of elements of a section exceeds the limits.
+    fn limit(&self, section: Section, count: usize) -> Result<(), Error> {
+        let (max, what) = match section {
+            Section::Header => return Ok(()),
+            Section::Sectors => (self.max_sectors, "sectors"),
+            Section::Walls => (self.max_walls, "walls"),
+            Section::Sprites => (self.max_sprites, "sprites"),
+        };
+        if count > max {
+            Err(Error::LimitExceeded(what))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check(&self, map: &mut Map) -> Result<RepairReport, Error> {
+        if !self.allow_unknown_bits && has_unknown_bits(map) {
+            return Err(Error::Malformed("unknown flags"));
+        }
+        if !self.strict {
+            return Ok(map.repair());
+        }
+        let report = map.clone().repair();
+        let issues = [
+            (!report.wall_ranges.is_empty(), "invalid sector wall ranges"),
+            (!report.point2.is_empty(), "broken wall loops"),
+            (!report.portals.is_empty(), "dangling portals"),
+            (!report.sprites.is_empty(), "invalid sprite sectors"),
+            (
+                !report.unplaced_sprites.is_empty(),
+                "sprites outside of any sector",
+            ),
+            (report.player, "invalid player sector"),
+        ];
+        match issues.iter().find(|(issue, _)| *issue) {
+            Some((_, what)) => Err(Error::Malformed(what)),
+            None => Ok(report),
+        }
+    }
+}
+
+fn has_unknown_bits(map: &Map) -> bool {
+    let sectors = map.sectors.sectors.iter().any(|s| {
+        s.ceiling_stat.intersects(SectorStat::RESERVED)
+            || s.floor_stat.intersects(SectorStat::RESERVED)
+    });
+    let walls = map
+        .sectors
+        .walls
+        .iter()
+        .any(|w| w.wall_stat.intersects(WallStat::RESERVED));
+    let sprites = map.sprites.iter().any(|s| {
+        s.sprite_stat.intersects(SpriteStat::RESERVED)
+            || s.sprite_stat.contains(SpriteStat::RESERVED_SPRITE_TYPE)
+    });
+    sectors || walls || sprites
+}
+
+impl Map {
+    /// Parse MAP file from a reader, with the given options, applying the
+    /// built-in quirks of its game.
+    pub fn from_reader_with_options<R: Read>(
+        reader: &mut R,
+        options: &ParseOptions,
+    ) -> Result<Self, Error> {
+        Self::from_reader_with_quirks(reader, options, &QuirksRegistry::new())
+    }
+
+    /// Same as [`from_reader_with_options`](Self::from_reader_with_options),
+    /// applying the quirks of the given registry.
+    pub fn from_reader_with_quirks<R: Read>(
+        reader: &mut R,
+        options: &ParseOptions,
+        registry: &QuirksRegistry,
+    ) -> Result<Self, Error> {
+        Self::from_reader_with_report(reader, options, registry).map(|(map, _)| map)
+    }
+
+    /// Same as [`from_reader_with_quirks`](Self::from_reader_with_quirks),
+    /// also returning the repairs made by lenient parsing (always clean with
+    /// strict parsing).
+    pub fn from_reader_with_report<R: Read>(
+        reader: &mut R,
+        options: &ParseOptions,
+        registry: &QuirksRegistry,
+    ) -> Result<(Self, RepairReport), Error> {
+        // limits are checked from the progress reports, as soon as the
+        // number of elements of each section is read.
+        let mut exceeded = None;
+        let mut on_progress = |progress| match progress {
+            Progress::Started { section, count } => match options.limit(section, count) {
+                Ok(()) => true,
+                Err(err) => {
+                    exceeded = Some(err);
+                    false
+                }
+            },
+            _ => true,
+        };
+        let map = if options.strict {
+            Self::from_reader_with_progress(reader, &mut on_progress)
+        } else {
+            from_reader_lenient(reader, &mut on_progress)
+        };
+        let mut map = match (map, exceeded) {
+            (Err(Error::Cancelled), Some(err)) => return Err(err),
+            (map, _) => map?,
+        };
+        let report = options.check(&mut map)?;
+        let game = options.game.unwrap_or_else(|| GameProfile::detect(&map));
+        if let Some(quirks) = registry.get(game) {
+            map.apply_quirks(quirks);
+        }
+        Ok((map, report))
+    }
+
+    /// Parse MAP file from a byte slice, with the given options.
+    pub fn from_slice_with_options(slice: &[u8], options: &ParseOptions) -> Result<Self, Error> {
+        Self::from_reader_with_options(&mut Cursor::new(slice), options)
+    }
+}
+
+// Parses the MAP, keeping the sprites read before the end of the input.
+fn from_reader_lenient<R: Read>(
+    reader: &mut R,
+    on_progress: &mut dyn FnMut(Progress) -> bool,
+) -> Result<Map, Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    match Map::from_slice_with_progress(&bytes, &mut *on_progress) {
+        Err(Error::Io(err)) if err.kind() == ErrorKind::UnexpectedEof => {}
+        result => return result,
+    }
+    let mut cursor = Cursor::new(&bytes[..]);
+    let version = cursor.read_i32::<LE>()?;
+    if !(7..=9).contains(&version) {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    let player = Player::from_reader(&mut cursor)?;
+    let mut reporter = Reporter(on_progress);
+    let sectors = Sectors::from_reader(&mut cursor, &mut reporter)?;
+    let count = usize::from(cursor.read_u16::<LE>().unwrap_or(0));
+    let available = (bytes.len() - cursor.position() as usize) / SPRITE_SIZE;
+    let sprites = reporter.section(Section::Sprites, count.min(available), || {
+        Sprite::from_reader(&mut cursor)
+    })?;
+    Ok(Map::new(version, player, sectors, sprites))
+}
+
+#[cfg(test)]
+mod test {
+    use super::ParseOptions;
+    use crate::{quirks::QuirksRegistry, sector::WallStat, Error, Map};
+    use std::io::Cursor;
+
+    #[test]
+    fn strict() {
+        let bytes = include_bytes!("../tests/maps/E1L1.MAP");
+        let mut map = Map::from_slice(bytes).unwrap();
+        map.sectors.walls[3].point2 = -1;
+        let broken = map.to_vec().unwrap();
+        assert!(matches!(
+            Map::from_slice_with_options(&broken, &ParseOptions::strict()),
+            Err(Error::Malformed("broken wall loops"))
+        ));
+        let repaired = Map::from_slice_with_options(&broken, &Default::default()).unwrap();
+        assert_eq!(&bytes[..], &repaired.to_vec().unwrap()[..]);
+
+        let options = ParseOptions {
+            max_sprites: 10,
+            ..ParseOptions::strict()
+        };
+        assert!(matches!(
+            Map::from_slice_with_options(bytes, &options),
+            Err(Error::LimitExceeded("sprites"))
+        ));
+        let mut map = Map::from_slice(bytes).unwrap();
+        map.sectors.walls[0].wall_stat |= WallStat::RESERVED;
+        assert!(matches!(
+            Map::from_slice_with_options(&map.to_vec().unwrap(), &ParseOptions::strict()),
+            Err(Error::Malformed("unknown flags"))
+        ));
+    }
+
+    #[test]
+    fn limits() {
+        let bytes = include_bytes!("../tests/maps/E1L1.MAP");
+        let lenient = ParseOptions {
+            max_sectors: 1,
+            ..Default::default()
+        };
+        let strict = ParseOptions {
+            strict: true,
+            ..lenient.clone()
+        };
+        // rejected as soon as the sectors are counted, before reaching the
+        // truncated data.
+        for bytes in [&bytes[..], &bytes[..200]] {
+            for options in [&lenient, &strict] {
+                assert!(matches!(
+                    Map::from_slice_with_options(bytes, options),
+                    Err(Error::LimitExceeded("sectors"))
+                ));
+            }
+        }
+        let options = ParseOptions {
+            max_walls: 10,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Map::from_slice_with_options(bytes, &options),
+            Err(Error::LimitExceeded("walls"))
+        ));
+    }
+
+    #[test]
+    fn report() {
+        let bytes = include_bytes!("../tests/maps/E1L1.MAP");
+        let registry = QuirksRegistry::new();
+        let parse = |bytes: &[u8], options: &ParseOptions| {
+            Map::from_reader_with_report(&mut Cursor::new(bytes), options, &registry)
+        };
+        let (_, report) = parse(bytes, &ParseOptions::strict()).unwrap();
+        assert!(report.is_clean());
+
+        let mut map = Map::from_slice(bytes).unwrap();
+        map.sectors.walls[3].point2 = -1;
+        let broken = map.to_vec().unwrap();
+        let (map, report) = parse(&broken, &Default::default()).unwrap();
+        assert_eq!(vec![3], report.point2);
+        assert_eq!(&bytes[..], &map.to_vec().unwrap()[..]);
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = include_bytes!("../tests/maps/E1L1.MAP");
+        let map = Map::from_slice(bytes).unwrap();
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(Map::from_slice_with_options(truncated, &ParseOptions::strict()).is_err());
+        let lenient = Map::from_slice_with_options(truncated, &Default::default()).unwrap();
+        assert_eq!(map.sprites.len() - 1, lenient.sprites.len());
+        // truncated geometry can't be recovered
+        assert!(Map::from_slice_with_options(&bytes[..100], &Default::default()).is_err());
+    }
+}
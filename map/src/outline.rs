@@ -0,0 +1,183 @@
+//! Floorplan outlines.
+use crate::Map;
+use std::collections::{HashMap, HashSet};
+
+type Point = [i32; 2];
+
+/// A simple polygon with holes, in MAP coordinates.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Polygon {
+    /// Outer contour, clockwise (as Build's outer wall loops).
+    pub exterior: Vec<Point>,
+
+    /// Inner contours, counter-clockwise.
+    pub holes: Vec<Vec<Point>>,
+}
+
+/// Unions the polygons of all sectors into simplified outlines.
+///
+/// Walls between sectors (portals, or pairs of overlapping solid walls) are
+/// dropped, and the remaining walls are chained into contours with collinear
+/// vertices removed. Holes are assigned to the smallest outer contour
+/// containing them; holes outside of any contour are discarded.
+pub fn outline(map: &Map) -> Vec<Polygon> {
+    let walls = map.sectors.walls();
+    let point = |wall: &crate::sector::Wall| [wall.x, wall.y];
+
+    // directed boundary edges.
+    let mut edges = HashSet::new();
+    for wall in walls {
+        if wall.next_wall != -1 || wall.point2 < 0 {
+            continue;
+        }
+        let right = match walls.get(wall.point2 as usize) {
+            Some(right) => right,
+            None => continue,
+        };
+        let edge = (point(wall), point(right));
+        if edge.0 == edge.1 {
+            continue;
+        }
+        // solid walls shared by two sectors cancel each other.
+        if !edges.remove(&(edge.1, edge.0)) {
+            edges.insert(edge);
+        }
+    }
+
+    let mut outgoing: HashMap<Point, Vec<Point>> = HashMap::new();
+    for &(from, to) in &edges {
+        outgoing.entry(from).or_default().push(to);
+    }
+    for targets in outgoing.values_mut() {
+        // deterministic contours regardless of hashing order.
+        targets.sort_unstable();
+    }
+    let mut starts: Vec<_> = outgoing.keys().copied().collect();
+    starts.sort_unstable();
+
+    let mut contours = Vec::new();
+    for start in starts {
+        while let Some(mut next) = outgoing.get_mut(&start).and_then(Vec::pop) {
+            let mut contour = vec![start];
+            while next != start {
+                contour.push(next);
+                next = match outgoing.get_mut(&next).and_then(Vec::pop) {
+                    Some(next) => next,
+                    // open chain, from broken wall loops.
+                    None => break,
+                };
+            }
+            let contour = simplify(contour);
+            if contour.len() >= 3 {
+                contours.push(contour);
+            }
+        }
+    }
+
+    let (exteriors, holes): (Vec<_>, Vec<_>) =
+        contours.into_iter().partition(|c| signed_area(c) > 0);
+    let mut polygons: Vec<_> = exteriors
+        .into_iter()
+        .map(|exterior| Polygon {
+            exterior,
+            holes: Vec::new(),
+        })
+        .collect();
+    for hole in holes {
+        let parent = polygons
+            .iter_mut()
+            .filter(|p| contains(&p.exterior, hole[0]))
+            .min_by_key(|p| signed_area(&p.exterior));
+        if let Some(parent) = parent {
+            parent.holes.push(hole);
+        }
+    }
+    polygons
+}
+
+/// Removes repeated and collinear vertices.
+fn simplify(mut contour: Vec<Point>) -> Vec<Point> {
+    contour.dedup();
+    let mut changed = true;
+    while changed && contour.len() >= 3 {
+        changed = false;
+        let mut i = 0;
+        while i < contour.len() && contour.len() >= 3 {
+            let len = contour.len();
+            let prev = contour[(i + len - 1) % len];
+            let next = contour[(i + 1) % len];
+            if cross(prev, contour[i], next) == 0 {
+                contour.remove(i);
+                changed = true;
+            } else {
+                i += 1;
+            }
+        }
+    }
+    contour
+}
+
+fn cross([ax, ay]: Point, [bx, by]: Point, [cx, cy]: Point) -> i64 {
+    let (abx, aby) = (i64::from(bx - ax), i64::from(by - ay));
+    let (acx, acy) = (i64::from(cx - ax), i64::from(cy - ay));
+    abx * acy - aby * acx
+}
+
+/// Twice the signed area. Positive for clockwise contours (Y pointing down).
+fn signed_area(contour: &[Point]) -> i64 {
+    let len = contour.len();
+    (0..len)
+        .map(|i| {
+            let [ax, ay] = contour[i];
+            let [bx, by] = contour[(i + 1) % len];
+            i64::from(ax) * i64::from(by) - i64::from(bx) * i64::from(ay)
+        })
+        .sum()
+}
+
+/// Even-odd point in polygon test.
+fn contains(contour: &[Point], [x, y]: Point) -> bool {
+    let (x, y) = (i64::from(x), i64::from(y));
+    let mut inside = false;
+    for i in 0..contour.len() {
+        let [ax, ay] = contour[i];
+        let [bx, by] = contour[(i + 1) % contour.len()];
+        let (ax, ay, bx, by) = (i64::from(ax), i64::from(ay), i64::from(bx), i64::from(by));
+        if (ay > y) != (by > y) {
+            let lhs = (x - ax) * (by - ay);
+            let rhs = (bx - ax) * (y - ay);
+            if (by > ay && lhs < rhs) || (by < ay && lhs > rhs) {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod test {
+    use super::{outline, signed_area, simplify};
+    use crate::Map;
+
+    #[test]
+    fn simplify_collinear() {
+        let square = vec![[0, 0], [5, 0], [10, 0], [10, 10], [10, 10], [0, 10]];
+        assert_eq!(vec![[0, 0], [10, 0], [10, 10], [0, 10]], simplify(square));
+    }
+
+    #[test]
+    fn outline_e1l1() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let polygons = outline(&map);
+        assert!(!polygons.is_empty());
+        let vertices: usize = polygons
+            .iter()
+            .map(|p| p.exterior.len() + p.holes.iter().map(Vec::len).sum::<usize>())
+            .sum();
+        assert!(vertices < map.sectors.walls().len());
+        for polygon in &polygons {
+            assert!(signed_area(&polygon.exterior) > 0);
+            assert!(polygon.holes.iter().all(|h| signed_area(h) < 0));
+        }
+    }
+}
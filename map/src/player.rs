@@ -1,8 +1,11 @@
+#[cfg(feature = "std")]
 use crate::Error;
-use byteorder::{ReadBytesExt, LE};
-use std::io::Read;
+#[cfg(feature = "std")]
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[repr(C)]
 pub struct Player {
     // position
@@ -17,6 +20,7 @@ pub struct Player {
     pub sector: i16,
 }
 
+#[cfg(feature = "std")]
 impl Player {
     pub(crate) fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
         Ok(Self {
@@ -27,20 +31,127 @@ impl Player {
             sector: reader.read_i16::<LE>()?,
         })
     }
+
+    pub(crate) fn to_writer<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_i32::<LE>(self.pos_x)?;
+        writer.write_i32::<LE>(self.pos_y)?;
+        writer.write_i32::<LE>(self.pos_z)?;
+        writer.write_i16::<LE>(self.angle.0)?;
+        writer.write_i16::<LE>(self.sector)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+/// Binary angle (BAM), in 2048 units per revolution.
+///
+/// 0 points towards +X and angles grow clockwise (towards +Y, as Y points
+/// south in Build maps), so 512 points towards +Y. Arithmetic wraps around a
+/// full revolution.
+///
+/// ```
+/// use map::player::Angle;
+///
+/// assert_eq!(Angle(1792), Angle(256) - Angle(512));
+/// assert_eq!(Angle(2024), Angle(1900).lerp(Angle(100), 0.5));
+/// assert_eq!(Angle(512), Angle::from_radians(Angle(512).to_radians()));
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 #[repr(transparent)]
 pub struct Angle(pub i16);
 
 impl Angle {
+    /// Number of units in a full revolution.
+    pub const REVOLUTION: i16 = 2048;
+
+    const MASK: i16 = Self::REVOLUTION - 1;
+
+    /// Inverse of [`to_radians`](Self::to_radians).
+    #[cfg(feature = "std")]
+    pub fn from_radians(radians: f32) -> Self {
+        const PI2: f64 = core::f64::consts::PI * 2.0;
+        let a = (f64::from(radians) + core::f64::consts::PI / 2.0) / PI2 * f64::from(Self::MASK);
+        Self(a.round() as i64 as i16).normalized()
+    }
+
+    /// Inverse of [`to_degrees`](Self::to_degrees).
+    #[cfg(feature = "std")]
+    pub fn from_degrees(degrees: f32) -> Self {
+        Self::from_radians(degrees.to_radians())
+    }
+
+    /// Angle in radians, offset by a quarter turn (0 units is -π/2) as
+    /// expected by the software renderer camera.
     pub fn to_radians(&self) -> f32 {
         // All angles are between 0..2047 inclusive. 0 is "north", parallel to the
         // Y-axis, moving away from the X-axis. 512 is "east", parallel to the X-axis
         // moving away from the Y-axis.
-        const PI2: f64 = std::f64::consts::PI * 2.0;
+        const PI2: f64 = core::f64::consts::PI * 2.0;
         const RANGE: i16 = 0x7ff;
-        let a = (self.0 & RANGE) as f64 / (RANGE as f64) * PI2 - std::f64::consts::PI / 2.0;
+        let a = (self.0 & RANGE) as f64 / (RANGE as f64) * PI2 - core::f64::consts::PI / 2.0;
         a as _
     }
+
+    /// Same as [`to_radians`](Self::to_radians), in degrees.
+    pub fn to_degrees(&self) -> f32 {
+        self.to_radians().to_degrees()
+    }
+
+    /// Returns the equivalent angle in the `0..2048` range.
+    pub fn normalized(self) -> Self {
+        Self(self.0 & Self::MASK)
+    }
+
+    /// Unit vector pointing in the direction of the angle, in map XY
+    /// coordinates.
+    #[cfg(feature = "std")]
+    pub fn direction(&self) -> [f32; 2] {
+        let a = f32::from(self.normalized().0) * core::f32::consts::PI * 2.0 / 2048.0;
+        [a.cos(), a.sin()]
+    }
+
+    /// Interpolates towards another angle along the shortest arc.
+    #[cfg(feature = "std")]
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        let mut delta = (other - self).0;
+        if delta >= Self::REVOLUTION / 2 {
+            delta -= Self::REVOLUTION;
+        }
+        self + Self((f32::from(delta) * t).round() as i16)
+    }
+}
+
+impl core::ops::Add for Angle {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_add(rhs.0)).normalized()
+    }
+}
+
+impl core::ops::Sub for Angle {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_sub(rhs.0)).normalized()
+    }
+}
+
+impl core::ops::Neg for Angle {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.wrapping_neg()).normalized()
+    }
+}
+
+impl core::ops::AddAssign for Angle {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl core::ops::SubAssign for Angle {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
 }
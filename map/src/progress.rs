@@ -0,0 +1,85 @@
+//! Parsing progress reports.
+//!
+//! ```
+//! use map::{progress::Progress, Map};
+//!
+//! let bytes = include_bytes!("../tests/maps/E1L1.MAP");
+//! let mut events = Vec::new();
+//! let map = Map::from_slice_with_progress(bytes, |progress| {
+//!     events.push(progress);
+//!     true
+//! })
+//! .unwrap();
+//! assert!(events.contains(&Progress::Completed { section: map::progress::Section::Sprites }));
+//! ```
+use crate::Error;
+
+/// Number of elements parsed between [`Progress::Advanced`] reports.
+pub const REPORT_INTERVAL: usize = 1024;
+
+/// Sections of a MAP file, in file order.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Section {
+    /// File version & player start.
+    Header,
+    Sectors,
+    Walls,
+    Sprites,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Progress {
+    /// A section with the given number of elements started parsing.
+    Started { section: Section, count: usize },
+
+    /// Some elements of the section have been parsed.
+    Advanced {
+        section: Section,
+        done: usize,
+        count: usize,
+    },
+
+    /// A section finished parsing.
+    Completed { section: Section },
+}
+
+/// Forwards progress to a callback, which returns `false` to cancel parsing.
+pub(crate) struct Reporter<'a>(pub(crate) &'a mut dyn FnMut(Progress) -> bool);
+
+impl Reporter<'_> {
+    pub(crate) fn report(&mut self, progress: Progress) -> Result<(), Error> {
+        if (self.0)(progress) {
+            Ok(())
+        } else {
+            Err(Error::Cancelled)
+        }
+    }
+
+    /// Parses `count` elements of a section.
+    pub(crate) fn section<T, F>(
+        &mut self,
+        section: Section,
+        count: usize,
+        mut f: F,
+    ) -> Result<Vec<T>, Error>
+    where
+        F: FnMut() -> Result<T, Error>,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("section", ?section, count).entered();
+        self.report(Progress::Started { section, count })?;
+        let mut items = Vec::with_capacity(count);
+        for done in 1..=count {
+            items.push(f()?);
+            if done % REPORT_INTERVAL == 0 || done == count {
+                self.report(Progress::Advanced {
+                    section,
+                    done,
+                    count,
+                })?;
+            }
+        }
+        self.report(Progress::Completed { section })?;
+        Ok(items)
+    }
+}
@@ -0,0 +1,232 @@
+//! Potentially visible sets.
+//!
+//! A sector can only be seen from another one through a sequence of portals
+//! that a straight line can cross in order. Such a line never comes back to
+//! the near side of a portal it crossed, so a portal is discarded when it's
+//! fully behind the first portal of the sequence, or behind the portal
+//! before it. The test is conservative: sectors that are actually hidden may
+//! still be reported as visible, but never the other way around.
+//!
+//! ```
+//! use map::{pvs, Map};
+//!
+//! let map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+//! let pvs = pvs::compute(&map);
+//! let sector = map.player.sector;
+//! assert!(pvs.is_visible(sector, sector));
+//! ```
+use crate::{
+    sector::{SectorId, Wall},
+    Map,
+};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::{
+    collections::HashSet,
+    io::{self, Read, Write},
+};
+
+const MAGIC: &[u8; 4] = b"PVS\0";
+const VERSION: u32 = 1;
+// as many as `SectorId`s can index.
+const MAX_SECTORS: usize = SectorId::MAX as usize + 1;
+
+/// Sector-to-sector potential visibility of a MAP.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Pvs {
+    sectors: usize,
+    // one row of bits per sector.
+    bits: Vec<u64>,
+}
+
+impl Pvs {
+    fn new(sectors: usize) -> Self {
+        Self {
+            sectors,
+            bits: vec![0; sectors * Self::words(sectors)],
+        }
+    }
+
+    // words per row
+    fn words(sectors: usize) -> usize {
+        sectors.div_ceil(64)
+    }
+
+    /// Number of sectors of the MAP the set was computed for.
+    pub fn sectors(&self) -> usize {
+        self.sectors
+    }
+
+    /// Returns true if `to` may be visible from anywhere inside `from`.
+    /// Sectors out of bounds are reported as visible.
+    pub fn is_visible(&self, from: SectorId, to: SectorId) -> bool {
+        let (from, to) = (from as usize, to as usize);
+        if from >= self.sectors || to >= self.sectors {
+            return true;
+        }
+        let word = self.bits[from * Self::words(self.sectors) + to / 64];
+        word & (1 << (to % 64)) != 0
+    }
+
+    /// Sectors that may be visible from `from`, in ascending order.
+    pub fn visible_from(&self, from: SectorId) -> Vec<SectorId> {
+        (0..self.sectors as SectorId)
+            .filter(|to| self.is_visible(from, *to))
+            .collect()
+    }
+
+    fn set(&mut self, from: usize, to: usize) {
+        self.bits[from * Self::words(self.sectors) + to / 64] |= 1 << (to % 64);
+    }
+
+    /// Serializes the set, as little endian:
+    ///
+    /// - `"PVS\0"` magic & `u32` version (1).
+    /// - `u32` number of sectors, followed by one row of `u64` words per
+    ///   sector (bit `i` of word `j` flags sector `64 * j + i`).
+    ///
+    /// It's meant to be stored next to the MAP file (e.g. `E1L1.PVS`), and
+    /// needs to be recomputed whenever the MAP changes.
+    pub fn to_writer<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<LE>(VERSION)?;
+        writer.write_u32::<LE>(self.sectors as u32)?;
+        for word in &self.bits {
+            writer.write_u64::<LE>(*word)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a set written by [`to_writer`](Self::to_writer).
+    pub fn from_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC || reader.read_u32::<LE>()? != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsupported pvs",
+            ));
+        }
+        let sectors = reader.read_u32::<LE>()? as usize;
+        if sectors > MAX_SECTORS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "too many sectors",
+            ));
+        }
+        // grows as words are read, so truncated inputs fail before the whole
+        // set is allocated.
+        let len = sectors * Self::words(sectors);
+        let mut bits = Vec::new();
+        for _ in 0..len {
+            bits.push(reader.read_u64::<LE>()?);
+        }
+        Ok(Self { sectors, bits })
+    }
+}
+
+/// Computes the potentially visible set of every sector of the MAP.
+pub fn compute(map: &Map) -> Pvs {
+    let sectors = map.sectors.sectors().len();
+    let mut pvs = Pvs::new(sectors);
+    for from in 0..sectors {
+        pvs.set(from, from);
+        for to in visible_from(map, from as SectorId) {
+            pvs.set(from, to as usize);
+        }
+    }
+    pvs
+}
+
+// sectors seen through the portals of a sector.
+fn visible_from(map: &Map, sector: SectorId) -> Vec<SectorId> {
+    let walls = map.sectors.walls();
+    let sectors = map.sectors.sectors();
+    // every loop of the sector (`Sectors::get` only follows the first one).
+    let portals = |sector: SectorId| -> Vec<(usize, &Wall, &Wall)> {
+        let range = match sectors.get(sector as usize) {
+            Some(sector) => sector.wall_range(),
+            None => return Vec::new(),
+        };
+        range
+            .filter(|id| *id < walls.len())
+            .map(|id| (id, &walls[id]))
+            .filter(|(_, left)| {
+                left.next_sector >= 0 && (left.next_sector as usize) < sectors.len()
+            })
+            .filter_map(|(id, left)| Some((id, left, walls.get(left.point2 as usize)?)))
+            .collect()
+    };
+    let mut visible = Vec::new();
+    for (first, first_left, first_right) in portals(sector) {
+        visible.push(first_left.next_sector);
+        // portals already crossed after `first`: crossing one again leads to
+        // the same portals, as only `first` and the previous portal matter.
+        let mut crossed = HashSet::new();
+        let mut stack = vec![(first, first_left.next_sector)];
+        while let Some((prev, sector)) = stack.pop() {
+            let (prev_left, prev_right) = (&walls[prev], &walls[walls[prev].point2 as usize]);
+            for (wall, left, right) in portals(sector) {
+                if wall as i16 == prev_left.next_wall
+                    || !beyond(first_left, first_right, left, right)
+                    || !beyond(prev_left, prev_right, left, right)
+                    || !crossed.insert(wall)
+                {
+                    continue;
+                }
+                visible.push(left.next_sector);
+                stack.push((wall, left.next_sector));
+            }
+        }
+    }
+    visible
+}
+
+// true if any point of the `left`-`right` segment is on the far side of the
+// portal (walls keep the inside of their sector to their left), or on it.
+fn beyond(portal_left: &Wall, portal_right: &Wall, left: &Wall, right: &Wall) -> bool {
+    let (lx, ly) = (f64::from(portal_left.x), f64::from(portal_left.y));
+    let (wx, wy) = (
+        f64::from(portal_right.x) - lx,
+        f64::from(portal_right.y) - ly,
+    );
+    let side = |w: &Wall| wx * (f64::from(w.y) - ly) - wy * (f64::from(w.x) - lx);
+    side(left) <= 0.0 || side(right) <= 0.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compute, Pvs, MAX_SECTORS};
+    use crate::{analysis, Map};
+
+    #[test]
+    fn subset_of_reachable() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let pvs = compute(&map);
+        let sector = map.player.sector;
+        let reachable = analysis::reachable_from(&map, sector, &analysis::Reachability::default());
+        let visible = pvs.visible_from(sector);
+        assert!(visible.contains(&sector));
+        assert!(visible.iter().all(|s| reachable.contains(s)));
+        // neighbours are always visible.
+        for neighbour in &analysis::adjacency(&map)[sector as usize] {
+            assert!(pvs.is_visible(sector, *neighbour));
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/SIMPLE0.MAP")).unwrap();
+        let pvs = compute(&map);
+        let mut bytes = Vec::new();
+        pvs.to_writer(&mut bytes).unwrap();
+        assert_eq!(pvs, Pvs::from_reader(&mut &bytes[..]).unwrap());
+        assert!(Pvs::from_reader(&mut &bytes[1..]).is_err());
+        // huge sector counts are rejected without allocating the set.
+        let mut huge = bytes[..8].to_vec();
+        huge.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Pvs::from_reader(&mut &huge[..]).is_err());
+        let mut truncated = bytes[..8].to_vec();
+        truncated.extend_from_slice(&(MAX_SECTORS as u32).to_le_bytes());
+        assert!(Pvs::from_reader(&mut &truncated[..]).is_err());
+    }
+}
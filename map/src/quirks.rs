@@ -0,0 +1,260 @@
+//! Per-game compatibility layer.
+//!
+//! Games derived from the engine reuse the MAP format but deviate in how
+//! sprites, walls and sectors are used. A [`Quirks`](Quirks) handler fixes up
+//! parsed data and decodes game-specific tags, so the rest of the crate
+//! doesn't have to assume Duke Nukem 3D semantics.
+//!
+//! [`Map::from_reader_with_options`](Map::from_reader_with_options) applies
+//! the handler of the game set in the options (or detected from the MAP).
+use crate::{
+    sector::{Sector, Wall},
+    sound::AmbientSound,
+    sprite::{Sprite, SpriteId},
+    tags::{self, GameProfile},
+    Map,
+};
+use std::collections::HashMap;
+
+/// Behavior of a sector, decoded from its lotag.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum SectorKind {
+    /// Floor lowers to the next floor below, and back up.
+    PlatformDown,
+
+    /// Floor raises to the next floor above, and back down.
+    PlatformUp,
+
+    /// Ceiling raises to the next ceiling above, and back down.
+    CeilingDoor,
+
+    /// Door sliding sideways (Redneck Rampage).
+    JailDoor,
+
+    /// Mine cart track (Redneck Rampage).
+    MineCart,
+
+    /// Secret place.
+    Secret,
+
+    /// Ends the level when entered.
+    EndOfLevel,
+}
+
+impl SectorKind {
+    /// Decodes a sector lotag, following the conventions of the given game.
+    /// The [locked](tags::duke::SECTOR_LOCKED) flag is ignored.
+    ///
+    /// Shadow Warrior sectors get their behavior from `ST1` sprites rather
+    /// than from their lotag, so they always decode to `None`.
+    pub fn from_lotag(profile: GameProfile, lotag: i16) -> Option<Self> {
+        use tags::{duke, rr};
+        if profile == GameProfile::ShadowWarrior {
+            return None;
+        }
+        match lotag {
+            duke::ST_SECRET_PLACE => return Some(Self::Secret),
+            duke::ST_END_OF_LEVEL => return Some(Self::EndOfLevel),
+            _ => {}
+        }
+        match (profile, lotag & !duke::SECTOR_LOCKED) {
+            (_, duke::ST_16_PLATFORM_DOWN) => Some(Self::PlatformDown),
+            (_, duke::ST_17_PLATFORM_UP) => Some(Self::PlatformUp),
+            (_, duke::ST_20_CEILING_DOOR) => Some(Self::CeilingDoor),
+            (GameProfile::RedneckRampage, rr::ST_41_JAILDOOR) => Some(Self::JailDoor),
+            (GameProfile::RedneckRampage, rr::ST_42_MINECART) => Some(Self::MineCart),
+            _ => None,
+        }
+    }
+}
+
+/// Game-specific behavior.
+pub trait Quirks {
+    /// Game the handler implements.
+    fn profile(&self) -> GameProfile;
+
+    /// Fix up a sector after parsing.
+    fn fix_sector(&self, _sector: &mut Sector) {}
+
+    /// Fix up a wall after parsing.
+    fn fix_wall(&self, _wall: &mut Wall) {}
+
+    /// Fix up a sprite after parsing.
+    fn fix_sprite(&self, _sprite: &mut Sprite) {}
+
+    /// Decode the ambient sound emitter of a sprite.
+    fn ambient_sound(&self, id: SpriteId, sprite: &Sprite) -> Option<AmbientSound> {
+        AmbientSound::from_sprite(self.profile(), id, sprite)
+    }
+
+    /// Decode the behavior of a sector.
+    fn sector_kind(&self, sector: &Sector) -> Option<SectorKind> {
+        SectorKind::from_lotag(self.profile(), sector.lotag)
+    }
+}
+
+/// Duke Nukem 3D (reference behavior).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Duke3D;
+
+impl Quirks for Duke3D {
+    fn profile(&self) -> GameProfile {
+        GameProfile::Duke3D
+    }
+}
+
+/// Shadow Warrior.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ShadowWarrior;
+
+impl Quirks for ShadowWarrior {
+    fn profile(&self) -> GameProfile {
+        GameProfile::ShadowWarrior
+    }
+}
+
+/// Redneck Rampage. Shares Duke Nukem 3D's effector and sound tags, and adds
+/// [jail door & mine cart](tags::rr) sectors.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RedneckRampage;
+
+impl Quirks for RedneckRampage {
+    fn profile(&self) -> GameProfile {
+        GameProfile::RedneckRampage
+    }
+}
+
+/// Quirk handlers indexed by game profile.
+///
+/// Built-in handlers are registered by default and can be replaced by custom
+/// ones.
+pub struct QuirksRegistry {
+    handlers: HashMap<GameProfile, Box<dyn Quirks>>,
+}
+
+impl std::fmt::Debug for QuirksRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.handlers.keys()).finish()
+    }
+}
+
+impl Default for QuirksRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuirksRegistry {
+    /// Registry with the built-in handlers.
+    pub fn new() -> Self {
+        let mut registry = Self {
+            handlers: HashMap::new(),
+        };
+        registry.register(Box::new(Duke3D));
+        registry.register(Box::new(ShadowWarrior));
+        registry.register(Box::new(RedneckRampage));
+        registry
+    }
+
+    /// Registers a handler for its profile, returning the replaced one.
+    pub fn register(&mut self, quirks: Box<dyn Quirks>) -> Option<Box<dyn Quirks>> {
+        self.handlers.insert(quirks.profile(), quirks)
+    }
+
+    /// Returns the handler of the given profile.
+    pub fn get(&self, profile: GameProfile) -> Option<&dyn Quirks> {
+        self.handlers.get(&profile).map(|q| q.as_ref())
+    }
+}
+
+impl Map {
+    /// Fixes up all sectors, walls and sprites with the given handler.
+    pub fn apply_quirks(&mut self, quirks: &dyn Quirks) {
+        self.sectors
+            .sectors
+            .iter_mut()
+            .for_each(|s| quirks.fix_sector(s));
+        self.sectors
+            .walls
+            .iter_mut()
+            .for_each(|w| quirks.fix_wall(w));
+        self.sprites.iter_mut().for_each(|s| quirks.fix_sprite(s));
+    }
+
+    /// Returns the behavior of every sector, decoded by the given handler.
+    pub fn sector_kinds_with(&self, quirks: &dyn Quirks) -> Vec<Option<SectorKind>> {
+        self.sectors
+            .sectors
+            .iter()
+            .map(|s| quirks.sector_kind(s))
+            .collect()
+    }
+
+    /// Returns the ambient sound emitters decoded by the given handler.
+    pub fn ambient_sounds_with(&self, quirks: &dyn Quirks) -> Vec<AmbientSound> {
+        self.sprites
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| quirks.ambient_sound(i as _, s))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Quirks, QuirksRegistry, SectorKind};
+    use crate::{
+        options::ParseOptions,
+        sprite::Sprite,
+        tags::{duke, rr, GameProfile},
+        Map,
+    };
+
+    #[test]
+    fn redneck_quirks() {
+        // stores the hitag of sprites in their extra field.
+        struct Custom;
+        impl Quirks for Custom {
+            fn profile(&self) -> GameProfile {
+                GameProfile::RedneckRampage
+            }
+            fn fix_sprite(&self, sprite: &mut Sprite) {
+                sprite.extra = sprite.hitag;
+            }
+        }
+
+        let mut map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        assert_eq!(GameProfile::Duke3D, GameProfile::detect(&map));
+        map.sectors.sectors[1].lotag = rr::ST_41_JAILDOOR;
+        map.sectors.sectors[2].lotag = rr::ST_42_MINECART | duke::SECTOR_LOCKED;
+        assert_eq!(GameProfile::RedneckRampage, GameProfile::detect(&map));
+
+        let registry = QuirksRegistry::new();
+        let duke = map.sector_kinds_with(registry.get(GameProfile::Duke3D).unwrap());
+        let rr = map.sector_kinds_with(registry.get(GameProfile::RedneckRampage).unwrap());
+        assert_eq!((None, None), (duke[1], duke[2]));
+        assert_eq!(
+            (Some(SectorKind::JailDoor), Some(SectorKind::MineCart)),
+            (rr[1], rr[2])
+        );
+        // both games share the rest of the sector tags
+        let differ = duke.iter().zip(&rr).filter(|(d, r)| d != r).count();
+        assert_eq!(2, differ);
+
+        // quirks of the detected game are applied when parsing
+        assert!(map.sprites.iter().any(|s| s.extra != s.hitag));
+        let bytes = map.to_vec().unwrap();
+        let mut registry = QuirksRegistry::new();
+        registry.register(Box::new(Custom));
+        let options = ParseOptions::default();
+        let parsed = Map::from_reader_with_quirks(&mut &bytes[..], &options, &registry).unwrap();
+        assert!(parsed.sprites.iter().all(|s| s.extra == s.hitag));
+        let options = ParseOptions {
+            game: Some(GameProfile::Duke3D),
+            ..ParseOptions::default()
+        };
+        let parsed = Map::from_reader_with_quirks(&mut &bytes[..], &options, &registry).unwrap();
+        let extra = |map: &Map| map.sprites.iter().map(|s| s.extra).collect::<Vec<_>>();
+        assert_eq!(extra(&map), extra(&parsed));
+    }
+}
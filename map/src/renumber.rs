@@ -0,0 +1,122 @@
+use crate::{
+    sector::{Sector, Wall},
+    Map,
+};
+use std::cmp::Ordering;
+
+/// Summary of the elements removed by [`Map::compact`](Map::compact).
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Compaction {
+    pub removed_sectors: usize,
+    pub removed_walls: usize,
+    pub removed_sprites: usize,
+}
+
+impl Map {
+    /// Removes sectors without walls, walls not owned by any sector, and
+    /// sprites placed in removed (or invalid) sectors. Remaining elements are
+    /// renumbered densely and all references to them are updated.
+    pub fn compact(&mut self) -> Compaction {
+        let order: Vec<_> = self
+            .sectors
+            .sectors
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.wallnum > 0)
+            .map(|(i, _)| i)
+            .collect();
+        self.renumber(&order)
+    }
+
+    /// Sorts sectors with the given comparator function, renumbering walls
+    /// and sprites so that all references remain valid. The sort is stable.
+    pub fn sort_sectors_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&Sector, &Sector) -> Ordering,
+    {
+        let sectors = &self.sectors.sectors;
+        let mut order: Vec<_> = (0..sectors.len()).collect();
+        order.sort_by(|a, b| compare(&sectors[*a], &sectors[*b]));
+        self.renumber(&order);
+    }
+
+    // Rebuild sectors following the given order of (old) sector indices.
+    // Sectors not present in `order` are removed along with their walls.
+    fn renumber(&mut self, order: &[usize]) -> Compaction {
+        let old_sectors = std::mem::take(&mut self.sectors.sectors);
+        let old_walls = std::mem::take(&mut self.sectors.walls);
+        let num_sectors = old_sectors.len();
+        let num_walls = old_walls.len();
+
+        let mut sector_map = vec![-1; num_sectors];
+        let mut wall_map = vec![-1; num_walls];
+        let mut old_sectors: Vec<_> = old_sectors.into_iter().map(Some).collect();
+        let mut old_walls: Vec<_> = old_walls.into_iter().map(Some).collect();
+        let mut sectors = Vec::with_capacity(order.len());
+        let mut walls: Vec<Wall> = Vec::with_capacity(num_walls);
+        for &old in order {
+            let mut sector = old_sectors[old].take().expect("duplicate sector in order");
+            let first = usize::from(sector.wallptr);
+            let last = (first + usize::from(sector.wallnum)).min(num_walls);
+            sector_map[old] = sectors.len() as i16;
+            sector.wallptr = walls.len() as u16;
+            sector.wallnum = 0;
+            for w in first..last {
+                if let Some(wall) = old_walls[w].take() {
+                    wall_map[w] = walls.len() as i16;
+                    walls.push(wall);
+                    sector.wallnum += 1;
+                }
+            }
+            sectors.push(sector);
+        }
+
+        let remap = |map: &[i16], i: i16| {
+            if i < 0 || i as usize >= map.len() {
+                -1
+            } else {
+                map[i as usize]
+            }
+        };
+        for wall in &mut walls {
+            wall.point2 = remap(&wall_map, wall.point2);
+            wall.next_sector = remap(&sector_map, wall.next_sector);
+            wall.next_wall = remap(&wall_map, wall.next_wall);
+            if wall.next_sector == -1 || wall.next_wall == -1 {
+                wall.next_sector = -1;
+                wall.next_wall = -1;
+            }
+        }
+
+        let num_sprites = self.sprites.len();
+        let mut sprite_map = vec![-1; num_sprites];
+        let mut next_sprite = 0;
+        for (i, sprite) in self.sprites.iter().enumerate() {
+            if remap(&sector_map, sprite.sectnum) != -1 {
+                sprite_map[i] = next_sprite;
+                next_sprite += 1;
+            }
+        }
+        let mut i = 0;
+        self.sprites.retain(|_| {
+            i += 1;
+            sprite_map[i - 1] != -1
+        });
+        for sprite in &mut self.sprites {
+            sprite.sectnum = remap(&sector_map, sprite.sectnum);
+            if sprite.owner >= 0 && (sprite.owner as usize) < num_sprites {
+                sprite.owner = sprite_map[sprite.owner as usize];
+            }
+        }
+        self.player.sector = remap(&sector_map, self.player.sector);
+
+        let compaction = Compaction {
+            removed_sectors: num_sectors - sectors.len(),
+            removed_walls: num_walls - walls.len(),
+            removed_sprites: num_sprites - self.sprites.len(),
+        };
+        self.sectors.sectors = sectors;
+        self.sectors.walls = walls;
+        compaction
+    }
+}
@@ -0,0 +1,288 @@
+//! Repair of common MAP corruption.
+//!
+//! Broken editors and hex-edited files leave MAPs with out of range wall
+//! ranges, `point2` loops that don't close, dangling portals and sprites in
+//! sectors that don't exist. Each routine fixes one of these, returning the
+//! elements it touched. [`Map::repair`](Map::repair) runs them all, in order.
+use crate::{
+    sector::{SectorId, WallId},
+    sprite::SpriteId,
+    Map,
+};
+
+/// Elements changed (or left broken) by [`Map::repair`](Map::repair).
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct RepairReport {
+    /// Sectors with wall ranges past the end of the walls (truncated).
+    pub wall_ranges: Vec<SectorId>,
+
+    /// Walls with a new `point2`.
+    pub point2: Vec<WallId>,
+
+    /// Walls with dangling or inconsistent portals (cleared).
+    pub portals: Vec<WallId>,
+
+    /// Sprites moved to the sector containing them.
+    pub sprites: Vec<SpriteId>,
+
+    /// Sprites in invalid sectors and not inside any sector (left untouched).
+    pub unplaced_sprites: Vec<SpriteId>,
+
+    /// The player start was in an invalid sector, and was moved to the sector
+    /// containing it.
+    pub player: bool,
+}
+
+impl RepairReport {
+    /// True if the MAP didn't need any repairs.
+    pub fn is_clean(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Map {
+    /// Runs all the repair routines of the [`repair`](crate::repair) module.
+    pub fn repair(&mut self) -> RepairReport {
+        let wall_ranges = clamp_wall_ranges(self);
+        let point2 = relink_loops(self);
+        let portals = clear_dangling_portals(self);
+        let (sprites, unplaced_sprites) = place_sprites(self);
+        let player = place_player(self);
+        RepairReport {
+            wall_ranges,
+            point2,
+            portals,
+            sprites,
+            unplaced_sprites,
+            player,
+        }
+    }
+}
+
+/// Truncates the wall ranges of the sectors to the existing walls.
+pub fn clamp_wall_ranges(map: &mut Map) -> Vec<SectorId> {
+    let count = map.sectors.walls.len();
+    let mut repaired = Vec::new();
+    for (id, sector) in map.sectors.sectors.iter_mut().enumerate() {
+        if sector.wall_range().end > count {
+            let first = usize::from(sector.wallptr).min(count);
+            sector.wallptr = first as u16;
+            sector.wallnum = (count - first) as u16;
+            repaired.push(id as SectorId);
+        }
+    }
+    repaired
+}
+
+/// Re-links `point2` so the walls of every sector form closed loops.
+///
+/// A wall is broken if its `point2` points out of its sector, or to a wall
+/// another wall already points to. Broken walls are linked to a wall no other
+/// wall points to, picking (in order of preference) the wall at the other
+/// end of its portal, the next wall, or the first wall of its loop.
+pub fn relink_loops(map: &mut Map) -> Vec<WallId> {
+    let mut repaired = Vec::new();
+    for sector in &map.sectors.sectors {
+        let range = sector.wall_range();
+        let walls = &mut map.sectors.walls;
+        let in_range = |w: WallId| w >= 0 && range.contains(&(w as usize));
+
+        // previous wall of each wall (by index into the range)
+        let mut prev: Vec<Option<usize>> = vec![None; range.len()];
+        let mut broken = Vec::new();
+        for i in range.clone() {
+            let p = walls[i].point2;
+            if in_range(p) && prev[p as usize - range.start].is_none() {
+                prev[p as usize - range.start] = Some(i);
+            } else {
+                broken.push(i);
+            }
+        }
+        for i in broken {
+            let unclaimed = |w: usize| range.contains(&w) && prev[w - range.start].is_none();
+            let next_wall = walls[i].next_wall;
+            let portal = walls
+                .get(next_wall.max(0) as usize)
+                .filter(|_| next_wall >= 0)
+                .map(|other| (other.x, other.y));
+            let by_portal = portal.and_then(|(x, y)| {
+                range
+                    .clone()
+                    .find(|w| unclaimed(*w) && (walls[*w].x, walls[*w].y) == (x, y))
+            });
+            let loop_start = || {
+                let mut first = i;
+                for _ in 0..range.len() {
+                    match prev[first - range.start] {
+                        Some(p) => first = p,
+                        None => break,
+                    }
+                }
+                Some(first).filter(|w| unclaimed(*w))
+            };
+            let target = by_portal
+                .or_else(|| Some(i + 1).filter(|w| unclaimed(*w)))
+                .or_else(loop_start)
+                .or_else(|| range.clone().find(|w| unclaimed(*w)));
+            if let Some(target) = target {
+                walls[i].point2 = target as WallId;
+                prev[target - range.start] = Some(i);
+                repaired.push(i as WallId);
+            }
+        }
+    }
+    repaired
+}
+
+/// Clears portals (`next_sector` & `next_wall`) pointing out of the MAP, or
+/// not matched by a portal back from the other side.
+pub fn clear_dangling_portals(map: &mut Map) -> Vec<WallId> {
+    let owners = map.sectors.wall_owners();
+    let sector_count = map.sectors.sectors.len();
+    let mut repaired = Vec::new();
+    // clearing a portal leaves the one on the other side dangling
+    loop {
+        let walls = &map.sectors.walls;
+        let dangling: Vec<usize> = (0..walls.len())
+            .filter(|i| {
+                let wall = &walls[*i];
+                let (ns, nw) = (wall.next_sector, wall.next_wall);
+                if ns == -1 && nw == -1 {
+                    return false;
+                }
+                ns < 0
+                    || nw < 0
+                    || ns as usize >= sector_count
+                    || nw as usize >= walls.len()
+                    || owners[nw as usize] != ns
+                    || walls[nw as usize].next_wall as usize != *i
+            })
+            .collect();
+        if dangling.is_empty() {
+            break;
+        }
+        for i in dangling {
+            let wall = &mut map.sectors.walls[i];
+            wall.next_sector = -1;
+            wall.next_wall = -1;
+            repaired.push(i as WallId);
+        }
+    }
+    repaired
+}
+
+/// Moves sprites in sectors that don't exist to the sector containing them.
+/// Returns the moved sprites, and the ones outside of every sector.
+pub fn place_sprites(map: &mut Map) -> (Vec<SpriteId>, Vec<SpriteId>) {
+    let (mut placed, mut unplaced) = (Vec::new(), Vec::new());
+    let count = map.sectors.sectors.len();
+    for (id, sprite) in map.sprites.iter_mut().enumerate() {
+        if sprite.sectnum >= 0 && (sprite.sectnum as usize) < count {
+            continue;
+        }
+        match map.sectors.sector_at(sprite.x, sprite.y) {
+            Some(sector) => {
+                sprite.sectnum = sector;
+                placed.push(id as SpriteId);
+            }
+            None => unplaced.push(id as SpriteId),
+        }
+    }
+    (placed, unplaced)
+}
+
+/// Moves the player start to the sector containing it, if its sector
+/// doesn't exist. Returns true if the player was moved.
+pub fn place_player(map: &mut Map) -> bool {
+    let player = &mut map.player;
+    if player.sector >= 0 && (player.sector as usize) < map.sectors.sectors.len() {
+        return false;
+    }
+    match map.sectors.sector_at(player.pos_x, player.pos_y) {
+        Some(sector) => {
+            player.sector = sector;
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Map;
+
+    fn e1l1() -> Map {
+        Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap()
+    }
+
+    #[test]
+    fn clean() {
+        assert!(e1l1().repair().is_clean());
+    }
+
+    #[test]
+    fn relink() {
+        let mut map = e1l1();
+        let bytes = map.to_vec().unwrap();
+        let owners = map.sectors.wall_owners();
+        let walls = &mut map.sectors.walls;
+        // a portal, the middle of a loop, and the end of a loop (one per
+        // sector, so there's a single way to close each loop).
+        let portal = walls.iter().position(|w| w.next_wall != -1).unwrap();
+        let middle = (0..walls.len())
+            .find(|i| walls[*i].point2 as usize == i + 1 && walls[*i].next_wall == -1)
+            .filter(|i| owners[*i] != owners[portal])
+            .unwrap();
+        let end = (0..walls.len())
+            .filter(|i| owners[*i] != owners[portal] && owners[*i] != owners[middle])
+            .find(|i| (walls[*i].point2 as usize) < *i)
+            .unwrap();
+        walls[portal].point2 = -1;
+        walls[middle].point2 = 30000;
+        walls[end].point2 += 1;
+        let mut sprite = map.sprites[0].clone();
+        let placed = map.sprites.len() as i16;
+        sprite.sectnum = 5000;
+        map.sprites.push(sprite.clone());
+        sprite.x = i32::MAX;
+        map.sprites.push(sprite);
+        let sector = map.player.sector;
+        map.player.sector = -1;
+
+        let report = map.repair();
+        assert!(report.player);
+        assert_eq!(sector, map.player.sector);
+        let mut expected = vec![portal as i16, middle as i16, end as i16];
+        expected.sort_unstable();
+        let mut point2 = report.point2.clone();
+        point2.sort_unstable();
+        assert_eq!(expected, point2);
+        assert_eq!(vec![placed], report.sprites);
+        assert_eq!(vec![placed + 1], report.unplaced_sprites);
+        assert_eq!(map.sprites[0].sectnum, map.sprites[placed as usize].sectnum);
+        map.sprites.truncate(placed as usize);
+        assert_eq!(bytes, map.to_vec().unwrap());
+    }
+
+    #[test]
+    fn portals() {
+        let mut map = e1l1();
+        let portal = map
+            .sectors
+            .walls
+            .iter()
+            .position(|w| w.next_wall != -1)
+            .unwrap();
+        let other = map.sectors.walls[portal].next_wall as usize;
+        map.sectors.walls[portal].next_sector = 10000;
+        let last = map.sectors.sectors.len() - 1;
+        map.sectors.sectors[last].wallnum += 1;
+
+        let report = map.repair();
+        assert_eq!(vec![last as i16], report.wall_ranges);
+        assert!(report.portals.contains(&(portal as i16)));
+        assert!(report.portals.contains(&(other as i16)));
+        assert_eq!(-1, map.sectors.walls[portal].next_wall);
+        assert_eq!(-1, map.sectors.walls[other].next_sector);
+    }
+}
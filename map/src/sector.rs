@@ -1,8 +1,18 @@
-use crate::Error;
-use byteorder::{ReadBytesExt, LE};
-use std::io::Read;
+use crate::units;
+#[cfg(feature = "std")]
+use crate::{
+    progress::{Reporter, Section},
+    Error,
+};
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "std")]
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
 
 pub type SectorId = i16;
+pub type WallId = i16;
 
 bitflags::bitflags! {
     pub struct SectorStat: u16 {
@@ -38,12 +48,12 @@ bitflags::bitflags! {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[repr(C)]
 pub struct Sector {
     // wall pointer and # of walls in the sector (in wall units)
-    wallptr: u16,
-    wallnum: u16,
+    pub(crate) wallptr: u16,
+    pub(crate) wallnum: u16,
 
     /// Z-coordinate (height) of ceiling at first point of sector.
     pub ceiling_z: i32,
@@ -75,7 +85,7 @@ pub struct Sector {
     /// How fast an area changes shade relative to distance.
     pub visibility: u8,
 
-    filler: [u8; 1],
+    pub(crate) filler: [u8; 1],
 
     // game-specific data
     pub lotag: i16,
@@ -83,7 +93,7 @@ pub struct Sector {
     pub extra: i16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[repr(C)]
 pub struct Wall {
     // wall position of the left side of the wall
@@ -124,6 +134,7 @@ pub struct Wall {
 }
 
 impl Wall {
+    #[cfg(feature = "std")]
     pub(crate) fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
         Ok(Self {
             x: reader.read_i32::<LE>()?,
@@ -146,9 +157,191 @@ impl Wall {
             extra: reader.read_i16::<LE>()?,
         })
     }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn to_writer<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_i32::<LE>(self.x)?;
+        writer.write_i32::<LE>(self.y)?;
+        writer.write_i16::<LE>(self.point2)?;
+        writer.write_i16::<LE>(self.next_wall)?;
+        writer.write_i16::<LE>(self.next_sector)?;
+        writer.write_u16::<LE>(self.wall_stat.bits())?;
+        writer.write_i16::<LE>(self.picnum)?;
+        writer.write_i16::<LE>(self.over_picnum)?;
+        writer.write_i8(self.shade)?;
+        writer.write_u8(self.pal)?;
+        writer.write_u8(self.x_repeat)?;
+        writer.write_u8(self.y_repeat)?;
+        writer.write_u8(self.x_panning)?;
+        writer.write_u8(self.y_panning)?;
+        writer.write_i16::<LE>(self.lotag)?;
+        writer.write_i16::<LE>(self.hitag)?;
+        writer.write_i16::<LE>(self.extra)?;
+        Ok(())
+    }
+
+    /// Length of the wall, given its right vertex (`point2`).
+    #[cfg(feature = "std")]
+    pub fn length(&self, right: &Wall) -> f64 {
+        let dx = f64::from(right.x) - f64::from(self.x);
+        let dy = f64::from(right.y) - f64::from(self.y);
+        dx.hypot(dy)
+    }
+
+    /// Unit normal of the wall, pointing towards the inside of its sector,
+    /// given its right vertex (`point2`).
+    #[cfg(feature = "std")]
+    pub fn normal(&self, right: &Wall) -> [f64; 2] {
+        let len = self.length(right);
+        if len == 0.0 {
+            return [0.0, 0.0];
+        }
+        let dx = f64::from(right.x) - f64::from(self.x);
+        let dy = f64::from(right.y) - f64::from(self.y);
+        [-dy / len, dx / len]
+    }
+
+    /// Effective texel density of the wall texture, given its right vertex
+    /// (`point2`), as (horizontal texels per XY unit, vertical texels per Z
+    /// unit).
+    ///
+    /// The engine stretches `8 * x_repeat` texels across the whole wall length,
+    /// and each texel row spans `1024 / y_repeat` Z units.
+    #[cfg(feature = "std")]
+    pub fn texel_density(&self, right: &Wall) -> [f64; 2] {
+        let len = self.length(right);
+        let horizontal = if len == 0.0 {
+            0.0
+        } else {
+            8.0 * f64::from(self.x_repeat) / len
+        };
+        [horizontal, f64::from(self.y_repeat) / 1024.0]
+    }
+}
+
+#[cfg(feature = "std")]
+fn to_z(xy: f64) -> i32 {
+    (xy * f64::from(units::Z_SCALE)).round() as i32
+}
+
+/// Plane in Build axes (Z points down), with Z in XY units (i.e. divided by
+/// 16) so that all three axes share the same scale.
+///
+/// Points on the plane satisfy `normal · p + d = 0`. The normal is unit
+/// length and points towards the inside of the sector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    pub normal: [f64; 3],
+    pub d: f64,
+}
+
+impl Plane {
+    /// Signed distance from a point to the plane. Positive on the side the
+    /// normal points to.
+    pub fn distance(&self, [x, y, z]: [f64; 3]) -> f64 {
+        let [nx, ny, nz] = self.normal;
+        nx * x + ny * y + nz * z + self.d
+    }
+
+    /// Height of the plane (in XY units) at the given point.
+    pub fn z_at(&self, x: f64, y: f64) -> f64 {
+        let [nx, ny, nz] = self.normal;
+        -(nx * x + ny * y + self.d) / nz
+    }
+}
+
+/// Returns the `x_repeat` needed to get the given horizontal texel density
+/// (texels per XY unit) on a wall of the given length.
+#[cfg(feature = "std")]
+pub fn x_repeat_for_density(length: f64, density: f64) -> u8 {
+    (density * length / 8.0).round().clamp(1.0, 255.0) as u8
 }
 
 impl Sector {
+    /// Index of the first wall of the sector. Slopes pivot around this wall,
+    /// and floor & ceiling textures can be aligned to it.
+    pub fn first_wall(&self) -> WallId {
+        self.wallptr as _
+    }
+
+    /// Range of wall indices owned by the sector.
+    pub fn wall_range(&self) -> Range<usize> {
+        let first = usize::from(self.wallptr);
+        first..first + usize::from(self.wallnum)
+    }
+
+    /// Number of walls of the sector (of all its loops).
+    pub fn wall_count(&self) -> usize {
+        usize::from(self.wallnum)
+    }
+
+    /// Ceiling height at the first point of the sector, in the Z-up XY unit
+    /// space of [`Vec3`](crate::units::Vec3).
+    pub fn ceiling_z_world(&self) -> f32 {
+        -units::z_to_xy(self.ceiling_z)
+    }
+
+    /// Floor height at the first point of the sector, in the Z-up XY unit
+    /// space of [`Vec3`](crate::units::Vec3).
+    pub fn floor_z_world(&self) -> f32 {
+        -units::z_to_xy(self.floor_z)
+    }
+
+    /// Floor plane, taking slopes into account. The normal points up.
+    #[cfg(feature = "std")]
+    pub fn floor_plane(&self, sectors: &Sectors) -> Plane {
+        let heinum = self.slope(self.floor_stat, self.floor_heinum);
+        self.plane(sectors, self.floor_z, heinum, -1.0)
+    }
+
+    /// Ceiling plane, taking slopes into account. The normal points down.
+    #[cfg(feature = "std")]
+    pub fn ceiling_plane(&self, sectors: &Sectors) -> Plane {
+        let heinum = self.slope(self.ceiling_stat, self.ceiling_heinum);
+        self.plane(sectors, self.ceiling_z, heinum, 1.0)
+    }
+
+    #[cfg(feature = "std")]
+    fn slope(&self, stat: SectorStat, heinum: i16) -> i16 {
+        if stat.contains(SectorStat::SLOPPED) {
+            heinum
+        } else {
+            0
+        }
+    }
+
+    // Slopes pivot around the first wall of the sector, and change height by
+    // heinum/4096 units per unit away from it (as getflorzofslope).
+    #[cfg(feature = "std")]
+    fn plane(&self, sectors: &Sectors, z: i32, heinum: i16, up: f64) -> Plane {
+        let z = f64::from(z) / f64::from(units::Z_SCALE);
+        let flat = Plane {
+            normal: [0.0, 0.0, up],
+            d: -up * z,
+        };
+        let first = match sectors.walls.get(usize::from(self.wallptr)) {
+            Some(wall) if heinum != 0 => wall,
+            _ => return flat,
+        };
+        let second = match sectors.walls.get(first.point2 as usize) {
+            Some(wall) if first.length(wall) > 0.0 => wall,
+            _ => return flat,
+        };
+        // z(p) = z + k * (n · (p - first))
+        let k = f64::from(heinum) / 4096.0;
+        let [nx, ny] = first.normal(second);
+        let (a, b) = (-k * nx, -k * ny);
+        let c = 1.0;
+        let d = k * (nx * f64::from(first.x) + ny * f64::from(first.y)) - z;
+        let len = (a * a + b * b + c * c).sqrt();
+        let sign = up / len;
+        Plane {
+            normal: [a * sign, b * sign, c * sign],
+            d: d * sign,
+        }
+    }
+
+    #[cfg(feature = "std")]
     pub(crate) fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
         Ok(Self {
             wallptr: reader.read_u16::<LE>()?,
@@ -178,33 +371,104 @@ impl Sector {
             extra: reader.read_i16::<LE>()?,
         })
     }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn to_writer<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_u16::<LE>(self.wallptr)?;
+        writer.write_u16::<LE>(self.wallnum)?;
+        writer.write_i32::<LE>(self.ceiling_z)?;
+        writer.write_i32::<LE>(self.floor_z)?;
+        writer.write_u16::<LE>(self.ceiling_stat.bits())?;
+        writer.write_u16::<LE>(self.floor_stat.bits())?;
+        writer.write_i16::<LE>(self.ceiling_picnum)?;
+        writer.write_i16::<LE>(self.ceiling_heinum)?;
+        writer.write_i8(self.ceiling_shade)?;
+        writer.write_u8(self.ceiling_pal)?;
+        writer.write_u8(self.ceiling_xpanning)?;
+        writer.write_u8(self.ceiling_ypanning)?;
+        writer.write_i16::<LE>(self.floor_picnum)?;
+        writer.write_i16::<LE>(self.floor_heinum)?;
+        writer.write_i8(self.floor_shade)?;
+        writer.write_u8(self.floor_pal)?;
+        writer.write_u8(self.floor_xpanning)?;
+        writer.write_u8(self.floor_ypanning)?;
+        writer.write_u8(self.visibility)?;
+        writer.write_all(&self.filler)?;
+        writer.write_i16::<LE>(self.lotag)?;
+        writer.write_i16::<LE>(self.hitag)?;
+        writer.write_i16::<LE>(self.extra)?;
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Sectors {
-    sectors: Vec<Sector>,
-    walls: Vec<Wall>,
+    pub(crate) sectors: Vec<Sector>,
+    pub(crate) walls: Vec<Wall>,
 }
 
 impl Sectors {
-    pub(crate) fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
-        let sectors = Self::read_sectors(reader)?;
-        let walls = Self::read_walls(reader)?;
-        Ok(Self { sectors, walls })
+    /// Sectors & walls, as stored in a MAP file.
+    pub fn new(sectors: Vec<Sector>, walls: Vec<Wall>) -> Self {
+        Self { sectors, walls }
     }
 
-    fn read_sectors<R: Read>(reader: &mut R) -> Result<Vec<Sector>, Error> {
+    #[cfg(feature = "std")]
+    pub(crate) fn from_reader<R: Read>(
+        reader: &mut R,
+        progress: &mut Reporter<'_>,
+    ) -> Result<Self, Error> {
         let num_sectors = reader.read_u16::<LE>()? as usize;
-        (0..num_sectors)
-            .map(|_| Sector::from_reader(reader))
-            .collect::<Result<Vec<_>, _>>()
+        let sectors = progress.section(Section::Sectors, num_sectors, || {
+            Sector::from_reader(reader)
+        })?;
+        let num_walls = reader.read_u16::<LE>()? as usize;
+        let walls = progress.section(Section::Walls, num_walls, || Wall::from_reader(reader))?;
+        Ok(Self { sectors, walls })
     }
 
-    fn read_walls<R: Read>(reader: &mut R) -> Result<Vec<Wall>, Error> {
-        let num_walls = reader.read_u16::<LE>()? as usize;
-        (0..num_walls)
-            .map(|_| Wall::from_reader(reader))
-            .collect::<Result<Vec<_>, _>>()
+    #[cfg(feature = "std")]
+    pub(crate) fn to_writer<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if self.sectors.len() > usize::from(u16::MAX) {
+            return Err(Error::LimitExceeded("sectors"));
+        }
+        if self.walls.len() > usize::from(u16::MAX) {
+            return Err(Error::LimitExceeded("walls"));
+        }
+        writer.write_u16::<LE>(self.sectors.len() as u16)?;
+        self.sectors.iter().try_for_each(|s| s.to_writer(writer))?;
+        writer.write_u16::<LE>(self.walls.len() as u16)?;
+        self.walls.iter().try_for_each(|w| w.to_writer(writer))
+    }
+
+    // Inserts a wall at the given index, shifting the indices of the walls
+    // after it. The wall is added to the sector owning the wall right before
+    // it. References of the inserted wall are given in indices prior to the
+    // insertion.
+    #[cfg(feature = "std")]
+    pub(crate) fn insert_wall(&mut self, index: usize, mut wall: Wall) {
+        let shift = |i: i16| {
+            if i >= 0 && i as usize >= index {
+                i + 1
+            } else {
+                i
+            }
+        };
+        wall.point2 = shift(wall.point2);
+        wall.next_wall = shift(wall.next_wall);
+        for w in &mut self.walls {
+            w.point2 = shift(w.point2);
+            w.next_wall = shift(w.next_wall);
+        }
+        for s in &mut self.sectors {
+            let first = usize::from(s.wallptr);
+            if first >= index {
+                s.wallptr += 1;
+            } else if index <= first + usize::from(s.wallnum) {
+                s.wallnum += 1;
+            }
+        }
+        self.walls.insert(index, wall);
     }
 
     /// Return a sector and an iterator over the sector's walls.
@@ -231,6 +495,91 @@ impl Sectors {
         self.walls.as_slice()
     }
 
+    /// Returns the sector owning the given wall.
+    ///
+    /// Binary searches the wall ranges of the sectors, which are sorted in
+    /// well-formed MAPs, and falls back to a linear scan otherwise. To look up
+    /// many walls, use [`wall_owners`](Self::wall_owners) instead.
+    pub fn sector_of_wall(&self, wall: WallId) -> Option<SectorId> {
+        if wall < 0 || wall as usize >= self.walls.len() {
+            return None;
+        }
+        let wall = wall as usize;
+        let contains = |i: usize| self.sectors[i].wall_range().contains(&wall);
+        let candidate = self
+            .sectors
+            .partition_point(|s| usize::from(s.wallptr) <= wall)
+            .checked_sub(1);
+        match candidate {
+            Some(i) if contains(i) => Some(i as _),
+            _ => (0..self.sectors.len())
+                .find(|i| contains(*i))
+                .map(|i| i as _),
+        }
+    }
+
+    /// Returns the sector owning each wall (`-1` for walls owned by no
+    /// sector), indexed by wall.
+    pub fn wall_owners(&self) -> Vec<SectorId> {
+        let mut owners = vec![-1; self.walls.len()];
+        for (i, sector) in self.sectors.iter().enumerate() {
+            let last = sector.wall_range().end.min(owners.len());
+            let first = usize::from(sector.wallptr).min(last);
+            owners[first..last].iter_mut().for_each(|o| *o = i as _);
+        }
+        owners
+    }
+
+    /// Ceiling height (Z units) of a sector at the given point, taking
+    /// slopes into account.
+    #[cfg(feature = "std")]
+    pub fn ceiling_z_at(&self, sector: SectorId, x: i32, y: i32) -> Option<i32> {
+        let (s, _) = self.get(sector)?;
+        Some(to_z(s.ceiling_plane(self).z_at(f64::from(x), f64::from(y))))
+    }
+
+    /// Floor height (Z units) of a sector at the given point, taking slopes
+    /// into account.
+    #[cfg(feature = "std")]
+    pub fn floor_z_at(&self, sector: SectorId, x: i32, y: i32) -> Option<i32> {
+        let (s, _) = self.get(sector)?;
+        Some(to_z(s.floor_plane(self).z_at(f64::from(x), f64::from(y))))
+    }
+
+    /// Returns true if the point is inside the sector (even-odd rule over
+    /// all the wall loops of the sector, as Build's `inside`).
+    pub fn inside(&self, sector: SectorId, x: i32, y: i32) -> bool {
+        let range = match self.sectors.get(sector.max(0) as usize) {
+            Some(s) if sector >= 0 => s.wall_range(),
+            _ => return false,
+        };
+        let (x, y) = (i64::from(x), i64::from(y));
+        let mut inside = false;
+        for wall in self.walls.get(range).unwrap_or(&[]) {
+            let right = match self.walls.get(wall.point2.max(0) as usize) {
+                Some(right) if wall.point2 >= 0 => right,
+                _ => continue,
+            };
+            let (ax, ay) = (i64::from(wall.x), i64::from(wall.y));
+            let (bx, by) = (i64::from(right.x), i64::from(right.y));
+            if (ay > y) != (by > y) {
+                // x coordinate of the wall at height y, compared without division.
+                let lhs = (x - ax) * (by - ay);
+                let rhs = (bx - ax) * (y - ay);
+                if (by > ay && lhs < rhs) || (by < ay && lhs > rhs) {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    /// Returns the first sector containing the point (see
+    /// [`inside`](Self::inside)).
+    pub fn sector_at(&self, x: i32, y: i32) -> Option<SectorId> {
+        (0..self.sectors.len() as SectorId).find(|s| self.inside(*s, x, y))
+    }
+
     fn sector_walls(&self, sector: SectorId) -> SectorWalls<'_> {
         assert_ne!(-1, sector);
         let first = self.sectors[sector as usize].wallptr as _;
@@ -241,31 +590,61 @@ impl Sectors {
             first,
             walls: self.walls.as_slice(),
             curr: Some(first),
+            truncated: false,
         }
     }
 }
 
-#[derive(Debug)]
+/// Iterator over the walls of a sector, following the `point2` chain from
+/// the first wall.
+///
+/// Iteration stops early (see [`is_truncated`](Self::is_truncated)) if the
+/// chain points out of the sector's walls, or doesn't loop back to the first
+/// wall within the sector's wall count, as happens in corrupt MAPs.
+#[derive(Debug, Clone)]
 pub struct SectorWalls<'a> {
     len: usize,
     index: usize,
     first: usize,
     walls: &'a [Wall],
     curr: Option<usize>,
+    truncated: bool,
+}
+
+impl SectorWalls<'_> {
+    /// Returns true if iteration stopped because of a malformed `point2`
+    /// chain.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    fn truncate(&mut self) -> Option<<Self as Iterator>::Item> {
+        self.truncated = true;
+        self.index = self.len;
+        None
+    }
 }
 
 impl<'a> Iterator for SectorWalls<'a> {
     type Item = (SectorId, &'a Wall, &'a Wall);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let curr = self.curr?;
+        let curr = self.curr.take()?;
+        let range = self.first..self.first + self.len;
+        if self.index == self.len || !range.contains(&curr) || curr >= self.walls.len() {
+            return self.truncate();
+        }
         let left = &self.walls[curr];
-        let right = &self.walls[left.point2 as usize];
+        let point2 = left.point2 as usize;
+        let right = match self.walls.get(point2) {
+            Some(right) if range.contains(&point2) => right,
+            _ => return self.truncate(),
+        };
         self.index += 1;
-        self.curr = if left.point2 as usize == self.first {
+        self.curr = if point2 == self.first {
             None
         } else {
-            Some(left.point2 as _)
+            Some(point2)
         };
         Some((curr as _, left, right))
     }
@@ -279,4 +658,142 @@ impl<'a> Iterator for SectorWalls<'a> {
 impl ExactSizeIterator for SectorWalls<'_> {}
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use super::{x_repeat_for_density, Sector, SectorStat, Sectors, Wall, WallStat};
+
+    fn sector(floor_z: i32, floor_heinum: i16) -> Sector {
+        Sector {
+            wallptr: 0,
+            wallnum: 4,
+            ceiling_z: 0,
+            floor_z,
+            ceiling_stat: SectorStat::empty(),
+            floor_stat: SectorStat::SLOPPED,
+            ceiling_picnum: 0,
+            ceiling_heinum: 0,
+            ceiling_shade: 0,
+            ceiling_pal: 0,
+            ceiling_xpanning: 0,
+            ceiling_ypanning: 0,
+            floor_picnum: 0,
+            floor_heinum,
+            floor_shade: 0,
+            floor_pal: 0,
+            floor_xpanning: 0,
+            floor_ypanning: 0,
+            visibility: 0,
+            filler: [0],
+            lotag: 0,
+            hitag: 0,
+            extra: 0,
+        }
+    }
+
+    fn wall(x: i32, y: i32, x_repeat: u8, y_repeat: u8) -> Wall {
+        Wall {
+            x,
+            y,
+            point2: -1,
+            next_wall: -1,
+            next_sector: -1,
+            wall_stat: WallStat::empty(),
+            picnum: 0,
+            over_picnum: 0,
+            shade: 0,
+            pal: 0,
+            x_repeat,
+            y_repeat,
+            x_panning: 0,
+            y_panning: 0,
+            lotag: 0,
+            hitag: 0,
+            extra: 0,
+        }
+    }
+
+    #[test]
+    fn wall_geometry() {
+        let left = wall(0, 0, 16, 8);
+        let right = wall(0, 512, 16, 8);
+        assert_eq!(512.0, left.length(&right));
+        assert_eq!([-1.0, 0.0], left.normal(&right));
+        assert_eq!([0.25, 8.0 / 1024.0], left.texel_density(&right));
+        assert_eq!(16, x_repeat_for_density(512.0, 0.25));
+        assert_eq!([0.0, 0.0], left.normal(&left));
+    }
+
+    #[test]
+    fn inside() {
+        let mut walls: Vec<_> = [(0, 0), (1024, 0), (1024, 1024), (0, 1024)]
+            .iter()
+            .map(|(x, y)| wall(*x, *y, 8, 8))
+            .collect();
+        for (i, wall) in walls.iter_mut().enumerate() {
+            wall.point2 = ((i + 1) % 4) as i16;
+        }
+        let sectors = Sectors {
+            sectors: vec![sector(0, 0)],
+            walls,
+        };
+        assert!(sectors.inside(0, 512, 512));
+        assert!(!sectors.inside(0, 2048, 512));
+        assert!(!sectors.inside(1, 512, 512));
+        assert_eq!(Some(0), sectors.sector_at(1, 1));
+        assert_eq!(None, sectors.sector_at(-1, 1));
+    }
+
+    #[test]
+    fn malformed_walls() {
+        let mut walls: Vec<_> = (0..4).map(|i| wall(i, 0, 8, 8)).collect();
+        // 0 -> 1 -> 2 -> 1 -> ... never loops back to 0
+        walls[0].point2 = 1;
+        walls[1].point2 = 2;
+        walls[2].point2 = 1;
+        walls[3].point2 = 100;
+        let mut second = sector(0, 0);
+        second.wallptr = 3;
+        second.wallnum = 1;
+        let sectors = Sectors {
+            sectors: vec![sector(0, 0), second],
+            walls,
+        };
+        let (_, mut iter) = sectors.get(0).unwrap();
+        assert_eq!(4, iter.by_ref().count());
+        assert!(iter.is_truncated());
+        // point2 out of bounds
+        let (_, mut iter) = sectors.get(1).unwrap();
+        assert_eq!(0, iter.by_ref().count());
+        assert!(iter.is_truncated());
+    }
+
+    #[test]
+    fn slopes() {
+        let mut walls: Vec<_> = [(0, 0), (1024, 0), (1024, 1024), (0, 1024)]
+            .iter()
+            .map(|(x, y)| wall(*x, *y, 8, 8))
+            .collect();
+        for (i, wall) in walls.iter_mut().enumerate() {
+            wall.point2 = ((i + 1) % 4) as i16;
+        }
+        // 45 degrees, rising away from the first wall (Z points down)
+        let sectors = Sectors {
+            sectors: vec![sector(16384, -4096)],
+            walls,
+        };
+        assert_eq!(Some(16384), sectors.floor_z_at(0, 512, 0));
+        assert_eq!(Some(16384 - 512 * 16), sectors.floor_z_at(0, 512, 512));
+        assert_eq!(Some(0), sectors.ceiling_z_at(0, 512, 512));
+        assert_eq!(None, sectors.floor_z_at(1, 0, 0));
+        assert_eq!(-1024.0, sectors.sectors()[0].floor_z_world());
+
+        let floor = sectors.sectors()[0].floor_plane(&sectors);
+        let [nx, ny, nz] = floor.normal;
+        assert!(nx.abs() < 1e-9 && ny < 0.0 && nz < 0.0);
+        assert!((floor.z_at(0.0, 256.0) - 768.0).abs() < 1e-9);
+        // above the floor is inside
+        assert!(floor.distance([512.0, 512.0, 0.0]) > 0.0);
+        let ceiling = sectors.sectors()[0].ceiling_plane(&sectors);
+        assert_eq!([0.0, 0.0, 1.0], ceiling.normal);
+        assert!(ceiling.distance([512.0, 512.0, 256.0]) > 0.0);
+    }
+}
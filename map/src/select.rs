@@ -0,0 +1,156 @@
+//! Selection of map elements within a region of the XY plane.
+use crate::{
+    sector::{SectorId, WallId},
+    sprite::SpriteId,
+    Map,
+};
+use std::collections::BTreeSet;
+
+/// Region of the map XY plane.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Region {
+    /// Axis-aligned rectangle (bounds inclusive).
+    Rect { min: [i32; 2], max: [i32; 2] },
+
+    /// Simple polygon (e.g. a lasso), implicitly closed.
+    Polygon(Vec<[i32; 2]>),
+}
+
+impl Region {
+    /// Rectangle spanning the two given corners, in any order.
+    pub fn rect(a: [i32; 2], b: [i32; 2]) -> Self {
+        Region::Rect {
+            min: [a[0].min(b[0]), a[1].min(b[1])],
+            max: [a[0].max(b[0]), a[1].max(b[1])],
+        }
+    }
+
+    /// Returns true if the point lies within the region.
+    pub fn contains(&self, [x, y]: [i32; 2]) -> bool {
+        match self {
+            Region::Rect { min, max } => x >= min[0] && x <= max[0] && y >= min[1] && y <= max[1],
+            Region::Polygon(points) => polygon_contains(points, [x, y]),
+        }
+    }
+}
+
+// even-odd rule
+fn polygon_contains(points: &[[i32; 2]], [x, y]: [i32; 2]) -> bool {
+    let (x, y) = (i64::from(x), i64::from(y));
+    let mut inside = false;
+    let mut j = points.len().wrapping_sub(1);
+    for (i, a) in points.iter().enumerate() {
+        let b = points[j];
+        let (ax, ay) = (i64::from(a[0]), i64::from(a[1]));
+        let (bx, by) = (i64::from(b[0]), i64::from(b[1]));
+        if (ay > y) != (by > y) {
+            // x coordinate of the edge at height y, compared without division.
+            let lhs = (x - ax) * (by - ay);
+            let rhs = (bx - ax) * (y - ay);
+            if (by > ay && lhs < rhs) || (by < ay && lhs > rhs) {
+                inside = !inside;
+            }
+        }
+        j = i;
+    }
+    inside
+}
+
+/// How much of an element must lie within the region for it to be selected.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Containment {
+    /// All vertices must be within the region.
+    Full,
+
+    /// At least one vertex must be within the region.
+    Partial,
+}
+
+/// Selected map elements.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Selection {
+    pub sectors: BTreeSet<SectorId>,
+    pub walls: BTreeSet<WallId>,
+    pub sprites: BTreeSet<SpriteId>,
+}
+
+/// Select the walls, sectors and sprites within the region.
+pub fn select(map: &Map, region: &Region, containment: Containment) -> Selection {
+    Selection {
+        sectors: sectors(map, region, containment),
+        walls: walls(map, region, containment),
+        sprites: sprites(map, region),
+    }
+}
+
+/// Select walls by their two vertices.
+pub fn walls(map: &Map, region: &Region, containment: Containment) -> BTreeSet<WallId> {
+    let walls = map.sectors.walls();
+    walls
+        .iter()
+        .enumerate()
+        .filter(|(_, left)| {
+            let right = walls.get(left.point2 as usize).unwrap_or(left);
+            let l = region.contains([left.x, left.y]);
+            let r = region.contains([right.x, right.y]);
+            match containment {
+                Containment::Full => l && r,
+                Containment::Partial => l || r,
+            }
+        })
+        .map(|(i, _)| i as WallId)
+        .collect()
+}
+
+/// Select sectors by the vertices of all of their walls (including inner
+/// loops).
+pub fn sectors(map: &Map, region: &Region, containment: Containment) -> BTreeSet<SectorId> {
+    let walls = map.sectors.walls();
+    map.sectors
+        .sectors()
+        .iter()
+        .enumerate()
+        .filter(|(_, sector)| {
+            let first = usize::from(sector.wallptr).min(walls.len());
+            let last = (first + usize::from(sector.wallnum)).min(walls.len());
+            let mut vertices = walls[first..last].iter().map(|w| [w.x, w.y]);
+            first != last
+                && match containment {
+                    Containment::Full => vertices.all(|v| region.contains(v)),
+                    Containment::Partial => vertices.any(|v| region.contains(v)),
+                }
+        })
+        .map(|(i, _)| i as SectorId)
+        .collect()
+}
+
+/// Select sprites by their position.
+pub fn sprites(map: &Map, region: &Region) -> BTreeSet<SpriteId> {
+    map.sprites
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| region.contains([s.x, s.y]))
+        .map(|(i, _)| i as SpriteId)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::Region;
+
+    #[test]
+    fn region_contains() {
+        let rect = Region::rect([10, 10], [-10, -10]);
+        assert!(rect.contains([0, 0]));
+        assert!(rect.contains([10, -10]));
+        assert!(!rect.contains([11, 0]));
+
+        // concave "L" shape
+        let lasso = Region::Polygon(vec![[0, 0], [10, 0], [10, 4], [4, 4], [4, 10], [0, 10]]);
+        assert!(lasso.contains([2, 2]));
+        assert!(lasso.contains([8, 2]));
+        assert!(lasso.contains([2, 8]));
+        assert!(!lasso.contains([8, 8]));
+        assert!(!lasso.contains([-1, 2]));
+    }
+}
@@ -0,0 +1,145 @@
+//! Geometric similarity between MAPs, to find edited copies of a level.
+use crate::Map;
+use std::collections::{HashMap, HashSet};
+
+type Point = (i64, i64);
+
+// wall segment, with its end points in lexicographic order.
+type Segment = (Point, Point);
+
+// shapes (segment vectors) more common than this don't vote for alignments:
+// repeated shapes (e.g. grid aligned walls) match anywhere, and the votes of
+// N repeated shapes are N^2 comparisons.
+const MAX_VOTERS: usize = 8;
+
+// most voted alignments tried, other than no translation.
+const CANDIDATES: usize = 4;
+
+// rotations by multiples of 90 degrees, and their mirror images.
+const TRANSFORMS: [fn(Point) -> Point; 8] = [
+    |(x, y)| (x, y),
+    |(x, y)| (-y, x),
+    |(x, y)| (-x, -y),
+    |(x, y)| (y, -x),
+    |(x, y)| (-x, y),
+    |(x, y)| (y, x),
+    |(x, y)| (x, -y),
+    |(x, y)| (-y, -x),
+];
+
+/// Fraction (from 0 to 1) of the walls two MAPs have in common.
+///
+/// The second MAP is aligned to the first one (translated, rotated by a
+/// multiple of 90 degrees and/or mirrored) so that as many walls as possible
+/// coincide, and the result is the [Dice coefficient] of both sets of walls.
+/// Only wall positions are compared: textures, heights and sprites are
+/// ignored.
+///
+/// ```
+/// let a = map::Map::from_file("tests/maps/E1L1.MAP").unwrap();
+/// let mut b = a.clone();
+/// b.rotate_90();
+/// b.translate(1024, -512, 0);
+/// assert_eq!(1.0, map::similarity(&a, &b));
+/// ```
+///
+/// [Dice coefficient]: https://en.wikipedia.org/wiki/S%C3%B8rensen%E2%80%93Dice_coefficient
+pub fn similarity(a: &Map, b: &Map) -> f32 {
+    let set_a: HashSet<Segment> = segments(a, TRANSFORMS[0]).collect();
+    let len_b = segments(b, TRANSFORMS[0]).collect::<HashSet<_>>().len();
+    if set_a.is_empty() || len_b == 0 {
+        return if set_a.len() == len_b { 1.0 } else { 0.0 };
+    }
+    let shapes_a = shapes(set_a.iter());
+
+    let mut best = 0;
+    for transform in &TRANSFORMS {
+        let set_b: HashSet<Segment> = segments(b, *transform).collect();
+        let mut votes: HashMap<Point, usize> = HashMap::new();
+        for (shape, starts_b) in shapes(set_b.iter()) {
+            let starts_a = match shapes_a.get(&shape) {
+                Some(starts) if starts.len() <= MAX_VOTERS && starts_b.len() <= MAX_VOTERS => {
+                    starts
+                }
+                _ => continue,
+            };
+            for (ax, ay) in starts_a {
+                for (bx, by) in &starts_b {
+                    *votes.entry((ax - bx, ay - by)).or_default() += 1;
+                }
+            }
+        }
+        let mut candidates: Vec<_> = votes.into_iter().collect();
+        candidates.sort_unstable_by_key(|(offset, count)| (std::cmp::Reverse(*count), *offset));
+        let offsets = candidates
+            .into_iter()
+            .take(CANDIDATES)
+            .map(|(offset, _)| offset)
+            .chain(Some((0, 0)));
+        for (dx, dy) in offsets {
+            let matched = set_b
+                .iter()
+                .filter(|((x0, y0), (x1, y1))| {
+                    set_a.contains(&((x0 + dx, y0 + dy), (x1 + dx, y1 + dy)))
+                })
+                .count();
+            best = best.max(matched);
+        }
+    }
+    (2 * best) as f32 / (set_a.len() + len_b) as f32
+}
+
+// wall segments of the map (skipping walls with broken point2 links).
+fn segments(map: &Map, transform: fn(Point) -> Point) -> impl Iterator<Item = Segment> + '_ {
+    let walls = map.sectors.walls();
+    walls.iter().filter_map(move |wall| {
+        if wall.point2 < 0 {
+            return None;
+        }
+        let right = walls.get(wall.point2 as usize)?;
+        let left = transform((i64::from(wall.x), i64::from(wall.y)));
+        let right = transform((i64::from(right.x), i64::from(right.y)));
+        Some(if left <= right {
+            (left, right)
+        } else {
+            (right, left)
+        })
+    })
+}
+
+// segments grouped by their vector, with the position of their first point.
+fn shapes<'a>(segments: impl Iterator<Item = &'a Segment>) -> HashMap<Point, Vec<Point>> {
+    let mut shapes: HashMap<Point, Vec<Point>> = HashMap::new();
+    for ((x0, y0), (x1, y1)) in segments {
+        shapes
+            .entry((x1 - x0, y1 - y0))
+            .or_default()
+            .push((*x0, *y0));
+    }
+    shapes
+}
+
+#[cfg(test)]
+mod test {
+    use super::similarity;
+    use crate::Map;
+
+    #[test]
+    fn similar() {
+        let a = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let mut b = a.clone();
+        b.mirror_x();
+        b.translate(-300, 77, 0);
+        assert_eq!(1.0, similarity(&a, &b));
+        assert_eq!(1.0, similarity(&b, &a));
+
+        // an edited copy: one wall moved
+        let mut c = b.clone();
+        c.sectors.walls[0].x += 16;
+        let edited = similarity(&a, &c);
+        assert!(edited > 0.99 && edited < 1.0);
+
+        let other = Map::from_slice(include_bytes!("../tests/maps/DUKEDC1.MAP")).unwrap();
+        assert!(similarity(&a, &other) < 0.1);
+    }
+}
@@ -0,0 +1,70 @@
+//! Savestates of the mutable state of a MAP.
+//!
+//! Games mostly change the player, sector heights (doors, lifts...) and
+//! sprites, and leave the geometry alone. A [`Snapshot`](Snapshot) copies
+//! only those, so quicksaving (or recording states every tick for rewind)
+//! doesn't duplicate the walls.
+use crate::{player::Player, sector::Sector, sprite::Sprite, Error, Map};
+
+/// Player, sectors and sprites of a MAP, at the time the snapshot was taken.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    player: Player,
+    sectors: Vec<Sector>,
+    sprites: Vec<Sprite>,
+    wall_count: usize,
+}
+
+impl Map {
+    /// Takes a snapshot of the player, sectors and sprites.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            player: self.player.clone(),
+            sectors: self.sectors.sectors.clone(),
+            sprites: self.sprites.clone(),
+            wall_count: self.sectors.walls.len(),
+        }
+    }
+
+    /// Restores the player, sectors and sprites from a snapshot.
+    ///
+    /// Walls aren't part of snapshots, so sectors and walls must not have
+    /// been added or removed since the snapshot was taken. Otherwise the MAP
+    /// is left untouched and [`Error::SnapshotMismatch`] is returned.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), Error> {
+        if snapshot.sectors.len() != self.sectors.sectors.len()
+            || snapshot.wall_count != self.sectors.walls.len()
+        {
+            return Err(Error::SnapshotMismatch);
+        }
+        self.player.clone_from(&snapshot.player);
+        self.sectors.sectors.clone_from(&snapshot.sectors);
+        self.sprites.clone_from(&snapshot.sprites);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{sector::Wall, Error, Map};
+
+    #[test]
+    fn restore() {
+        let mut map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let bytes = map.to_vec().unwrap();
+        let snapshot = map.snapshot();
+
+        map.player.pos_x += 1024;
+        map.sectors.sectors[0].ceiling_z -= 4096;
+        map.sprites.clear();
+        map.restore(&snapshot).unwrap();
+        assert_eq!(bytes, map.to_vec().unwrap());
+
+        let wall: Wall = map.sectors.walls[0].clone();
+        map.sectors.insert_wall(1, wall);
+        assert!(matches!(
+            map.restore(&snapshot),
+            Err(Error::SnapshotMismatch)
+        ));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{
+    sector::SectorId,
+    sprite::{Sprite, SpriteId},
+    tags,
+    tags::GameProfile,
+    Map,
+};
+use alloc::vec::Vec;
+
+/// Ambient sound emitter placed in the map.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AmbientSound {
+    /// Index of the sprite the emitter was extracted from.
+    pub sprite: SpriteId,
+
+    /// Game sound number.
+    pub sound: i16,
+
+    /// Distance (in map units) at which the sound can be heard, or 0 if the
+    /// game doesn't store one in the sprite.
+    pub radius: i16,
+
+    // position
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+
+    /// Sector containing the emitter.
+    pub sector: SectorId,
+}
+
+impl AmbientSound {
+    /// Extract the ambient sound emitter of a sprite, following the tag
+    /// conventions of the given game:
+    ///
+    /// - Duke Nukem 3D & Redneck Rampage: `MUSICANDSFX` sprites (lotag: sound,
+    ///   hitag: radius).
+    /// - Shadow Warrior: `ST1` sprites with a `SOUND_SPOT` hitag (lotag:
+    ///   sound).
+    pub fn from_sprite(profile: GameProfile, id: SpriteId, sprite: &Sprite) -> Option<Self> {
+        let radius = match profile {
+            GameProfile::Duke3D | GameProfile::RedneckRampage
+                if sprite.picnum == tags::duke::MUSICANDSFX =>
+            {
+                sprite.hitag
+            }
+            GameProfile::ShadowWarrior
+                if sprite.picnum == tags::sw::ST1 && sprite.hitag == tags::sw::SOUND_SPOT =>
+            {
+                0
+            }
+            _ => return None,
+        };
+        Some(Self {
+            sprite: id,
+            sound: sprite.lotag,
+            radius,
+            x: sprite.x,
+            y: sprite.y,
+            z: sprite.z,
+            sector: sprite.sectnum,
+        })
+    }
+}
+
+impl Map {
+    /// Returns the ambient sound emitters placed in the map.
+    pub fn ambient_sounds(&self, profile: GameProfile) -> Vec<AmbientSound> {
+        self.sprites
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| AmbientSound::from_sprite(profile, i as _, s))
+            .collect()
+    }
+}
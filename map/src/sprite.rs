@@ -1,6 +1,15 @@
-use crate::{player::Angle, Error};
-use byteorder::{ReadBytesExt, LE};
-use std::io::Read;
+#[cfg(feature = "std")]
+use crate::progress::{Reporter, Section};
+use crate::{player::Angle, sector::SectorId, Error, Map};
+#[cfg(feature = "std")]
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+
+pub type SpriteId = i16;
+
+// largest sprite count addressable with SpriteId indices.
+const MAX_SPRITES: usize = SpriteId::MAX as usize;
 
 bitflags::bitflags! {
     pub struct SpriteStat: u16 {
@@ -36,7 +45,7 @@ pub enum SpriteType {
     Floor = 0b10 << 4,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[repr(C)]
 pub struct Sprite {
     // position
@@ -58,7 +67,7 @@ pub struct Sprite {
     /// Size of the movement clipping square (face sprites only).
     pub clip_dist: u8,
 
-    filler: [u8; 1],
+    pub(crate) filler: [u8; 1],
 
     pub x_repeat: u8,
     pub y_repeat: u8,
@@ -86,13 +95,44 @@ pub struct Sprite {
 }
 
 impl Sprite {
-    fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
+    /// Face sprite at the given position and sector, with picnum 0, no
+    /// owner and default (64) repeats.
+    pub fn new(x: i32, y: i32, z: i32, sectnum: SectorId) -> Self {
+        Self {
+            x,
+            y,
+            z,
+            sprite_stat: SpriteStat::empty(),
+            picnum: 0,
+            shade: 0,
+            pal: 0,
+            clip_dist: 32,
+            filler: [0],
+            x_repeat: 64,
+            y_repeat: 64,
+            x_offset: 0,
+            y_offset: 0,
+            sectnum,
+            statnum: 0,
+            angle: Angle(0),
+            owner: -1,
+            x_vel: 0,
+            y_vel: 0,
+            z_vel: 0,
+            lotag: 0,
+            hitag: 0,
+            extra: 0,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
         Ok(Self {
             x: reader.read_i32::<LE>()?,
             y: reader.read_i32::<LE>()?,
             z: reader.read_i32::<LE>()?,
 
-            /// TODO(german): validate RESERVED_SPRITE_TYPE cannot be '0b11'
+            // TODO(german): validate RESERVED_SPRITE_TYPE cannot be '0b11'
             sprite_stat: SpriteStat::from_bits(reader.read_u16::<LE>()?)
                 .expect("Error parsing sprite stat bits."),
             picnum: reader.read_i16::<LE>()?,
@@ -117,20 +157,172 @@ impl Sprite {
         })
     }
 
+    #[cfg(feature = "std")]
+    pub(crate) fn to_writer<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_i32::<LE>(self.x)?;
+        writer.write_i32::<LE>(self.y)?;
+        writer.write_i32::<LE>(self.z)?;
+        writer.write_u16::<LE>(self.sprite_stat.bits())?;
+        writer.write_i16::<LE>(self.picnum)?;
+        writer.write_i8(self.shade)?;
+        writer.write_u8(self.pal)?;
+        writer.write_u8(self.clip_dist)?;
+        writer.write_all(&self.filler)?;
+        writer.write_u8(self.x_repeat)?;
+        writer.write_u8(self.y_repeat)?;
+        writer.write_u8(self.x_offset)?;
+        writer.write_u8(self.y_offset)?;
+        writer.write_i16::<LE>(self.sectnum)?;
+        writer.write_i16::<LE>(self.statnum)?;
+        writer.write_i16::<LE>(self.angle.0)?;
+        writer.write_i16::<LE>(self.owner)?;
+        writer.write_i16::<LE>(self.x_vel)?;
+        writer.write_i16::<LE>(self.y_vel)?;
+        writer.write_i16::<LE>(self.z_vel)?;
+        writer.write_i16::<LE>(self.lotag)?;
+        writer.write_i16::<LE>(self.hitag)?;
+        writer.write_i16::<LE>(self.extra)?;
+        Ok(())
+    }
+
     /// Return the sprite type.
     pub fn sprite_type(&self) -> SpriteType {
         let stat = (self.sprite_stat.bits >> 4) & 0b11;
         match stat {
-            0b00 | 0b01 | 0b10 => unsafe { std::mem::transmute(stat) },
+            0b00 => SpriteType::Face,
+            0b01 => SpriteType::Wall,
+            0b10 => SpriteType::Floor,
             0b11 => panic!(),
             _ => unreachable!(),
         }
     }
 }
 
-pub(crate) fn from_reader<R: Read>(reader: &mut R) -> Result<Vec<Sprite>, Error> {
+impl Map {
+    /// Appends a sprite, returning its index.
+    ///
+    /// Fails if the `sectnum` of the sprite isn't a sector of the map.
+    pub fn add_sprite(&mut self, sprite: Sprite) -> Result<SpriteId, Error> {
+        self.check_sector(sprite.sectnum)?;
+        if self.sprites.len() >= MAX_SPRITES {
+            return Err(Error::LimitExceeded("sprites"));
+        }
+        self.sprites.push(sprite);
+        Ok((self.sprites.len() - 1) as SpriteId)
+    }
+
+    /// Removes a sprite, returning it.
+    ///
+    /// Sprites with a higher index are shifted down, and `owner` references
+    /// are updated to match (references to the removed sprite become `-1`).
+    pub fn remove_sprite(&mut self, id: SpriteId) -> Option<Sprite> {
+        if id < 0 || id as usize >= self.sprites.len() {
+            return None;
+        }
+        let sprite = self.sprites.remove(id as usize);
+        for other in &mut self.sprites {
+            if other.owner == id {
+                other.owner = -1;
+            } else if other.owner > id {
+                other.owner -= 1;
+            }
+        }
+        Some(sprite)
+    }
+
+    /// Moves a sprite to another sector (its position is left untouched).
+    pub fn move_sprite(&mut self, id: SpriteId, sector: SectorId) -> Result<(), Error> {
+        self.check_sector(sector)?;
+        if id < 0 || id as usize >= self.sprites.len() {
+            return Err(Error::InvalidSprite(id));
+        }
+        let sprite = &mut self.sprites[id as usize];
+        sprite.sectnum = sector;
+        Ok(())
+    }
+
+    /// Sprites in the given sector, along with their index.
+    pub fn sprites_in(&self, sector: SectorId) -> impl Iterator<Item = (SpriteId, &Sprite)> {
+        self.sprites
+            .iter()
+            .enumerate()
+            .filter(move |(_, s)| s.sectnum == sector)
+            .map(|(id, s)| (id as SpriteId, s))
+    }
+
+    fn check_sector(&self, sector: SectorId) -> Result<(), Error> {
+        match self.sectors.get(sector) {
+            Some(_) => Ok(()),
+            None => Err(Error::InvalidSector(sector)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn from_reader<R: Read>(
+    reader: &mut R,
+    progress: &mut Reporter<'_>,
+) -> Result<Vec<Sprite>, Error> {
     let num_sprites = reader.read_u16::<LE>()? as usize;
-    (0..num_sprites)
-        .map(|_| Sprite::from_reader(reader))
-        .collect::<Result<Vec<_>, _>>()
+    progress.section(Section::Sprites, num_sprites, || {
+        Sprite::from_reader(reader)
+    })
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn to_writer<W: Write>(sprites: &[Sprite], writer: &mut W) -> Result<(), Error> {
+    if sprites.len() > usize::from(u16::MAX) {
+        return Err(Error::LimitExceeded("sprites"));
+    }
+    writer.write_u16::<LE>(sprites.len() as u16)?;
+    sprites
+        .iter()
+        .try_for_each(|sprite| sprite.to_writer(writer))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Sprite;
+    use crate::{Error, Map};
+
+    #[test]
+    fn lifecycle() {
+        let mut map = Map::from_slice(include_bytes!("../tests/maps/SIMPLE0.MAP")).unwrap();
+        let sector = map.player.sector;
+        let count = map.sprites.len();
+
+        let a = map.add_sprite(Sprite::new(0, 0, 0, sector)).unwrap();
+        let mut owned = Sprite::new(0, 0, 0, sector);
+        owned.owner = a;
+        let b = map.add_sprite(owned).unwrap();
+        let mut owned = Sprite::new(0, 0, 0, sector);
+        owned.owner = b;
+        let c = map.add_sprite(owned).unwrap();
+        assert_eq!(count + 3, map.sprites.len());
+        assert!(matches!(
+            map.add_sprite(Sprite::new(0, 0, 0, -1)),
+            Err(Error::InvalidSector(-1))
+        ));
+
+        map.move_sprite(c, 1).unwrap();
+        assert_eq!(1, map.sprites[c as usize].sectnum);
+        assert_eq!(
+            vec![a, b],
+            map.sprites_in(sector).map(|(id, _)| id).collect::<Vec<_>>()[count..]
+        );
+        assert!(matches!(
+            map.move_sprite(c, 1000),
+            Err(Error::InvalidSector(1000))
+        ));
+        assert!(matches!(
+            map.move_sprite(1000, 1),
+            Err(Error::InvalidSprite(1000))
+        ));
+
+        // owners follow the shifted sprites
+        map.remove_sprite(a).unwrap();
+        assert_eq!(-1, map.sprites[a as usize].owner);
+        assert_eq!(a, map.sprites[b as usize].owner);
+        assert!(map.remove_sprite(c).is_none());
+    }
 }
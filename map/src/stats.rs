@@ -0,0 +1,293 @@
+use crate::{
+    analysis,
+    sector::{SectorId, WallStat},
+    Map,
+};
+use std::collections::{btree_map, BTreeMap};
+
+/// Number of times a tile is referenced by each kind of map element.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct TileUsage {
+    pub walls: usize,
+
+    /// Masked & one-way walls (`over_picnum`).
+    pub masked_walls: usize,
+
+    pub floors: usize,
+    pub ceilings: usize,
+    pub sprites: usize,
+}
+
+impl TileUsage {
+    /// Total number of references to the tile.
+    pub fn total(&self) -> usize {
+        self.walls + self.masked_walls + self.floors + self.ceilings + self.sprites
+    }
+}
+
+/// Tile usage of a map, indexed by picnum.
+#[derive(Debug, Default, Clone)]
+pub struct TextureUsage(BTreeMap<i16, TileUsage>);
+
+impl TextureUsage {
+    /// Compute the tile usage of the given map.
+    pub fn new(map: &Map) -> Self {
+        let mut usage = BTreeMap::<_, TileUsage>::new();
+        for wall in map.sectors.walls() {
+            usage.entry(wall.picnum).or_default().walls += 1;
+            if wall
+                .wall_stat
+                .intersects(WallStat::MASKING_WALL | WallStat::ONE_WAY_WALL)
+            {
+                usage.entry(wall.over_picnum).or_default().masked_walls += 1;
+            }
+        }
+        for sector in map.sectors.sectors() {
+            usage.entry(sector.floor_picnum).or_default().floors += 1;
+            usage.entry(sector.ceiling_picnum).or_default().ceilings += 1;
+        }
+        for sprite in &map.sprites {
+            usage.entry(sprite.picnum).or_default().sprites += 1;
+        }
+        Self(usage)
+    }
+
+    /// Returns the usage of the given tile.
+    pub fn get(&self, picnum: i16) -> Option<&TileUsage> {
+        self.0.get(&picnum)
+    }
+
+    /// Number of distinct tiles referenced by the map.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the referenced tiles in ascending picnum order.
+    pub fn iter(&self) -> btree_map::Iter<'_, i16, TileUsage> {
+        self.0.iter()
+    }
+
+    /// Returns the referenced picnums for which `exists` returns false (e.g.
+    /// tiles missing from a set of ART files).
+    pub fn missing<F: Fn(i16) -> bool>(&self, exists: F) -> Vec<i16> {
+        self.0.keys().copied().filter(|p| !exists(*p)).collect()
+    }
+}
+
+/// Metrics of the sector adjacency graph (sectors connected by portals).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GraphMetrics {
+    /// Connected components. Usually 1, unless the MAP has unreachable
+    /// areas.
+    pub components: usize,
+
+    /// Number of distinct pairs of sectors sharing a portal.
+    pub edges: usize,
+
+    /// Chokepoints: sectors whose removal disconnects the graph (articulation
+    /// points), in ascending order.
+    pub articulation_points: Vec<SectorId>,
+
+    /// Number of independent cycles (loops) in the layout, which is the
+    /// cyclomatic number `edges - sectors + components`.
+    pub cycles: usize,
+
+    /// Average number of neighbours per sector.
+    pub average_branching: f64,
+}
+
+impl GraphMetrics {
+    /// Compute the metrics of the sector graph of a map.
+    pub fn new(map: &Map) -> Self {
+        let mut adjacency = analysis::adjacency(map);
+        // portals are meant to be two-way, but don't trust broken MAPs.
+        for sector in 0..adjacency.len() {
+            for i in 0..adjacency[sector].len() {
+                let next = adjacency[sector][i] as usize;
+                if let Err(pos) = adjacency[next].binary_search(&(sector as SectorId)) {
+                    adjacency[next].insert(pos, sector as SectorId);
+                }
+            }
+        }
+        let vertices = adjacency.len();
+        let degrees: usize = adjacency.iter().map(Vec::len).sum();
+        let edges = degrees / 2;
+        let (components, articulation_points) = articulation_points(&adjacency);
+        Self {
+            components,
+            edges,
+            articulation_points,
+            cycles: (edges + components).saturating_sub(vertices),
+            average_branching: if vertices == 0 {
+                0.0
+            } else {
+                degrees as f64 / vertices as f64
+            },
+        }
+    }
+}
+
+/// Iterative Hopcroft-Tarjan. Returns the number of connected components and
+/// the articulation points of an undirected graph.
+fn articulation_points(adjacency: &[Vec<SectorId>]) -> (usize, Vec<SectorId>) {
+    const UNVISITED: usize = usize::MAX;
+    let len = adjacency.len();
+    let mut depth = vec![UNVISITED; len];
+    let mut low = vec![0; len];
+    let mut parent = vec![UNVISITED; len];
+    let mut is_articulation = vec![false; len];
+    let mut components = 0;
+    for root in 0..len {
+        if depth[root] != UNVISITED {
+            continue;
+        }
+        components += 1;
+        depth[root] = 0;
+        let mut root_children = 0;
+        // (vertex, index of the next neighbour to visit)
+        let mut stack = vec![(root, 0)];
+        while let Some(&mut (vertex, ref mut next)) = stack.last_mut() {
+            if let Some(&child) = adjacency[vertex].get(*next) {
+                *next += 1;
+                let child = child as usize;
+                if depth[child] == UNVISITED {
+                    depth[child] = depth[vertex] + 1;
+                    low[child] = depth[child];
+                    parent[child] = vertex;
+                    stack.push((child, 0));
+                } else if child != parent[vertex] {
+                    low[vertex] = low[vertex].min(depth[child]);
+                }
+                continue;
+            }
+            stack.pop();
+            let up = parent[vertex];
+            if up == UNVISITED {
+                continue;
+            }
+            low[up] = low[up].min(low[vertex]);
+            if up == root {
+                root_children += 1;
+            } else if low[vertex] >= depth[up] {
+                is_articulation[up] = true;
+            }
+        }
+        is_articulation[root] = root_children > 1;
+    }
+    let points = is_articulation
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| **a)
+        .map(|(s, _)| s as SectorId)
+        .collect();
+    (components, points)
+}
+
+/// Map statistics.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub sectors: usize,
+    pub walls: usize,
+    pub sprites: usize,
+
+    /// Referenced tiles.
+    pub textures: TextureUsage,
+
+    /// Layout metrics.
+    pub graph: GraphMetrics,
+}
+
+impl Map {
+    /// Compute map statistics.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            sectors: self.sectors.sectors().len(),
+            walls: self.sectors.walls().len(),
+            sprites: self.sprites.len(),
+            textures: TextureUsage::new(self),
+            graph: GraphMetrics::new(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{articulation_points, GraphMetrics, TextureUsage, TileUsage};
+    use crate::{sector::WallStat, Map};
+
+    #[test]
+    fn chokepoints() {
+        // triangle 0-1-2, with a corridor 2-3-4, and a detached sector 5.
+        let graph = [
+            vec![1, 2],
+            vec![0, 2],
+            vec![0, 1, 3],
+            vec![2, 4],
+            vec![3],
+            vec![],
+        ];
+        assert_eq!((2, vec![2, 3]), articulation_points(&graph));
+    }
+
+    #[test]
+    fn metrics() {
+        let map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let graph = GraphMetrics::new(&map);
+        let sectors = map.sectors.sectors().len();
+        assert_eq!(graph.cycles + sectors, graph.edges + graph.components);
+        assert!(graph.average_branching > 1.0);
+        assert!(!graph.articulation_points.is_empty());
+        assert_eq!(graph, map.stats().graph);
+    }
+
+    #[test]
+    fn texture_usage() {
+        // every surface of SIMPLE0 uses tile 0
+        let map = Map::from_slice(include_bytes!("../tests/maps/SIMPLE0.MAP")).unwrap();
+        let stats = map.stats();
+        assert_eq!((20, 94, 0), (stats.sectors, stats.walls, stats.sprites));
+        assert_eq!(1, stats.textures.len());
+        let usage = TileUsage {
+            walls: 94,
+            masked_walls: 0,
+            floors: 20,
+            ceilings: 20,
+            sprites: 0,
+        };
+        assert_eq!(Some(&usage), stats.textures.get(0));
+        assert_eq!(134, usage.total());
+        assert_eq!(vec![0], stats.textures.missing(|_| false));
+
+        let map = Map::from_slice(include_bytes!("../tests/maps/E1L1.MAP")).unwrap();
+        let textures = TextureUsage::new(&map);
+        let walls = map.sectors.walls();
+        let masked = walls
+            .iter()
+            .filter(|w| {
+                w.wall_stat
+                    .intersects(WallStat::MASKING_WALL | WallStat::ONE_WAY_WALL)
+            })
+            .count();
+        let total: usize = textures.iter().map(|(_, u)| u.total()).sum();
+        assert_eq!(
+            walls.len() + masked + 2 * map.sectors.sectors().len() + map.sprites.len(),
+            total
+        );
+        let picnum = map.sprites[0].picnum;
+        let sprites = map.sprites.iter().filter(|s| s.picnum == picnum).count();
+        assert_eq!(sprites, textures.get(picnum).unwrap().sprites);
+        let picnum = walls[0].picnum;
+        let count = walls.iter().filter(|w| w.picnum == picnum).count();
+        assert_eq!(count, textures.get(picnum).unwrap().walls);
+        // picnums in ascending order
+        assert!(textures
+            .iter()
+            .zip(textures.iter().skip(1))
+            .all(|(a, b)| a.0 < b.0));
+        assert!(textures.missing(|_| true).is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+//! Top-down SVG export.
+use crate::Map;
+use std::fmt::Write;
+
+/// Returns a top-down SVG drawing of the MAP walls, scaled so the largest
+/// side of the MAP spans `size` pixels. Solid walls are white and portals
+/// red, over a black background.
+pub fn top_down(map: &Map, size: f64) -> String {
+    let walls = map.sectors.walls();
+    let min_x = walls.iter().map(|w| w.x).min().unwrap_or(0);
+    let max_x = walls.iter().map(|w| w.x).max().unwrap_or(0);
+    let min_y = walls.iter().map(|w| w.y).min().unwrap_or(0);
+    let max_y = walls.iter().map(|w| w.y).max().unwrap_or(0);
+    let extent = f64::from((max_x - min_x).max(max_y - min_y).max(1));
+    let scale = size / extent;
+    let width = (f64::from(max_x - min_x) * scale).ceil();
+    let height = (f64::from(max_y - min_y) * scale).ceil();
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n<rect width=\"100%\" height=\"100%\" fill=\"black\"/>\n",
+        width, height
+    );
+    for wall in walls {
+        let next = match walls.get(wall.point2 as usize) {
+            Some(next) => next,
+            None => continue,
+        };
+        let color = if wall.next_wall == -1 { "white" } else { "red" };
+        writeln!(
+            svg,
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\"/>",
+            f64::from(wall.x - min_x) * scale,
+            f64::from(wall.y - min_y) * scale,
+            f64::from(next.x - min_x) * scale,
+            f64::from(next.y - min_y) * scale,
+            color,
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
@@ -0,0 +1,149 @@
+//! Game-specific sprite tags.
+//!
+//! The meaning of sprite picnums, lotags and hitags is not defined by the
+//! engine but by each game.
+//!
+//! Shadow Warrior support is limited to telling its MAPs apart
+//! ([`GameProfile::detect`]) and decoding its `ST1` & `ST2` sprites (sound
+//! spots & markers). The meaning of the other marker hitags, SW sector tags
+//! and SW key cards aren't decoded.
+use crate::Map;
+
+/// Game whose tag conventions are used to decode sprites.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum GameProfile {
+    /// Duke Nukem 3D (and derivatives sharing its tags).
+    #[default]
+    Duke3D,
+
+    /// Shadow Warrior. Only sound spots & markers are decoded (see the
+    /// [module](self) docs).
+    ShadowWarrior,
+
+    /// Redneck Rampage.
+    RedneckRampage,
+}
+
+impl GameProfile {
+    /// Guesses the game a MAP was made for, from the tags only used by
+    /// Shadow Warrior (`ST1` & `ST2` sprites) or Redneck Rampage (jail door &
+    /// mine cart sectors). Defaults to Duke Nukem 3D.
+    pub fn detect(map: &Map) -> Self {
+        let sw = map
+            .sprites
+            .iter()
+            .any(|s| s.picnum == sw::ST1 || s.picnum == sw::ST2);
+        let rr = map
+            .sectors
+            .sectors()
+            .iter()
+            .any(|s| s.lotag == rr::ST_41_JAILDOOR || s.lotag == rr::ST_42_MINECART);
+        if sw {
+            Self::ShadowWarrior
+        } else if rr {
+            Self::RedneckRampage
+        } else {
+            Self::Duke3D
+        }
+    }
+}
+
+/// Duke Nukem 3D special sprite picnums.
+pub mod duke {
+    /// Sector effector (`SE`). Lotag selects the effect.
+    pub const SECTOREFFECTOR: i16 = 1;
+
+    /// Activator. Triggers effectors with a matching lotag.
+    pub const ACTIVATOR: i16 = 2;
+
+    /// Touchplate. Triggers effectors when the player steps in the sector.
+    pub const TOUCHPLATE: i16 = 3;
+
+    /// Locked activator.
+    pub const ACTIVATORLOCKED: i16 = 4;
+
+    /// Ambient sound & music. Lotag is the sound number, hitag is the
+    /// distance at which the sound can be heard.
+    pub const MUSICANDSFX: i16 = 5;
+
+    /// Locator (used by subways & train effectors).
+    pub const LOCATORS: i16 = 6;
+
+    /// Cycler. Makes a sector's shade cycle.
+    pub const CYCLER: i16 = 7;
+
+    /// Master switch.
+    pub const MASTERSWITCH: i16 = 8;
+
+    /// Respawn point.
+    pub const RESPAWN: i16 = 9;
+
+    /// Game speed modifier.
+    pub const GPSPEED: i16 = 10;
+
+    /// Key card. Its palette selects the color of the key.
+    pub const ACCESSCARD: i16 = 60;
+
+    /// Key card wall switch. Its palette selects the key color, and its lotag
+    /// the activators it triggers.
+    pub const ACCESSSWITCH: i16 = 130;
+
+    /// Alternative key card wall switch (see [`ACCESSSWITCH`]).
+    pub const ACCESSSWITCH2: i16 = 1111;
+
+    /// End of level button.
+    pub const NUKEBUTTON: i16 = 142;
+
+    /// Palettes of the blue, red & yellow key cards.
+    pub const KEY_PALS: [u8; 3] = [0, 21, 23];
+
+    /// Sector effector lotag. Teleports to the sector of the effector with
+    /// the same hitag.
+    pub const SE_7_TELEPORT: i16 = 7;
+
+    /// Sector effector lotag. Transport elevator between the sectors of the
+    /// effectors with the same hitag.
+    pub const SE_17_WARP_ELEVATOR: i16 = 17;
+
+    /// Sector lotag. Floor lowers to the next floor below, and back up.
+    pub const ST_16_PLATFORM_DOWN: i16 = 16;
+
+    /// Sector lotag. Floor raises to the next floor above, and back down.
+    pub const ST_17_PLATFORM_UP: i16 = 17;
+
+    /// Sector lotag. Ceiling raises to the next ceiling above, and back down.
+    pub const ST_20_CEILING_DOOR: i16 = 20;
+
+    /// Sector lotag. Secret place.
+    pub const ST_SECRET_PLACE: i16 = 32767;
+
+    /// Sector lotag. Ends the level when entered.
+    pub const ST_END_OF_LEVEL: i16 = -1;
+
+    /// Sector lotag flag. The sector is a locked door, opened by a key or a
+    /// switch rather than by the player using it. Doesn't apply to
+    /// [`ST_SECRET_PLACE`] and [`ST_END_OF_LEVEL`].
+    pub const SECTOR_LOCKED: i16 = 0x4000;
+}
+
+/// Shadow Warrior special sprite picnums and tags.
+pub mod sw {
+    /// Sprite tag marker. Hitag selects the kind of marker.
+    pub const ST1: i16 = 2307;
+
+    /// Secondary sprite tag marker.
+    pub const ST2: i16 = 2308;
+
+    /// `ST1` hitag of ambient sound spots. Lotag is the sound number.
+    pub const SOUND_SPOT: i16 = 134;
+}
+
+/// Redneck Rampage sector tags. Special sprites share the picnums of
+/// [`duke`](duke).
+pub mod rr {
+    /// Sector lotag. Door sliding sideways when opened.
+    pub const ST_41_JAILDOOR: i16 = 41;
+
+    /// Sector lotag. Mine cart track.
+    pub const ST_42_MINECART: i16 = 42;
+}
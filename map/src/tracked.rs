@@ -0,0 +1,283 @@
+//! Map wrapper that notifies observers of mutations.
+use crate::{
+    player::Player,
+    sector::{Sector, SectorId, Sectors, Wall, WallId},
+    sprite::{Sprite, SpriteId},
+    Error, Map,
+};
+
+// largest element count addressable with i16 indices.
+const MAX_ELEMENTS: usize = i16::MAX as usize;
+
+/// Map mutation event.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Event {
+    /// Sector attributes changed.
+    SectorChanged(SectorId),
+
+    /// Wall attributes (other than its position) changed.
+    WallChanged(WallId),
+
+    /// Left vertex of a wall was moved to the given position.
+    WallMoved {
+        wall: WallId,
+        x: i32,
+        y: i32,
+    },
+
+    SpriteAdded(SpriteId),
+    SpriteChanged(SpriteId),
+
+    /// Sprite was removed. Sprites with a higher index are shifted down.
+    SpriteRemoved(SpriteId),
+
+    PlayerChanged,
+
+    /// Arbitrary change (e.g. map transforms). Any derived data should be
+    /// invalidated.
+    MapChanged,
+}
+
+type Observer = Box<dyn FnMut(&Event)>;
+
+/// [`Map`](Map) wrapper emitting an [`Event`](Event) for every mutation made
+/// through its API.
+pub struct TrackedMap {
+    map: Map,
+    observers: Vec<Observer>,
+}
+
+impl std::fmt::Debug for TrackedMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrackedMap")
+            .field("map", &self.map)
+            .field("observers", &self.observers.len())
+            .finish()
+    }
+}
+
+impl TrackedMap {
+    pub fn new(map: Map) -> Self {
+        Self {
+            map,
+            observers: Vec::new(),
+        }
+    }
+
+    /// Returns the tracked map.
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
+    /// Returns the tracked map, dropping all observers.
+    pub fn into_inner(self) -> Map {
+        self.map
+    }
+
+    /// Registers an observer called after every mutation.
+    pub fn subscribe<F: FnMut(&Event) + 'static>(&mut self, observer: F) {
+        self.observers.push(Box::new(observer));
+    }
+
+    fn emit(&mut self, event: Event) {
+        self.observers.iter_mut().for_each(|o| o(&event));
+    }
+
+    /// Modifies the attributes of a sector.
+    pub fn modify_sector<F: FnOnce(&mut Sector)>(&mut self, id: SectorId, f: F) {
+        if let Some(sector) = self.map.sectors.sectors.get_mut(id as usize) {
+            f(sector);
+            self.emit(Event::SectorChanged(id));
+        }
+    }
+
+    /// Modifies the attributes of a wall. Position changes must be made with
+    /// [`TrackedMap::move_wall`](TrackedMap::move_wall).
+    pub fn modify_wall<F: FnOnce(&mut Wall)>(&mut self, id: WallId, f: F) {
+        if let Some(wall) = self.map.sectors.walls.get_mut(id as usize) {
+            let (x, y) = (wall.x, wall.y);
+            f(wall);
+            wall.x = x;
+            wall.y = y;
+            self.emit(Event::WallChanged(id));
+        }
+    }
+
+    /// Moves the left vertex of a wall. The coincident vertex of the wall on
+    /// the other side of the previous wall (if it's a portal) is moved too,
+    /// so sectors stay connected.
+    pub fn move_wall(&mut self, id: WallId, x: i32, y: i32) {
+        let walls = &self.map.sectors.walls;
+        if id < 0 || id as usize >= walls.len() {
+            return;
+        }
+        let (old_x, old_y) = (walls[id as usize].x, walls[id as usize].y);
+        let valid = |w: WallId| w >= 0 && (w as usize) < walls.len();
+        // walls sharing the vertex, found by rotating around it through
+        // portals in both directions.
+        let rotate_cw = |w: WallId| {
+            walls
+                .iter()
+                .position(|p| p.point2 == w)
+                .map(|p| walls[p].next_wall)
+        };
+        let rotate_ccw = |w: WallId| {
+            Some(walls[w as usize].next_wall)
+                .filter(|&n| valid(n))
+                .map(|n| walls[n as usize].point2)
+        };
+        let mut moved = vec![id];
+        let rotations: [&dyn Fn(WallId) -> Option<WallId>; 2] = [&rotate_cw, &rotate_ccw];
+        for rotate in rotations.iter() {
+            let mut curr = id;
+            while let Some(next) = rotate(curr).filter(|&n| valid(n) && !moved.contains(&n)) {
+                let w = &walls[next as usize];
+                if (w.x, w.y) != (old_x, old_y) {
+                    break;
+                }
+                moved.push(next);
+                curr = next;
+            }
+        }
+        for wall in moved {
+            let w = &mut self.map.sectors.walls[wall as usize];
+            w.x = x;
+            w.y = y;
+            self.emit(Event::WallMoved { wall, x, y });
+        }
+    }
+
+    /// Splits a wall in two at the given point, returning the index of the
+    /// new wall (right after `id`). If the wall is a portal, the wall on the
+    /// other side is split too.
+    ///
+    /// Wall indices after the new walls are shifted, so a single
+    /// [`Event::MapChanged`](Event::MapChanged) is emitted.
+    pub fn split_wall(&mut self, id: WallId, x: i32, y: i32) -> Option<WallId> {
+        let sectors = &mut self.map.sectors;
+        if id < 0 || id as usize >= sectors.walls.len() || sectors.walls.len() + 2 > MAX_ELEMENTS {
+            return None;
+        }
+        let split = |sectors: &mut Sectors, wall: usize| {
+            let mut new = sectors.walls[wall].clone();
+            new.x = x;
+            new.y = y;
+            sectors.insert_wall(wall + 1, new);
+            sectors.walls[wall].point2 = (wall + 1) as WallId;
+        };
+        let shift = |i: usize, index: usize| if i >= index { i + 1 } else { i };
+        let a = id as usize;
+        let other = sectors.walls[a].next_wall;
+        split(sectors, a);
+        if other >= 0 {
+            // a: P -> M, a + 1: M -> Q, b: Q -> M, b + 1: M -> P
+            let b = shift(other as usize, a + 1);
+            split(sectors, b);
+            let (a, a2) = (shift(a, b + 1), shift(a + 1, b + 1));
+            let link = |sectors: &mut Sectors, l: usize, r: usize| {
+                sectors.walls[l].next_wall = r as WallId;
+                sectors.walls[r].next_wall = l as WallId;
+            };
+            link(sectors, a, b + 1);
+            link(sectors, a2, b);
+            self.emit(Event::MapChanged);
+            return Some(a2 as WallId);
+        }
+        self.emit(Event::MapChanged);
+        Some(id + 1)
+    }
+
+    /// Appends a new sector with a single loop of walls through the given
+    /// points, returning its index. Attributes are copied from the given
+    /// `sector` & `wall` prototypes.
+    pub fn add_sector(
+        &mut self,
+        points: &[[i32; 2]],
+        sector: &Sector,
+        wall: &Wall,
+    ) -> Option<SectorId> {
+        let sectors = &mut self.map.sectors;
+        let first = sectors.walls.len();
+        if points.len() < 3
+            || first + points.len() > MAX_ELEMENTS
+            || sectors.sectors.len() + 1 > MAX_ELEMENTS
+        {
+            return None;
+        }
+        // walls of the outer loop are ordered so the inside of the sector is
+        // to their left (positive signed area).
+        let area: i64 = (0..points.len())
+            .map(|i| {
+                let [ax, ay] = points[i];
+                let [bx, by] = points[(i + 1) % points.len()];
+                i64::from(ax) * i64::from(by) - i64::from(bx) * i64::from(ay)
+            })
+            .sum();
+        let mut points = points.to_vec();
+        if area < 0 {
+            points.reverse();
+        }
+        for (i, [x, y]) in points.iter().enumerate() {
+            let mut wall = wall.clone();
+            wall.x = *x;
+            wall.y = *y;
+            wall.point2 = (first + (i + 1) % points.len()) as WallId;
+            wall.next_wall = -1;
+            wall.next_sector = -1;
+            sectors.walls.push(wall);
+        }
+        let mut sector = sector.clone();
+        sector.wallptr = first as u16;
+        sector.wallnum = points.len() as u16;
+        sectors.sectors.push(sector);
+        let id = (sectors.sectors.len() - 1) as SectorId;
+        self.emit(Event::MapChanged);
+        Some(id)
+    }
+
+    /// Appends a sprite, returning its index (see
+    /// [`Map::add_sprite`](Map::add_sprite)).
+    pub fn add_sprite(&mut self, sprite: Sprite) -> Result<SpriteId, Error> {
+        let id = self.map.add_sprite(sprite)?;
+        self.emit(Event::SpriteAdded(id));
+        Ok(id)
+    }
+
+    /// Modifies the attributes of a sprite.
+    pub fn modify_sprite<F: FnOnce(&mut Sprite)>(&mut self, id: SpriteId, f: F) {
+        if let Some(sprite) = self.map.sprites.get_mut(id as usize) {
+            f(sprite);
+            self.emit(Event::SpriteChanged(id));
+        }
+    }
+
+    /// Removes a sprite, returning it (see
+    /// [`Map::remove_sprite`](Map::remove_sprite)).
+    pub fn remove_sprite(&mut self, id: SpriteId) -> Option<Sprite> {
+        let sprite = self.map.remove_sprite(id)?;
+        self.emit(Event::SpriteRemoved(id));
+        Some(sprite)
+    }
+
+    /// Moves a sprite to another sector (see
+    /// [`Map::move_sprite`](Map::move_sprite)).
+    pub fn move_sprite(&mut self, id: SpriteId, sector: SectorId) -> Result<(), Error> {
+        self.map.move_sprite(id, sector)?;
+        self.emit(Event::SpriteChanged(id));
+        Ok(())
+    }
+
+    /// Modifies the player start.
+    pub fn modify_player<F: FnOnce(&mut Player)>(&mut self, f: F) {
+        f(&mut self.map.player);
+        self.emit(Event::PlayerChanged);
+    }
+
+    /// Applies an arbitrary change to the map (e.g. a transform), emitting a
+    /// single [`Event::MapChanged`](Event::MapChanged).
+    pub fn modify<R, F: FnOnce(&mut Map) -> R>(&mut self, f: F) -> R {
+        let r = f(&mut self.map);
+        self.emit(Event::MapChanged);
+        r
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{
+    player::Angle,
+    sector::{SectorStat, WallStat},
+    sprite::SpriteStat,
+    Map,
+};
+
+// 2x2 integer matrix (row-major) mapping world XY to floor texture UV.
+type Mat2 = [[i32; 2]; 2];
+
+const ROTATE_90_INV: Mat2 = [[0, 1], [-1, 0]];
+const MIRROR_X_INV: Mat2 = [[-1, 0], [0, 1]];
+
+impl Map {
+    /// Moves all the map geometry, sprites and player start by the given
+    /// offset.
+    pub fn translate(&mut self, dx: i32, dy: i32, dz: i32) {
+        for wall in &mut self.sectors.walls {
+            wall.x += dx;
+            wall.y += dy;
+        }
+        for sector in &mut self.sectors.sectors {
+            sector.ceiling_z += dz;
+            sector.floor_z += dz;
+        }
+        for sprite in &mut self.sprites {
+            sprite.x += dx;
+            sprite.y += dy;
+            sprite.z += dz;
+        }
+        self.player.pos_x += dx;
+        self.player.pos_y += dy;
+        self.player.pos_z += dz;
+    }
+
+    /// Rotates the map 90 degrees clockwise (as seen from the top, with Y
+    /// pointing down) around the origin.
+    ///
+    /// Angles are rotated accordingly, and world-aligned floor & ceiling
+    /// textures are re-oriented so they rotate along with the geometry.
+    pub fn rotate_90(&mut self) {
+        let rotate = |x: &mut i32, y: &mut i32| {
+            let (rx, ry) = (-*y, *x);
+            *x = rx;
+            *y = ry;
+        };
+        for wall in &mut self.sectors.walls {
+            rotate(&mut wall.x, &mut wall.y);
+        }
+        for sector in &mut self.sectors.sectors {
+            sector.floor_stat = transform_sector_stat(sector.floor_stat, ROTATE_90_INV);
+            sector.ceiling_stat = transform_sector_stat(sector.ceiling_stat, ROTATE_90_INV);
+        }
+        for sprite in &mut self.sprites {
+            rotate(&mut sprite.x, &mut sprite.y);
+            sprite.angle += Angle(512);
+        }
+        rotate(&mut self.player.pos_x, &mut self.player.pos_y);
+        self.player.angle += Angle(512);
+    }
+
+    /// Mirrors the map along the X axis (i.e. negates all X coordinates).
+    ///
+    /// Wall loops are reversed to preserve the clockwise winding Build
+    /// requires, and textures are flipped so the result is an exact mirror
+    /// image of the original map.
+    pub fn mirror_x(&mut self) {
+        let walls = &mut self.sectors.walls;
+        let mut prev = vec![-1; walls.len()];
+        for (i, wall) in walls.iter().enumerate() {
+            if let Some(p) = prev.get_mut(wall.point2 as usize) {
+                *p = i as i16;
+            }
+        }
+        // each wall now spans from its (old) right vertex to its left vertex.
+        let right: Vec<_> = walls
+            .iter()
+            .map(|w| walls.get(w.point2 as usize).map(|r| (r.x, r.y)))
+            .collect();
+        for (i, wall) in walls.iter_mut().enumerate() {
+            if let (Some((x, y)), p) = (right[i], prev[i]) {
+                if p != -1 {
+                    wall.x = -x;
+                    wall.y = y;
+                    wall.point2 = p;
+                }
+            }
+            wall.wall_stat.toggle(WallStat::X_FLIPPED);
+        }
+        for sector in &mut self.sectors.sectors {
+            sector.floor_stat = transform_sector_stat(sector.floor_stat, MIRROR_X_INV);
+            sector.ceiling_stat = transform_sector_stat(sector.ceiling_stat, MIRROR_X_INV);
+        }
+        for sprite in &mut self.sprites {
+            sprite.x = -sprite.x;
+            sprite.angle = Angle(1024) - sprite.angle;
+            if sprite
+                .sprite_stat
+                .intersects(SpriteStat::RESERVED_SPRITE_TYPE)
+            {
+                sprite.sprite_stat.toggle(SpriteStat::X_FLIPPED);
+            }
+        }
+        self.player.pos_x = -self.player.pos_x;
+        self.player.angle = Angle(1024) - self.player.angle;
+    }
+
+    /// Scales the map around the origin by the given factor.
+    ///
+    /// Wall and sprite repeats are scaled too so that texel density is
+    /// preserved (repeats are clamped to the `1..=255` range).
+    pub fn scale(&mut self, factor: f64) {
+        let scale = |v: i32| (f64::from(v) * factor).round() as i32;
+        let repeat = |v: u8| (f64::from(v) * factor).round().clamp(1.0, 255.0) as u8;
+        for wall in &mut self.sectors.walls {
+            wall.x = scale(wall.x);
+            wall.y = scale(wall.y);
+            wall.x_repeat = repeat(wall.x_repeat);
+        }
+        for sector in &mut self.sectors.sectors {
+            sector.ceiling_z = scale(sector.ceiling_z);
+            sector.floor_z = scale(sector.floor_z);
+        }
+        for sprite in &mut self.sprites {
+            sprite.x = scale(sprite.x);
+            sprite.y = scale(sprite.y);
+            sprite.z = scale(sprite.z);
+            sprite.x_repeat = repeat(sprite.x_repeat);
+            sprite.y_repeat = repeat(sprite.y_repeat);
+            sprite.clip_dist = repeat(sprite.clip_dist);
+        }
+        self.player.pos_x = scale(self.player.pos_x);
+        self.player.pos_y = scale(self.player.pos_y);
+        self.player.pos_z = scale(self.player.pos_z);
+    }
+}
+
+// texture orientation matrix of the floor/ceiling flags (swap first, then
+// flips, as the engine does).
+fn sector_stat_matrix(stat: SectorStat) -> Mat2 {
+    let mut m = if stat.contains(SectorStat::SWAP_X_Y) {
+        [[0, 1], [1, 0]]
+    } else {
+        [[1, 0], [0, 1]]
+    };
+    if stat.contains(SectorStat::X_FLIP) {
+        m[0] = [-m[0][0], -m[0][1]];
+    }
+    if stat.contains(SectorStat::Y_FLIP) {
+        m[1] = [-m[1][0], -m[1][1]];
+    }
+    m
+}
+
+// Update the texture orientation flags of a floor/ceiling after transforming
+// the geometry by a matrix whose inverse is `inv`. Textures aligned to the
+// first wall follow the walls and are left untouched.
+fn transform_sector_stat(stat: SectorStat, inv: Mat2) -> SectorStat {
+    if stat.contains(SectorStat::ALIGN_TEXTURE_TO_FIRST_WALL) {
+        return stat;
+    }
+    let m = sector_stat_matrix(stat);
+    let target = [
+        [
+            m[0][0] * inv[0][0] + m[0][1] * inv[1][0],
+            m[0][0] * inv[0][1] + m[0][1] * inv[1][1],
+        ],
+        [
+            m[1][0] * inv[0][0] + m[1][1] * inv[1][0],
+            m[1][0] * inv[0][1] + m[1][1] * inv[1][1],
+        ],
+    ];
+    let orientation = SectorStat::SWAP_X_Y | SectorStat::X_FLIP | SectorStat::Y_FLIP;
+    let base = stat - orientation;
+    (0..8)
+        .map(|bits| {
+            let mut flags = base;
+            flags.set(SectorStat::SWAP_X_Y, bits & 1 != 0);
+            flags.set(SectorStat::X_FLIP, bits & 2 != 0);
+            flags.set(SectorStat::Y_FLIP, bits & 4 != 0);
+            flags
+        })
+        .find(|flags| sector_stat_matrix(*flags) == target)
+        .expect("texture orientation not representable")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sector_stat_matrix, transform_sector_stat, MIRROR_X_INV, ROTATE_90_INV};
+    use crate::sector::SectorStat;
+
+    #[test]
+    fn rotate_sector_stat_four_times() {
+        let mut stat = SectorStat::X_FLIP | SectorStat::PARALLAXING;
+        for _ in 0..4 {
+            let rotated = transform_sector_stat(stat, ROTATE_90_INV);
+            assert_ne!(sector_stat_matrix(stat), sector_stat_matrix(rotated));
+            assert!(rotated.contains(SectorStat::PARALLAXING));
+            stat = rotated;
+        }
+        assert_eq!(SectorStat::X_FLIP | SectorStat::PARALLAXING, stat);
+    }
+
+    #[test]
+    fn mirror_sector_stat() {
+        let stat = transform_sector_stat(SectorStat::empty(), MIRROR_X_INV);
+        assert_eq!(SectorStat::X_FLIP, stat);
+        let stat = transform_sector_stat(SectorStat::ALIGN_TEXTURE_TO_FIRST_WALL, MIRROR_X_INV);
+        assert_eq!(SectorStat::ALIGN_TEXTURE_TO_FIRST_WALL, stat);
+    }
+}
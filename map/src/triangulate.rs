@@ -0,0 +1,239 @@
+//! Triangulation of sector floors & ceilings.
+use crate::{
+    sector::{SectorId, WallId},
+    Map,
+};
+
+type Point = [i64; 2];
+
+/// Triangulates the floor (or ceiling) polygon of a sector, including any
+/// inner loops (holes), by ear clipping.
+///
+/// Triangles are returned as triplets of walls, whose first vertex is the
+/// triangle corner, in counter-clockwise order (X right, Y up). Triangles of
+/// degenerate sectors may be missing.
+pub fn triangulate(map: &Map, sector: SectorId) -> Vec<[WallId; 3]> {
+    let walls = map.sectors.walls();
+    let sector = match map.sectors.sectors().get(sector as usize) {
+        Some(sector) => sector,
+        None => return Vec::new(),
+    };
+    let first = usize::from(sector.wallptr).min(walls.len());
+    let last = (first + usize::from(sector.wallnum)).min(walls.len());
+
+    // split sector walls into loops.
+    let mut visited = vec![false; last - first];
+    let mut loops = Vec::new();
+    for start in first..last {
+        let mut wall_loop = Vec::new();
+        let mut wall = start;
+        while (first..last).contains(&wall) && !visited[wall - first] {
+            visited[wall - first] = true;
+            wall_loop.push(wall);
+            wall = walls[wall].point2 as usize;
+        }
+        if wall_loop.len() >= 3 {
+            loops.push(wall_loop);
+        }
+    }
+
+    let points: Vec<Vec<Point>> = loops
+        .iter()
+        .map(|l| {
+            l.iter()
+                .map(|w| [i64::from(walls[*w].x), i64::from(walls[*w].y)])
+                .collect()
+        })
+        .collect();
+    triangulate_loops(&points)
+        .into_iter()
+        .map(|[a, b, c]| {
+            let wall = |(l, i): (usize, usize)| loops[l][i] as WallId;
+            [wall(a), wall(b), wall(c)]
+        })
+        .collect()
+}
+
+/// Signed area (times two) of a polygon. Positive if counter-clockwise.
+fn area2(points: &[Point]) -> i64 {
+    let mut area = 0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        area += a[0] * b[1] - b[0] * a[1];
+    }
+    area
+}
+
+fn cross(o: Point, a: Point, b: Point) -> i64 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+// true if segments p-q & a-b cross at a single point interior to both.
+fn segments_cross(p: Point, q: Point, a: Point, b: Point) -> bool {
+    let d1 = cross(p, q, a).signum();
+    let d2 = cross(p, q, b).signum();
+    let d3 = cross(a, b, p).signum();
+    let d4 = cross(a, b, q).signum();
+    d1 * d2 < 0 && d3 * d4 < 0
+}
+
+// true if p lies on segment a-b, excluding its end points.
+fn on_segment(p: Point, a: Point, b: Point) -> bool {
+    p != a
+        && p != b
+        && cross(a, b, p) == 0
+        && p[0] >= a[0].min(b[0])
+        && p[0] <= a[0].max(b[0])
+        && p[1] >= a[1].min(b[1])
+        && p[1] <= a[1].max(b[1])
+}
+
+// Triangulates the polygon given by the largest loop, minus the rest of
+// loops. Vertices are identified by (loop, index) pairs.
+fn triangulate_loops(loops: &[Vec<Point>]) -> Vec<[(usize, usize); 3]> {
+    let outer = match (0..loops.len()).max_by_key(|l| area2(&loops[*l]).abs()) {
+        Some(outer) => outer,
+        None => return Vec::new(),
+    };
+    // outer loop counter-clockwise, holes clockwise.
+    let oriented = |l: usize, ccw: bool| -> Vec<(usize, usize)> {
+        let mut ids: Vec<_> = (0..loops[l].len()).map(|i| (l, i)).collect();
+        if (area2(&loops[l]) > 0) != ccw {
+            ids.reverse();
+        }
+        ids
+    };
+    let point = |(l, i): (usize, usize)| loops[l][i];
+    let mut polygon = oriented(outer, true);
+
+    // bridge holes into the outer polygon, rightmost holes first.
+    let mut holes: Vec<_> = (0..loops.len())
+        .filter(|l| *l != outer)
+        .map(|l| oriented(l, false))
+        .collect();
+    holes.sort_by_key(|h| std::cmp::Reverse(h.iter().map(|v| point(*v)[0]).max()));
+    for (h, hole) in holes.iter().enumerate() {
+        let (m, &mv) = hole
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, v)| point(**v))
+            .unwrap();
+        let mp = point(mv);
+        let edges = |vertices: &[(usize, usize)]| {
+            let n = vertices.len();
+            (0..n)
+                .map(move |i| (point(vertices[i]), point(vertices[(i + 1) % n])))
+                .collect::<Vec<_>>()
+        };
+        let mut blocking = edges(&polygon);
+        holes[h..].iter().for_each(|h| blocking.extend(edges(h)));
+        // the bridge can't cross any edge, nor go through any vertex.
+        let visible = |pp: Point| {
+            blocking
+                .iter()
+                .all(|(a, b)| !segments_cross(mp, pp, *a, *b) && !on_segment(*a, mp, pp))
+        };
+        let distance = |p: Point| (p[0] - mp[0]).pow(2) + (p[1] - mp[1]).pow(2);
+        let bridge = (0..polygon.len())
+            .filter(|i| visible(point(polygon[*i])))
+            .min_by_key(|i| distance(point(polygon[*i])));
+        if let Some(p) = bridge {
+            let mut merged = polygon[..=p].to_vec();
+            merged.extend(hole[m..].iter().chain(hole[..=m].iter()));
+            merged.extend_from_slice(&polygon[p..]);
+            polygon = merged;
+        }
+    }
+
+    ear_clip(polygon, point)
+}
+
+fn ear_clip<F>(mut polygon: Vec<(usize, usize)>, point: F) -> Vec<[(usize, usize); 3]>
+where
+    F: Fn((usize, usize)) -> Point,
+{
+    let mut triangles = Vec::with_capacity(polygon.len().saturating_sub(2));
+    let mut i = 0;
+    let mut attempts = 0;
+    while polygon.len() > 3 {
+        let n = polygon.len();
+        let (prev, curr, next) = (
+            polygon[(i + n - 1) % n],
+            polygon[i % n],
+            polygon[(i + 1) % n],
+        );
+        let (a, b, c) = (point(prev), point(curr), point(next));
+        let convex = cross(a, b, c) > 0;
+        // no other vertex inside the triangle (bridges duplicate vertices).
+        let is_ear = convex
+            && polygon.iter().all(|v| {
+                let p = point(*v);
+                p == a
+                    || p == b
+                    || p == c
+                    || cross(a, b, p) < 0
+                    || cross(b, c, p) < 0
+                    || cross(c, a, p) < 0
+            });
+        // if no ears are left (degenerate or self-intersecting loops), clip
+        // vertices anyway. Collinear & reflex vertices don't emit triangles.
+        if is_ear || attempts > n {
+            if convex {
+                triangles.push([prev, curr, next]);
+            }
+            polygon.remove(i % n);
+            attempts = 0;
+        } else {
+            i += 1;
+            attempts += 1;
+        }
+        i %= polygon.len();
+    }
+    if let [a, b, c] = polygon[..] {
+        if cross(point(a), point(b), point(c)) > 0 {
+            triangles.push([a, b, c]);
+        }
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod test {
+    use super::{area2, triangulate_loops, Point};
+
+    fn triangulated_area(loops: &[Vec<Point>]) -> i64 {
+        triangulate_loops(loops)
+            .iter()
+            .map(|t| {
+                let points: Vec<_> = t.iter().map(|(l, i)| loops[*l][*i]).collect();
+                let area = area2(&points);
+                assert!(area > 0, "triangle not counter-clockwise");
+                area
+            })
+            .sum()
+    }
+
+    #[test]
+    fn concave() {
+        // "L" shape, clockwise
+        let l = vec![[0, 0], [0, 10], [4, 10], [4, 4], [10, 4], [10, 0]];
+        assert_eq!(4, triangulate_loops(std::slice::from_ref(&l)).len());
+        assert_eq!(area2(&l).abs(), triangulated_area(&[l]));
+    }
+
+    #[test]
+    fn holes() {
+        let outer = vec![[0, 0], [20, 0], [20, 20], [0, 20]];
+        let hole_a = vec![[2, 2], [2, 6], [6, 6], [6, 2]];
+        let hole_b = vec![[12, 12], [16, 12], [16, 16], [12, 16]];
+        let loops = [hole_a.clone(), outer.clone(), hole_b.clone()];
+        let expected = area2(&outer).abs() - area2(&hole_a).abs() - area2(&hole_b).abs();
+        assert_eq!(expected, triangulated_area(&loops));
+    }
+
+    #[test]
+    fn degenerate() {
+        assert!(triangulate_loops(&[]).is_empty());
+        assert!(triangulate_loops(&[vec![[0, 0], [1, 1], [2, 2]]]).is_empty());
+    }
+}
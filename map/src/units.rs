@@ -0,0 +1,105 @@
+//! Unit conversions.
+//!
+//! Build maps use integer coordinates in two different scales: X & Y are in
+//! *XY units*, and Z (floor & ceiling heights, sprite and player positions) is
+//! in *Z units*, which are 16 times finer (Z is shifted left by
+//! [`Z_SHIFT`](Z_SHIFT) bits relative to X & Y). Z also grows downwards.
+//!
+//! There is no official real-world scale. This module uses the common
+//! approximation of 512 XY units per meter.
+//!
+//! ```
+//! use map::units::{self, Vec3};
+//!
+//! assert_eq!(1024.0, units::z_to_xy(16384));
+//! assert_eq!(2.0, units::xy_to_meters(1024));
+//! assert_eq!(Vec3::new(1.0, -2.0, -1.0), Vec3::from_build(1, 2, 16));
+//! ```
+
+/// Z coordinates are X & Y coordinates shifted left by this many bits.
+pub const Z_SHIFT: u32 = 4;
+
+/// Number of Z units in an XY unit.
+pub const Z_SCALE: f32 = (1 << Z_SHIFT) as f32;
+
+/// Number of XY units in a meter.
+pub const XY_UNITS_PER_METER: f32 = 512.0;
+
+/// Number of Z units in a meter.
+pub const Z_UNITS_PER_METER: f32 = XY_UNITS_PER_METER * Z_SCALE;
+
+/// Converts Z units to XY units.
+pub fn z_to_xy(z: i32) -> f32 {
+    z as f32 / Z_SCALE
+}
+
+/// Converts XY units to Z units.
+#[cfg(feature = "std")]
+pub fn xy_to_z(xy: f32) -> i32 {
+    (xy * Z_SCALE).round() as i32
+}
+
+pub fn xy_to_meters(xy: i32) -> f32 {
+    xy as f32 / XY_UNITS_PER_METER
+}
+
+pub fn z_to_meters(z: i32) -> f32 {
+    z as f32 / Z_UNITS_PER_METER
+}
+
+#[cfg(feature = "std")]
+pub fn meters_to_xy(meters: f32) -> i32 {
+    (meters * XY_UNITS_PER_METER).round() as i32
+}
+
+#[cfg(feature = "std")]
+pub fn meters_to_z(meters: f32) -> i32 {
+    (meters * Z_UNITS_PER_METER).round() as i32
+}
+
+/// Point in a right handed, Z-up space: X points east, Y north and Z up
+/// (i.e. Build's Y & Z axes are flipped), all in XY units.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Converts a point in Build coordinates (XY units for X & Y, Z units
+    /// for Z).
+    pub fn from_build(x: i32, y: i32, z: i32) -> Self {
+        Self::new(x as f32, -(y as f32), -z_to_xy(z))
+    }
+
+    /// Inverse of [`from_build`](Self::from_build), rounded to the nearest
+    /// unit.
+    #[cfg(feature = "std")]
+    pub fn to_build(&self) -> [i32; 3] {
+        [
+            self.x.round() as i32,
+            (-self.y).round() as i32,
+            xy_to_z(-self.z),
+        ]
+    }
+
+    /// Same point, in meters.
+    pub fn to_meters(&self) -> Self {
+        Self::new(
+            self.x / XY_UNITS_PER_METER,
+            self.y / XY_UNITS_PER_METER,
+            self.z / XY_UNITS_PER_METER,
+        )
+    }
+}
+
+impl From<Vec3> for [f32; 3] {
+    fn from(v: Vec3) -> Self {
+        [v.x, v.y, v.z]
+    }
+}
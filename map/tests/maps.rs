@@ -4,7 +4,22 @@ macro_rules! tests {
             #[test]
             fn $test() {
                 let file = include_bytes!($file);
-                map::Map::from_slice(file).unwrap();
+                let map = map::Map::from_slice(file).unwrap();
+                // round-trip
+                assert_eq!(&file[..], &map.to_vec().unwrap()[..]);
+                for (i, sector) in map.sectors.sectors().iter().enumerate() {
+                    let range = sector.wall_range();
+                    assert_eq!(range.len(), sector.wall_count());
+                    assert_eq!(range.start as i16, sector.first_wall());
+                    assert!(range.end <= map.sectors.walls().len());
+                    let (_, mut walls) = map.sectors.get(i as _).unwrap();
+                    assert_eq!(Some(sector.first_wall()), walls.next().map(|(id, _, _)| id));
+                }
+                let owners = map.sectors.wall_owners();
+                for (wall, owner) in owners.iter().enumerate() {
+                    let expected = Some(*owner).filter(|o| *o != -1);
+                    assert_eq!(expected, map.sectors.sector_of_wall(wall as _));
+                }
             }
         )+
     }
@@ -25,4 +40,434 @@ tests! {
     dukedc1 => "maps/DUKEDC1.MAP",
     vaca1 => "maps/VACA1.MAP",
     german => "maps/GERMAN.MAP",
+    sw_minimal => "maps/SW-MINIMAL.MAP",
+}
+
+#[test]
+fn e1l1_ambient_sounds() {
+    let map = map::Map::from_slice(include_bytes!("maps/E1L1.MAP")).unwrap();
+    let sounds = map.ambient_sounds(map::tags::GameProfile::Duke3D);
+    assert!(!sounds.is_empty());
+    assert!(map
+        .ambient_sounds(map::tags::GameProfile::ShadowWarrior)
+        .is_empty());
+    for sound in sounds {
+        let sprite = &map.sprites[sound.sprite as usize];
+        assert_eq!(map::tags::duke::MUSICANDSFX, sprite.picnum);
+        assert_eq!(sprite.lotag, sound.sound);
+    }
+}
+
+#[test]
+fn sort_sectors() {
+    let mut map = map::Map::from_slice(include_bytes!("maps/E1L1.MAP")).unwrap();
+    let num_walls = map.sectors.walls().len();
+    let player_floor_z = map.sectors.sectors()[map.player.sector as usize].floor_z;
+    map.sort_sectors_by(|a, b| b.floor_z.cmp(&a.floor_z));
+    assert_eq!(num_walls, map.sectors.walls().len());
+    assert_eq!(
+        player_floor_z,
+        map.sectors.sectors()[map.player.sector as usize].floor_z
+    );
+    let sectors = map.sectors.sectors();
+    assert!(sectors.windows(2).all(|s| s[0].floor_z >= s[1].floor_z));
+    let sector_walls: usize = (0..sectors.len())
+        .map(|i| map.sectors.get(i as _).unwrap().1.len())
+        .sum();
+    assert_eq!(num_walls, sector_walls);
+    let walls = map.sectors.walls();
+    for (i, wall) in walls.iter().enumerate().filter(|(_, w)| w.next_wall != -1) {
+        assert_eq!(i as i16, walls[wall.next_wall as usize].next_wall);
+    }
+    assert_eq!(map::renumber::Compaction::default(), map.compact());
+}
+
+#[test]
+fn transform_roundtrip() {
+    let original = map::Map::from_slice(include_bytes!("maps/E1L1.MAP")).unwrap();
+    let mut map = map::Map::from_slice(include_bytes!("maps/E1L1.MAP")).unwrap();
+    map.translate(100, -200, 300);
+    for _ in 0..4 {
+        map.rotate_90();
+    }
+    map.mirror_x();
+    map.mirror_x();
+    map.translate(-100, 200, -300);
+    let walls = original.sectors.walls().iter().zip(map.sectors.walls());
+    for (a, b) in walls {
+        assert_eq!((a.x, a.y, a.point2), (b.x, b.y, b.point2));
+        assert_eq!(a.wall_stat, b.wall_stat);
+    }
+    let sectors = original.sectors.sectors().iter().zip(map.sectors.sectors());
+    for (a, b) in sectors {
+        assert_eq!((a.floor_z, a.floor_stat), (b.floor_z, b.floor_stat));
+    }
+    let sprites = original.sprites.iter().zip(&map.sprites);
+    for (a, b) in sprites {
+        assert_eq!((a.x, a.y, a.z, a.angle.0), (b.x, b.y, b.z, b.angle.0));
+    }
+    assert_eq!(original.player.angle.0, map.player.angle.0);
+}
+
+#[test]
+fn merge_and_stitch() {
+    let mut map = map::Map::from_slice(include_bytes!("maps/SIMPLE0.MAP")).unwrap();
+    let other = map.clone();
+    let num_walls = map.sectors.walls().len();
+    let num_sectors = map.sectors.sectors().len();
+    map.merge(&other, [1 << 16, 0, 0]).unwrap();
+    assert_eq!(2 * num_walls, map.sectors.walls().len());
+    assert_eq!(2 * num_sectors, map.sectors.sectors().len());
+    let (_, walls) = map.sectors.get(num_sectors as _).unwrap();
+    for (_, left, _) in walls {
+        assert!(left.point2 as usize >= num_walls);
+    }
+    // both copies are far apart, so there are no coincident walls to stitch.
+    assert_eq!(0, map.stitch_portals());
+
+    // sector 1 of SIMPLE0 is a 2304 units wide room, with solid east (wall
+    // 10) and west (wall 12) walls. Shifting a copy east by its width makes
+    // the west wall of the copy coincide with the east wall of the original.
+    let mut map = other.clone();
+    map.merge(&other, [2304, 0, 0]).unwrap();
+    let east = 10;
+    let west = num_walls as i16 + 12;
+    let walls = map.sectors.walls();
+    assert_eq!(-1, walls[east as usize].next_wall);
+    assert_eq!(-1, walls[west as usize].next_wall);
+    assert_eq!(1, map.stitch_portals());
+    let walls = map.sectors.walls();
+    let (east_wall, west_wall) = (&walls[east as usize], &walls[west as usize]);
+    assert_eq!(
+        (west, num_sectors as i16 + 1),
+        (east_wall.next_wall, east_wall.next_sector)
+    );
+    assert_eq!((east, 1), (west_wall.next_wall, west_wall.next_sector));
+    // stitching is idempotent
+    assert_eq!(0, map.stitch_portals());
+}
+
+#[test]
+fn align_walls() {
+    let mut map = map::Map::from_slice(include_bytes!("maps/E1L1.MAP")).unwrap();
+    let walls = map.sectors.walls();
+    let start = (0..walls.len())
+        .find(|&i| walls[walls[i].point2 as usize].picnum == walls[i].picnum)
+        .unwrap();
+    let next = walls[start].point2 as usize;
+    let expected = walls[start].x_panning as u32 + 8 * walls[start].x_repeat as u32;
+    let y_repeat = walls[start].y_repeat;
+    assert!(map::align::align_walls(&mut map, start as _) > 0);
+    let walls = map.sectors.walls();
+    assert_eq!((expected % 256) as u8, walls[next].x_panning);
+    assert_eq!(y_repeat, walls[next].y_repeat);
+}
+
+#[test]
+fn tracked_map_events() {
+    use map::tracked::{Event, TrackedMap};
+    use std::{cell::RefCell, rc::Rc};
+
+    let map = map::Map::from_slice(include_bytes!("maps/E1L1.MAP")).unwrap();
+    let portal = map
+        .sectors
+        .walls()
+        .iter()
+        .position(|w| w.next_wall != -1)
+        .unwrap();
+    let mut map = TrackedMap::new(map);
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let observer = Rc::clone(&events);
+    map.subscribe(move |e| observer.borrow_mut().push(*e));
+
+    map.modify_sector(0, |s| s.floor_z += 1024);
+    map.move_wall(portal as _, 0, 0);
+    let events = events.borrow();
+    assert_eq!(Event::SectorChanged(0), events[0]);
+    // both sides of the vertex are moved
+    let moved: Vec<_> = events[1..]
+        .iter()
+        .filter(|e| matches!(e, Event::WallMoved { .. }))
+        .collect();
+    assert!(moved.len() >= 2);
+    for e in moved {
+        if let Event::WallMoved { wall, .. } = e {
+            let wall = &map.map().sectors.walls()[*wall as usize];
+            assert_eq!((0, 0), (wall.x, wall.y));
+        }
+    }
+}
+
+#[test]
+fn select_everything() {
+    use map::select::{select, Containment, Region};
+
+    let map = map::Map::from_slice(include_bytes!("maps/E1L1.MAP")).unwrap();
+    let region = Region::rect([i32::MIN, i32::MIN], [i32::MAX, i32::MAX]);
+    let selection = select(&map, &region, Containment::Full);
+    assert_eq!(map.sectors.sectors().len(), selection.sectors.len());
+    assert_eq!(map.sectors.walls().len(), selection.walls.len());
+    assert_eq!(map.sprites.len(), selection.sprites.len());
+    let player = [map.player.pos_x, map.player.pos_y];
+    let region = Region::rect(player, player);
+    assert!(select(&map, &region, Containment::Partial).walls.is_empty());
+}
+
+#[test]
+fn shadow_warrior_ambient_sounds() {
+    use map::tags::{duke, sw, GameProfile};
+
+    // no SW maps among the fixtures, so turn Duke's sound sprites into SW
+    // sound spots.
+    let mut map = map::Map::from_slice(include_bytes!("maps/E1L1.MAP")).unwrap();
+    let duke = map.ambient_sounds(GameProfile::Duke3D);
+    for sprite in map.sprites.iter_mut() {
+        if sprite.picnum == duke::MUSICANDSFX {
+            sprite.picnum = sw::ST1;
+            sprite.hitag = sw::SOUND_SPOT;
+        }
+    }
+    let sw = map.ambient_sounds(GameProfile::ShadowWarrior);
+    assert_eq!(duke.len(), sw.len());
+    for (duke, sw) in duke.iter().zip(&sw) {
+        assert_eq!(
+            (duke.sprite, duke.sound, 0),
+            (sw.sprite, sw.sound, sw.radius)
+        );
+    }
+    assert!(map.ambient_sounds(GameProfile::Duke3D).is_empty());
+}
+
+#[test]
+fn shadow_warrior() {
+    use map::{
+        effector::EffectorKind,
+        options::ParseOptions,
+        tags::{sw, GameProfile},
+    };
+
+    // SIMPLE0 with an ST1 sound spot (sound 42) and an ST2 marker (hitag 7).
+    let file = include_bytes!("maps/SW-MINIMAL.MAP");
+    let map = map::Map::from_slice_with_options(file, &ParseOptions::default()).unwrap();
+    assert_eq!(GameProfile::ShadowWarrior, GameProfile::detect(&map));
+
+    let sounds = map.ambient_sounds(GameProfile::ShadowWarrior);
+    assert_eq!(1, sounds.len());
+    assert_eq!((42, 0), (sounds[0].sound, sounds[0].radius));
+    assert_eq!(sw::ST1, map.sprites[sounds[0].sprite as usize].picnum);
+    assert!(map.ambient_sounds(GameProfile::Duke3D).is_empty());
+
+    let effectors: Vec<_> = map
+        .effectors(GameProfile::ShadowWarrior)
+        .map(|(_, _, kind)| kind)
+        .collect();
+    assert_eq!(
+        vec![EffectorKind::Sound, EffectorKind::Marker(7)],
+        effectors
+    );
+    assert_eq!(0, map.effectors(GameProfile::Duke3D).count());
+    assert!(map
+        .sector_kinds_with(&map::quirks::ShadowWarrior)
+        .iter()
+        .all(Option::is_none));
+}
+
+#[test]
+fn custom_quirks() {
+    use map::{
+        quirks::{Quirks, QuirksRegistry},
+        sprite::Sprite,
+        tags::GameProfile,
+    };
+
+    // pretend RR stores the sound radius in the sprite's extra field.
+    struct Custom;
+    impl Quirks for Custom {
+        fn profile(&self) -> GameProfile {
+            GameProfile::RedneckRampage
+        }
+        fn fix_sprite(&self, sprite: &mut Sprite) {
+            sprite.extra = sprite.hitag;
+        }
+    }
+
+    let mut registry = QuirksRegistry::new();
+    let mut map = map::Map::from_slice(include_bytes!("maps/E1L1.MAP")).unwrap();
+    let builtin = registry.get(GameProfile::RedneckRampage).unwrap();
+    assert_eq!(
+        map.ambient_sounds(GameProfile::Duke3D),
+        map.ambient_sounds_with(builtin)
+    );
+    assert!(registry.register(Box::new(Custom)).is_some());
+    map.apply_quirks(registry.get(GameProfile::RedneckRampage).unwrap());
+    assert!(map.sprites.iter().all(|s| s.extra == s.hitag));
+}
+
+#[test]
+fn triangulate_sectors() {
+    let map = map::Map::from_slice(include_bytes!("maps/DX-LIBRARY.MAP")).unwrap();
+    let walls = map.sectors.walls();
+    let area = |w: &[map::sector::WallId]| {
+        let mut area = 0;
+        for (i, a) in w.iter().enumerate() {
+            let (a, b) = (&walls[*a as usize], &walls[w[(i + 1) % w.len()] as usize]);
+            area += i64::from(a.x) * i64::from(b.y) - i64::from(b.x) * i64::from(a.y);
+        }
+        area
+    };
+    for sector in 0..map.sectors.sectors().len() as i16 {
+        let (_, sector_walls) = map.sectors.get(sector).unwrap();
+        let wallnum = sector_walls.len();
+        let outline: Vec<_> = sector_walls.map(|(w, _, _)| w).collect();
+        let triangles = map::triangulate::triangulate(&map, sector);
+        assert!(triangles.iter().all(|t| area(t) > 0));
+        let triangulated: i64 = triangles.iter().map(|t| area(t)).sum();
+        if wallnum == outline.len() {
+            assert_eq!(area(&outline).abs(), triangulated, "sector {}", sector);
+        } else {
+            // the first loop isn't necessarily the outer one.
+            assert!(triangulated > 0, "sector {}", sector);
+        }
+    }
+}
+
+#[test]
+fn tracked_map_editing() {
+    use map::tracked::TrackedMap;
+
+    let map = map::Map::from_slice(include_bytes!("maps/E1L1.MAP")).unwrap();
+    let walls = map.sectors.walls();
+    let portal = walls.iter().position(|w| w.next_wall != -1).unwrap();
+    let right = &walls[walls[portal].point2 as usize];
+    let mid = [
+        (walls[portal].x + right.x) / 2,
+        (walls[portal].y + right.y) / 2,
+    ];
+    let num_walls = walls.len();
+    let (sector, wall) = (map.sectors.sectors()[0].clone(), walls[0].clone());
+    let mut map = TrackedMap::new(map);
+
+    let new = map.split_wall(portal as _, mid[0], mid[1]).unwrap();
+    let walls = map.map().sectors.walls();
+    assert_eq!(num_walls + 2, walls.len());
+    assert_eq!(mid, [walls[new as usize].x, walls[new as usize].y]);
+    // portals are still symmetric, and both sides share vertices.
+    for (i, w) in walls.iter().enumerate() {
+        if w.next_wall != -1 {
+            let other = &walls[w.next_wall as usize];
+            let right = &walls[w.point2 as usize];
+            assert_eq!(i as i16, other.next_wall);
+            assert_eq!([right.x, right.y], [other.x, other.y]);
+        }
+    }
+    // sector loops are closed.
+    for s in 0..map.map().sectors.sectors().len() {
+        let (_, loop_walls) = map.map().sectors.get(s as _).unwrap();
+        assert!(loop_walls.count() > 0);
+    }
+
+    let square = [[0, 0], [0, 1024], [1024, 1024], [1024, 0]];
+    let id = map.add_sector(&square, &sector, &wall).unwrap();
+    let map = map.into_inner();
+    let bytes = map.to_vec().unwrap();
+    let map = map::Map::from_slice(&bytes).unwrap();
+    let (_, walls) = map.sectors.get(id).unwrap();
+    assert_eq!(4, walls.len());
+    assert_eq!(
+        1024 * 1024,
+        map::triangulate::triangulate(&map, id).len() as i32 * 512 * 1024
+    );
+}
+
+#[test]
+fn parse_progress() {
+    use map::progress::{Progress, Section};
+
+    let bytes = include_bytes!("maps/E1L1.MAP");
+    let map = map::Map::from_slice(bytes).unwrap();
+    let mut events = Vec::new();
+    map::Map::from_slice_with_progress(bytes, |p| {
+        events.push(p);
+        true
+    })
+    .unwrap();
+    let count = map.sectors.walls().len();
+    let section = Section::Walls;
+    assert!(events.contains(&Progress::Started { section, count }));
+    let done = count;
+    assert!(events.contains(&Progress::Advanced {
+        section,
+        done,
+        count
+    }));
+    assert_eq!(
+        Some(&Progress::Completed {
+            section: Section::Sprites
+        }),
+        events.last()
+    );
+
+    // cancel as soon as walls start parsing
+    let result = map::Map::from_slice_with_progress(bytes, |p| {
+        !matches!(
+            p,
+            Progress::Started {
+                section: Section::Walls,
+                ..
+            }
+        )
+    });
+    assert!(matches!(result, Err(map::Error::Cancelled)));
+}
+
+#[test]
+fn trailing_data_roundtrip() {
+    let mut bytes = include_bytes!("maps/E1L1.MAP").to_vec();
+    assert!(map::Map::from_slice(&bytes)
+        .unwrap()
+        .trailing_data()
+        .is_empty());
+    bytes.extend_from_slice(b"XTRA\x01\x02\x03");
+    let mut map = map::Map::from_slice(&bytes).unwrap();
+    assert_eq!(b"XTRA\x01\x02\x03", map.trailing_data());
+    assert_eq!(bytes, map.to_vec().unwrap());
+    map.set_trailing_data(Vec::new());
+    assert_eq!(bytes.len() - 7, map.to_vec().unwrap().len());
+}
+
+#[test]
+fn parse_cancellation() {
+    use map::cancel::CancellationToken;
+    use std::time::Duration;
+
+    let bytes = include_bytes!("maps/E1L1.MAP");
+    let token = CancellationToken::new();
+    let map = map::Map::from_reader_cancellable(&mut &bytes[..], &token);
+    assert!(map.is_ok());
+    token.cancel();
+    let map = map::Map::from_reader_cancellable(&mut &bytes[..], &token);
+    assert!(matches!(map, Err(map::Error::Cancelled)));
+    let token = CancellationToken::with_timeout(Duration::from_secs(0));
+    let map = map::Map::from_reader_cancellable(&mut &bytes[..], &token);
+    assert!(matches!(map, Err(map::Error::Cancelled)));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn batch_load_dir() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/maps");
+    let batch = map::batch::load_dir(dir).unwrap();
+    let summary = batch.summary();
+    assert_eq!(batch.entries.len(), summary.loaded + summary.failed);
+    assert!(summary.loaded > 10);
+    assert!(batch.entries.windows(2).all(|e| e[0].name < e[1].name));
+    let (_, e1l1) = batch.maps().find(|(name, _)| *name == "E1L1.MAP").unwrap();
+    assert_eq!(
+        map::Map::from_slice(include_bytes!("maps/E1L1.MAP"))
+            .unwrap()
+            .sectors
+            .walls()
+            .len(),
+        e1l1.sectors.walls().len()
+    );
 }
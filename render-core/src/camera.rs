@@ -0,0 +1,28 @@
+use map::{
+    player::{Angle, Player},
+    sector::SectorId,
+};
+
+/// Point of view from which a MAP is rendered.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Camera {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub angle: Angle,
+
+    /// Sector containing the camera (where sector traversal starts).
+    pub sector: SectorId,
+}
+
+impl From<&Player> for Camera {
+    fn from(player: &Player) -> Self {
+        Self {
+            x: player.pos_x,
+            y: player.pos_y,
+            z: player.pos_z,
+            angle: player.angle,
+            sector: player.sector,
+        }
+    }
+}
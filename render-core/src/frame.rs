@@ -0,0 +1,367 @@
+use alloc::{boxed::Box, vec::Vec};
+
+pub const WIDTH: usize = 320;
+pub const HEIGHT: usize = 240;
+
+/// Frame render content.
+pub type Frame = [[u32; WIDTH]; HEIGHT];
+
+/// Frame render content stored column by column (`columns[x][y]`).
+///
+/// Renderers that fill the frame one column at a time write contiguous
+/// memory into it. Use [`from_columns`](from_columns) to convert it to a
+/// [`Frame`](Frame).
+pub type ColumnFrame = [[u32; HEIGHT]; WIDTH];
+
+// side (in pixels) of the square blocks transposed at a time
+const TRANSPOSE_BLOCK: usize = 8;
+
+/// Transposes a column-major frame into `frame`.
+pub fn from_columns(columns: &ColumnFrame, frame: &mut Frame) {
+    for y0 in (0..HEIGHT).step_by(TRANSPOSE_BLOCK) {
+        for x0 in (0..WIDTH).step_by(TRANSPOSE_BLOCK) {
+            for y in y0..(y0 + TRANSPOSE_BLOCK).min(HEIGHT) {
+                for x in x0..(x0 + TRANSPOSE_BLOCK).min(WIDTH) {
+                    frame[y][x] = columns[x][y];
+                }
+            }
+        }
+    }
+}
+
+/// Transposes `frame` into a column-major frame.
+pub fn to_columns(frame: &Frame, columns: &mut ColumnFrame) {
+    for x0 in (0..WIDTH).step_by(TRANSPOSE_BLOCK) {
+        for y0 in (0..HEIGHT).step_by(TRANSPOSE_BLOCK) {
+            for x in x0..(x0 + TRANSPOSE_BLOCK).min(WIDTH) {
+                for y in y0..(y0 + TRANSPOSE_BLOCK).min(HEIGHT) {
+                    columns[x][y] = frame[y][x];
+                }
+            }
+        }
+    }
+}
+
+/// Frame render content in palette space (8-bit palette indices).
+///
+/// Shading, palette swaps and translucency are table lookups on palette
+/// indices, as in the original engine. Use [`resolve`](resolve) to convert
+/// the frame to RGB.
+///
+/// The renderers of the `render` crate paint RGB frames, and only compute the
+/// colors of their (untextured) surfaces in palette space.
+pub type IndexedFrame = [[u8; WIDTH]; HEIGHT];
+
+/// Converts an indexed frame to RGB using the given palette (8-bit RGB
+/// colors).
+pub fn resolve(indexed: &IndexedFrame, palette: &[[u8; 3]; 256], frame: &mut Frame) {
+    let mut colors = [0; 256];
+    for (color, [r, g, b]) in colors.iter_mut().zip(palette) {
+        *color = u32::from(*r) << 16 | u32::from(*g) << 8 | u32::from(*b);
+    }
+    for (src, dst) in indexed.iter().zip(frame.iter_mut()) {
+        for (index, pixel) in src.iter().zip(dst.iter_mut()) {
+            *pixel = colors[usize::from(*index)];
+        }
+    }
+}
+
+/// Byte layout of frame pixels expected by a frontend.
+///
+/// Frames always hold `0x00RRGGBB` colors. [`pixels`](pixels) borrows them
+/// as-is (the layout minifb expects), and [`encode`](encode) converts them
+/// to bytes in the given order, with opaque alpha.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum PixelFormat {
+    /// Red, green, blue and alpha bytes (canvas `ImageData`, PNG).
+    Rgba,
+
+    /// Blue, green, red and alpha bytes (`0xAARRGGBB` words on little-endian
+    /// targets).
+    Bgra,
+}
+
+impl PixelFormat {
+    /// Bytes of the given `0x00RRGGBB` color.
+    pub fn pack(self, color: u32) -> [u8; 4] {
+        let [r, g, b] = [(color >> 16) as u8, (color >> 8) as u8, color as u8];
+        match self {
+            PixelFormat::Rgba => [r, g, b, 0xff],
+            PixelFormat::Bgra => [b, g, r, 0xff],
+        }
+    }
+}
+
+/// Frame pixels as a single row-major slice of `0x00RRGGBB` colors.
+pub fn pixels(frame: &Frame) -> &[u32] {
+    frame.as_flattened()
+}
+
+/// Writes the frame pixels to `bytes` (replacing its contents) in the given
+/// format.
+pub fn encode(frame: &Frame, format: PixelFormat, bytes: &mut Vec<u8>) {
+    bytes.clear();
+    bytes.reserve(WIDTH * HEIGHT * 4);
+    bytes.extend(pixels(frame).iter().flat_map(|c| format.pack(*c)));
+}
+
+/// How [`scale`](scale) fits a frame into a window of a different size.
+/// Pixels are sampled with nearest-neighbor filtering, and any area of the
+/// window left uncovered is black.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum Scaling {
+    /// Fill the whole window, ignoring the aspect ratio.
+    Stretch,
+
+    /// Scale as much as possible keeping the aspect ratio, centered.
+    #[default]
+    Fit,
+
+    /// Scale by the largest integer factor that fits (at least 1), centered.
+    /// Pixels are all the same size, at the cost of wider borders.
+    Integer,
+}
+
+/// Scales the frame to a `width` by `height` window, writing the result to
+/// `pixels` (replacing its contents) as row-major `0x00RRGGBB` colors.
+pub fn scale(frame: &Frame, scaling: Scaling, width: usize, height: usize, pixels: &mut Vec<u32>) {
+    pixels.clear();
+    pixels.resize(width * height, 0);
+    if width == 0 || height == 0 {
+        return;
+    }
+    let (scaled_width, scaled_height) = match scaling {
+        Scaling::Stretch => (width, height),
+        Scaling::Fit => {
+            // compare aspect ratios without rounding.
+            if width * HEIGHT > height * WIDTH {
+                (height * WIDTH / HEIGHT, height)
+            } else {
+                (width, width * HEIGHT / WIDTH)
+            }
+        }
+        Scaling::Integer => {
+            let factor = (width / WIDTH).min(height / HEIGHT).max(1);
+            (WIDTH * factor, HEIGHT * factor)
+        }
+    };
+    let (scaled_width, scaled_height) = (scaled_width.max(1), scaled_height.max(1));
+    // offsets are negative when an integer-scaled frame overflows the window.
+    let left = (width as isize - scaled_width as isize) / 2;
+    let top = (height as isize - scaled_height as isize) / 2;
+    for y in 0..height {
+        let sy = y as isize - top;
+        if sy < 0 || sy >= scaled_height as isize {
+            continue;
+        }
+        let row = &frame[sy as usize * HEIGHT / scaled_height];
+        let out = &mut pixels[y * width..(y + 1) * width];
+        for (x, pixel) in out.iter_mut().enumerate() {
+            let sx = x as isize - left;
+            if sx >= 0 && sx < scaled_width as isize {
+                *pixel = row[sx as usize * WIDTH / scaled_width];
+            }
+        }
+    }
+}
+
+/// Size (in pixels) of the square tiles [`FramePair`](FramePair) compares to
+/// find changed regions.
+pub const TILE_SIZE: usize = 16;
+
+/// Rectangle of frame pixels.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Pair of frames: a back frame renderers draw into, and a front frame with
+/// the contents last presented to the screen.
+///
+/// [`present`](FramePair::present) returns the regions that changed since
+/// the last present, so frontends can upload only those.
+///
+/// ```
+/// use render_core::frame::{FramePair, Rect};
+///
+/// let mut frames = FramePair::new();
+/// frames.back_mut()[20][40] = 0xff0000;
+/// let dirty = frames.present().to_vec();
+/// assert_eq!(vec![Rect { x: 32, y: 16, width: 16, height: 16 }], dirty);
+/// assert!(frames.present().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FramePair {
+    front: Box<Frame>,
+    back: Box<Frame>,
+    dirty: Vec<Rect>,
+}
+
+impl Default for FramePair {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FramePair {
+    /// Pair of black frames.
+    pub fn new() -> Self {
+        Self {
+            front: Box::new([[0; WIDTH]; HEIGHT]),
+            back: Box::new([[0; WIDTH]; HEIGHT]),
+            dirty: Vec::new(),
+        }
+    }
+
+    /// Contents last presented.
+    pub fn front(&self) -> &Frame {
+        &self.front
+    }
+
+    pub fn back(&self) -> &Frame {
+        &self.back
+    }
+
+    /// Frame to render into. It keeps its contents between presents.
+    pub fn back_mut(&mut self) -> &mut Frame {
+        &mut self.back
+    }
+
+    /// Copies the back frame to the front frame, and returns the regions
+    /// that changed, as horizontal runs of [`TILE_SIZE`](TILE_SIZE) tiles.
+    pub fn present(&mut self) -> &[Rect] {
+        self.dirty.clear();
+        for y in (0..HEIGHT).step_by(TILE_SIZE) {
+            let height = TILE_SIZE.min(HEIGHT - y);
+            let mut run: Option<Rect> = None;
+            for x in (0..WIDTH).step_by(TILE_SIZE) {
+                let width = TILE_SIZE.min(WIDTH - x);
+                let changed = (y..y + height)
+                    .any(|row| self.front[row][x..x + width] != self.back[row][x..x + width]);
+                match (&mut run, changed) {
+                    (Some(rect), true) => rect.width += width,
+                    (None, true) => {
+                        run = Some(Rect {
+                            x,
+                            y,
+                            width,
+                            height,
+                        })
+                    }
+                    (Some(_), false) => self.dirty.extend(run.take()),
+                    (None, false) => {}
+                }
+            }
+            self.dirty.extend(run);
+        }
+        for rect in &self.dirty {
+            for row in rect.y..rect.y + rect.height {
+                let cols = rect.x..rect.x + rect.width;
+                self.front[row][cols.clone()].copy_from_slice(&self.back[row][cols]);
+            }
+        }
+        &self.dirty
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        encode, from_columns, resolve, scale, to_columns, ColumnFrame, Frame, FramePair,
+        IndexedFrame, PixelFormat, Scaling, HEIGHT, TILE_SIZE, WIDTH,
+    };
+
+    #[test]
+    fn scaling() {
+        let mut frame: Box<Frame> = Box::new([[0; WIDTH]; HEIGHT]);
+        frame[0][0] = 1;
+        frame[HEIGHT - 1][WIDTH - 1] = 2;
+        let mut pixels = Vec::new();
+
+        scale(
+            &frame,
+            Scaling::Integer,
+            WIDTH * 2 + 2,
+            HEIGHT * 2,
+            &mut pixels,
+        );
+        let width = WIDTH * 2 + 2;
+        assert_eq!(width * HEIGHT * 2, pixels.len());
+        assert_eq!([0, 1, 1], pixels[..3]);
+        assert_eq!(1, pixels[width + 2]);
+        assert_eq!([2, 2, 0], pixels[pixels.len() - 3..]);
+
+        // letterboxed vertically.
+        scale(&frame, Scaling::Fit, WIDTH, HEIGHT * 2, &mut pixels);
+        assert_eq!(0, pixels[0]);
+        assert_eq!(1, pixels[WIDTH * HEIGHT / 2]);
+
+        scale(&frame, Scaling::Stretch, WIDTH * 2, HEIGHT, &mut pixels);
+        assert_eq!([1, 1, 0], pixels[..3]);
+        assert_eq!([0, 2, 2], pixels[pixels.len() - 3..]);
+    }
+
+    #[test]
+    fn resolve_palette() {
+        let mut palette = [[0; 3]; 256];
+        palette[1] = [0x12, 0x34, 0x56];
+        let mut indexed: IndexedFrame = [[0; WIDTH]; HEIGHT];
+        indexed[2][3] = 1;
+        let mut frame: Frame = [[0xffffff; WIDTH]; HEIGHT];
+        resolve(&indexed, &palette, &mut frame);
+        assert_eq!(0x123456, frame[2][3]);
+        assert_eq!(0, frame[0][0]);
+    }
+
+    #[test]
+    fn pixel_formats() {
+        let mut frame: Frame = [[0; WIDTH]; HEIGHT];
+        frame[0][1] = 0x123456;
+        let mut bytes = Vec::new();
+        encode(&frame, PixelFormat::Rgba, &mut bytes);
+        assert_eq!(WIDTH * HEIGHT * 4, bytes.len());
+        assert_eq!([0, 0, 0, 0xff, 0x12, 0x34, 0x56, 0xff], bytes[..8]);
+        encode(&frame, PixelFormat::Bgra, &mut bytes);
+        assert_eq!(WIDTH * HEIGHT * 4, bytes.len());
+        assert_eq!([0x56, 0x34, 0x12, 0xff], bytes[4..8]);
+    }
+
+    #[test]
+    fn transpose() {
+        let mut frame: Box<Frame> = Box::new([[0; WIDTH]; HEIGHT]);
+        for (y, row) in frame.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = (y * WIDTH + x) as u32;
+            }
+        }
+        let mut columns: Box<ColumnFrame> = Box::new([[0; HEIGHT]; WIDTH]);
+        to_columns(&frame, &mut columns);
+        assert_eq!(frame[3][5], columns[5][3]);
+        assert_eq!(frame[HEIGHT - 1][WIDTH - 1], columns[WIDTH - 1][HEIGHT - 1]);
+        let mut back: Box<Frame> = Box::new([[0; WIDTH]; HEIGHT]);
+        from_columns(&columns, &mut back);
+        assert_eq!(frame, back);
+    }
+
+    #[test]
+    fn dirty_rects() {
+        let mut frames = FramePair::new();
+        assert!(frames.present().is_empty());
+        // adjacent tiles merge into a single run
+        frames.back_mut()[0][0] = 1;
+        frames.back_mut()[0][TILE_SIZE] = 1;
+        frames.back_mut()[HEIGHT - 1][WIDTH - 1] = 1;
+        let dirty = frames.present().to_vec();
+        assert_eq!(2, dirty.len());
+        assert_eq!(
+            (0, 0, 2 * TILE_SIZE),
+            (dirty[0].x, dirty[0].y, dirty[0].width)
+        );
+        let last = dirty[1];
+        assert_eq!((WIDTH, HEIGHT), (last.x + last.width, last.y + last.height));
+        assert_eq!(frames.back(), frames.front());
+    }
+}
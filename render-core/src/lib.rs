@@ -0,0 +1,29 @@
+//! Types shared by MAP renderers: the [`Frame`](frame::Frame) they render
+//! into, the [`Camera`](camera::Camera) they render from, and the
+//! [`Renderer`](Renderer) trait itself.
+//!
+//! Unlike the `render` crate, it has no dependencies other than `map`, so
+//! GPU and external renderers can interoperate with the software renderers
+//! (and their frontends) without pulling them in.
+//!
+//! The crate is `no_std` (it only needs `alloc`), and only uses the `no_std`
+//! subset of `map` (the MAP types). Check it on a bare-metal target with:
+//!
+//! ```text
+//! cargo build -p render-core --target thumbv7em-none-eabi
+//! ```
+#![cfg_attr(not(test), no_std)]
+extern crate alloc;
+
+use camera::Camera;
+use frame::Frame;
+use map::Map;
+
+pub mod camera;
+pub mod frame;
+
+/// MAP renderer.
+pub trait Renderer {
+    /// Render MAP, as seen from the given camera, to the frame.
+    fn render(&mut self, map: &Map, camera: &Camera, frame: &mut Frame);
+}
@@ -0,0 +1,265 @@
+//! Hardware accelerated MAP renderer (wgpu).
+//!
+//! Unlike the software renderers, MAP geometry is uploaded to the GPU ahead of
+//! time (see [`Renderer::upload`](Renderer::upload)) and rendered with a depth
+//! buffer, so there is no sector traversal nor per-column clipping.
+//!
+//! Sectors are drawn flat shaded with a [`ColorScheme`](ColorScheme): floor
+//! and ceiling slopes are ignored (sloped sectors are drawn level) and tiles
+//! are not textured.
+use map::{units::Vec3, Map};
+use nalgebra_glm as glm;
+use render::d3::color::{ColorScheme, PicnumColors};
+use render_core::camera::Camera;
+use wgpu::util::DeviceExt;
+
+pub mod mesh;
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// vertical field of view (radians)
+const FOV_Y: f32 = 1.2;
+const Z_NEAR: f32 = 8.0;
+const Z_FAR: f32 = 131_072.0;
+
+/// MAP renderer.
+#[derive(Debug)]
+pub struct Renderer {
+    pipeline: wgpu::RenderPipeline,
+    uniforms: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    mesh: Option<(wgpu::Buffer, u32)>,
+    depth: Option<(wgpu::TextureView, [u32; 2])>,
+}
+
+impl Renderer {
+    /// Creates a renderer drawing to textures of the given format.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("map shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+        });
+        let uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("map uniforms"),
+            size: std::mem::size_of::<[[f32; 4]; 4]>() as _,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("map bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("map bind group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniforms.as_entire_binding(),
+            }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("map pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("map pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<mesh::Vertex>() as _,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &mesh::Vertex::ATTRIBUTES,
+                }],
+            },
+            // sector geometry has no consistent winding, so no culling.
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: Default::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(format.into())],
+            }),
+            multiview: None,
+            cache: None,
+        });
+        Self {
+            pipeline,
+            uniforms,
+            bind_group,
+            mesh: None,
+            depth: None,
+        }
+    }
+
+    /// Uploads the geometry of a MAP, painted with the default color scheme.
+    ///
+    /// Must be called again whenever the MAP geometry changes.
+    pub fn upload(&mut self, device: &wgpu::Device, map: &Map) {
+        self.upload_with(device, map, &PicnumColors)
+    }
+
+    /// Uploads the geometry of a MAP, painted with the given color scheme.
+    pub fn upload_with(&mut self, device: &wgpu::Device, map: &Map, colors: &dyn ColorScheme) {
+        let vertices = mesh::build(map, colors);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("map vertices"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        self.mesh = Some((buffer, vertices.len() as u32));
+    }
+
+    /// Renders the uploaded MAP geometry to the target texture view (of the
+    /// given size in pixels), as seen from the camera.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &wgpu::TextureView,
+        size: [u32; 2],
+        camera: &Camera,
+    ) {
+        let view_proj = view_projection(camera, size[0] as f32 / size[1].max(1) as f32);
+        let view_proj: [[f32; 4]; 4] = view_proj.into();
+        queue.write_buffer(&self.uniforms, 0, bytemuck::cast_slice(&view_proj));
+
+        if self.depth.as_ref().map(|(_, s)| *s) != Some(size) {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("map depth"),
+                size: wgpu::Extent3d {
+                    width: size[0],
+                    height: size[1],
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: DEPTH_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            });
+            self.depth = Some((texture.create_view(&Default::default()), size));
+        }
+        let (depth, _) = self.depth.as_ref().unwrap();
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("map pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            if let Some((buffer, len)) = &self.mesh {
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &self.bind_group, &[]);
+                pass.set_vertex_buffer(0, buffer.slice(..));
+                pass.draw(0..*len, 0..1);
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+/// Camera view & projection matrix, in the space of the
+/// [`mesh::Vertex`](mesh::Vertex) positions.
+pub fn view_projection(camera: &Camera, aspect: f32) -> glm::Mat4 {
+    let [cos, sin] = camera.angle.direction();
+    let Vec3 { x, y, z } = Vec3::from_build(camera.x, camera.y, camera.z);
+    let eye = glm::vec3(x, y, z);
+    let forward = glm::vec3(cos, -sin, 0.0);
+    let view = glm::look_at_rh(&eye, &(eye + forward), &glm::vec3(0.0, 0.0, 1.0));
+    glm::perspective_rh_zo(aspect, FOV_Y, Z_NEAR, Z_FAR) * view
+}
+
+#[cfg(test)]
+mod test {
+    use map::{player::Angle, units::Vec3};
+    use nalgebra_glm as glm;
+    use render_core::camera::Camera;
+
+    #[test]
+    fn shader() {
+        let module = naga::front::wgsl::parse_str(include_str!("shader.wgsl")).unwrap();
+        naga::valid::Validator::new(Default::default(), Default::default())
+            .validate(&module)
+            .unwrap();
+    }
+
+    #[test]
+    fn view_projection() {
+        // looking south (+Y in Build coordinates)
+        let camera = Camera {
+            x: 0,
+            y: 0,
+            z: 0,
+            angle: Angle(512),
+            sector: 0,
+        };
+        let view_proj = super::view_projection(&camera, 1.0);
+        // normalized device coordinates of a point, in Build coordinates.
+        let ndc = |x: i32, y: i32, z: i32| {
+            let Vec3 { x, y, z } = Vec3::from_build(x, y, z);
+            let v = view_proj * glm::vec4(x, y, z, 1.0);
+            (v.xyz() / v.w, v.w)
+        };
+        let focal = 1.0 / (super::FOV_Y / 2.0).tan();
+        let depth = |distance: f32| {
+            super::Z_FAR * (distance - super::Z_NEAR) / (distance * (super::Z_FAR - super::Z_NEAR))
+        };
+        let assert_near = |expected: [f32; 3], actual: glm::Vec3| {
+            for (e, a) in expected.iter().zip(actual.iter()) {
+                assert!((e - a).abs() < 1e-4, "{:?} != {:?}", expected, actual);
+            }
+        };
+
+        let (ahead, w) = ndc(0, 1024, 0);
+        assert_near([0.0, 0.0, depth(1024.0)], ahead);
+        assert!((w - 1024.0).abs() < 1e-2);
+        // west is to the right when looking south.
+        let (right, _) = ndc(-512, 1024, 0);
+        assert_near([focal / 2.0, 0.0, depth(1024.0)], right);
+        // Z grows downwards in Build coordinates.
+        let (up, _) = ndc(0, 1024, -16 * 512);
+        assert_near([0.0, focal / 2.0, depth(1024.0)], up);
+        // behind the camera.
+        let (_, w) = ndc(0, -1024, 0);
+        assert!((w + 1024.0).abs() < 1e-2);
+    }
+}
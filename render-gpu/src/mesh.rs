@@ -0,0 +1,101 @@
+//! MAP geometry as triangle lists.
+use bytemuck::{Pod, Zeroable};
+use map::{sector::Sector, triangulate::triangulate, units::Vec3, Map};
+use render::d3::color::ColorScheme;
+
+/// Mesh vertex.
+///
+/// Positions are in the [`Vec3`](map::units::Vec3) space: right handed and
+/// Z-up, in XY units.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl Vertex {
+    pub(crate) const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    fn new(x: i32, y: i32, z: i32, color: u32) -> Self {
+        let channel = |c: u32| (c & 0xff) as f32 / 255.0;
+        Self {
+            position: Vec3::from_build(x, y, z).into(),
+            color: [channel(color >> 16), channel(color >> 8), channel(color)],
+        }
+    }
+}
+
+/// Builds a triangle list with the floors, ceilings and walls of all the
+/// sectors, painted with the given color scheme.
+///
+/// Slopes are not supported yet, so floors & ceilings are flat.
+pub fn build(map: &Map, colors: &dyn ColorScheme) -> Vec<Vertex> {
+    let walls = map.sectors.walls();
+    let sectors = map.sectors.sectors();
+    let mut vertices = Vec::new();
+    for (id, sector) in sectors.iter().enumerate() {
+        let floor = colors.floor(sector);
+        let ceiling = colors.ceiling(sector);
+        for triangle in triangulate(map, id as _) {
+            for (z, color) in [(sector.floor_z, floor), (sector.ceiling_z, ceiling)] {
+                vertices.extend(triangle.iter().map(|w| {
+                    let wall = &walls[*w as usize];
+                    Vertex::new(wall.x, wall.y, z, color)
+                }));
+            }
+        }
+
+        let (_, sector_walls) = map.sectors.get(id as _).unwrap();
+        for (_, left, right) in sector_walls {
+            let mut quad = |top: i32, bottom: i32, color: u32| {
+                let tl = Vertex::new(left.x, left.y, top, color);
+                let tr = Vertex::new(right.x, right.y, top, color);
+                let bl = Vertex::new(left.x, left.y, bottom, color);
+                let br = Vertex::new(right.x, right.y, bottom, color);
+                vertices.extend_from_slice(&[tl, tr, br, tl, br, bl]);
+            };
+            match sectors.get(left.next_sector as usize) {
+                None => quad(sector.ceiling_z, sector.floor_z, colors.wall(sector, left)),
+                Some(Sector {
+                    ceiling_z, floor_z, ..
+                }) => {
+                    // (Z points down)
+                    if *ceiling_z > sector.ceiling_z {
+                        let color = colors.portal_top(sector, left);
+                        quad(sector.ceiling_z, *ceiling_z, color);
+                    }
+                    if *floor_z < sector.floor_z {
+                        let color = colors.portal_bottom(sector, left);
+                        quad(*floor_z, sector.floor_z, color);
+                    }
+                }
+            }
+        }
+    }
+    vertices
+}
+
+#[cfg(test)]
+mod test {
+    use map::Map;
+    use render::d3::color::FlatColors;
+
+    #[test]
+    fn build() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        let colors = FlatColors::default();
+        let vertices = super::build(&map, &colors);
+        assert!(!vertices.is_empty());
+        assert_eq!(0, vertices.len() % 3);
+        let floors = vertices
+            .iter()
+            .filter(|v| v.color == [0x22 as f32 / 255.0, 0x22 as f32 / 255.0, 1.0])
+            .count();
+        let triangles: usize = (0..map.sectors.sectors().len())
+            .map(|s| map::triangulate::triangulate(&map, s as _).len())
+            .sum();
+        assert_eq!(triangles * 3, floors);
+    }
+}
@@ -0,0 +1,79 @@
+//! Compares rendering with a renderer whose buffers were reserved for the MAP
+//! (see `d3::Renderer::reserve`) against one that allocates them from
+//! scratch on every frame.
+//!
+//! Measured on a single-core Xeon VM, 500 frames with sprites enabled, 3 runs
+//! each (runs of the same build can differ by more than the ranges below):
+//!
+//! | MAP        | fresh      | reserved   |
+//! |------------|------------|------------|
+//! | DX-LIBRARY | 479-498µs  | 424-449µs  |
+//! | E1L1       | 479-570µs  | 388-558µs  |
+//!
+//! Counting allocations with a wrapping `GlobalAlloc`, a long-lived renderer
+//! made 4 (DX-LIBRARY) and 5 (E1L1) allocations per frame before the sprite
+//! buffers were kept across frames, and none after. Its frame times overlap
+//! between the two builds (450-513µs and 407-473µs on DX-LIBRARY, 400-514µs
+//! and 393-453µs on E1L1), so the allocation count is the measurable change.
+//!
+//! Usage: `cargo bench -p render --bench d3_alloc [MAP...]`
+use map::Map;
+use render::{camera::Camera, d3::Renderer, frame};
+use std::time::{Duration, Instant};
+
+const FRAMES: u32 = 500;
+
+fn main() {
+    let paths: Vec<_> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .collect();
+    let maps: Vec<(String, Map)> = if paths.is_empty() {
+        vec![
+            (
+                "DX-LIBRARY.MAP".to_string(),
+                Map::from_slice(include_bytes!("../../map/tests/maps/DX-LIBRARY.MAP")).unwrap(),
+            ),
+            (
+                "E1L1.MAP".to_string(),
+                Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap(),
+            ),
+        ]
+    } else {
+        paths
+            .into_iter()
+            .map(|path| {
+                let map = Map::from_file(&path).unwrap();
+                (path, map)
+            })
+            .collect()
+    };
+    for (name, map) in &maps {
+        let fresh = bench(map, false);
+        let reserved = bench(map, true);
+        println!(
+            "{}: fresh {:?}, reserved {:?} per frame ({} frames)",
+            name,
+            fresh / FRAMES,
+            reserved / FRAMES,
+            FRAMES
+        );
+    }
+}
+
+fn bench(map: &Map, reserve: bool) -> Duration {
+    let mut renderer = Renderer::new();
+    renderer.sprites = true;
+    renderer.reserve(map);
+    let mut frame = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+    let camera = Camera::from(&map.player);
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        if !reserve {
+            renderer = Renderer::new();
+            renderer.sprites = true;
+        }
+        renderer.render(map, &camera, &mut frame);
+    }
+    start.elapsed()
+}
@@ -0,0 +1,55 @@
+//! Compares the frame layouts of the 3D renderer.
+//!
+//! Measured on DX-LIBRARY on a single-core Xeon VM, 16 interleaved runs of
+//! each build, fastest / median run per frame:
+//!
+//! | build                 | row major     | column major  |
+//! |-----------------------|---------------|---------------|
+//! | buffers reallocated   | 403µs / 436µs | 401µs / 422µs |
+//! | buffers reused        | 422µs / 445µs | 424µs / 441µs |
+//!
+//! Single runs range from 403µs to 615µs (row major) on the same build, so
+//! the 2-5% gap is within the noise. Sprites are disabled here, and the only
+//! change on this path removed a wall clone, so no work was added.
+//!
+//! Usage: `cargo bench -p render --bench d3_layout [MAP]`
+use map::Map;
+use render::{
+    camera::Camera,
+    d3::{Layout, Renderer},
+    frame,
+};
+use std::time::{Duration, Instant};
+
+const FRAMES: u32 = 500;
+
+fn main() {
+    let map = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with('-'))
+        .map(|path| Map::from_file(path).unwrap())
+        .unwrap_or_else(|| {
+            Map::from_slice(include_bytes!("../../map/tests/maps/DX-LIBRARY.MAP")).unwrap()
+        });
+    for layout in [Layout::RowMajor, Layout::ColumnMajor].iter() {
+        let time = bench(&map, *layout);
+        println!(
+            "{:?}: {:?} per frame ({} frames)",
+            layout,
+            time / FRAMES,
+            FRAMES
+        );
+    }
+}
+
+fn bench(map: &Map, layout: Layout) -> Duration {
+    let mut renderer = Renderer::new();
+    renderer.layout = layout;
+    let mut frame = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+    let camera = Camera::from(&map.player);
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        renderer.render(map, &camera, &mut frame);
+    }
+    start.elapsed()
+}
@@ -0,0 +1,156 @@
+//! Tiny developer console shared by the examples: a line of text input, a
+//! registry of commands, and the last lines of output drawn on top of the
+//! frame.
+//!
+//! Commands are registered with a name and a function of the example state
+//! (`C`) and the arguments of the command, returning the text to print.
+use minifb::{InputCallback, Key, KeyRepeat, Window};
+use render::{
+    frame::{self, Frame},
+    overlay::{Canvas, CHAR_SIZE},
+};
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+// lines of output kept on screen
+const MAX_LINES: usize = 8;
+const BACKGROUND_COLOR: u32 = 0x202020;
+const TEXT_COLOR: u32 = 0xffffff;
+const PROMPT: &str = "> ";
+
+type Command<C> = Box<dyn FnMut(&mut C, &[&str]) -> Result<String, String>>;
+
+// characters typed into the window, collected by the input callback.
+#[derive(Default, Clone)]
+struct Typed(Rc<RefCell<String>>);
+
+impl InputCallback for Typed {
+    fn add_char(&mut self, uni_char: u32) {
+        if let Some(c) = char::from_u32(uni_char).filter(|c| !c.is_control() && *c != '`') {
+            self.0.borrow_mut().push(c);
+        }
+    }
+}
+
+pub struct Console<C> {
+    open: bool,
+    line: String,
+    output: Vec<String>,
+    typed: Typed,
+    commands: BTreeMap<&'static str, (&'static str, Command<C>)>,
+}
+
+impl<C> Console<C> {
+    /// Creates a console reading the text typed into the window. It's
+    /// opened & closed with the backquote key.
+    pub fn new(window: &mut Window) -> Self {
+        let typed = Typed::default();
+        window.set_input_callback(Box::new(typed.clone()));
+        Self {
+            open: false,
+            line: String::new(),
+            output: Vec::new(),
+            typed,
+            commands: BTreeMap::new(),
+        }
+    }
+
+    /// True while the console takes the keyboard input.
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Registers a command. `usage` is printed by `help`.
+    pub fn register<F>(&mut self, name: &'static str, usage: &'static str, command: F)
+    where
+        F: FnMut(&mut C, &[&str]) -> Result<String, String> + 'static,
+    {
+        self.commands.insert(name, (usage, Box::new(command)));
+    }
+
+    /// Handles the keyboard input of the frame, running the command entered
+    /// (if any).
+    pub fn update(&mut self, window: &Window, state: &mut C) {
+        let typed = std::mem::take(&mut *self.typed.0.borrow_mut());
+        if window.is_key_pressed(Key::Backquote, KeyRepeat::No) {
+            self.open = !self.open;
+            return;
+        }
+        if !self.open {
+            return;
+        }
+        self.line.push_str(&typed);
+        if window.is_key_pressed(Key::Backspace, KeyRepeat::Yes) {
+            self.line.pop();
+        }
+        if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+            self.open = false;
+        }
+        if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+            let line = std::mem::take(&mut self.line);
+            self.print(format!("{}{}", PROMPT, line));
+            self.execute(state, &line);
+        }
+    }
+
+    /// Runs a line of input.
+    pub fn execute(&mut self, state: &mut C, line: &str) {
+        let mut words = line.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None => return,
+        };
+        let args: Vec<&str> = words.collect();
+        if name == "help" {
+            let usage: Vec<String> = self
+                .commands
+                .iter()
+                .map(|(name, (usage, _))| format!("{} {}", name, usage))
+                .collect();
+            for line in usage {
+                self.print(line);
+            }
+            return;
+        }
+        let result = match self.commands.get_mut(name) {
+            Some((_, command)) => command(state, &args),
+            None => Err(format!("unknown command {:?} (try help)", name)),
+        };
+        match result {
+            Ok(text) => text.lines().for_each(|line| self.print(line.to_string())),
+            Err(err) => self.print(format!("error: {}", err)),
+        }
+    }
+
+    fn print(&mut self, line: String) {
+        println!("{}", line);
+        self.output.push(line);
+        if self.output.len() > MAX_LINES {
+            self.output.remove(0);
+        }
+    }
+
+    /// Draws the output & the line being typed at the bottom of the frame,
+    /// if the console is open.
+    pub fn draw(&self, frame: &mut Frame) {
+        if !self.open {
+            return;
+        }
+        let [_, h] = CHAR_SIZE;
+        let line_height = h + 2;
+        let lines = self.output.len() as i32 + 1;
+        let top = frame::HEIGHT as i32 - lines * line_height - 2;
+        let mut canvas = Canvas::new(frame);
+        let right = frame::WIDTH as i32 - 1;
+        canvas.rect(
+            [0, top],
+            [right, frame::HEIGHT as i32 - 1],
+            BACKGROUND_COLOR,
+            true,
+        );
+        let prompt = format!("{}{}_", PROMPT, self.line);
+        for (i, line) in self.output.iter().chain(Some(&prompt)).enumerate() {
+            let y = top + 2 + i as i32 * line_height;
+            canvas.text(line, [2, y], TEXT_COLOR);
+        }
+    }
+}
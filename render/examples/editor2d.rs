@@ -0,0 +1,204 @@
+//! Minimal BUILD-like 2D editor.
+//!
+//! Usage: `cargo run --example editor2d MAP [OUTPUT]`
+//!
+//! - Arrows: move the view.
+//! - Left mouse button: drag vertices.
+//! - Insert: split the wall under the cursor.
+//! - Space: add a point to the sector being drawn. Enter: finish the sector.
+//!   Backspace: discard it.
+//! - Ctrl+Z: undo.
+//! - Ctrl+S: save to OUTPUT (defaults to overwriting MAP).
+use map::{player::Angle, sector::WallId, tracked::TrackedMap, Map};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Scale, Window, WindowOptions};
+use render::{
+    camera::Camera,
+    d2::{self, Pick},
+    frame::{self, Frame},
+    Renderer,
+};
+use std::{env, path::PathBuf};
+
+const GRID: i32 = 64;
+const PAN_SPEED: i32 = 256;
+const PICK_TOLERANCE: f32 = 4.0;
+const MAX_UNDO: usize = 64;
+
+struct Editor {
+    map: TrackedMap,
+    undo: Vec<Map>,
+    drag: Option<WallId>,
+    drawing: Vec<[i32; 2]>,
+}
+
+impl Editor {
+    fn checkpoint(&mut self) {
+        if self.undo.len() == MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(self.map.map().clone());
+    }
+
+    fn undo(&mut self) {
+        if let Some(map) = self.undo.pop() {
+            self.map = TrackedMap::new(map);
+            self.drag = None;
+        }
+    }
+
+    fn finish_sector(&mut self) {
+        let points = std::mem::take(&mut self.drawing);
+        let map = self.map.map();
+        // new sectors copy the attributes of the player sector.
+        let prototype = map
+            .sectors
+            .get(map.player.sector)
+            .map(|(sector, mut walls)| (sector.clone(), walls.next().unwrap().1.clone()));
+        if let (true, Some((sector, wall))) = (points.len() >= 3, prototype) {
+            self.checkpoint();
+            if self.map.add_sector(&points, &sector, &wall).is_none() {
+                self.undo.pop();
+            }
+        }
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1).map(PathBuf::from);
+    let path = args.next().expect("Missing MAP argument.");
+    let output = args.next().unwrap_or_else(|| path.clone());
+
+    let map = Map::from_file(&path).unwrap();
+    let mut camera = Camera::from(&map.player);
+    // X to the right and Y down, as in the BUILD editor.
+    camera.angle = Angle(1536);
+    let mut editor = Editor {
+        map: TrackedMap::new(map),
+        undo: Vec::new(),
+        drag: None,
+        drawing: Vec::new(),
+    };
+    let mut renderer = d2::Renderer::new();
+    renderer.flags = d2::Flags::SECTOR | d2::Flags::ALL_SECTORS;
+    let mut frame = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+
+    let opts = WindowOptions {
+        scale: Scale::X2,
+        ..WindowOptions::default()
+    };
+    let mut window = Window::new("editor2d", frame::WIDTH, frame::HEIGHT, opts).unwrap();
+    window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
+
+    while window.is_open() {
+        let ctrl = window.is_key_down(Key::LeftCtrl) || window.is_key_down(Key::RightCtrl);
+        let pressed = |key| window.is_key_pressed(key, KeyRepeat::No);
+
+        if window.is_key_down(Key::Left) {
+            camera.x -= PAN_SPEED;
+        }
+        if window.is_key_down(Key::Right) {
+            camera.x += PAN_SPEED;
+        }
+        if window.is_key_down(Key::Up) {
+            camera.y -= PAN_SPEED;
+        }
+        if window.is_key_down(Key::Down) {
+            camera.y += PAN_SPEED;
+        }
+
+        let mouse = mouse_position(&window);
+        let cursor = mouse.map(|m| snap(renderer.unproject(m)));
+        let hover = mouse.and_then(|m| renderer.pick(editor.map.map(), m, PICK_TOLERANCE));
+
+        // vertex dragging
+        if window.get_mouse_down(MouseButton::Left) {
+            match (editor.drag, hover, cursor) {
+                (Some(wall), _, Some([x, y])) => editor.map.move_wall(wall, x, y),
+                (None, Some(Pick::Vertex(wall)), _) => {
+                    editor.checkpoint();
+                    editor.drag = Some(wall);
+                }
+                _ => {}
+            }
+        } else {
+            editor.drag = None;
+        }
+
+        if let (true, Some(Pick::Wall(wall)), Some([x, y])) = (pressed(Key::Insert), hover, cursor)
+        {
+            editor.checkpoint();
+            editor.map.split_wall(wall, x, y);
+        }
+        if let (true, Some(point)) = (pressed(Key::Space), cursor) {
+            editor.drawing.push(point);
+        }
+        if pressed(Key::Enter) {
+            editor.finish_sector();
+        }
+        if pressed(Key::Backspace) {
+            editor.drawing.clear();
+        }
+        if ctrl && pressed(Key::Z) {
+            editor.undo();
+        }
+        if ctrl && pressed(Key::S) {
+            match editor.map.map().to_file(&output) {
+                Ok(_) => println!("Saved {}", output.display()),
+                Err(err) => eprintln!("Error saving {}: {}", output.display(), err),
+            }
+        }
+
+        *frame = [[0; frame::WIDTH]; frame::HEIGHT];
+        renderer.render(editor.map.map(), &camera, &mut frame);
+        let mut points: Vec<_> = editor
+            .drawing
+            .iter()
+            .map(|p| renderer.project(*p))
+            .collect();
+        points.extend(
+            cursor
+                .filter(|_| !editor.drawing.is_empty())
+                .map(|c| renderer.project(c)),
+        );
+        for segment in points.windows(2) {
+            draw_line(&mut frame, segment[0], segment[1], 0xffff00);
+        }
+        window.set_title(&format!(
+            "editor2d - {:?} - undo: {}",
+            hover,
+            editor.undo.len()
+        ));
+        update_window_buffer(&mut window, &frame);
+    }
+}
+
+fn snap([x, y]: [i32; 2]) -> [i32; 2] {
+    let snap = |v: i32| (v as f32 / GRID as f32).round() as i32 * GRID;
+    [snap(x), snap(y)]
+}
+
+// mouse position in frame pixels
+fn mouse_position(window: &Window) -> Option<[i32; 2]> {
+    let (x, y) = window.get_mouse_pos(MouseMode::Discard)?;
+    let (width, height) = window.get_size();
+    let x = x * frame::WIDTH as f32 / width as f32;
+    let y = y * frame::HEIGHT as f32 / height as f32;
+    Some([x as i32, y as i32])
+}
+
+fn draw_line(frame: &mut Frame, [x0, y0]: [i32; 2], [x1, y1]: [i32; 2], color: u32) {
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1);
+    for i in 0..=steps {
+        let x = x0 + (x1 - x0) * i / steps;
+        let y = y0 + (y1 - y0) * i / steps;
+        if x >= 0 && y >= 0 && (x as usize) < frame::WIDTH && (y as usize) < frame::HEIGHT {
+            frame[y as usize][x as usize] = color;
+        }
+    }
+}
+
+fn update_window_buffer(window: &mut Window, frame: &Frame) {
+    window
+        .update_with_buffer(frame::pixels(frame), frame::WIDTH, frame::HEIGHT)
+        .unwrap();
+}
@@ -1,86 +1,335 @@
-use map::Map;
-use minifb::{Key, KeyRepeat, Scale, Window, WindowOptions};
-use render::{controller::Input, d2, d3, frame, frame::Frame};
-use std::{env, path::PathBuf};
+use map::{batch, triangulate::triangulate, Map};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use render::{
+    camera::Camera,
+    compositor::Compositor,
+    controller::{Input, InputController, Simulation},
+    d2, d3, frame,
+    frame::{Frame, PixelFormat, Scaling},
+    overlay::Canvas,
+    Renderer,
+};
+use std::{
+    env,
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime},
+};
+
+mod console;
+mod keys;
 
 const MAX_SPEED: i32 = 32;
 
+/// Frames per second of captured clips (matches the update rate).
+const CAPTURE_FPS: u16 = 60;
+
+/// MAPs & player, driven by the keyboard and the console.
+struct Session {
+    maps: Vec<(String, Map)>,
+    current: usize,
+    map: Map,
+    controller: InputController,
+    compositor: Compositor,
+    d3: usize,
+}
+
+impl Session {
+    fn load(&mut self, index: usize) {
+        self.current = index;
+        self.map = self.maps[index].1.clone();
+        self.controller = InputController::new(&self.map);
+        self.controller.max_speed = MAX_SPEED;
+    }
+
+    fn name(&self) -> &str {
+        &self.maps[self.current].0
+    }
+}
+
 fn main() {
     let path = env::args()
-        .skip(1)
-        .next()
+        .nth(1)
         .map(PathBuf::from)
-        .expect("Missing MAP argument.");
+        .expect("Missing MAP, GRP or directory argument.");
 
-    let mut map = Map::from_file(&path).unwrap();
+    let maps = load_maps(&path);
+    assert!(!maps.is_empty(), "No MAPs found in {:?}", path);
+    for (index, (name, _)) in maps.iter().enumerate() {
+        println!("{:>4} {}", index, name);
+    }
+    let map = maps[0].1.clone();
     let mut frame = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
-    let mut d3 = d3::Renderer::new();
-    let mut d2 = d2::Renderer::new();
-    let mut controller = render::controller::InputController::new(&mut map);
+    let mut compositor = Compositor::new();
+    let d3 = compositor.push(d3::Renderer::new());
+    let d2 = compositor.push(d2::Renderer::new());
+    compositor.get_mut::<d2::Renderer>(d2).unwrap().flags = d2::Flags::SECTOR | d2::Flags::PLAYER;
+    let mut controller = InputController::new(&map);
     controller.max_speed = MAX_SPEED;
+    let mut session = Session {
+        maps,
+        current: 0,
+        map,
+        controller,
+        compositor,
+        d3,
+    };
 
-    let mut opts = WindowOptions::default();
-    //opts.scale = Scale::X2;
-    //opts.borderless = true;
-    let title = path.file_name().unwrap().to_str().unwrap();
-    let mut window = Window::new(&title, frame::WIDTH, frame::HEIGHT, opts).unwrap();
+    // the frame is always rendered at WIDTH x HEIGHT, then scaled to the size
+    // of the window.
+    let opts = WindowOptions {
+        resize: true,
+        ..WindowOptions::default()
+    };
+    let mut scaling = Scaling::default();
+    let mut buffer = Vec::new();
+    // F11 records frames (RGBA bytes) until pressed again.
+    let mut capture: Option<Vec<Vec<u8>>> = None;
+    let mut window = Window::new(session.name(), frame::WIDTH, frame::HEIGHT, opts).unwrap();
     let delta = std::time::Duration::from_micros(16600);
     window.limit_update_rate(Some(delta));
-    let mut d2_enabled = true;
-    let mut d3_enabled = true;
+    let key_map = keys::key_map();
+    // backquote opens the console (type `help` for the list of commands).
+    let mut console = console::Console::new(&mut window);
+    register_commands(&mut console);
 
+    let mut last_frame = Instant::now();
     while window.is_open() {
-        // reset frame
-        if window.is_key_pressed(Key::R, KeyRepeat::No) {
-            *frame = [[0; frame::WIDTH]; frame::HEIGHT];
-        }
+        let current = session.current;
+        console.update(&window, &mut session);
+        if console.is_open() {
+            session.controller.tick(&mut session.map, &Input::empty());
+        } else {
+            // reset frame
+            if window.is_key_pressed(Key::R, KeyRepeat::No) {
+                *frame = [[0; frame::WIDTH]; frame::HEIGHT];
+            }
 
-        let input = resolve_input(&window);
-        controller.update(&input, delta, &mut map);
+            // next & previous MAP
+            let count = session.maps.len();
+            if window.is_key_pressed(Key::N, KeyRepeat::No) {
+                session.load((current + 1) % count);
+            } else if window.is_key_pressed(Key::P, KeyRepeat::No) {
+                session.load((current + count - 1) % count);
+            }
 
-        if window.is_key_pressed(Key::F, KeyRepeat::No) {
-            controller.fly = !controller.fly;
-            println!("fly = {}", controller.fly);
-        }
-        if window.is_key_pressed(Key::Key2, KeyRepeat::No) {
-            d2_enabled = !d2_enabled;
+            let input = keys::resolve_input(&window, &key_map);
+            session.controller.tick(&mut session.map, &input);
+
+            if window.is_key_pressed(Key::F, KeyRepeat::No) {
+                session.controller.fly = !session.controller.fly;
+                println!("fly = {}", session.controller.fly);
+            }
+            if window.is_key_pressed(Key::Key2, KeyRepeat::No) {
+                session.compositor.toggle(d2);
+            }
+            if window.is_key_pressed(Key::Key3, KeyRepeat::No) {
+                session.compositor.toggle(d3);
+            }
+            if window.is_key_pressed(Key::O, KeyRepeat::No) {
+                let renderer = session.compositor.get_mut::<d3::Renderer>(d3).unwrap();
+                let overlay = &mut renderer.overlay;
+                overlay.coverage = !overlay.coverage;
+                overlay.portals = !overlay.portals;
+            }
+            if window.is_key_pressed(Key::Z, KeyRepeat::No) {
+                scaling = match scaling {
+                    Scaling::Stretch => Scaling::Fit,
+                    Scaling::Fit => Scaling::Integer,
+                    Scaling::Integer => Scaling::Stretch,
+                };
+                println!("scaling = {:?}", scaling);
+            }
         }
-        if window.is_key_pressed(Key::Key3, KeyRepeat::No) {
-            d3_enabled = !d3_enabled;
+        if session.current != current {
+            *frame = [[0; frame::WIDTH]; frame::HEIGHT];
+            window.set_title(session.name());
         }
 
         // render map to frame
-        if d3_enabled {
-            d3.render(&map, &mut frame);
+        let camera = Camera::from(&session.map.player);
+        session.compositor.render(&session.map, &camera, &mut frame);
+
+        // screenshots & captures (without the FPS counter)
+        if window.is_key_pressed(Key::F12, KeyRepeat::No) {
+            save_png(&output_path("screenshot"), &[rgba(&frame)]);
+        }
+        if let Some(frames) = &mut capture {
+            frames.push(rgba(&frame));
         }
-        if d2_enabled {
-            d2.flags = d2::Flags::SECTOR | d2::Flags::PLAYER;
-            d2.render(&map, &mut frame);
+        if window.is_key_pressed(Key::F11, KeyRepeat::No) {
+            match capture.take() {
+                Some(frames) => save_png(&output_path("capture"), &frames),
+                None => capture = Some(Vec::new()),
+            }
         }
+
+        let fps = 1.0 / last_frame.elapsed().as_secs_f64();
+        last_frame = Instant::now();
+        Canvas::new(&mut frame).text(&format!("{:.0} FPS", fps), [2, 2], 0xffff00);
+        console.draw(&mut frame);
         // update window framebuffer
-        update_window_buffer(&mut window, &frame);
+        update_window_buffer(&mut window, &frame, scaling, &mut buffer);
+    }
+}
+
+fn register_commands(console: &mut console::Console<Session>) {
+    console.register("warp", "<sector>", |session, args| {
+        let sector = match args {
+            [sector] => sector.parse().map_err(|_| "invalid sector".to_string())?,
+            _ => return Err("usage: warp <sector>".into()),
+        };
+        // centroid of a triangle of the sector, which is always inside of it.
+        let walls = session.map.sectors.walls();
+        let triangle = triangulate(&session.map, sector);
+        let [a, b, c] = triangle
+            .first()
+            .ok_or("no such sector")?
+            .map(|w| &walls[w as usize]);
+        let [x, y] = [(a.x + b.x + c.x) / 3, (a.y + b.y + c.y) / 3];
+        let z = session.map.sectors.floor_z_at(sector, x, y).unwrap();
+        session
+            .controller
+            .teleport(&mut session.map, [x, y, z], sector);
+        Ok(format!("warped to sector {}", sector))
+    });
+    console.register("pos", "", |session, _| {
+        let player = &session.map.player;
+        Ok(format!(
+            "x={} y={} z={} angle={} sector={}",
+            player.pos_x, player.pos_y, player.pos_z, player.angle.0, player.sector
+        ))
+    });
+    console.register("fly", "", |session, _| {
+        session.controller.fly = !session.controller.fly;
+        Ok(format!("fly = {}", session.controller.fly))
+    });
+    console.register("noclip", "", |session, _| {
+        session.controller.noclip = !session.controller.noclip;
+        Ok(format!("noclip = {}", session.controller.noclip))
+    });
+    console.register("stats", "", |session, _| {
+        let d3 = session.d3;
+        let stats = session
+            .compositor
+            .get_mut::<d3::Renderer>(d3)
+            .unwrap()
+            .stats();
+        Ok(format!(
+            "{} sectors, {} walls, {} portals, {:.1} ms",
+            stats.sectors_visited,
+            stats.walls_drawn,
+            stats.portals_traversed,
+            stats.duration.as_secs_f64() * 1000.0
+        ))
+    });
+    console.register("load", "<name|index|path>", |session, args| {
+        let name = match args {
+            [name] => *name,
+            _ => return Err("usage: load <name|index|path>".into()),
+        };
+        let index = name
+            .parse()
+            .ok()
+            .filter(|index| *index < session.maps.len())
+            .or_else(|| {
+                session
+                    .maps
+                    .iter()
+                    .position(|(n, _)| n.eq_ignore_ascii_case(name))
+            });
+        let index = match index {
+            Some(index) => index,
+            None => {
+                let map = Map::from_file(name).map_err(|err| err.to_string())?;
+                session.maps.push((name.to_string(), map));
+                session.maps.len() - 1
+            }
+        };
+        session.load(index);
+        Ok(format!("loaded {}", session.name()))
+    });
+}
+
+fn update_window_buffer(
+    window: &mut Window,
+    frame: &Frame,
+    scaling: Scaling,
+    buffer: &mut Vec<u32>,
+) {
+    let (width, height) = window.get_size();
+    frame::scale(frame, scaling, width, height, buffer);
+    window.update_with_buffer(buffer, width, height).unwrap();
+}
+
+/// Loads a single MAP file, or every MAP in a GRP archive or directory, in
+/// name order. MAPs that fail to load are reported and skipped.
+fn load_maps(path: &Path) -> Vec<(String, Map)> {
+    let batch = if path.is_dir() {
+        batch::load_dir(path).map_err(map::Error::from)
+    } else if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("grp"))
+    {
+        batch::load_grp(path)
+    } else {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        return vec![(name, Map::from_file(path).unwrap())];
+    };
+    let batch = batch.unwrap_or_else(|err| panic!("Error reading {:?}: {}", path, err));
+    for entry in &batch.entries {
+        if let Err(err) = &entry.result {
+            eprintln!("Skipping {}: {}", entry.name, err);
+        }
     }
+    println!("{}", batch.summary());
+    batch
+        .entries
+        .into_iter()
+        .filter_map(|e| Some((e.name, e.result.ok()?)))
+        .collect()
+}
+
+fn rgba(frame: &Frame) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    frame::encode(frame, PixelFormat::Rgba, &mut bytes);
+    bytes
+}
+
+/// Timestamped PNG file name, in the working directory.
+fn output_path(prefix: &str) -> PathBuf {
+    let time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    PathBuf::from(format!("{}-{}.png", prefix, time))
 }
 
-fn update_window_buffer(window: &mut Window, frame: &Frame) {
-    let len = frame::WIDTH * frame::HEIGHT;
-    let buffer = unsafe { std::slice::from_raw_parts(frame.as_ptr() as _, len) };
-    window
-        .update_with_buffer(buffer, frame::WIDTH, frame::HEIGHT)
-        .unwrap();
+/// Writes a PNG, animated (APNG) if there is more than one frame.
+fn save_png(path: &Path, frames: &[Vec<u8>]) {
+    match write_png(path, frames) {
+        Ok(()) => println!("{} frame(s) saved to {:?}", frames.len(), path),
+        Err(err) => eprintln!("Error writing {:?}: {}", path, err),
+    }
 }
 
-#[rustfmt::skip]
-fn resolve_input(window: &Window) -> Input {
-    let mut input = Input::empty();
-    if window.is_key_down(Key::W) || window.is_key_down(Key::Up) { input |= Input::FORWARDS; }
-    if window.is_key_down(Key::S) || window.is_key_down(Key::Down) { input |= Input::BACKWARDS; }
-    if window.is_key_down(Key::D) { input |= Input::RIGHT; }
-    if window.is_key_down(Key::A) { input |= Input::LEFT; }
-    if window.is_key_down(Key::Right) || window.is_key_down(Key::E) { input |= Input::LOOK_RIGHT; }
-    if window.is_key_down(Key::Left) || window.is_key_down(Key::Q) { input |= Input::LOOK_LEFT; }
-    if window.is_key_down(Key::C) { input |= Input::CROUCH; }
-    if window.is_key_down(Key::Space) { input |= Input::UP; }
-    if window.is_key_down(Key::LeftShift) { input |= Input::DOWN; }
-    input
+fn write_png(path: &Path, frames: &[Vec<u8>]) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, frame::WIDTH as u32, frame::HEIGHT as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    if frames.len() > 1 {
+        encoder
+            .set_animated(frames.len() as u32, 0)
+            .map_err(io::Error::other)?;
+        encoder
+            .set_frame_delay(1, CAPTURE_FPS)
+            .map_err(io::Error::other)?;
+    }
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    for frame in frames {
+        writer.write_image_data(frame).map_err(io::Error::other)?;
+    }
+    writer.finish().map_err(io::Error::other)
 }
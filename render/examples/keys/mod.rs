@@ -0,0 +1,31 @@
+//! Default controls of the examples, shared by all of them.
+use minifb::{Key, Window};
+use render::controller::{bindings::KeyMap, Input};
+
+/// Default controls.
+pub fn key_map() -> KeyMap {
+    vec![
+        (Key::W, Input::FORWARDS),
+        (Key::Up, Input::FORWARDS),
+        (Key::S, Input::BACKWARDS),
+        (Key::Down, Input::BACKWARDS),
+        (Key::D, Input::RIGHT),
+        (Key::A, Input::LEFT),
+        (Key::E, Input::LOOK_RIGHT),
+        (Key::Right, Input::LOOK_RIGHT),
+        (Key::Q, Input::LOOK_LEFT),
+        (Key::Left, Input::LOOK_LEFT),
+        (Key::C, Input::CROUCH),
+        (Key::Space, Input::UP),
+        (Key::LeftShift, Input::DOWN),
+    ]
+    .into_iter()
+    .map(|(key, input)| (key as u32, input))
+    .collect()
+}
+
+/// Input held down in the window.
+pub fn resolve_input(window: &Window, keys: &KeyMap) -> Input {
+    let pressed = window.get_keys().unwrap_or_default();
+    keys.input(pressed.into_iter().map(|key| key as u32))
+}
@@ -0,0 +1,87 @@
+//! First-person walkthrough with collision, gravity and doors.
+//!
+//! Usage: `cargo run --example walkthrough MAP [ART...]`
+//!
+//! - WASD & arrows: move. Q/E: look around.
+//! - Space: jump. C: crouch.
+//! - Enter: open & close doors and platforms (Duke Nukem 3D sector lotags 16,
+//!   17 & 20, see [`fx`](render::controller::fx)) in the current sector or
+//!   next to it.
+//! - Tab: show & hide sprites. With the `hightile` feature, sprites are
+//!   animated with the tiles of the given ART files.
+use map::{tracked::TrackedMap, Map};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use render::{
+    camera::Camera,
+    controller::{fx::Effects, InputController, Simulation},
+    d3,
+    frame::{self, Frame},
+};
+use std::{env, path::PathBuf, time::Duration};
+
+mod keys;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let path = args
+        .next()
+        .map(PathBuf::from)
+        .expect("Missing MAP argument.");
+
+    let mut map = TrackedMap::new(Map::from_file(&path).unwrap());
+    let mut renderer = d3::Renderer::new();
+    renderer.sprites = true;
+    #[cfg(feature = "art")]
+    {
+        let mut tiles = art::Art::default();
+        for path in args {
+            tiles.extend(art::Art::from_file(path).unwrap());
+        }
+        renderer.set_animation(tiles);
+    }
+    let mut controller = InputController::new(map.map());
+    controller.collision = true;
+    controller.gravity = true;
+    let mut simulation: Box<dyn Simulation> = Box::new(controller);
+    let mut effects = Effects::new();
+    let mut frame = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+
+    let title = path.file_name().unwrap().to_str().unwrap();
+    let mut window =
+        Window::new(title, frame::WIDTH, frame::HEIGHT, WindowOptions::default()).unwrap();
+    let delta = Duration::from_micros(16600);
+    window.limit_update_rate(Some(delta));
+    let key_map = keys::key_map();
+
+    while window.is_open() {
+        if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+            let sector = map.map().player.sector;
+            effects.activate(map.map(), sector);
+        }
+        if window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            renderer.sprites = !renderer.sprites;
+        }
+        effects.update(&mut map);
+        let input = keys::resolve_input(&window, &key_map);
+        map.modify(|map| simulation.tick(map, &input));
+
+        // Build clock ticks at 120Hz, twice per frame.
+        renderer.clock += 2;
+        *frame = [[0; frame::WIDTH]; frame::HEIGHT];
+        let stats = renderer.render(map.map(), &Camera::from(&map.map().player), &mut frame);
+        window.set_title(&format!(
+            "{} - {} sectors, {} walls, {:.1} ms",
+            title,
+            stats.sectors_visited,
+            stats.walls_drawn,
+            stats.duration.as_secs_f64() * 1000.0
+        ));
+        update_window_buffer(&mut window, &frame);
+    }
+}
+
+fn update_window_buffer(window: &mut Window, frame: &Frame) {
+    window
+        .update_with_buffer(frame::pixels(frame), frame::WIDTH, frame::HEIGHT)
+        .unwrap();
+}
@@ -0,0 +1,271 @@
+//! Line segment clipping.
+//!
+//! Segments are given by their two end points (`a` & `b`), which are updated
+//! in place. Clipping only ever moves points along the segment, so the
+//! direction from `a` to `b` is preserved. Any extra vector components (e.g.
+//! the homogeneous coordinate) are interpolated too.
+use nalgebra_glm as glm;
+use nalgebra_glm::{Alloc, DefaultAllocator, Dimension, RealField, TVec};
+
+/// Half-space kept by [`clip_plane`](clip_plane).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Keep {
+    /// Keep points where `v[axis] >= c`.
+    Above,
+
+    /// Keep points where `v[axis] <= c`.
+    Below,
+}
+
+/// Clips the segment against the plane `v[axis] = c`, keeping the half-space
+/// given by `keep`.
+///
+/// Returns false if the segment lies completely outside the half-space, in
+/// which case the points are left untouched.
+pub fn clip_plane<N, D>(
+    a: &mut TVec<N, D>,
+    b: &mut TVec<N, D>,
+    axis: usize,
+    c: N,
+    keep: Keep,
+) -> bool
+where
+    N: RealField,
+    D: Dimension,
+    DefaultAllocator: Alloc<N, D>,
+{
+    let outside = |v: &TVec<N, D>| match keep {
+        Keep::Above => v[axis] < c,
+        Keep::Below => v[axis] > c,
+    };
+    match (outside(a), outside(b)) {
+        (false, false) => true,
+        (true, true) => false,
+        (a_outside, _) => {
+            let t = (c - a[axis]) / (b[axis] - a[axis]);
+            let mut clip = glm::lerp(a, b, t);
+            // avoid rounding errors leaving the point outside
+            clip[axis] = c;
+            if a_outside {
+                *a = clip;
+            } else {
+                *b = clip;
+            }
+            true
+        }
+    }
+}
+
+/// Clips the segment against the plane `plane · v = 0`, keeping the
+/// half-space where `plane · v >= 0`. Unlike [`clip_plane`](clip_plane), the
+/// plane doesn't have to be axis-aligned (e.g. `x >= -w` in homogeneous
+/// coordinates).
+///
+/// Returns false if the segment lies completely outside the half-space, in
+/// which case the points are left untouched.
+pub fn clip_half_space<N, D>(a: &mut TVec<N, D>, b: &mut TVec<N, D>, plane: &TVec<N, D>) -> bool
+where
+    N: RealField,
+    D: Dimension,
+    DefaultAllocator: Alloc<N, D>,
+{
+    let (da, db) = (plane.dot(a), plane.dot(b));
+    match (da < N::zero(), db < N::zero()) {
+        (false, false) => true,
+        (true, true) => false,
+        (a_outside, _) => {
+            let clip = glm::lerp(a, b, da / (da - db));
+            if a_outside {
+                *a = clip;
+            } else {
+                *b = clip;
+            }
+            true
+        }
+    }
+}
+
+/// Clips the segment to the box `min..=max` over the first two components
+/// (Liang–Barsky).
+///
+/// Returns false if the segment lies completely outside the box, in which
+/// case the points are left untouched.
+pub fn clip_box<N, D>(a: &mut TVec<N, D>, b: &mut TVec<N, D>, min: [N; 2], max: [N; 2]) -> bool
+where
+    N: RealField,
+    D: Dimension,
+    DefaultAllocator: Alloc<N, D>,
+{
+    let d = &*b - &*a;
+    let edges = [
+        (-d[0], a[0] - min[0]),
+        (d[0], max[0] - a[0]),
+        (-d[1], a[1] - min[1]),
+        (d[1], max[1] - a[1]),
+    ];
+    let (mut t0, mut t1) = (N::zero(), N::one());
+    for (p, q) in edges.iter().copied() {
+        if p == N::zero() {
+            // parallel to the edge
+            if q < N::zero() {
+                return false;
+            }
+        } else if p < N::zero() {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    if t0 > t1 {
+        return false;
+    }
+    let (a0, b0) = (a.clone(), b.clone());
+    *a = glm::lerp(&a0, &b0, t0);
+    *b = glm::lerp(&a0, &b0, t1);
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::{clip_box, clip_half_space, clip_plane, Keep};
+    use nalgebra_glm as glm;
+    use proptest::prelude::*;
+
+    const EPSILON: f64 = 1e-6;
+
+    fn clip(a: [f32; 2], b: [f32; 2]) -> Option<[[f32; 2]; 2]> {
+        let mut a = glm::vec3(a[0], a[1], 1.0);
+        let mut b = glm::vec3(b[0], b[1], 1.0);
+        let round = |v: f32| (v * 1e4).round() / 1e4;
+        if clip_box(&mut a, &mut b, [-1.0, 0.0], [1.0, 1.0]) {
+            Some([[round(a.x), round(a.y)], [round(b.x), round(b.y)]])
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn box_inside() {
+        assert_eq!(
+            Some([[-0.5, 0.5], [0.5, 0.25]]),
+            clip([-0.5, 0.5], [0.5, 0.25])
+        );
+    }
+
+    #[test]
+    fn box_diagonal_outside_corners() {
+        // crosses the (1, 1) corner region without touching the box. Both
+        // points are outside different edges, so a per-edge test can't
+        // reject it.
+        assert_eq!(None, clip([0.5, 2.0], [2.0, 0.5]));
+        assert_eq!(None, clip([-2.0, 0.5], [-0.5, -1.0]));
+    }
+
+    #[test]
+    fn box_diagonal_through_corners() {
+        assert_eq!(
+            Some([[-1.0, 0.0], [1.0, 1.0]]),
+            clip([-3.0, -1.0], [3.0, 2.0])
+        );
+        assert_eq!(Some([[1.0, 0.5], [0.5, 1.0]]), clip([1.5, 0.0], [0.0, 1.5]));
+    }
+
+    #[test]
+    fn box_parallel() {
+        assert_eq!(None, clip([-2.0, 1.5], [2.0, 1.5]));
+        assert_eq!(
+            Some([[-1.0, 0.5], [1.0, 0.5]]),
+            clip([-2.0, 0.5], [2.0, 0.5])
+        );
+    }
+
+    #[test]
+    fn plane_homogeneous() {
+        let mut a = glm::vec4(0.0, -1.0, 0.0, 1.0);
+        let mut b = glm::vec4(0.0, 1.0, 2.0, 1.0);
+        assert!(clip_plane(&mut a, &mut b, 1, 0.0, Keep::Above));
+        assert_eq!(glm::vec4(0.0, 0.0, 1.0, 1.0), a);
+        assert_eq!(glm::vec4(0.0, 1.0, 2.0, 1.0), b);
+        assert!(!clip_plane(&mut a, &mut b, 1, 2.0, Keep::Above));
+        assert_eq!(glm::vec4(0.0, 0.0, 1.0, 1.0), a);
+    }
+
+    #[test]
+    fn half_space_homogeneous() {
+        // keep x >= -w
+        let plane = glm::vec3(1.0, 1.0, 0.0);
+        let mut a = glm::vec3(-3.0, 1.0, 0.0);
+        let mut b = glm::vec3(1.0, 1.0, 4.0);
+        assert!(clip_half_space(&mut a, &mut b, &plane));
+        assert_eq!(glm::vec3(-1.0, 1.0, 2.0), a);
+        assert_eq!(glm::vec3(1.0, 1.0, 4.0), b);
+        assert!(!clip_half_space(
+            &mut glm::vec3(-3.0, 1.0, 0.0),
+            &mut glm::vec3(-2.0, 1.0, 0.0),
+            &plane
+        ));
+    }
+
+    // distance from p to the line through a & b
+    fn distance_to_line(p: &glm::DVec2, a: &glm::DVec2, b: &glm::DVec2) -> f64 {
+        let d = b - a;
+        (d.x * (p.y - a.y) - d.y * (p.x - a.x)).abs() / d.norm()
+    }
+
+    fn point() -> impl Strategy<Value = glm::DVec2> {
+        (-4.0..4.0f64, -4.0..4.0f64).prop_map(|(x, y)| glm::vec2(x, y))
+    }
+
+    proptest! {
+        #[test]
+        fn plane_invariants(a in point(), b in point(), c in -2.0..2.0f64, above: bool) {
+            prop_assume!((b - a).norm() > 1e-3);
+            let keep = if above { Keep::Above } else { Keep::Below };
+            let (mut ca, mut cb) = (a, b);
+            let kept = |v: &glm::DVec2| if above { v.x >= c } else { v.x <= c };
+            if clip_plane(&mut ca, &mut cb, 0, c, keep) {
+                prop_assert!(kept(&ca) && kept(&cb));
+                // moved points lie on the boundary
+                if ca != a { prop_assert!((ca.x - c).abs() < EPSILON); }
+                if cb != b { prop_assert!((cb.x - c).abs() < EPSILON); }
+                // on the original segment, in the same order
+                prop_assert!(distance_to_line(&ca, &a, &b) < EPSILON);
+                prop_assert!(distance_to_line(&cb, &a, &b) < EPSILON);
+                prop_assert!((cb - ca).dot(&(b - a)) >= 0.0);
+            } else {
+                prop_assert!(!kept(&a) && !kept(&b));
+                prop_assert_eq!((a, b), (ca, cb));
+            }
+        }
+
+        #[test]
+        fn box_invariants(a in point(), b in point()) {
+            prop_assume!((b - a).norm() > 1e-3);
+            let (min, max) = ([-1.0, 0.0], [1.0, 1.0]);
+            let inside = |v: &glm::DVec2| {
+                v.x >= min[0] - EPSILON && v.x <= max[0] + EPSILON
+                    && v.y >= min[1] - EPSILON && v.y <= max[1] + EPSILON
+            };
+            let on_boundary = |v: &glm::DVec2| {
+                (v.x - min[0]).abs() < EPSILON || (v.x - max[0]).abs() < EPSILON
+                    || (v.y - min[1]).abs() < EPSILON || (v.y - max[1]).abs() < EPSILON
+            };
+            let (mut ca, mut cb) = (a, b);
+            if clip_box(&mut ca, &mut cb, min, max) {
+                prop_assert!(inside(&ca) && inside(&cb));
+                if (ca - a).norm() > EPSILON { prop_assert!(on_boundary(&ca)); }
+                if (cb - b).norm() > EPSILON { prop_assert!(on_boundary(&cb)); }
+                prop_assert!(distance_to_line(&ca, &a, &b) < EPSILON);
+                prop_assert!(distance_to_line(&cb, &a, &b) < EPSILON);
+                prop_assert!((cb - ca).dot(&(b - a)) >= 0.0);
+            } else {
+                // no point of the segment is inside the box
+                prop_assert_eq!((a, b), (ca, cb));
+                for i in 0..=32 {
+                    let p = glm::lerp(&a, &b, f64::from(i) / 32.0);
+                    prop_assert!(!inside(&p) || on_boundary(&p));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{camera::Camera, frame::Frame, Renderer};
+use map::Map;
+use std::any::Any;
+
+// type-erased renderer that can be downcast back to its concrete type.
+trait Layer: Renderer {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<R: Renderer + 'static> Layer for R {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Renders a stack of renderers on the same frame, in the order they were
+/// added.
+///
+/// # Example
+/// ```
+/// # #[cfg(all(feature = "d2", feature = "d3"))] {
+/// use render::{compositor::Compositor, d2, d3};
+///
+/// let mut compositor = Compositor::new();
+/// let d3 = compositor.push(d3::Renderer::new());
+/// let d2 = compositor.push(d2::Renderer::new());
+///
+/// // 2D map on top of the 3D view, which is disabled for now.
+/// compositor.set_enabled(d3, false);
+/// compositor.get_mut::<d2::Renderer>(d2).unwrap().flags = d2::Flags::SECTOR;
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Compositor {
+    layers: Vec<(Box<dyn Layer>, bool)>,
+}
+
+impl std::fmt::Debug for Compositor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Compositor")
+            .field("layers", &self.layers.len())
+            .finish()
+    }
+}
+
+impl Compositor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a renderer on top of the existing ones. Returns the index of the
+    /// new layer.
+    pub fn push<R: Renderer + 'static>(&mut self, renderer: R) -> usize {
+        self.layers.push((Box::new(renderer), true));
+        self.layers.len() - 1
+    }
+
+    /// Number of layers.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Enables or disables rendering of a layer.
+    pub fn set_enabled(&mut self, layer: usize, enabled: bool) {
+        if let Some((_, e)) = self.layers.get_mut(layer) {
+            *e = enabled;
+        }
+    }
+
+    pub fn is_enabled(&self, layer: usize) -> bool {
+        self.layers.get(layer).is_some_and(|(_, e)| *e)
+    }
+
+    /// Toggles a layer on or off.
+    pub fn toggle(&mut self, layer: usize) {
+        self.set_enabled(layer, !self.is_enabled(layer));
+    }
+
+    /// Returns the renderer of a layer, if it is of type `R`.
+    pub fn get_mut<R: Renderer + 'static>(&mut self, layer: usize) -> Option<&mut R> {
+        self.layers
+            .get_mut(layer)
+            .and_then(|(renderer, _)| renderer.as_any_mut().downcast_mut())
+    }
+}
+
+impl Renderer for Compositor {
+    fn render(&mut self, map: &Map, camera: &Camera, frame: &mut Frame) {
+        for (renderer, _) in self.layers.iter_mut().filter(|(_, enabled)| *enabled) {
+            renderer.render(map, camera, frame);
+        }
+    }
+}
@@ -1,7 +1,34 @@
-use std::time::Duration;
+//! Player movement.
+//!
+//! Movement is integer math, except for the direction of the player, which
+//! uses the platform `sin` & `cos` and may differ (slightly) between targets.
+//! Enable the `deterministic` feature to use Build's sine table instead, so
+//! replays and lockstep networking give the same results everywhere. (The
+//! collision tests only use basic IEEE float operations, which are exact.)
+use map::{
+    player::Angle,
+    sector::{Sector, SectorId},
+};
+pub use sim::{fx, script, Input, Simulation};
+
+pub mod bindings;
+
+/// Highest floor step the player can climb (in Z units).
+pub const MAX_STEP: i32 = 16 << 8;
+
+/// Smallest floor-to-ceiling gap the player fits through (in Z units).
+pub const MIN_CLEARANCE: i32 = 24 << 8;
+
+/// Closest the player gets to a wall when colliding (same as Build's
+/// `walldist` for players).
+pub const PLAYER_RADIUS: i32 = 164;
+
+// Z units per frame
+const GRAVITY: i32 = 128;
+const JUMP_VELOCITY: i32 = 2048;
 
 /// Player update parameters.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct UpdateOpts {
     /// Linear forwards velocity.
     pub forwards: i32,
@@ -11,28 +38,54 @@ pub struct UpdateOpts {
 
     /// Rotation velocity
     pub rotate: i16,
+
+    /// Block movement through solid walls, and through portals into sectors
+    /// the player can't step into (see [`MAX_STEP`](MAX_STEP) and
+    /// [`MIN_CLEARANCE`](MIN_CLEARANCE)), keeping the player
+    /// [`PLAYER_RADIUS`](PLAYER_RADIUS) away from them.
+    pub clip: bool,
 }
 
-bitflags::bitflags! {
-    pub struct Input: u16 {
-        const FORWARDS   = 0b0000_0000_0001;
-        const BACKWARDS  = 0b0000_0000_0010;
-        const RIGHT      = 0b0000_0000_0100;
-        const LEFT       = 0b0000_0000_1000;
-        const UP         = 0b0000_0001_0000;
-        const DOWN       = 0b0000_0010_0000;
-        const LOOK_RIGHT = 0b0000_0100_0000;
-        const LOOK_LEFT  = 0b0000_1000_0000;
-        const CROUCH     = 0b0001_0000_0000;
-    }
+/// Analog input (e.g. a gamepad stick or an on-screen joystick). Axes range
+/// from `-1.0` to `1.0`, and any non-zero axis overrides the digital
+/// [`Input`](Input) along it.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Analog {
+    /// Forwards (positive) or backwards (negative) movement.
+    pub forwards: f32,
+
+    /// Movement to the right (positive) or to the left (negative).
+    pub sideways: f32,
+
+    /// Rotation to the right (positive) or to the left (negative).
+    pub look: f32,
 }
 
 /// Very basic player controller
-#[derive(Debug)]
+///
+/// Controllers are `Clone`, so they can be saved along with a MAP
+/// [`Snapshot`](map::snapshot::Snapshot).
+#[derive(Debug, Clone)]
 pub struct InputController {
     pub max_speed: i32,
     pub fly: bool,
+
+    /// Collide with walls (see [`UpdateOpts::clip`](UpdateOpts::clip)).
+    pub collision: bool,
+
+    /// Move through walls, ignoring [`collision`](InputController::collision).
+    /// The sector of the player is looked up after every move, so it stays
+    /// correct even when the player doesn't go through portals.
+    pub noclip: bool,
+
+    /// Fall with gravity and jump (with [`Input::UP`](Input::UP)) instead of
+    /// snapping to the floor.
+    pub gravity: bool,
+
+    /// Analog input, applied on every update until changed.
+    pub analog: Analog,
     eye_height: i32,
+    z_velocity: i32,
     opts: UpdateOpts,
 }
 
@@ -43,37 +96,84 @@ impl InputController {
         Self {
             max_speed: 32,
             fly: false,
+            collision: false,
+            noclip: false,
+            gravity: false,
+            analog: Analog::default(),
             eye_height,
+            z_velocity: 0,
             opts: UpdateOpts::default(),
         }
     }
 
-    /// Update controller
+    /// Update controller, advancing the given number of ticks with the same
+    /// input held down.
     #[rustfmt::skip]
-    pub fn update(&mut self, input: &Input, delta: Duration, map: &mut map::Map) {
-        self.update_opts(input, delta);
-        update_player(map, &self.opts);
-        self.update_eye_height(input, delta, map);
+    pub fn update(&mut self, input: &Input, ticks: u32, map: &mut map::Map) {
+        for _ in 0..ticks {
+            self.update_opts(input);
+            self.opts.clip = self.collision && !self.fly && !self.noclip;
+            update_player(map, &self.opts);
+            if self.noclip {
+                update_sector(map);
+            }
+            self.update_eye_height(input, map);
+        }
+    }
+
+    /// Moves the player to the given position, stopping it. Returns false
+    /// (leaving the player where it was) if the sector doesn't exist.
+    pub fn teleport(&mut self, map: &mut map::Map, [x, y, z]: [i32; 3], sector: SectorId) -> bool {
+        if map.sectors.get(sector).is_none() {
+            return false;
+        }
+        let player = &mut map.player;
+        player.pos_x = x;
+        player.pos_y = y;
+        player.pos_z = z;
+        player.sector = sector;
+        self.opts = UpdateOpts::default();
+        self.z_velocity = 0;
+        true
     }
 
     #[rustfmt::skip]
-    fn update_eye_height(&mut self, input: &Input, duration: Duration, map: &mut map::Map) {
-        let sector = &map.sectors.sectors()[map.player.sector as usize];
+    fn update_eye_height(&mut self, input: &Input, map: &mut map::Map) {
+        let map::player::Player { pos_x, pos_y, sector, .. } = map.player;
+        let floor_z = map.sectors.floor_z_at(sector, pos_x, pos_y).unwrap();
+        let ceiling_z = map.sectors.ceiling_z_at(sector, pos_x, pos_y).unwrap();
         if self.fly {
             if input.contains(Input::UP) { map.player.pos_z -= 500; }
             if input.contains(Input::DOWN) { map.player.pos_z += 500; }
-            map.player.pos_z = map.player.pos_z.min(sector.floor_z).max(sector.ceiling_z);
+            map.player.pos_z = map.player.pos_z.max(ceiling_z).min(floor_z);
         } else {
-            let mut target_z = sector.floor_z + self.eye_height;
+            let mut target_z = floor_z + self.eye_height;
             if input.contains(Input::CROUCH) {
                 target_z -= self.eye_height / 2;
             }
-            map.player.pos_z += (target_z - map.player.pos_z) >> 1;
+            // rising after a jump counts as being in the air.
+            let grounded = !self.gravity || (map.player.pos_z >= target_z && self.z_velocity >= 0);
+            if grounded && self.gravity && input.contains(Input::UP) {
+                // jump
+                self.z_velocity = -JUMP_VELOCITY;
+                map.player.pos_z += self.z_velocity;
+            } else if grounded {
+                // on the ground (or climbing a step)
+                self.z_velocity = 0;
+                map.player.pos_z += (target_z - map.player.pos_z) >> 1;
+            } else {
+                self.z_velocity += GRAVITY;
+                map.player.pos_z = (map.player.pos_z + self.z_velocity).min(target_z);
+            }
+            if map.player.pos_z < ceiling_z {
+                map.player.pos_z = ceiling_z;
+                self.z_velocity = self.z_velocity.max(0);
+            }
         }
     }
 
     #[rustfmt::skip]
-    fn update_opts(&mut self, input: &Input, duration: Duration) {
+    fn update_opts(&mut self, input: &Input) {
         let opts = &mut self.opts;
         if input.contains(Input::LOOK_RIGHT) || input.contains(Input::LOOK_LEFT) {
             opts.rotate += 2;
@@ -103,34 +203,32 @@ impl InputController {
             if opts.sideways < 0 { opts.sideways += 1; }
         }
         let max_speed = self.max_speed;
-        opts.forwards = opts.forwards.max(-max_speed).min(max_speed);
-        opts.sideways = opts.sideways.max(-max_speed).min(max_speed);
-        opts.rotate = opts.rotate.max(-8).min(8);
+        let Analog { forwards, sideways, look } = self.analog;
+        if forwards != 0.0 { opts.forwards = (forwards.clamp(-1.0, 1.0) * max_speed as f32) as i32; }
+        if sideways != 0.0 { opts.sideways = (sideways.clamp(-1.0, 1.0) * max_speed as f32) as i32; }
+        if look != 0.0 { opts.rotate = (look.clamp(-1.0, 1.0) * 8.0) as i16; }
+        opts.forwards = opts.forwards.clamp(-max_speed, max_speed);
+        opts.sideways = opts.sideways.clamp(-max_speed, max_speed);
+        opts.rotate = opts.rotate.clamp(-8, 8);
+    }
+}
+
+impl Simulation for InputController {
+    fn tick(&mut self, map: &mut map::Map, input: &Input) {
+        self.update(input, 1, map);
     }
 }
 
 /// Update player's sector.
 pub fn update_player(map: &mut map::Map, opts: &UpdateOpts) {
     if opts.rotate != 0 {
-        map.player.angle.0 += opts.rotate;
-    }
-    let mut x = 0;
-    let mut y = 0;
-    let sin = map.player.angle.to_radians().sin();
-    let cos = map.player.angle.to_radians().cos();
-    if opts.forwards != 0 {
-        let forwards = opts.forwards as f32;
-        let dx = -sin * forwards;
-        let dy = cos * forwards;
-        x += dx as i32;
-        y += dy as i32;
-    }
-    if opts.sideways != 0 {
-        let sideways = opts.sideways as f32;
-        let dx = cos * sideways;
-        let dy = sin * sideways;
-        x -= dx as i32;
-        y -= dy as i32;
+        map.player.angle += Angle(opts.rotate);
+    }
+    let [mut x, mut y] = displacement(map.player.angle, opts.forwards, opts.sideways);
+    if opts.clip {
+        let [cx, cy] = clip_move(map, [x, y]);
+        x = cx;
+        y = cy;
     }
     // update player sector
     let (_, walls) = map.sectors.get(map.player.sector).unwrap();
@@ -148,6 +246,141 @@ pub fn update_player(map: &mut map::Map, opts: &UpdateOpts) {
     map.player.pos_y += y;
 }
 
+// looks up the sector containing the player, keeping the current one if it
+// still does, or if the player is out of the MAP.
+fn update_sector(map: &mut map::Map) {
+    let map::player::Player {
+        pos_x,
+        pos_y,
+        sector,
+        ..
+    } = map.player;
+    if !map.sectors.inside(sector, pos_x, pos_y) {
+        if let Some(sector) = map.sectors.sector_at(pos_x, pos_y) {
+            map.player.sector = sector;
+        }
+    }
+}
+
+// XY displacement of a forwards & sideways movement.
+#[cfg(not(feature = "deterministic"))]
+fn displacement(angle: Angle, forwards: i32, sideways: i32) -> [i32; 2] {
+    let [cos, sin] = angle.direction();
+    let (forwards, sideways) = (forwards as f32, sideways as f32);
+    [
+        (cos * forwards) as i32 - (sin * sideways) as i32,
+        (sin * forwards) as i32 + (cos * sideways) as i32,
+    ]
+}
+
+// Same as above, with Build's sine table (see `map::math`), so the result
+// doesn't depend on the platform trig functions. Divisions round towards
+// zero, as the float to int casts above.
+#[cfg(feature = "deterministic")]
+fn displacement(angle: Angle, forwards: i32, sideways: i32) -> [i32; 2] {
+    use map::math::{cos, sin, ONE};
+    let (sin, cos) = (sin(angle), cos(angle));
+    [
+        cos * forwards / ONE - sin * sideways / ONE,
+        sin * forwards / ONE + cos * sideways / ONE,
+    ]
+}
+
+// Clips the player movement against the walls of its sector, sliding along
+// the X or Y axis if the full movement is blocked.
+fn clip_move(map: &map::Map, [x, y]: [i32; 2]) -> [i32; 2] {
+    let from = [map.player.pos_x, map.player.pos_y];
+    [[x, y], [x, 0], [0, y]]
+        .iter()
+        .copied()
+        .find(|delta| *delta == [0, 0] || can_move(map, from, *delta))
+        .unwrap_or([0, 0])
+}
+
+// true if the player (a circle of `PLAYER_RADIUS`) can move by `[dx, dy]`.
+// Walls are looked up in the sector of the player, and in the sectors behind
+// the portals it can step through that are within reach of the movement.
+fn can_move(map: &map::Map, from: [i32; 2], [dx, dy]: [i32; 2]) -> bool {
+    let to = [from[0] + dx, from[1] + dy];
+    let (sectors, walls) = (map.sectors.sectors(), map.sectors.walls());
+    let player_sector = &sectors[map.player.sector as usize];
+    let mut visited = vec![map.player.sector];
+    let mut stack = vec![map.player.sector];
+    while let Some(sector) = stack.pop() {
+        for left in walls
+            .get(sectors[sector as usize].wall_range())
+            .unwrap_or(&[])
+        {
+            let right = match walls.get(left.point2 as usize) {
+                Some(right) => right,
+                None => continue,
+            };
+            let distance = distance_to_wall(left, right, to);
+            let crossed = blocks_movement(left, right, from, to);
+            if !crossed && distance >= f64::from(PLAYER_RADIUS) {
+                continue;
+            }
+            let next = left.next_sector;
+            let enterable = sectors
+                .get(next as usize)
+                .filter(|_| next >= 0)
+                .is_some_and(|next| can_enter(player_sector, next));
+            if enterable {
+                if !visited.contains(&next) {
+                    visited.push(next);
+                    stack.push(next);
+                }
+            } else if crossed || distance < distance_to_wall(left, right, from) {
+                // moving away from the wall is always allowed, so the player
+                // can't get stuck next to it.
+                return false;
+            }
+        }
+    }
+    true
+}
+
+// distance from a point to the wall segment
+fn distance_to_wall(left: &map::sector::Wall, right: &map::sector::Wall, [x, y]: [i32; 2]) -> f64 {
+    let (lx, ly) = (f64::from(left.x), f64::from(left.y));
+    let (wx, wy) = (f64::from(right.x) - lx, f64::from(right.y) - ly);
+    let (px, py) = (f64::from(x) - lx, f64::from(y) - ly);
+    let len = wx * wx + wy * wy;
+    let t = if len == 0.0 {
+        0.0
+    } else {
+        ((px * wx + py * wy) / len).clamp(0.0, 1.0)
+    };
+    (px - t * wx).hypot(py - t * wy)
+}
+
+// true if the movement ends outside (or on) the wall, through the wall segment
+// (end points included). Walls keep the inside of their sector to their left.
+fn blocks_movement(
+    left: &map::sector::Wall,
+    right: &map::sector::Wall,
+    from: [i32; 2],
+    to: [i32; 2],
+) -> bool {
+    let (lx, ly) = (f64::from(left.x), f64::from(left.y));
+    let (wx, wy) = (f64::from(right.x) - lx, f64::from(right.y) - ly);
+    let side = |[x, y]: [i32; 2]| wx * (f64::from(y) - ly) - wy * (f64::from(x) - lx);
+    let (side_from, side_to) = (side(from), side(to));
+    if side_to > 0.0 || side_from < 0.0 || side_from == side_to {
+        return false;
+    }
+    let u = side_from / (side_from - side_to);
+    let px = f64::from(from[0]) + u * f64::from(to[0] - from[0]) - lx;
+    let py = f64::from(from[1]) + u * f64::from(to[1] - from[1]) - ly;
+    let t = (px * wx + py * wy) / (wx * wx + wy * wy);
+    (0.0..=1.0).contains(&t)
+}
+
+// Z grows downwards
+fn can_enter(from: &Sector, to: &Sector) -> bool {
+    from.floor_z - to.floor_z <= MAX_STEP && to.floor_z - to.ceiling_z >= MIN_CLEARANCE
+}
+
 fn intrsect_movement_with_wall(
     left: &map::sector::Wall,
     right: &map::sector::Wall,
@@ -166,3 +399,176 @@ fn intrsect_movement_with_wall(
         && num0.signum() == den.signum()
         && num1.signum() == den.signum()
 }
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "deterministic")]
+    use super::{displacement, Simulation};
+    use super::{
+        distance_to_wall, intrsect_movement_with_wall, update_player, Analog, Input,
+        InputController, UpdateOpts, PLAYER_RADIUS,
+    };
+    #[cfg(feature = "deterministic")]
+    use map::player::Angle;
+    use map::Map;
+
+    #[test]
+    fn collision() {
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let opts = UpdateOpts {
+            forwards: 64,
+            sideways: 16,
+            rotate: 0,
+            clip: true,
+        };
+        for turn in 0..8 {
+            map.player.angle.0 = turn * 256;
+            for _ in 0..100 {
+                let from = [map.player.pos_x, map.player.pos_y];
+                let sector = map.player.sector;
+                update_player(&mut map, &opts);
+                let to = [map.player.pos_x, map.player.pos_y];
+                let (_, mut walls) = map.sectors.get(sector).unwrap();
+                let crossed = walls.any(|(_, left, right)| {
+                    left.next_sector == -1
+                        && from != to
+                        && intrsect_movement_with_wall(left, right, from, to)
+                });
+                assert!(!crossed, "crossed a solid wall from {:?} to {:?}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn jump() {
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut controller = InputController::new(&map);
+        controller.gravity = true;
+        controller.update(&Input::empty(), 60, &mut map);
+        let ground = map.player.pos_z;
+        controller.update(&Input::UP, 1, &mut map);
+        // Z grows downwards
+        assert!(map.player.pos_z < ground);
+        controller.update(&Input::empty(), 5, &mut map);
+        assert!(map.player.pos_z < ground);
+        controller.update(&Input::empty(), 120, &mut map);
+        assert_eq!(ground, map.player.pos_z);
+    }
+
+    // walking into walls keeps the player away from them.
+    #[test]
+    fn radius() {
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut controller = InputController::new(&map);
+        controller.collision = true;
+        for turn in 0..8 {
+            map.player.angle.0 = turn * 256;
+            controller.update(&Input::FORWARDS, 200, &mut map);
+            let player = [map.player.pos_x, map.player.pos_y];
+            let sector = &map.sectors.sectors()[map.player.sector as usize];
+            let walls = map.sectors.walls();
+            for left in &walls[sector.wall_range()] {
+                if left.next_sector == -1 {
+                    let right = &walls[left.point2 as usize];
+                    let distance = distance_to_wall(left, right, player);
+                    assert!(distance >= f64::from(PLAYER_RADIUS) - 1.0, "{}", distance);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn analog() {
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        let mut controller = InputController::new(&map);
+        controller.analog = Analog {
+            forwards: 0.5,
+            sideways: -2.0,
+            look: 0.0,
+        };
+        controller.update(&Input::LOOK_RIGHT, 1, &mut map);
+        assert_eq!(16, controller.opts.forwards);
+        assert_eq!(-32, controller.opts.sideways);
+        // digital input still applies along the axes left at zero.
+        assert_eq!(2, controller.opts.rotate);
+    }
+
+    #[test]
+    fn teleport() {
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut controller = InputController::new(&map);
+        controller.update(&Input::FORWARDS, 10, &mut map);
+        let (_, mut walls) = map.sectors.get(0).unwrap();
+        let (_, wall, _) = walls.next().unwrap();
+        let [x, y] = [wall.x, wall.y];
+        assert!(!controller.teleport(&mut map, [x, y, 0], -1));
+        assert!(controller.teleport(&mut map, [x, y, 0], 0));
+        assert_eq!(
+            (x, y, 0),
+            (map.player.pos_x, map.player.pos_y, map.player.sector)
+        );
+        assert_eq!(0, controller.opts.forwards);
+    }
+
+    #[test]
+    fn noclip() {
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut controller = InputController::new(&map);
+        controller.collision = true;
+        controller.noclip = true;
+        let mut sectors = std::collections::HashSet::new();
+        for _ in 0..300 {
+            controller.update(&Input::FORWARDS, 1, &mut map);
+            let player = &map.player;
+            if let Some(sector) = map.sectors.sector_at(player.pos_x, player.pos_y) {
+                assert!(map
+                    .sectors
+                    .inside(player.sector, player.pos_x, player.pos_y));
+                sectors.insert(sector);
+            }
+        }
+        assert!(sectors.len() > 1);
+    }
+
+    #[test]
+    #[cfg(feature = "deterministic")]
+    fn displacement_matches_float() {
+        for angle in (0..2048).step_by(7) {
+            let [cos, sin] = Angle(angle).direction();
+            let [x, y] = displacement(Angle(angle), 32, -16);
+            assert!((cos * 32.0 + sin * 16.0 - x as f32).abs() <= 2.0);
+            assert!((sin * 32.0 - cos * 16.0 - y as f32).abs() <= 2.0);
+        }
+    }
+
+    // Same inputs give the same player on every platform.
+    #[test]
+    #[cfg(feature = "deterministic")]
+    fn replay() {
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut controller = InputController::new(&map);
+        controller.collision = true;
+        controller.gravity = true;
+        for tick in 0..600 {
+            let mut input = Input::FORWARDS;
+            if tick % 120 < 30 {
+                input |= Input::LOOK_LEFT;
+            }
+            if tick % 200 < 50 {
+                input |= Input::RIGHT;
+            }
+            controller.tick(&mut map, &input);
+        }
+        let player = &map.player;
+        assert_eq!(
+            (-26186, 7894, -181472, Angle(1190), 306),
+            (
+                player.pos_x,
+                player.pos_y,
+                player.pos_z,
+                player.angle,
+                player.sector
+            )
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Configurable controls.
+//!
+//! A [`KeyMap`](KeyMap) maps key codes to [`Input`](Input) bits. Key codes
+//! are whatever the frontend uses to identify keys (minifb `Key`s, DOM key
+//! codes, scancodes...), so the same controller can be driven by any of them.
+//!
+//! ```
+//! use render::controller::{bindings::KeyMap, Input};
+//!
+//! // DOM key codes
+//! let keys: KeyMap = vec![(87, Input::FORWARDS), (38, Input::FORWARDS), (65, Input::LEFT)]
+//!     .into_iter()
+//!     .collect();
+//! assert_eq!(Input::FORWARDS | Input::LEFT, keys.input([38, 65, 0]));
+//! ```
+//!
+//! With the `serde` feature, key maps are (de)serialized as maps from key
+//! codes to lists of input names (e.g. `{"87": ["FORWARDS"]}` in JSON), so
+//! they can be loaded from & saved to configuration files.
+use super::Input;
+use std::{collections::BTreeMap, iter::FromIterator};
+
+// names of the inputs, as (de)serialized.
+#[cfg(feature = "serde")]
+const NAMES: [(&str, Input); 9] = [
+    ("FORWARDS", Input::FORWARDS),
+    ("BACKWARDS", Input::BACKWARDS),
+    ("RIGHT", Input::RIGHT),
+    ("LEFT", Input::LEFT),
+    ("UP", Input::UP),
+    ("DOWN", Input::DOWN),
+    ("LOOK_RIGHT", Input::LOOK_RIGHT),
+    ("LOOK_LEFT", Input::LOOK_LEFT),
+    ("CROUCH", Input::CROUCH),
+];
+
+/// Key code to input bindings.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct KeyMap {
+    bindings: BTreeMap<u32, Input>,
+}
+
+impl KeyMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds a key to an input, on top of the inputs it's already bound to.
+    pub fn bind(&mut self, key: u32, input: Input) {
+        *self.bindings.entry(key).or_insert_with(Input::empty) |= input;
+    }
+
+    /// Removes all the bindings of a key.
+    pub fn unbind(&mut self, key: u32) {
+        self.bindings.remove(&key);
+    }
+
+    /// Inputs bound to a key.
+    pub fn get(&self, key: u32) -> Input {
+        self.bindings
+            .get(&key)
+            .copied()
+            .unwrap_or_else(Input::empty)
+    }
+
+    /// Keys bound to (any of the bits of) an input, in ascending order.
+    pub fn keys(&self, input: Input) -> impl Iterator<Item = u32> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, i)| i.intersects(input))
+            .map(|(key, _)| *key)
+    }
+
+    /// Input held down with the given keys. Unbound keys are ignored.
+    pub fn input<I: IntoIterator<Item = u32>>(&self, pressed: I) -> Input {
+        pressed
+            .into_iter()
+            .fold(Input::empty(), |input, key| input | self.get(key))
+    }
+}
+
+impl FromIterator<(u32, Input)> for KeyMap {
+    fn from_iter<I: IntoIterator<Item = (u32, Input)>>(iter: I) -> Self {
+        let mut keys = Self::new();
+        iter.into_iter()
+            .for_each(|(key, input)| keys.bind(key, input));
+        keys
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for KeyMap {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(self.bindings.len()))?;
+        for (key, input) in &self.bindings {
+            let names: Vec<&str> = NAMES
+                .iter()
+                .filter(|(_, i)| input.contains(*i))
+                .map(|(name, _)| *name)
+                .collect();
+            map.serialize_entry(key, &names)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for KeyMap {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw: BTreeMap<u32, Vec<String>> = serde::Deserialize::deserialize(deserializer)?;
+        let mut keys = Self::new();
+        for (key, names) in raw {
+            for name in names {
+                let input = NAMES
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, input)| *input)
+                    .ok_or_else(|| serde::de::Error::custom(format!("unknown input {:?}", name)))?;
+                keys.bind(key, input);
+            }
+        }
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Input, KeyMap};
+
+    #[test]
+    fn bindings() {
+        let mut keys = KeyMap::new();
+        keys.bind(1, Input::FORWARDS);
+        keys.bind(1, Input::LOOK_RIGHT);
+        keys.bind(2, Input::FORWARDS);
+        assert_eq!(Input::FORWARDS | Input::LOOK_RIGHT, keys.input([1, 2, 3]));
+        assert_eq!(vec![1, 2], keys.keys(Input::FORWARDS).collect::<Vec<_>>());
+        keys.unbind(1);
+        assert_eq!(Input::empty(), keys.get(1));
+        assert_eq!(Input::FORWARDS, keys.input([1, 2]));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let keys: KeyMap = vec![(87, Input::FORWARDS), (87, Input::UP), (65, Input::LEFT)]
+            .into_iter()
+            .collect();
+        let json = serde_json::to_string(&keys).unwrap();
+        assert_eq!(r#"{"65":["LEFT"],"87":["FORWARDS","UP"]}"#, json);
+        assert_eq!(keys, serde_json::from_str(&json).unwrap());
+        assert!(serde_json::from_str::<KeyMap>(r#"{"1":["FLY"]}"#).is_err());
+    }
+}
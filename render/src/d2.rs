@@ -1,17 +1,12 @@
 use crate::{
-    frame,
-    frame::{EGFrame, Frame},
-};
-use embedded_graphics::{
-    fonts::{Font6x6, Text},
-    pixelcolor::Rgb888,
-    prelude::*,
-    primitives::{Circle, Line, Rectangle},
-    style::{PrimitiveStyle, TextStyle},
+    camera::Camera,
+    clip, frame,
+    frame::Frame,
+    overlay::{Canvas, CHAR_SIZE},
 };
 use map::{
-    player::Player,
-    sector::{SectorId, Wall},
+    sector::{SectorId, Wall, WallId},
+    sprite::SpriteId,
     Map,
 };
 use nalgebra_glm as glm;
@@ -19,6 +14,13 @@ use std::collections::BTreeMap;
 
 const MAX_SECTOR_RENDER_DEPTH: usize = 32;
 const EPSILON: f32 = 1e-5;
+// map units spanning the width of the frame
+const SCALE: f32 = 20_000.0;
+const SECTOR_LABEL_COLOR: u32 = 0xffffff;
+const WALL_LABEL_COLOR: u32 = 0x808080;
+const SLOPE_ARROW_COLOR: u32 = 0xffff00;
+// pixels
+const SLOPE_ARROW_LENGTH: f32 = 12.0;
 
 bitflags::bitflags! {
     pub struct Flags: u8 {
@@ -28,54 +30,139 @@ bitflags::bitflags! {
 
         /// Clip sector geometry (hide everything behind the player).
         const CLIP   = 0b0000_1000;
+
+        /// Render every sector, not only those reachable from the camera.
+        const ALL_SECTORS = 0b0001_0000;
+
+        /// Print the indices (and lotags) of the rendered sectors, and the
+        /// indices of their walls. Disabled by default.
+        const LABELS = 0b0010_0000;
+
+        /// Print the floor & ceiling Z of the rendered sectors (at their
+        /// first wall, for sloped ones). Disabled by default.
+        const HEIGHTS = 0b0100_0000;
+
+        /// Draw an arrow on sloped floors, pointing downhill. Disabled by
+        /// default.
+        const SLOPES = 0b1000_0000;
     }
 }
 
+/// How the [`Renderer`](Renderer) colors walls. Except for
+/// [`Portals`](ColorMode::Portals), walls are colored after an attribute of
+/// their sector (or their own), and portals are drawn at half brightness.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum ColorMode {
+    /// Solid walls in green, portals in red.
+    #[default]
+    Portals,
+
+    /// Floor height, from blue (lowest floor of the MAP) to red (highest).
+    FloorHeight,
+
+    /// Color derived from the sector lotag. Untagged sectors are gray.
+    Lotag,
+
+    /// Sector visibility, from white (0) to black (255).
+    Visibility,
+
+    /// Color derived from the wall picnum, so textures can be told apart.
+    Picnum,
+}
+
+/// Map element returned by [`Renderer::pick`](Renderer::pick).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Pick {
+    /// Vertex, identified by the wall starting at it.
+    Vertex(WallId),
+    Wall(WallId),
+    Sprite(SpriteId),
+}
+
 /// 2D MAP renderer.
 #[derive(Debug)]
 pub struct Renderer {
     /// Renderer bitflags.
     pub flags: Flags,
 
+    /// Wall colors.
+    pub colors: ColorMode,
+
     visited_depth: BTreeMap<SectorId, usize>,
     view: glm::Mat3,
     clip: glm::Mat3,
+    sector: SectorId,
+    // highest & lowest floor Z of the MAP (Z points down).
+    floor_range: [i32; 2],
 }
 
-macro_rules! draw_axis_label {
-    ($frame:expr, $text:expr, ($x:expr, $y:expr), $color:expr) => {
-        Text::new($text, Point::new($x, $y))
-            .into_styled(TextStyle::new(Font6x6, $color))
-            .draw(&mut EGFrame($frame))
-            .unwrap();
-    };
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Renderer {
     pub fn new() -> Self {
         Self {
-            flags: Flags::all(),
+            flags: Flags::all() - Flags::LABELS - Flags::HEIGHTS - Flags::SLOPES,
+            colors: ColorMode::default(),
             visited_depth: BTreeMap::new(),
             view: glm::identity(),
             clip: glm::identity(),
+            sector: -1,
+            floor_range: [0, 0],
         }
     }
 
-    /// Render MAP to the given frame.
-    pub fn render(&mut self, map: &Map, frame: &mut Frame) {
-        if self.flags.contains(Flags::AXIS) {
-            Self::render_axis(frame);
-        }
-        if self.flags.contains(Flags::SECTOR) {
-            self.view = compute_view(&map.player);
-            self.clip = compute_clip(20000.0);
-            self.visited_depth.clear();
-            self.visited_depth.insert(map.player.sector, 0);
-            self.render_sector(map, map.player.sector, frame);
-        }
-        if self.flags.contains(Flags::PLAYER) {
-            Self::render_player(&map.player, frame);
-        }
+    /// Maps a point in map space to a frame pixel, using the view of the last
+    /// rendered frame.
+    pub fn project(&self, [x, y]: [i32; 2]) -> [i32; 2] {
+        let v = self.apply_viewport(self.clip * self.view * glm::vec3(x as f32, y as f32, 1.0));
+        [v.x, v.y]
+    }
+
+    /// Maps a frame pixel back to map space, using the view of the last
+    /// rendered frame.
+    pub fn unproject(&self, [x, y]: [i32; 2]) -> [i32; 2] {
+        let clip = glm::vec3(
+            0.5 - x as f32 / frame::WIDTH as f32,
+            0.5 - y as f32 / frame::HEIGHT as f32,
+            1.0,
+        );
+        let v = glm::inverse(&(self.clip * self.view)) * clip;
+        [v.x.round() as i32, v.y.round() as i32]
+    }
+
+    /// Returns the map element closest to a frame pixel, within `tolerance`
+    /// pixels, using the view of the last rendered frame. Vertices & sprites
+    /// take precedence over walls.
+    pub fn pick(&self, map: &Map, point: [i32; 2], tolerance: f32) -> Option<Pick> {
+        let [x, y] = self.unproject(point);
+        let p = glm::vec2(x as f32, y as f32);
+        let tolerance = tolerance * SCALE / frame::WIDTH as f32;
+        let walls = map.sectors.walls();
+        let nearest = |candidates: &mut dyn Iterator<Item = (Pick, f32)>| {
+            candidates
+                .filter(|(_, d)| *d <= tolerance)
+                .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+                .map(|(pick, _)| pick)
+        };
+        let vertices = walls.iter().enumerate().map(|(i, w)| {
+            let d = glm::distance(&p, &glm::vec2(w.x as f32, w.y as f32));
+            (Pick::Vertex(i as WallId), d)
+        });
+        let sprites = map.sprites.iter().enumerate().map(|(i, s)| {
+            let d = glm::distance(&p, &glm::vec2(s.x as f32, s.y as f32));
+            (Pick::Sprite(i as SpriteId), d)
+        });
+        let mut segments = walls.iter().enumerate().filter_map(|(i, left)| {
+            let right = walls.get(left.point2 as usize)?;
+            let a = glm::vec2(left.x as f32, left.y as f32);
+            let b = glm::vec2(right.x as f32, right.y as f32);
+            Some((Pick::Wall(i as WallId), segment_distance(&p, &a, &b)))
+        });
+        nearest(&mut vertices.chain(sprites)).or_else(|| nearest(&mut segments))
     }
 
     fn render_sector(&mut self, map: &Map, sector: SectorId, frame: &mut Frame) {
@@ -89,41 +176,159 @@ impl Renderer {
                 self.visited_depth.insert(l.next_sector, child_depth);
                 self.render_sector(map, l.next_sector, frame);
             }
-            self.render_wall(frame, map, sector, l, r);
+            self.render_wall(map, frame, sector, l, r);
         });
     }
 
-    fn render_wall(&self, frame: &mut Frame, map: &Map, sector: i16, left: &Wall, right: &Wall) {
-        let clip_view = &self.clip * &self.view;
+    fn render_wall(&self, map: &Map, frame: &mut Frame, sector: i16, left: &Wall, right: &Wall) {
+        let clip_view = self.clip * self.view;
         let mut left_clip = clip_view * glm::vec3(left.x as f32, left.y as f32, 1.0);
         let mut right_clip = clip_view * glm::vec3(right.x as f32, right.y as f32, 1.0);
         // clip vertices to POV
         if self.flags.contains(Flags::CLIP) {
-            #[rustfmt::skip]
-            if is_outside_clip(&left_clip, &right_clip, EPSILON) { return; };
-            crate::util::clip_xy(&mut left_clip, &mut right_clip, EPSILON);
+            let (min, max) = clip_box(EPSILON);
+            if !clip::clip_box(&mut left_clip, &mut right_clip, min, max) {
+                return;
+            }
         }
-        #[rustfmt::skip]
-        let color = if left.next_sector == -1 { Rgb888::GREEN } else { Rgb888::RED };
-        let stroke = if map.player.sector == sector { 3 } else { 1 };
+        let color = self.wall_color(map, sector, left);
+        let stroke = if self.sector == sector { 3 } else { 1 };
         let left = self.apply_viewport(left_clip);
         let right = self.apply_viewport(right_clip);
-        let point_left = Point::new(left.x as _, left.y as _);
-        let point_right = Point::new(right.x as _, right.y as _);
-        Line::new(point_left, point_right)
-            .into_styled(PrimitiveStyle::with_stroke(color, stroke))
-            .draw(&mut EGFrame(frame))
-            .unwrap();
-        let mut r0 = point_left.clone();
-        r0.y -= 1;
-        r0.x -= 1;
-        let mut r1 = point_left.clone();
-        r1.y += 1;
-        r1.x += 1;
-        Rectangle::new(r0, r1)
-            .into_styled(PrimitiveStyle::with_stroke(Rgb888::BLACK, 1))
-            .draw(&mut EGFrame(frame))
-            .unwrap();
+        let mut canvas = Canvas::new(frame);
+        canvas.line([left.x, left.y], [right.x, right.y], color, stroke);
+        canvas.rect(
+            [left.x - 1, left.y - 1],
+            [left.x + 1, left.y + 1],
+            0x000000,
+            false,
+        );
+    }
+
+    fn wall_color(&self, map: &Map, sector: SectorId, wall: &Wall) -> u32 {
+        let portal = wall.next_sector != -1;
+        let sector = &map.sectors.sectors()[sector as usize];
+        let color = match self.colors {
+            ColorMode::Portals => return if portal { 0xff0000 } else { 0x00ff00 },
+            ColorMode::FloorHeight => {
+                let [highest, lowest] = self.floor_range;
+                let range = (lowest - highest).max(1) as u32;
+                let t = (lowest - sector.floor_z).clamp(0, lowest - highest) as u32 * 0xff / range;
+                t << 16 | (0xff - t)
+            }
+            ColorMode::Lotag if sector.lotag == 0 => 0x808080,
+            ColorMode::Lotag => hash_color(sector.lotag),
+            ColorMode::Visibility => 0x010101 * u32::from(0xff - sector.visibility),
+            ColorMode::Picnum => hash_color(wall.picnum),
+        };
+        if portal {
+            (color >> 1) & 0x7f7f7f
+        } else {
+            color
+        }
+    }
+
+    // labels, heights & slope arrows of the rendered sectors.
+    fn render_annotations(&self, map: &Map, frame: &mut Frame) {
+        let sectors: Vec<SectorId> = if self.flags.contains(Flags::ALL_SECTORS) {
+            (0..map.sectors.sectors().len() as SectorId).collect()
+        } else {
+            self.visited_depth.keys().copied().collect()
+        };
+        let mut canvas = Canvas::new(frame);
+        for id in sectors {
+            let (sector, walls) = match map.sectors.get(id) {
+                Some(sector) => sector,
+                None => continue,
+            };
+            let (mut sum, mut count) = ([0.0; 2], 0);
+            for (wall_id, left, right) in walls {
+                sum[0] += f64::from(left.x);
+                sum[1] += f64::from(left.y);
+                count += 1;
+                if self.flags.contains(Flags::LABELS) {
+                    let mid = [
+                        (f64::from(left.x) + f64::from(right.x)) / 2.0,
+                        (f64::from(left.y) + f64::from(right.y)) / 2.0,
+                    ];
+                    self.render_label(&mut canvas, &wall_id.to_string(), mid, WALL_LABEL_COLOR);
+                }
+            }
+            if count == 0 {
+                continue;
+            }
+            let center = [sum[0] / f64::from(count), sum[1] / f64::from(count)];
+            if self.flags.contains(Flags::SLOPES) {
+                let [nx, ny, _] = sector.floor_plane(&map.sectors).normal;
+                if nx != 0.0 || ny != 0.0 {
+                    self.render_arrow(&mut canvas, center, [nx, ny]);
+                }
+            }
+            let mut lines = Vec::new();
+            if self.flags.contains(Flags::LABELS) {
+                lines.push(format!("{}\nlo={}", id, sector.lotag));
+            }
+            if self.flags.contains(Flags::HEIGHTS) {
+                lines.push(format!("f={}\nc={}", sector.floor_z, sector.ceiling_z));
+            }
+            if !lines.is_empty() {
+                let text = lines.join("\n");
+                self.render_label(&mut canvas, &text, center, SECTOR_LABEL_COLOR);
+            }
+        }
+    }
+
+    // frame position of a map point, or None if it's clipped away.
+    fn project_visible(&self, [x, y]: [f64; 2]) -> Option<glm::I32Vec2> {
+        let v = self.clip * self.view * glm::vec3(x as f32, y as f32, 1.0);
+        let (min, max) = clip_box(EPSILON);
+        if self.flags.contains(Flags::CLIP)
+            && (v.x < min[0] || v.x > max[0] || v.y < min[1] || v.y > max[1])
+        {
+            return None;
+        }
+        Some(self.apply_viewport(v))
+    }
+
+    // prints text centered at a map point, unless the point is clipped.
+    fn render_label(&self, canvas: &mut Canvas, text: &str, point: [f64; 2], color: u32) {
+        let p = match self.project_visible(point) {
+            Some(p) => p,
+            None => return,
+        };
+        let lines = text.lines();
+        let columns = lines.clone().map(str::len).max().unwrap_or(0) as i32;
+        let rows = lines.count() as i32;
+        let [w, h] = CHAR_SIZE;
+        canvas.text(text, [p.x - columns * w / 2, p.y - rows * h / 2], color);
+    }
+
+    // draws an arrow centered at a map point, pointing towards the given map
+    // direction. Arrows are the same size (in pixels) at any scale.
+    fn render_arrow(&self, canvas: &mut Canvas, center: [f64; 2], [dx, dy]: [f64; 2]) {
+        let center = match self.project_visible(center) {
+            Some(center) => [center.x, center.y],
+            None => return,
+        };
+        // the viewport flips both axes.
+        let v = self.clip * self.view * glm::vec3(dx as f32, dy as f32, 0.0);
+        let dir = glm::vec2(-v.x * frame::WIDTH as f32, -v.y * frame::HEIGHT as f32);
+        if glm::length(&dir) <= EPSILON {
+            return;
+        }
+        let dir = glm::normalize(&dir);
+        let offset = |[x, y]: [i32; 2], length: f32, angle: f32| {
+            let d = glm::rotate_vec2(&dir, angle) * length;
+            [x + d.x.round() as i32, y + d.y.round() as i32]
+        };
+        let half = SLOPE_ARROW_LENGTH / 2.0;
+        let tail = offset(center, half, std::f32::consts::PI);
+        let tip = offset(center, half, 0.0);
+        canvas.line(tail, tip, SLOPE_ARROW_COLOR, 1);
+        for angle in [0.8, -0.8] {
+            let head = offset(tip, half / 2.0, std::f32::consts::PI * angle);
+            canvas.line(tip, head, SLOPE_ARROW_COLOR, 1);
+        }
     }
 
     fn apply_viewport(&self, mut v: glm::Vec3) -> glm::I32Vec2 {
@@ -135,29 +340,21 @@ impl Renderer {
         glm::vec2(v.x as i32, v.y as i32)
     }
 
-    fn render_player(player: &Player, frame: &mut Frame) {
+    fn render_player(camera: &Camera, frame: &mut Frame) {
         let w = frame::WIDTH as i32;
         let h = frame::HEIGHT as i32;
         let w2 = w / 2;
         let h2 = h / 2;
         // reference axis
         // player & look direction
-        let color = Rgb888::CYAN;
-        Circle::new(Point::new(w2, h2), 2)
-            .into_styled(PrimitiveStyle::with_fill(color))
-            .draw(&mut EGFrame(frame))
-            .unwrap();
+        let color = 0x00ffff;
+        let mut canvas = Canvas::new(frame);
+        canvas.circle([w2, h2], 2, color, true);
         let offset = 12;
-        Line::new(Point::new(w2, h2), Point::new(w2, h2 - offset))
-            .into_styled(PrimitiveStyle::with_stroke(color, 1))
-            .draw(&mut EGFrame(frame))
-            .unwrap();
+        canvas.line([w2, h2], [w2, h2 - offset], color, 1);
         // help text
-        let text = format!("x={}\ny={}\nz={}", player.pos_x, player.pos_y, player.pos_z);
-        Text::new(&text, Point::new(w2 + 6, h2 + 6))
-            .into_styled(TextStyle::new(Font6x6, Rgb888::CYAN))
-            .draw(&mut EGFrame(frame))
-            .unwrap();
+        let text = format!("x={}\ny={}\nz={}", camera.x, camera.y, camera.z);
+        canvas.text(&text, [w2 + 6, h2 + 6], color);
     }
 
     fn render_axis(frame: &mut Frame) {
@@ -165,29 +362,56 @@ impl Renderer {
         let h = frame::HEIGHT as i32;
         let w2 = w / 2;
         let h2 = h / 2;
-        let color = Rgb888::new(0x11, 0x11, 0x11);
-
-        Line::new(Point::new(0, h2), Point::new(w, h2))
-            .into_styled(PrimitiveStyle::with_stroke(color, 1))
-            .draw(&mut EGFrame(frame))
-            .unwrap();
-        Line::new(Point::new(w2, 0), Point::new(w2, h))
-            .into_styled(PrimitiveStyle::with_stroke(color, 1))
-            .draw(&mut EGFrame(frame))
-            .unwrap();
+        let color = 0x111111;
+        let mut canvas = Canvas::new(frame);
+        canvas.line([0, h2], [w, h2], color, 1);
+        canvas.line([w2, 0], [w2, h], color, 1);
+        canvas.text("-1, 0", [0, h2 + 2], color);
+        canvas.text("1, 0", [w - 12, h2 + 2], color);
+        canvas.text("0, -1", [w2 + 2, h - 6], color);
+        canvas.text("0, 1", [w2 + 2, 0], color);
+    }
+}
 
-        draw_axis_label!(frame, "-1, 0", (0, h2 + 2), color);
-        draw_axis_label!(frame, "1, 0", (w - 12, h2 + 2), color);
-        draw_axis_label!(frame, "0, -1", (w2 + 2, h - 6), color);
-        draw_axis_label!(frame, "0, 1", (w2 + 2, 0), color);
+impl crate::Renderer for Renderer {
+    fn render(&mut self, map: &Map, camera: &Camera, frame: &mut Frame) {
+        if self.flags.contains(Flags::AXIS) {
+            Self::render_axis(frame);
+        }
+        if self.flags.contains(Flags::SECTOR) {
+            self.view = compute_view(camera);
+            self.clip = compute_clip(SCALE);
+            self.sector = camera.sector;
+            self.visited_depth.clear();
+            if self.colors == ColorMode::FloorHeight {
+                let floors = map.sectors.sectors().iter().map(|s| s.floor_z);
+                let highest = floors.clone().min().unwrap_or(0);
+                self.floor_range = [highest, floors.max().unwrap_or(0)];
+            }
+            if self.flags.contains(Flags::ALL_SECTORS) {
+                for sector in 0..map.sectors.sectors().len() as SectorId {
+                    let (_, walls) = map.sectors.get(sector).unwrap();
+                    walls.for_each(|(_, l, r)| self.render_wall(map, frame, sector, l, r));
+                }
+            } else if camera.sector >= 0 {
+                self.visited_depth.insert(camera.sector, 0);
+                self.render_sector(map, camera.sector, frame);
+            }
+            if self.flags.intersects(Flags::LABELS | Flags::HEIGHTS | Flags::SLOPES) {
+                self.render_annotations(map, frame);
+            }
+        }
+        if self.flags.contains(Flags::PLAYER) {
+            Self::render_player(camera, frame);
+        }
     }
 }
 
-fn compute_view(player: &Player) -> glm::Mat3 {
+fn compute_view(camera: &Camera) -> glm::Mat3 {
     #[rustfmt::skip]
-    let Player { pos_x, pos_y, angle, .. } = player;
-    let posx = *pos_x as f32;
-    let posy = *pos_y as f32;
+    let Camera { x, y, angle, .. } = camera;
+    let posx = *x as f32;
+    let posy = *y as f32;
     let cos = angle.to_radians().cos();
     let sin = angle.to_radians().sin();
     let transform: glm::Mat3 = [[cos, sin, 0.0], [-sin, cos, 0.0], [posx, posy, 1.0]].into();
@@ -199,14 +423,140 @@ fn compute_clip(scale: f32) -> glm::Mat3 {
     glm::scaling2d(&glm::vec2(1.0 / scale, aspect / scale))
 }
 
-// test if both left & right wall vertices are behind the player's POV
-// if they are, the wall doesn't need to be rendered at all
+// deterministic color for a tag or picnum (same hash as the d3 picnum colors).
+fn hash_color(value: i16) -> u32 {
+    let mut h = value as u16 as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    // keep channels away from black so portals can be told apart.
+    let channel = |c: u32| 0x40 + (c & 0xff) * 0xbf / 0xff;
+    channel(h >> 16) << 16 | channel(h >> 8) << 8 | channel(h)
+}
+
+// distance from p to the segment a-b
+fn segment_distance(p: &glm::Vec2, a: &glm::Vec2, b: &glm::Vec2) -> f32 {
+    let ab = b - a;
+    let len2 = glm::dot(&ab, &ab);
+    let t = if len2 > 0.0 {
+        (glm::dot(&(p - a), &ab) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    glm::distance(p, &(a + ab * t))
+}
+
+// visible region in clip space (in front of the player's POV)
+fn clip_box(eps: f32) -> ([f32; 2], [f32; 2]) {
+    ([eps - 1.0, eps], [1.0 - eps, 1.0 - eps])
+}
+
+// test if the wall lies completely outside of the player's POV
+// if it does, the wall doesn't need to be rendered at all
 pub fn is_outside_clip(left: &glm::Vec3, right: &glm::Vec3, eps: f32) -> bool {
-    // FIXME(german): line-box intersection has false-positives
-    let one_eps = 1.0 - eps;
-    let eps_one = eps - 1.0;
-    (left.y < eps && right.y < eps)
-        || (left.y > one_eps && right.y > one_eps)
-        || (left.x > one_eps && right.x > one_eps)
-        || (left.x < eps_one && right.x < eps_one)
+    let (min, max) = clip_box(eps);
+    !clip::clip_box(&mut left.clone(), &mut right.clone(), min, max)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ColorMode, Flags, Pick, Renderer};
+    use crate::{
+        camera::Camera,
+        frame::{self, Frame},
+        Renderer as _,
+    };
+    use map::{sector::SectorStat, tracked::TrackedMap, Map};
+
+    #[test]
+    fn pick() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        renderer.flags = Flags::SECTOR;
+        let camera = Camera::from(&map.player);
+        renderer.render(&map, &camera, &mut frame);
+
+        // the camera is at the center of the frame
+        let center = [frame::WIDTH as i32 / 2, frame::HEIGHT as i32 / 2];
+        let [x, y] = renderer.unproject(center);
+        assert!((x - camera.x).abs() <= 64 && (y - camera.y).abs() <= 64);
+
+        let (id, wall) = map
+            .sectors
+            .get(camera.sector)
+            .map(|(_, mut walls)| walls.next().unwrap())
+            .map(|(id, wall, _)| (id, wall))
+            .unwrap();
+        let screen = renderer.project([wall.x, wall.y]);
+        match renderer.pick(&map, screen, 4.0) {
+            Some(Pick::Vertex(vertex)) => {
+                let v = &map.sectors.walls()[vertex as usize];
+                assert_eq!([wall.x, wall.y], [v.x, v.y]);
+            }
+            pick => panic!("expected wall {} vertex, got {:?}", id, pick),
+        }
+        assert_eq!(None, renderer.pick(&map, [-10_000, -10_000], 4.0));
+    }
+
+    #[test]
+    fn labels() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        let camera = Camera::from(&map.player);
+        let mut a: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut b: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        assert!(!renderer.flags.contains(Flags::LABELS));
+        renderer.flags = Flags::SECTOR;
+        renderer.render(&map, &camera, &mut a);
+        renderer.flags |= Flags::LABELS;
+        renderer.render(&map, &camera, &mut b);
+        assert!(a != b);
+        assert!(b.iter().flatten().any(|c| *c == super::SECTOR_LABEL_COLOR));
+    }
+
+    #[test]
+    fn color_modes() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let camera = Camera::from(&map.player);
+        let mut renderer = Renderer::new();
+        renderer.flags = Flags::SECTOR | Flags::ALL_SECTORS;
+        let mut render = |colors| {
+            let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+            renderer.colors = colors;
+            renderer.render(&map, &camera, &mut frame);
+            frame
+        };
+        let portals = render(ColorMode::Portals);
+        for mode in [
+            ColorMode::FloorHeight,
+            ColorMode::Lotag,
+            ColorMode::Visibility,
+            ColorMode::Picnum,
+        ] {
+            assert!(portals != render(mode), "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn slopes() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        let camera = Camera::from(&map.player);
+        let mut renderer = Renderer::new();
+        renderer.flags = Flags::SECTOR | Flags::SLOPES;
+        let mut render = |map: &Map| {
+            let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+            renderer.render(map, &camera, &mut frame);
+            frame.iter().flatten().any(|c| *c == super::SLOPE_ARROW_COLOR)
+        };
+        assert!(!render(&map));
+        let mut sloped = TrackedMap::new(map);
+        sloped.modify_sector(camera.sector, |s| {
+            s.floor_stat |= SectorStat::SLOPPED;
+            s.floor_heinum = 2048;
+        });
+        assert!(render(sloped.map()));
+    }
 }
@@ -1,30 +1,60 @@
-use crate::{frame, frame::Frame};
-use algo::{Coverage, Interval};
+use crate::{
+    camera::Camera,
+    clip::{self, Keep},
+    frame,
+    frame::{ColumnFrame, Frame},
+    lighting::Lighting,
+    sky::Sky,
+    tile::{Texel, TileSource},
+};
+use algo::{Coverage, Interval, Visited};
+use cache::RenderCache;
+use color::{ColorScheme, PicnumColors};
 use map::{
-    player::Player,
-    sector::{Sector, SectorId, Wall},
-    Map,
+    cancel::CancellationToken,
+    pvs::Pvs,
+    sector::{Sector, SectorId, SectorStat, Wall, WallId},
+    sprite::SpriteId,
+    units, Map,
 };
 use nalgebra_glm as glm;
-use nalgebra_glm::{DVec4, IVec2};
-use std::collections::VecDeque;
+use nalgebra_glm::IVec2;
+use std::{collections::VecDeque, fmt, time::Duration};
 
 mod algo;
+pub mod cache;
+pub mod color;
+mod overhead;
+mod sprites;
+mod stereo;
+
+pub use sprites::SPRITE_TILE_SIZE;
+pub use stereo::{eyes, StereoFrame, DEFAULT_IPD};
+
+/// Default distance from the camera to the near clipping plane (see
+/// [`Renderer::near`](Renderer::near)).
+pub const DEFAULT_NEAR: f32 = 0.5;
 
-const EPSILON: f64 = 1e-4;
+// Camera space is normalized so that the frustum spans [-1, 1] horizontally
+// & vertically at depth 1.
+//
+// distance (XY units) ahead of the camera mapped to depth 1.
+const SCALE_Y: f64 = 15.625 * units::XY_UNITS_PER_METER as f64;
+// half width of the view (XY units) at SCALE_Y, i.e. a horizontal field of
+// view of 2 * atan(3/4).
+const SCALE_X: f64 = SCALE_Y * 3.0 / 4.0;
+// half height of the view (Z units) at SCALE_Y. Build pixels are stretched
+// vertically, so the view is as tall as a 320x200 frame would be.
+const SCALE_Z: f64 = SCALE_X * 200.0 / frame::WIDTH as f64 * units::Z_SCALE as f64;
 
-// magic scaling factors
-const SCALE_X: f64 = 6_000.0;
-const SCALE_Y: f64 = 8_000.0;
-const SCALE_Z: f64 = 60_000.0;
+// Fractional bits of the (16.16) fixed-point frame coordinates. Values are
+// stored in i64 because walls close to the camera project far off-screen.
+const FIXED_SHIFT: u32 = 16;
+const FIXED_ONE: i64 = 1 << FIXED_SHIFT;
+const FIXED_HALF: i64 = FIXED_ONE >> 1;
 
-// debug colors
-const BLACK_COLOR: u32 = 0x000000;
-const WALL_COLOR: u32 = 0x888888;
-const CEILING_COLOR: u32 = 0x444444;
-const FLOOR_COLOR: u32 = 0x2222ff;
-const TOP_FRAME_COLOR: u32 = 0x666666;
-const BOTTOM_FRAME_COLOR: u32 = 0xaa33aa;
+// color of the uncovered pixels in the debug overlay
+const OVERLAY_COVERAGE_COLOR: u32 = 0xff00ff;
 
 /// Represents a sector in the rendering queue.
 #[derive(Debug)]
@@ -46,61 +76,582 @@ struct NAWall<T> {
     portal_br: T,
 }
 
-/// Wall coordinates in camera space.
-type CameraWall = NAWall<DVec4>;
-/// Wall coordinates in frame (i.e. window) space.
-type FramedWall = NAWall<IVec2>;
+/// Wall coordinates in frame (i.e. window) space, in fixed-point pixels (see
+/// [`FIXED_SHIFT`](FIXED_SHIFT)).
+type FramedWall = NAWall<glm::I64Vec2>;
+
+/// Colors (and ids, for picking) of a wall column.
+struct WallColors {
+    sector_id: SectorId,
+    wall_id: WallId,
+    ceiling: u32,
+    parallax: bool,
+    floor: u32,
+    wall: u32,
+    portal_top: u32,
+    portal_bottom: u32,
+}
+
+/// Sky drawn on parallaxed ceilings.
+struct SkyPass {
+    sky: Sky,
+    tiles: Box<dyn TileSource>,
+    palette: Box<[[u8; 3]; 256]>,
+}
+
+impl fmt::Debug for SkyPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SkyPass").field("sky", &self.sky).finish()
+    }
+}
+
+/// Tiles animating the sprites.
+struct Animation(Box<dyn TileSource>);
+
+impl fmt::Debug for Animation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Animation").finish()
+    }
+}
+
+impl WallColors {
+    fn pick_wall(&self) -> PickResult {
+        PickResult {
+            sector: self.sector_id,
+            surface: Surface::Wall(self.wall_id),
+        }
+    }
+
+    fn pick_floor(&self) -> PickResult {
+        PickResult {
+            sector: self.sector_id,
+            surface: Surface::Floor,
+        }
+    }
+
+    fn pick_ceiling(&self) -> PickResult {
+        PickResult {
+            sector: self.sector_id,
+            surface: Surface::Ceiling,
+        }
+    }
+}
+
+/// Surface covering a pixel of the last rendered frame.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Surface {
+    /// Solid wall, or the wall above or below a portal.
+    Wall(WallId),
+    Floor,
+    Ceiling,
+
+    /// Face sprite (see [`Renderer::sprites`](Renderer::sprites)).
+    Sprite(SpriteId),
+}
+
+/// Result of [`Renderer::pick`](Renderer::pick).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct PickResult {
+    /// Sector the surface was rendered from.
+    pub sector: SectorId,
+    pub surface: Surface,
+}
+
+/// Projection used by the [`Renderer`](Renderer).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// First-person view from the camera.
+    #[default]
+    Perspective,
+
+    /// Top-down orthographic view centered on the camera (X right, Y down,
+    /// regardless of the camera angle). Floor heights are encoded as
+    /// shading: the lower the floor, the darker.
+    Overhead {
+        /// Map XY units per frame pixel.
+        units_per_pixel: f32,
+    },
+}
+
+/// Work done rendering a frame, returned by [`Renderer::render`].
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct RenderStats {
+    /// Sectors popped from the rendering queue (a sector seen through
+    /// several portals is counted once per portal).
+    pub sectors_visited: usize,
+
+    /// Walls projected on screen.
+    pub walls_drawn: usize,
+
+    /// Frame columns left fully covered.
+    pub columns_filled: usize,
+
+    /// Portals through which another sector was queued for rendering.
+    pub portals_traversed: usize,
+
+    /// Wall-clock time spent rendering. Always zero on `wasm32`, where
+    /// there is no clock in `std`.
+    pub duration: Duration,
+}
+
+/// Debug information drawn on top of the rendered frame.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Overlay {
+    /// Highlight the pixels left uncovered after rendering (i.e. holes).
+    pub coverage: bool,
+
+    /// Draw the column interval of each rendered sector, one row per sector
+    /// in traversal order.
+    pub portals: bool,
+}
+
+/// Rendering limits for very large MAPs (perspective projection only).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RendererConfig {
+    /// Walls farther than this distance (in map XY units) from the camera
+    /// are painted with the fog color, and sectors seen through them aren't
+    /// rendered, which bounds the work done per frame. `None` renders
+    /// everything.
+    pub max_draw_distance: Option<f32>,
+
+    /// Color (`0x00RRGGBB`) of the geometry beyond the draw distance.
+    pub fog_color: u32,
+}
+
+/// Memory layout of the frame the perspective projection renders into.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum Layout {
+    /// Render straight into the (row-major) output frame.
+    #[default]
+    RowMajor,
+
+    /// Render into an internal [`ColumnFrame`](frame::ColumnFrame), then
+    /// transpose it into the output frame. Walls, floors and ceilings are
+    /// drawn one column at a time, so this turns strided writes into
+    /// sequential ones, at the cost of two transposes per frame.
+    ColumnMajor,
+}
+
+/// Rectangle of the frame the perspective projection renders into, in
+/// pixels. Parts out of the frame are ignored.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+impl Viewport {
+    /// The whole frame.
+    pub const FULL: Self = Self {
+        x: 0,
+        y: 0,
+        width: frame::WIDTH,
+        height: frame::HEIGHT,
+    };
+
+    // the part of the viewport inside of the frame.
+    fn clipped(&self) -> Self {
+        let x = self.x.min(frame::WIDTH);
+        let y = self.y.min(frame::HEIGHT);
+        Self {
+            x,
+            y,
+            width: self.width.min(frame::WIDTH - x),
+            height: self.height.min(frame::HEIGHT - y),
+        }
+    }
+}
+
+// Render target of the perspective projection.
+trait Target {
+    fn set(&mut self, x: usize, y: usize, color: u32);
+}
+
+// Target drawing into a viewport of another target, in viewport pixels.
+struct Offset<'a, T> {
+    target: &'a mut T,
+    x: usize,
+    y: usize,
+}
+
+impl<T: Target> Target for Offset<'_, T> {
+    fn set(&mut self, x: usize, y: usize, color: u32) {
+        self.target.set(self.x + x, self.y + y, color);
+    }
+}
+
+impl Target for Frame {
+    fn set(&mut self, x: usize, y: usize, color: u32) {
+        self[y][x] = color;
+    }
+}
+
+impl Target for ColumnFrame {
+    fn set(&mut self, x: usize, y: usize, color: u32) {
+        self[x][y] = color;
+    }
+}
 
 /// 3D MAP renderer.
 #[derive(Debug)]
 pub struct Renderer {
+    pub projection: Projection,
+
+    /// Frame layout used by the perspective projection.
+    pub layout: Layout,
+
+    /// Rectangle of the frame to render into (perspective projection only),
+    /// e.g. for splitscreen views. The vertical field of view is kept, so
+    /// narrower viewports show less of the MAP instead of squashing it.
+    pub viewport: Viewport,
+
+    /// Distance (in map XY units) from the camera to the near clipping
+    /// plane. Geometry closer than this is clipped away, so it should be
+    /// smaller than the closest the camera gets to a wall.
+    pub near: f32,
+
+    /// Debug overlay (perspective projection only).
+    pub overlay: Overlay,
+
+    /// Draw distance & fog.
+    pub config: RendererConfig,
+
+    /// Shade offsets applied on top of the MAP shades.
+    pub lighting: Lighting,
+
+    /// Draw face sprites (perspective projection only), clipped to the
+    /// portals their sector is seen through. Sprites are sized as if their
+    /// tiles were [`SPRITE_TILE_SIZE`](SPRITE_TILE_SIZE) pixels wide & tall.
+    pub sprites: bool,
+
+    /// Clock time (in Build clock ticks, 120 per second) of the sprite
+    /// animations (see [`set_animation`](Renderer::set_animation)).
+    pub clock: u32,
+
+    /// Potentially visible sets of the MAP being rendered. Sectors not
+    /// visible from the camera sector are never traversed.
+    pub pvs: Option<Pvs>,
+
+    /// Stops rendering (leaving a partially rendered frame) once cancelled.
+    /// Checked before rendering each sector.
+    pub cancellation: Option<CancellationToken>,
+    cancelled: bool,
     coverage: Coverage,
     queue: VecDeque<RenderSector>,
     camera: glm::DMat4,
+    yaw: f64,
+    // clipped viewport & horizontal scale of the frame being rendered.
+    view: Viewport,
+    scale_x: f64,
+    colors: Box<dyn ColorScheme>,
+    sky: Option<SkyPass>,
+    animation: Option<Animation>,
+    traversal: Vec<(SectorId, [i32; 2])>,
+    visited: Visited,
+    stats: RenderStats,
+    picks: Vec<Option<PickResult>>,
+    columns: Option<Box<ColumnFrame>>,
+    eye_frame: Option<Box<Frame>>,
+    snapshots: sprites::Snapshots,
+    sprite_buffers: sprites::Buffers,
+    cache: Option<RenderCache>,
+    #[cfg(feature = "art")]
+    voxels: Option<art::kvx::Voxels>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Renderer {
     pub fn new() -> Self {
         Self {
+            projection: Projection::default(),
+            layout: Layout::default(),
+            viewport: Viewport::FULL,
+            near: DEFAULT_NEAR,
+            overlay: Overlay::default(),
+            config: RendererConfig::default(),
+            lighting: Lighting::new(),
+            sprites: false,
+            clock: 0,
+            pvs: None,
+            cancellation: None,
+            cancelled: false,
             coverage: Coverage::new(frame::WIDTH, frame::HEIGHT),
             queue: VecDeque::new(),
             camera: glm::identity(),
+            yaw: 0.0,
+            view: Viewport::FULL,
+            scale_x: SCALE_X,
+            colors: Box::new(PicnumColors),
+            sky: None,
+            animation: None,
+            traversal: Vec::new(),
+            visited: Visited::default(),
+            stats: RenderStats::default(),
+            picks: vec![None; frame::WIDTH * frame::HEIGHT],
+            columns: None,
+            eye_frame: None,
+            snapshots: sprites::Snapshots::default(),
+            sprite_buffers: sprites::Buffers::default(),
+            cache: None,
+            #[cfg(feature = "art")]
+            voxels: None,
         }
     }
 
-    /// Render MAP to the given frame.
-    pub fn render(&mut self, map: &Map, frame: &mut Frame) {
-        self.init_render(map);
-        self.render_sectors(map, frame);
+    /// Sets the colors used to paint the (untextured) geometry. Defaults to
+    /// [`PicnumColors`](color::PicnumColors).
+    pub fn set_color_scheme<C: ColorScheme + 'static>(&mut self, colors: C) {
+        self.colors = Box::new(colors);
+    }
+
+    /// Sets the sky drawn on parallaxed ceilings, with tiles taken from
+    /// `tiles`. Indexed tiles are converted to RGB using `palette` (8-bit RGB
+    /// colors). Without a sky, parallaxed ceilings are painted like any other
+    /// ceiling.
+    pub fn set_sky<T>(&mut self, sky: Sky, tiles: T, palette: [[u8; 3]; 256])
+    where
+        T: TileSource + 'static,
+    {
+        self.sky = Some(SkyPass {
+            sky,
+            tiles: Box::new(tiles),
+            palette: Box::new(palette),
+        });
+    }
+
+    /// Grows the buffers reused from frame to frame (sector queue, traversal,
+    /// sprites) to the size the MAP may need, so rendering doesn't allocate
+    /// while they grow over the first frames.
+    pub fn reserve(&mut self, map: &Map) {
+        // every sector is queued at most once per portal into it.
+        let portals = map
+            .sectors
+            .walls()
+            .iter()
+            .filter(|w| w.next_sector != -1)
+            .count();
+        let sectors = portals + 1;
+        self.queue.reserve(sectors);
+        self.traversal.reserve(sectors);
+        self.visited.reserve(map.sectors.sectors().len());
+        self.snapshots.reserve(sectors);
+        self.sprite_buffers.reserve(map);
+    }
+
+    /// Sets the geometry cache used instead of converting the MAP geometry
+    /// on every frame. It must have been built from the MAP being rendered,
+    /// and it is [refreshed](RenderCache::refresh) before every frame.
+    pub fn set_cache(&mut self, cache: Option<RenderCache>) {
+        self.cache = cache;
+    }
+
+    pub fn cache(&self) -> Option<&RenderCache> {
+        self.cache.as_ref()
+    }
+
+    /// Removes the sky set with [`set_sky`](Renderer::set_sky).
+    pub fn clear_sky(&mut self) {
+        self.sky = None;
+    }
+
+    /// Animates sprites with the tiles of `tiles` (see
+    /// [`TileSource::animate`](TileSource::animate)): sprites are painted
+    /// with the color of the frame shown at [`clock`](Renderer::clock).
+    pub fn set_animation<T: TileSource + 'static>(&mut self, tiles: T) {
+        self.animation = Some(Animation(Box::new(tiles)));
     }
 
-    fn init_render(&mut self, map: &Map) {
-        self.camera = compute_camera_normalized(&map.player);
+    pub fn clear_animation(&mut self) {
+        self.animation = None;
+    }
+
+    /// Draws the sprites whose picnum has a registered voxel model as voxels
+    /// instead of billboards.
+    #[cfg(feature = "art")]
+    pub fn set_voxels(&mut self, voxels: art::kvx::Voxels) {
+        self.voxels = Some(voxels);
+    }
+
+    #[cfg(feature = "art")]
+    pub fn clear_voxels(&mut self) {
+        self.voxels = None;
+    }
+
+    /// Sectors rendered in the last frame, in traversal order, along with
+    /// the range of frame columns (`[left, right]`) they were clipped to.
+    pub fn traversal(&self) -> &[(SectorId, [i32; 2])] {
+        &self.traversal
+    }
+
+    /// Statistics of the last frame (same as returned by `render`).
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// Returns true if the last frame was cut short by the
+    /// [`cancellation`](Renderer::cancellation) token.
+    pub fn was_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Returns the surface covering the given frame pixel in the last
+    /// rendered frame, or `None` if the pixel was left uncovered (or is out of
+    /// bounds).
+    pub fn pick(&self, x: usize, y: usize) -> Option<PickResult> {
+        if x >= frame::WIDTH || y >= frame::HEIGHT {
+            return None;
+        }
+        self.picks[y * frame::WIDTH + x]
+    }
+
+    #[cfg(feature = "tracing")]
+    fn trace_frame(&self) {
+        tracing::debug!(
+            sectors = self.stats.sectors_visited,
+            walls = self.stats.walls_drawn,
+            portals = self.stats.portals_traversed,
+            coverage = self.coverage.filled() * 100.0,
+            cancelled = self.cancelled,
+            "frame"
+        );
+    }
+
+    // near plane distance in camera space
+    fn near_plane(&self) -> f64 {
+        f64::from(self.near.max(f32::EPSILON)) / SCALE_Y
+    }
+
+    // true if the wall is entirely beyond the draw distance.
+    fn is_far(&self, wall: &NAWall<glm::DVec4>) -> bool {
+        match self.config.max_draw_distance {
+            Some(distance) => wall.tl.y.min(wall.tr.y) > f64::from(distance) / SCALE_Y,
+            None => false,
+        }
+    }
+
+    // paints the uncovered pixels of the columns with the fog color, and
+    // marks them as covered.
+    fn fill_fog(&mut self, interval: &Interval, frame: &mut impl Target) {
+        let columns = interval.left().max(0)..interval.right().min(self.view.width as i32);
+        for x in columns {
+            let x = x as usize;
+            for y in self.coverage.column(x).iter() {
+                frame.set(x, y as usize, self.config.fog_color);
+            }
+            self.coverage.intersect(x, &Interval::EMPTY);
+        }
+    }
+
+    fn init_render(&mut self, camera: &Camera) {
+        self.view = self.viewport.clipped();
+        let Viewport { width, height, .. } = self.view;
+        // same pixels per unit of angle as the full frame.
+        self.scale_x =
+            SCALE_X * (width.max(1) * frame::HEIGHT) as f64 / (frame::WIDTH * height.max(1)) as f64;
+        self.camera = compute_camera_normalized(camera, self.scale_x);
+        self.yaw = f64::from(camera.angle.0);
+        self.cancelled = false;
+        if self.coverage.width() != width || self.coverage.height() != height {
+            self.coverage = Coverage::new(width, height);
+        }
         self.coverage.clear();
         self.queue.clear();
+        self.traversal.clear();
+        self.visited.clear();
+        self.stats = RenderStats::default();
+        self.snapshots.clear();
+        self.picks.iter_mut().for_each(|p| *p = None);
         self.queue.push_back(RenderSector {
-            id: map.player.sector,
-            interval: Interval::new(0, frame::WIDTH as i32),
+            id: camera.sector,
+            interval: Interval::new(0, width as i32),
         });
     }
 
-    pub fn render_sectors(&mut self, map: &Map, frame: &mut Frame) {
+    fn render_perspective(&mut self, map: &Map, frame: &mut impl Target) {
+        self.render_sectors(map, frame);
+        if self.sprites && !self.cancelled {
+            sprites::render(self, map, frame);
+        }
+    }
+
+    fn render_sectors(&mut self, map: &Map, frame: &mut impl Target) {
         while let Some(sector) = self.queue.pop_back() {
+            if self.coverage.is_full() {
+                break;
+            }
+            if self.cancellation.as_ref().is_some_and(|t| t.is_cancelled()) {
+                self.cancelled = true;
+                break;
+            }
             let sector_int = &sector.interval;
             let sector_id = sector.id;
+            self.traversal
+                .push((sector_id, [sector_int.left(), sector_int.right()]));
+            self.visited.insert(sector_id, sector_int);
+            self.stats.sectors_visited += 1;
+            if self.sprites {
+                self.snapshots.push(sector_id, sector_int, &self.coverage);
+            }
             let (sector, sector_walls) = map.sectors.get(sector_id).expect("expected sector");
-            for (_, left, right) in sector_walls {
-                let nawall_ivec2 = self
-                    .wall_to_nawall_dvec4(map, sector, left, right)
-                    .and_then(|na| self.wall_to_nawall_ivec2(left, &na));
+            let lit = self.lighting.sector(sector_id, sector);
+            for (wall_id, left, right) in sector_walls {
+                let nawall_dvec4 =
+                    self.wall_to_nawall_dvec4(map, sector_id, sector, wall_id, left, right);
+                let far = nawall_dvec4.as_ref().is_some_and(|na| self.is_far(na));
+                let nawall_ivec2 = nawall_dvec4.and_then(|na| self.wall_to_nawall_ivec2(left, na));
                 if let Some(na) = nawall_ivec2 {
+                    self.stats.walls_drawn += 1;
+                    let wall = self.lighting.wall(sector_id, wall_id, left);
+                    let mut colors = WallColors {
+                        sector_id,
+                        wall_id,
+                        ceiling: self.colors.ceiling(&lit),
+                        parallax: sector.ceiling_stat.contains(SectorStat::PARALLAXING),
+                        floor: self.colors.floor(&lit),
+                        wall: self.colors.wall(&lit, &wall),
+                        portal_top: self.colors.portal_top(&lit, &wall),
+                        portal_bottom: self.colors.portal_bottom(&lit, &wall),
+                    };
+                    if far {
+                        colors.wall = self.config.fog_color;
+                        colors.portal_top = self.config.fog_color;
+                        colors.portal_bottom = self.config.fog_color;
+                    }
                     if left.next_sector == -1 {
-                        self.render_solid(&na, &sector_int, frame);
-                    } else {
-                        if let Some(interval) = self.render_portal(&na, &sector_int, frame) {
+                        self.render_solid(&na, sector_int, &colors, frame);
+                    } else if let Some(interval) =
+                        self.render_portal(&na, sector_int, &colors, frame)
+                    {
+                        let interval = interval.intersect(sector_int);
+                        // clipping errors can make two sectors see each other
+                        // through the same columns, so skip sectors already
+                        // rendered over this interval to avoid looping forever.
+                        let visited = self.visited.contains(left.next_sector, &interval);
+                        let hidden = self.pvs.as_ref().is_some_and(|pvs| {
+                            !pvs.is_visible(self.traversal[0].0, left.next_sector)
+                        });
+                        if far {
+                            // the sector behind is too far, collapse it into
+                            // the fog.
+                            self.fill_fog(&interval, frame);
+                        } else if !interval.is_empty() && !visited && !hidden {
+                            self.stats.portals_traversed += 1;
                             self.queue.push_back(RenderSector {
                                 id: left.next_sector,
-                                interval: interval.intersect(&sector_int),
+                                interval,
                             });
                         }
                     }
@@ -109,13 +660,25 @@ impl Renderer {
         }
     }
 
-    fn render_solid(&mut self, geometry: &FramedWall, int: &Interval, frame: &mut Frame) {
+    fn render_solid(
+        &mut self,
+        geometry: &FramedWall,
+        int: &Interval,
+        colors: &WallColors,
+        frame: &mut impl Target,
+    ) {
         for (top, bot, _, _) in self.lines_iter(geometry, int) {
             let top_ceil = glm::IVec2::new(top.x, 0);
-            let bottom_floor = glm::IVec2::new(bot.x, frame::HEIGHT as _);
-            self.render_line(&top_ceil, &top, frame, CEILING_COLOR);
-            self.render_line(&top, &bot, frame, WALL_COLOR);
-            self.render_line(&bot, &bottom_floor, frame, FLOOR_COLOR);
+            let bottom_floor = glm::IVec2::new(bot.x, self.view.height as _);
+            self.render_ceiling(&top_ceil, &top, frame, colors);
+            self.render_line(&top, &bot, frame, colors.wall, colors.pick_wall());
+            self.render_line(
+                &bot,
+                &bottom_floor,
+                frame,
+                colors.floor,
+                colors.pick_floor(),
+            );
             // no more rendering left to do on this column
             // so mark it as fully covered.
             self.coverage.intersect(top.x as usize, &Interval::EMPTY);
@@ -124,58 +687,78 @@ impl Renderer {
 
     fn render_portal(
         &mut self,
-        geometry: &NAWall<glm::IVec2>,
+        geometry: &FramedWall,
         int: &Interval,
-        frame: &mut Frame,
+        colors: &WallColors,
+        frame: &mut impl Target,
     ) -> Option<Interval> {
         self.lines_iter(geometry, int)
             .fold(None, |int, (t, b, pt, pb)| {
                 let top_ceil = glm::IVec2::new(t.x, 0);
-                let bottom_floor = glm::IVec2::new(b.x, frame::HEIGHT as _);
-                self.render_line(&top_ceil, &t, frame, CEILING_COLOR);
+                let bottom_floor = glm::IVec2::new(b.x, self.view.height as _);
+                self.render_ceiling(&top_ceil, &t, frame, colors);
                 if t.y < pt.y {
-                    self.render_line(&t, &pt, frame, TOP_FRAME_COLOR);
+                    self.render_line(&t, &pt, frame, colors.portal_top, colors.pick_wall());
                 }
                 if pb.y < b.y {
-                    self.render_line(&pb, &b, frame, BOTTOM_FRAME_COLOR);
+                    self.render_line(&pb, &b, frame, colors.portal_bottom, colors.pick_wall());
                 }
-                self.render_line(&b, &bottom_floor, frame, FLOOR_COLOR);
+                self.render_line(&b, &bottom_floor, frame, colors.floor, colors.pick_floor());
                 let portal_int = Interval::new(t.y.max(pt.y), b.y.min(pb.y));
                 self.coverage.intersect(t.x as usize, &portal_int);
                 if let Some(int) = int {
-                    Some(Interval::new(int.left().min(t.x), int.right().max(t.x)))
+                    Some(Interval::new(int.left().min(t.x), int.right().max(t.x + 1)))
                 } else {
-                    Some(Interval::new(t.x, t.x))
+                    Some(Interval::new(t.x, t.x + 1))
                 }
             })
     }
 
+    fn render_overlay(&self, frame: &mut impl Target) {
+        let Viewport { width, height, .. } = self.view;
+        if self.overlay.coverage {
+            for x in 0..width {
+                for y in self.coverage.column(x).iter() {
+                    frame.set(x, y as usize, OVERLAY_COVERAGE_COLOR);
+                }
+            }
+        }
+        if self.overlay.portals && width > 0 {
+            for (i, (sector, [left, right])) in self.traversal.iter().enumerate() {
+                let row = 1 + 2 * i;
+                if row >= height {
+                    break;
+                }
+                let left = (*left).clamp(0, width as i32 - 1) as usize;
+                let right = (*right).clamp(0, width as i32 - 1) as usize;
+                let color = color::picnum_color(*sector);
+                (left..=right).for_each(|x| frame.set(x, row, color));
+            }
+        }
+    }
+
     #[rustfmt::skip]
-    fn wall_to_nawall_ivec2(&self, wall: &Wall, nawall_dvec4: &NAWall<glm::DVec4>) -> Option<NAWall<glm::IVec2>> {
-        let mut nawall_d4 = nawall_dvec4.clone();
-        crate::util::clip_y(&mut nawall_d4.tl, &mut nawall_d4.tr, EPSILON);
-        crate::util::clip_y(&mut nawall_d4.bl, &mut nawall_d4.br, EPSILON);
-        crate::util::clip_y(&mut nawall_d4.portal_tl, &mut nawall_d4.portal_tr, EPSILON);
-        crate::util::clip_y(&mut nawall_d4.portal_bl, &mut nawall_d4.portal_br, EPSILON);
+    fn wall_to_nawall_ivec2(&self, wall: &Wall, mut nawall_d4: NAWall<glm::DVec4>) -> Option<FramedWall> {
+        // walls are seen from their left (i.e. the inside of the sector), so
+        // on screen the left point of front-facing walls is to the left of
+        // the right point (for any depths, even behind the camera).
+        let NAWall { tl, tr, .. } = &nawall_d4;
+        if tl.x * tr.y - tr.x * tl.y >= 0.0 { return None; } // back-facing
+        let near = self.near_plane();
+        if !clip_frustum(&mut nawall_d4.tl, &mut nawall_d4.tr, near) { return None; }
+        clip_frustum(&mut nawall_d4.bl, &mut nawall_d4.br, near);
+        clip_frustum(&mut nawall_d4.portal_tl, &mut nawall_d4.portal_tr, near);
+        clip_frustum(&mut nawall_d4.portal_bl, &mut nawall_d4.portal_br, near);
         nawall_d4.tl /= nawall_d4.tl.y;
-        if nawall_d4.tl.x > 1.0 - EPSILON { return None; } // out bounds (right)
-        if nawall_d4.tl.y < EPSILON - 1.0 && nawall_d4.tr.y < EPSILON - 1.0 { return None; } // out bounds (bottom)
         nawall_d4.tr /= nawall_d4.tr.y;
-        if nawall_d4.tr.x < EPSILON - 1.0 { return None; } // out bounds (left)
         nawall_d4.bl /= nawall_d4.bl.y;
-        if nawall_d4.bl.z > 1.0 - EPSILON && nawall_d4.br.z > 1.0 - EPSILON { return None; } // out bounds (top)
         nawall_d4.br /= nawall_d4.br.y;
         nawall_d4.portal_tl /= nawall_d4.portal_tl.y;
         nawall_d4.portal_tr /= nawall_d4.portal_tr.y;
         nawall_d4.portal_bl /= nawall_d4.portal_bl.y;
         nawall_d4.portal_br /= nawall_d4.portal_br.y;
-        crate::util::clip_x(&mut nawall_d4.tl, &mut nawall_d4.tr, EPSILON);
-        crate::util::clip_x(&mut nawall_d4.bl, &mut nawall_d4.br, EPSILON);
-        crate::util::clip_x(&mut nawall_d4.portal_tl, &mut nawall_d4.portal_tr, EPSILON);
-        crate::util::clip_x(&mut nawall_d4.portal_bl, &mut nawall_d4.portal_br, EPSILON);
         let tl = self.tr_viewport(&nawall_d4.tl);
         let tr = self.tr_viewport(&nawall_d4.tr);
-        if tl.x > tr.x { return None; } // ???
         let bl = self.tr_viewport(&nawall_d4.bl);
         let br = self.tr_viewport(&nawall_d4.br);
         if wall.next_sector == -1 {
@@ -189,81 +772,764 @@ impl Renderer {
         }
     }
 
+    // XY of the left vertex of a wall.
+    fn wall_xy(&self, id: WallId, wall: &Wall) -> [f64; 2] {
+        self.cache
+            .as_ref()
+            .and_then(|cache| cache.wall(id))
+            .unwrap_or([f64::from(wall.x), f64::from(wall.y)])
+    }
+
+    // ceiling & floor Z of a sector.
+    fn sector_z(&self, id: SectorId, sector: &Sector) -> [f64; 2] {
+        self.cache
+            .as_ref()
+            .and_then(|cache| cache.heights(id))
+            .unwrap_or([f64::from(sector.ceiling_z), f64::from(sector.floor_z)])
+    }
+
     #[rustfmt::skip]
-    fn wall_to_nawall_dvec4(&self, map: &Map, sector: &Sector, left: &Wall, right: &Wall) -> Option<NAWall<glm::DVec4>> {
-        let ceiling_floor = glm::vec2(sector.ceiling_z as f64, sector.floor_z as f64);
-        let tl = &self.camera * glm::vec4(left.x as f64, left.y as f64, ceiling_floor.x, 1.0);
-        let tr = &self.camera * glm::vec4(right.x as f64, right.y as f64, ceiling_floor.x, 1.0);
-        if tl.y < EPSILON && tr.y < EPSILON { return None; } // behind
-        let bl = &self.camera * glm::vec4(left.x as f64, left.y as f64, ceiling_floor.y, 1.0);
-        let br = &self.camera * glm::vec4(right.x as f64, right.y as f64, ceiling_floor.y, 1.0);
+    fn wall_to_nawall_dvec4(&self, map: &Map, sector_id: SectorId, sector: &Sector, wall_id: WallId, left: &Wall, right: &Wall) -> Option<NAWall<glm::DVec4>> {
+        let [ceiling_z, floor_z] = self.sector_z(sector_id, sector);
+        let [lx, ly] = self.wall_xy(wall_id, left);
+        let [rx, ry] = self.wall_xy(left.point2, right);
+        let tl = self.camera * glm::vec4(lx, ly, ceiling_z, 1.0);
+        let tr = self.camera * glm::vec4(rx, ry, ceiling_z, 1.0);
+        let near = self.near_plane();
+        if tl.y < near && tr.y < near { return None; } // behind
+        let bl = self.camera * glm::vec4(lx, ly, floor_z, 1.0);
+        let br = self.camera * glm::vec4(rx, ry, floor_z, 1.0);
         if left.next_sector == -1 {
             Some(NAWall { tl, tr, bl, br, ..Default::default() })
         } else {
             let next_sector = &map.sectors.sectors()[left.next_sector as usize];
-            let ceil_d = (next_sector.ceiling_z - sector.ceiling_z) as f64;
-            let floor_d = (next_sector.floor_z - sector.floor_z) as f64;
-            let portal_tl = &self.camera * glm::vec4(left.x as f64, left.y as f64, ceiling_floor.x + ceil_d, 1.0);
-            let portal_tr = &self.camera * glm::vec4(right.x as f64, right.y as f64, ceiling_floor.x + ceil_d, 1.0);
-            let portal_bl = &self.camera * glm::vec4(left.x as f64, left.y as f64, ceiling_floor.y + floor_d, 1.0);
-            let portal_br = &self.camera * glm::vec4(right.x as f64, right.y as f64, ceiling_floor.y + floor_d, 1.0);
+            let [next_ceiling_z, next_floor_z] = self.sector_z(left.next_sector, next_sector);
+            let portal_tl = self.camera * glm::vec4(lx, ly, next_ceiling_z, 1.0);
+            let portal_tr = self.camera * glm::vec4(rx, ry, next_ceiling_z, 1.0);
+            let portal_bl = self.camera * glm::vec4(lx, ly, next_floor_z, 1.0);
+            let portal_br = self.camera * glm::vec4(rx, ry, next_floor_z, 1.0);
             Some(NAWall { tl, tr, bl, br, portal_tl, portal_tr, portal_bl, portal_br })
         }
     }
 
+    // Columns covered by the wall, along with the rows of its edges (top,
+    // bottom, portal top, portal bottom) on each column. A pixel is covered
+    // when its center is, so walls sharing a vertex or an edge don't leave
+    // gaps, nor overlap, between them.
     #[rustfmt::skip]
     fn lines_iter<'a>(
         &self,
-        geometry: &'a NAWall<glm::IVec2>,
+        geometry: &'a FramedWall,
         int: &'a Interval,
     ) -> impl Iterator<Item = (IVec2, IVec2, IVec2, IVec2)> + 'a {
-        let d = geometry.tr.x - geometry.tl.x + 1;
-        ((geometry.tl.x)..=(geometry.tr.x))
-            .enumerate()
-            .filter(move |(_, x)| int.contains(*x))
-            .map(move |(i, x)| {
-                let mut top = glm::IVec2::new(x, 0);
-                let mut bot = glm::IVec2::new(x, 0);
-                let mut portal_top = glm::IVec2::new(x, 0);
-                let mut portal_bot = glm::IVec2::new(x, 0);
-                let n = (i as i32);
-                let t = (d - n);
-                top.y = ((geometry.tl.y * t + (geometry.tr.y * n)) / d).clamp(0, frame::HEIGHT as i32);
-                bot.y = ((geometry.bl.y * t + (geometry.br.y * n)) / d).clamp(0, frame::HEIGHT as i32);
-                portal_top.y = ((geometry.portal_tl.y * t + (geometry.portal_tr.y * n)) / d).clamp(0, frame::HEIGHT as i32);
-                portal_bot.y = ((geometry.portal_bl.y * t + (geometry.portal_br.y * n)) / d).clamp(0, frame::HEIGHT as i32);
+        let first = to_pixel(geometry.tl.x) as i32;
+        let end = to_pixel(geometry.tr.x) as i32;
+        let height = self.view.height as i64;
+        (first..end)
+            .filter(move |x| int.contains(*x))
+            .map(move |x| {
+                let center = (i64::from(x) << FIXED_SHIFT) + FIXED_HALF;
+                let row = |left: &glm::I64Vec2, right: &glm::I64Vec2| {
+                    let y = to_pixel(edge_at(left, right, center));
+                    glm::IVec2::new(x, y.clamp(0, height) as i32)
+                };
+                let top = row(&geometry.tl, &geometry.tr);
+                let bot = row(&geometry.bl, &geometry.br);
+                let portal_top = row(&geometry.portal_tl, &geometry.portal_tr);
+                let portal_bot = row(&geometry.portal_bl, &geometry.portal_br);
                 (top, bot, portal_top, portal_bot)
             })
     }
 
-    fn render_line(&mut self, top: &IVec2, bottom: &IVec2, frame: &mut Frame, color: u32) {
+    fn render_line(
+        &mut self,
+        top: &IVec2,
+        bottom: &IVec2,
+        frame: &mut impl Target,
+        color: u32,
+        pick: PickResult,
+    ) {
         assert_eq!(top.x, bottom.x);
         let int = self
             .coverage
             .column(top.x as usize)
             .intersect(&Interval::new(top.y, bottom.y));
         int.iter()
-            .for_each(|row| frame[row as usize][top.x as usize] = color);
+            .for_each(|row| frame.set(top.x as usize, row as usize, color));
+        self.record_pick(top, bottom, pick);
+    }
+
+    fn record_pick(&mut self, top: &IVec2, bottom: &IVec2, pick: PickResult) {
+        let int = self
+            .coverage
+            .column(top.x as usize)
+            .intersect(&Interval::new(top.y, bottom.y));
+        int.iter()
+            .for_each(|row| self.set_pick(top.x as usize, row as usize, pick));
     }
 
-    // convert from normalized coordinates back to window pixel coordinates
-    fn tr_viewport(&self, v: &glm::DVec4) -> glm::IVec2 {
-        let mut v = v.clone();
-        v.x = (v.x + 1.0) / 2.0 * (frame::WIDTH as f64);
-        v.z = (v.z + 1.0) / 2.0 * (frame::HEIGHT as f64);
-        glm::vec2(v.x as i32, v.z as i32)
+    // records the pick of a viewport pixel.
+    fn set_pick(&mut self, x: usize, y: usize, pick: PickResult) {
+        let (x, y) = (self.view.x + x, self.view.y + y);
+        self.picks[y * frame::WIDTH + x] = Some(pick);
     }
+
+    fn render_ceiling(
+        &mut self,
+        top: &IVec2,
+        bottom: &IVec2,
+        frame: &mut impl Target,
+        colors: &WallColors,
+    ) {
+        let pick = colors.pick_ceiling();
+        match &self.sky {
+            Some(sky) if colors.parallax => self.render_sky_line(top, bottom, frame, sky),
+            _ => return self.render_line(top, bottom, frame, colors.ceiling, pick),
+        }
+        self.record_pick(top, bottom, pick);
+    }
+
+    // the sky is stretched over the full frame height, and scrolls
+    // horizontally with the yaw of the camera.
+    fn render_sky_line(&self, top: &IVec2, bottom: &IVec2, frame: &mut impl Target, sky: &SkyPass) {
+        let x = (f64::from(top.x) + 0.5) / (self.view.width as f64) * 2.0 - 1.0;
+        let yaw = self.yaw + (x * self.scale_x / SCALE_Y).atan() / std::f64::consts::TAU * 2048.0;
+        let (picnum, u) = sky.sky.sample(yaw);
+        let tile = match sky.tiles.tile(picnum) {
+            Some(tile) if tile.width > 0 && tile.height > 0 => tile,
+            _ => return,
+        };
+        let tx = ((u * f64::from(tile.width)) as u32).min(tile.width - 1);
+        let int = self
+            .coverage
+            .column(top.x as usize)
+            .intersect(&Interval::new(top.y, bottom.y));
+        for row in int.iter() {
+            let ty = row as u32 * tile.height / self.view.height as u32;
+            let color = match tile.texel(tx, ty) {
+                Some(Texel::Indexed(i)) => {
+                    let [r, g, b] = sky.palette[usize::from(i)];
+                    u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b)
+                }
+                Some(Texel::Rgba(color)) => color & 0xffffff,
+                None => continue,
+            };
+            frame.set(top.x as usize, row as usize, color);
+        }
+    }
+
+    // convert from normalized coordinates back to (fixed-point) window pixel
+    // coordinates
+    fn tr_viewport(&self, v: &glm::DVec4) -> glm::I64Vec2 {
+        let x = (v.x + 1.0) / 2.0 * (self.view.width as f64);
+        let y = (v.z + 1.0) / 2.0 * (self.view.height as f64);
+        glm::vec2(to_fixed(x), to_fixed(y))
+    }
+}
+
+impl Renderer {
+    /// Renders the MAP as seen from the camera (see
+    /// [`crate::Renderer::render`]), returning statistics of the work done.
+    pub fn render(&mut self, map: &Map, camera: &Camera, frame: &mut Frame) -> RenderStats {
+        #[cfg(not(target_arch = "wasm32"))]
+        let start = std::time::Instant::now();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("render_d3", projection = ?self.projection).entered();
+        self.init_render(camera);
+        if let Some(cache) = &mut self.cache {
+            cache.refresh(map);
+        }
+        let Viewport { x, y, .. } = self.view;
+        match self.projection {
+            Projection::Perspective => {
+                match self.layout {
+                    Layout::RowMajor => self.render_perspective(
+                        map,
+                        &mut Offset {
+                            target: &mut *frame,
+                            x,
+                            y,
+                        },
+                    ),
+                    Layout::ColumnMajor => {
+                        // uncovered pixels keep the contents of the frame.
+                        let mut columns = self
+                            .columns
+                            .take()
+                            .unwrap_or_else(|| Box::new([[0; frame::HEIGHT]; frame::WIDTH]));
+                        frame::to_columns(frame, &mut columns);
+                        let target = &mut *columns;
+                        self.render_perspective(map, &mut Offset { target, x, y });
+                        frame::from_columns(&columns, frame);
+                        self.columns = Some(columns);
+                    }
+                }
+                self.render_overlay(&mut Offset {
+                    target: &mut *frame,
+                    x,
+                    y,
+                });
+            }
+            Projection::Overhead { units_per_pixel } => {
+                overhead::render(self, map, camera, units_per_pixel, frame)
+            }
+        }
+        self.stats.columns_filled = self.coverage.full_columns();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.stats.duration = start.elapsed();
+        }
+        #[cfg(feature = "tracing")]
+        self.trace_frame();
+        self.stats
+    }
+}
+
+impl crate::Renderer for Renderer {
+    fn render(&mut self, map: &Map, camera: &Camera, frame: &mut Frame) {
+        Renderer::render(self, map, camera, frame);
+    }
+}
+
+fn to_fixed(v: f64) -> i64 {
+    (v * FIXED_ONE as f64).round() as i64
+}
+
+// index of the first pixel whose center is at or after the (fixed-point)
+// coordinate, i.e. ceil(v - 0.5)
+fn to_pixel(v: i64) -> i64 {
+    (v - FIXED_HALF + FIXED_ONE - 1) >> FIXED_SHIFT
 }
 
-fn compute_camera_normalized(player: &Player) -> glm::DMat4 {
+// (fixed-point) y coordinate of the edge from `left` to `right` at `x`,
+// rounded to the nearest fixed-point unit
+fn edge_at(left: &glm::I64Vec2, right: &glm::I64Vec2, x: i64) -> i64 {
+    let dx = i128::from(right.x - left.x);
+    if dx == 0 {
+        return left.y;
+    }
+    let num = i128::from(right.y - left.y) * i128::from(x - left.x);
+    let (quot, rem) = (num / dx, num % dx);
+    let round = if 2 * rem.abs() >= dx.abs() {
+        num.signum() * dx.signum()
+    } else {
+        0
+    };
+    left.y + (quot + round) as i64
+}
+
+// Clips the segment (in camera space, before the perspective divide by y) to
+// the near plane and the horizontal frustum planes (x = -y & x = y).
+// Returns false if the segment is completely outside.
+fn clip_frustum(left: &mut glm::DVec4, right: &mut glm::DVec4, near: f64) -> bool {
+    clip::clip_plane(left, right, 1, near, Keep::Above)
+        && clip::clip_half_space(left, right, &glm::vec4(1.0, 1.0, 0.0, 0.0))
+        && clip::clip_half_space(left, right, &glm::vec4(-1.0, 1.0, 0.0, 0.0))
+}
+
+fn compute_camera_normalized(camera: &Camera, scale_x: f64) -> glm::DMat4 {
     // in Build maps, UP (z) is negative :-)
-    let scale = glm::scaling(&glm::vec3(-1.0 / SCALE_X, 1.0 / SCALE_Y, 1.0 / SCALE_Z));
-    let posx = player.pos_x as f64;
-    let posy = player.pos_y as f64;
-    let posz = player.pos_z as f64;
-    let angle = player.angle.to_radians() as f64;
+    let scale = glm::scaling(&glm::vec3(-1.0 / scale_x, 1.0 / SCALE_Y, 1.0 / SCALE_Z));
+    let posx = camera.x as f64;
+    let posy = camera.y as f64;
+    let posz = camera.z as f64;
+    let angle = camera.angle.to_radians() as f64;
     let tr = glm::translation(&glm::vec3(posx, posy, posz));
     let rot = glm::rotation(angle, &glm::vec3(0.0, 0.0, 1.0));
     let camera = glm::inverse(&(tr * rot));
     scale * camera
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        algo::Interval, Layout, PickResult, Projection, Renderer, RendererConfig, Surface, Viewport,
+    };
+    use crate::{
+        camera::Camera,
+        frame::{self, Frame},
+        sky::Sky,
+        tile::{Checkerboard, TileRef, TileSource},
+    };
+    use map::{
+        cancel::CancellationToken, sector::SectorStat, sprite::SpriteStat, tracked::TrackedMap, Map,
+    };
+    use nalgebra_glm as glm;
+
+    #[test]
+    fn scale() {
+        // the classic Build projection
+        assert_eq!(6_000.0, super::SCALE_X);
+        assert_eq!(8_000.0, super::SCALE_Y);
+        assert_eq!(60_000.0, super::SCALE_Z);
+    }
+
+    #[test]
+    fn overlay() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        renderer.overlay.portals = true;
+        renderer.render(&map, &Camera::from(&map.player), &mut frame);
+        let (sector, [left, right]) = renderer.traversal()[0];
+        assert_eq!(map.player.sector, sector);
+        assert_eq!([0, frame::WIDTH as i32], [left, right]);
+        assert_eq!(super::color::picnum_color(sector), frame[1][0]);
+    }
+
+    #[test]
+    fn cache() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let camera = Camera::from(&map.player);
+        let mut a: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut b: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        Renderer::new().render(&map, &camera, &mut a);
+        let mut renderer = Renderer::new();
+        renderer.set_cache(Some(super::RenderCache::new(&map)));
+        renderer.render(&map, &camera, &mut b);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn draw_distance() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let camera = Camera::from(&map.player);
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        let full = renderer.render(&map, &camera, &mut frame);
+        renderer.config = RendererConfig {
+            max_draw_distance: Some(1.0),
+            fog_color: 0x123456,
+        };
+        let fogged = renderer.render(&map, &camera, &mut frame);
+        assert_eq!(1, fogged.sectors_visited);
+        assert!(full.sectors_visited > 1);
+        assert!(frame.iter().flatten().any(|c| *c == 0x123456));
+    }
+
+    #[test]
+    fn viewport() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let camera = Camera::from(&map.player);
+        let mut frame: Box<Frame> = Box::new([[0x123456; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        let half = frame::WIDTH / 2;
+        renderer.viewport = Viewport {
+            x: half,
+            y: 0,
+            width: half,
+            height: frame::HEIGHT,
+        };
+        for layout in [Layout::RowMajor, Layout::ColumnMajor] {
+            renderer.layout = layout;
+            renderer.render(&map, &camera, &mut frame);
+            assert!(frame
+                .iter()
+                .all(|row| row[..half].iter().all(|c| *c == 0x123456)));
+            assert!(frame
+                .iter()
+                .any(|row| row[half..].iter().any(|c| *c != 0x123456)));
+            assert_eq!(None, renderer.pick(half / 2, frame::HEIGHT / 2));
+            assert!(renderer.pick(half + half / 2, frame::HEIGHT / 2).is_some());
+        }
+    }
+
+    // the PVS is conservative, so it doesn't change the rendered frames.
+    #[test]
+    fn pvs() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let camera = Camera::from(&map.player);
+        let mut a: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut b: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        Renderer::new().render(&map, &camera, &mut a);
+        let mut renderer = Renderer::new();
+        renderer.pvs = Some(map::pvs::compute(&map));
+        renderer.render(&map, &camera, &mut b);
+        assert_eq!(a, b);
+    }
+
+    // reused buffers don't change the rendered frames.
+    #[test]
+    fn reserve() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let camera = Camera::from(&map.player);
+        let mut reserved = Renderer::new();
+        reserved.sprites = true;
+        reserved.reserve(&map);
+        let mut a: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut b: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        for _ in 0..2 {
+            reserved.render(&map, &camera, &mut a);
+        }
+        let mut fresh = Renderer::new();
+        fresh.sprites = true;
+        let stats = fresh.render(&map, &camera, &mut b);
+        assert_eq!(a, b);
+        assert_eq!(fresh.traversal(), reserved.traversal());
+        assert!(reserved.queue.capacity() > stats.sectors_visited);
+    }
+
+    #[test]
+    fn pick() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        assert_eq!(None, renderer.pick(0, 0));
+        renderer.render(&map, &Camera::from(&map.player), &mut frame);
+        let picks: Vec<_> = (0..frame::HEIGHT)
+            .flat_map(|y| (0..frame::WIDTH).map(move |x| (x, y)))
+            .map(|(x, y)| (renderer.pick(x, y), frame[y][x]))
+            .collect();
+        // uncovered pixels aren't painted.
+        assert!(picks
+            .iter()
+            .all(|(pick, color)| pick.is_some() || *color == 0));
+        let surfaces = |surface: Surface| {
+            picks.iter().any(
+                |(pick, _)| matches!(pick, Some(PickResult { surface: s, .. }) if *s == surface),
+            )
+        };
+        assert!(surfaces(Surface::Floor));
+        assert!(surfaces(Surface::Ceiling));
+        assert!(picks.iter().any(|(pick, _)| matches!(
+            pick,
+            Some(PickResult {
+                surface: Surface::Wall(_),
+                ..
+            })
+        )));
+        assert_eq!(None, renderer.pick(frame::WIDTH, 0));
+    }
+
+    #[test]
+    fn sky() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut map = TrackedMap::new(map);
+        map.modify_sector(map.map().player.sector, |sector| {
+            sector.ceiling_stat |= SectorStat::PARALLAXING;
+        });
+        let map = map.into_inner();
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        let colors = [0xff123456, 0xff654321];
+        renderer.set_sky(
+            Sky::new(0, 2),
+            Checkerboard::new(64, 8, colors),
+            [[0; 3]; 256],
+        );
+        renderer.render(&map, &Camera::from(&map.player), &mut frame);
+        assert!(frame[0].contains(&0x123456));
+        assert!(frame[0].contains(&0x654321));
+    }
+
+    #[test]
+    fn stats() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        let stats = renderer.render(&map, &Camera::from(&map.player), &mut frame);
+        assert_eq!(renderer.traversal().len(), stats.sectors_visited);
+        assert!(stats.sectors_visited <= stats.portals_traversed + 1);
+        assert!(stats.walls_drawn >= stats.sectors_visited);
+        assert!(stats.columns_filled > 0 && stats.columns_filled <= frame::WIDTH);
+        // traversal stays bounded by the portals of the visible sectors.
+        assert!(stats.sectors_visited < map.sectors.sectors().len());
+    }
+
+    #[test]
+    fn cancellation() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        let token = CancellationToken::new();
+        renderer.cancellation = Some(token.clone());
+        renderer.render(&map, &Camera::from(&map.player), &mut frame);
+        assert!(!renderer.was_cancelled());
+        assert!(!renderer.traversal().is_empty());
+
+        token.cancel();
+        renderer.render(&map, &Camera::from(&map.player), &mut frame);
+        assert!(renderer.was_cancelled());
+        assert!(renderer.traversal().is_empty());
+    }
+
+    #[test]
+    fn column_major() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let camera = Camera::from(&map.player);
+        let mut rows: Box<Frame> = Box::new([[0x123456; frame::WIDTH]; frame::HEIGHT]);
+        let mut columns = rows.clone();
+        let mut renderer = Renderer::new();
+        renderer.render(&map, &camera, &mut rows);
+        renderer.layout = Layout::ColumnMajor;
+        renderer.render(&map, &camera, &mut columns);
+        assert_eq!(rows, columns);
+    }
+
+    #[test]
+    fn overhead() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        renderer.projection = Projection::Overhead {
+            units_per_pixel: 16.0,
+        };
+        let camera = Camera::from(&map.player);
+        renderer.render(&map, &camera, &mut frame);
+        // the player stands on its sector's floor
+        let center = renderer.pick(frame::WIDTH / 2, frame::HEIGHT / 2).unwrap();
+        assert_eq!(Surface::Floor, center.surface);
+        let floor_z = |id: i16| map.sectors.sectors()[id as usize].floor_z;
+        assert!(floor_z(center.sector) <= floor_z(camera.sector));
+        assert!(renderer.traversal().is_empty());
+    }
+
+    // golden test: the walls of a convex sector, seen from inside, cover
+    // every column of the frame exactly once.
+    #[test]
+    fn shared_edges() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        assert_columns_covered_once(&map, &Camera::from(&map.player));
+    }
+
+    // walls crossing the camera plane (at any angle) are clipped to the near
+    // plane without leaving gaps.
+    #[test]
+    fn near_plane() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        let (_, mut walls) = map.sectors.get(map.player.sector).unwrap();
+        let (_, left, right) = walls.next().unwrap();
+        // inside the sector, right next to the middle of its first wall
+        let len = f64::from((right.x - left.x).pow(2) + (right.y - left.y).pow(2)).sqrt();
+        for distance in [1.0, 2.0, 5.0, 16.0].iter() {
+            let mut camera = Camera::from(&map.player);
+            let normal = [-(right.y - left.y), right.x - left.x];
+            camera.x = (left.x + right.x) / 2 + (f64::from(normal[0]) * distance / len) as i32;
+            camera.y = (left.y + right.y) / 2 + (f64::from(normal[1]) * distance / len) as i32;
+            assert_columns_covered_once(&map, &camera);
+        }
+    }
+
+    #[test]
+    fn sprites() {
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        renderer.sprites = true;
+        let camera = Camera::from(&map.player);
+        renderer.render(&map, &camera, &mut frame);
+        let hidden = (0..map.sectors.sectors().len() as i16)
+            .find(|id| renderer.traversal().iter().all(|(s, _)| s != id))
+            .unwrap();
+
+        // a single sprite, right in front of the camera
+        let mut sprite = map.sprites[0].clone();
+        let [dx, dy] = map.player.angle.direction();
+        sprite.x = camera.x + (dx * 256.0) as i32;
+        sprite.y = camera.y + (dy * 256.0) as i32;
+        sprite.z = camera.z + 1024;
+        sprite.sprite_stat = SpriteStat::REAL_CENTERED_CENTERING;
+        sprite.x_repeat = 64;
+        sprite.y_repeat = 64;
+        sprite.shade = 0;
+        map.sprites = vec![sprite];
+        let center = |renderer: &Renderer| renderer.pick(frame::WIDTH / 2, frame::HEIGHT / 2);
+
+        // seen through the walls of its sector only
+        map.sprites[0].sectnum = hidden;
+        renderer.render(&map, &camera, &mut frame);
+        assert_ne!(
+            Some(Surface::Sprite(0)),
+            center(&renderer).map(|p| p.surface)
+        );
+        map.sprites[0].sectnum = camera.sector;
+        renderer.render(&map, &camera, &mut frame);
+        assert_eq!(
+            Some(Surface::Sprite(0)),
+            center(&renderer).map(|p| p.surface)
+        );
+        assert_eq!(
+            super::color::picnum_color(map.sprites[0].picnum),
+            frame[frame::HEIGHT / 2][frame::WIDTH / 2]
+        );
+
+        // painted with the color of the animation frame
+        renderer.set_animation(Animated);
+        renderer.clock = 1;
+        renderer.render(&map, &camera, &mut frame);
+        assert_eq!(
+            super::color::picnum_color(map.sprites[0].picnum + 1),
+            frame[frame::HEIGHT / 2][frame::WIDTH / 2]
+        );
+    }
+
+    #[cfg(feature = "art")]
+    #[test]
+    fn voxels() {
+        use art::kvx::{Kvx, Voxels};
+
+        // single red voxel, pivoting around its center.
+        #[rustfmt::skip]
+        let mut data = vec![
+            0, 0, 0, 0, // numbytes (patched below)
+            1, 0, 0, 0, // xsiz
+            1, 0, 0, 0, // ysiz
+            1, 0, 0, 0, // zsiz
+            0x80, 0, 0, 0, // xpivot
+            0x80, 0, 0, 0, // ypivot
+            0x80, 0, 0, 0, // zpivot
+            12, 0, 0, 0, // xoffset[0]
+            16, 0, 0, 0, // xoffset[1]
+            0, 0, 4, 0, // xyoffset
+            0, 1, 0b11_1111, 1, // slab
+        ];
+        data[0] = (data.len() - 4) as u8;
+        let mut palette = [0; 768];
+        palette[3] = 63;
+        data.extend_from_slice(&palette);
+        let mut voxels = Voxels::new();
+
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let camera = Camera::from(&map.player);
+        let mut sprite = map.sprites[0].clone();
+        let [dx, dy] = map.player.angle.direction();
+        sprite.x = camera.x + (dx * 256.0) as i32;
+        sprite.y = camera.y + (dy * 256.0) as i32;
+        sprite.z = camera.z;
+        sprite.sectnum = camera.sector;
+        sprite.sprite_stat = SpriteStat::REAL_CENTERED_CENTERING;
+        sprite.x_repeat = 255;
+        sprite.y_repeat = 255;
+        sprite.shade = 0;
+        voxels.register(sprite.picnum, Kvx::from_slice(&data).unwrap());
+        map.sprites = vec![sprite];
+
+        let mut renderer = Renderer::new();
+        renderer.sprites = true;
+        renderer.set_voxels(voxels);
+        renderer.render(&map, &camera, &mut frame);
+        let center = renderer.pick(frame::WIDTH / 2, frame::HEIGHT / 2);
+        assert_eq!(Some(Surface::Sprite(0)), center.map(|p| p.surface));
+        assert_eq!(0xff0000, frame[frame::HEIGHT / 2][frame::WIDTH / 2]);
+        // a voxel is much smaller than a billboard
+        let covered = |renderer: &Renderer| {
+            (0..frame::HEIGHT)
+                .flat_map(|y| (0..frame::WIDTH).map(move |x| (x, y)))
+                .filter(|(x, y)| {
+                    renderer.pick(*x, *y).map(|p| p.surface) == Some(Surface::Sprite(0))
+                })
+                .count()
+        };
+        let voxel = covered(&renderer);
+        renderer.clear_voxels();
+        renderer.render(&map, &camera, &mut frame);
+        assert!(voxel * 4 < covered(&renderer));
+    }
+
+    // two frame animation of every tile
+    struct Animated;
+
+    impl TileSource for Animated {
+        fn tile(&self, _: i16) -> Option<TileRef<'_>> {
+            None
+        }
+
+        fn animate(&self, picnum: i16, clock: u32) -> i16 {
+            picnum + (clock % 2) as i16
+        }
+    }
+
+    // the player start of E1L1 stands right next to a wall that crosses the
+    // camera plane.
+    #[test]
+    fn no_holes() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        for angle in (0..2048).step_by(64) {
+            let mut camera = Camera::from(&map.player);
+            camera.angle.0 = angle;
+            renderer.render(&map, &camera, &mut frame);
+            let holes = (0..frame::HEIGHT)
+                .flat_map(|y| (0..frame::WIDTH).map(move |x| (x, y)))
+                .filter(|(x, y)| renderer.pick(*x, *y).is_none())
+                .count();
+            assert_eq!(0, holes, "angle {}", angle);
+        }
+    }
+
+    #[test]
+    fn clip_frustum() {
+        // crosses the camera plane, from behind (left) to the front (right)
+        let mut left = glm::vec4(-1.0, -1.0, 0.0, 1.0);
+        let mut right = glm::vec4(0.5, 1.0, 2.0, 1.0);
+        assert!(super::clip_frustum(&mut left, &mut right, 0.1));
+        assert_eq!(glm::vec4(0.5, 1.0, 2.0, 1.0), right);
+        // moved into the frustum, along the segment
+        assert!(left.y >= 0.1 - 1e-9 && left.x + left.y >= -1e-9);
+        assert!((left.x + 1.0) * 2.0 - (left.y + 1.0) * 1.5 < 1e-9);
+        assert!((left.z - (left.y + 1.0)).abs() < 1e-9);
+        // behind the near plane
+        let mut left = glm::vec4(-1.0, 0.05, 0.0, 1.0);
+        let mut right = glm::vec4(1.0, 0.05, 0.0, 1.0);
+        assert!(!super::clip_frustum(&mut left, &mut right, 0.1));
+        // in front, but outside the horizontal field of view
+        let mut left = glm::vec4(2.0, 1.0, 0.0, 1.0);
+        let mut right = glm::vec4(3.0, 2.0, 0.0, 1.0);
+        assert!(!super::clip_frustum(&mut left, &mut right, 0.1));
+    }
+
+    fn assert_columns_covered_once(map: &Map, camera: &Camera) {
+        let mut renderer = Renderer::new();
+        let full = Interval::new(0, frame::WIDTH as i32);
+        for angle in (0..2048).step_by(8) {
+            let mut camera = *camera;
+            camera.angle.0 = angle;
+            renderer.init_render(&camera);
+            let mut columns = [0; frame::WIDTH];
+            let (sector, walls) = map.sectors.get(camera.sector).unwrap();
+            for (wall_id, left, right) in walls {
+                let geometry = renderer
+                    .wall_to_nawall_dvec4(map, camera.sector, sector, wall_id, left, right)
+                    .and_then(|na| renderer.wall_to_nawall_ivec2(left, na));
+                for (top, ..) in geometry.iter().flat_map(|g| renderer.lines_iter(g, &full)) {
+                    columns[top.x as usize] += 1;
+                }
+            }
+            assert!(
+                columns.iter().all(|c| *c == 1),
+                "{:?} angle {}: {:?}",
+                [camera.x, camera.y],
+                angle,
+                columns
+            );
+        }
+    }
+
+    // golden test: splitting a wall in two doesn't change the rendered frame.
+    #[test]
+    fn split_wall() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        let (_, walls) = map.sectors.get(map.player.sector).unwrap();
+        let mut split = TrackedMap::new(map.clone());
+        // splits shift the ids of the walls after the split one, so go from
+        // the last wall (the player sector is the first one).
+        let mut walls: Vec<_> = walls.collect();
+        walls.reverse();
+        for (id, left, right) in walls {
+            split.split_wall(id, (left.x + right.x) / 2, (left.y + right.y) / 2);
+        }
+        let split = split.into_inner();
+        let mut renderer = Renderer::new();
+        for angle in (0..2048).step_by(8) {
+            let mut camera = Camera::from(&map.player);
+            camera.angle.0 = angle;
+            let mut expected: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+            let mut frame = expected.clone();
+            renderer.render(&map, &camera, &mut expected);
+            renderer.render(&split, &camera, &mut frame);
+            assert!(expected == frame, "angle {}", angle);
+        }
+    }
+}
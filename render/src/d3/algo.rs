@@ -1,4 +1,7 @@
-/// 1D open-ended Interval.
+use map::sector::SectorId;
+
+/// 1D open-ended Interval. Empty intervals are always equal to
+/// [`EMPTY`](Interval::EMPTY).
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub struct Interval([i32; 2]);
 
@@ -13,9 +16,14 @@ impl Interval {
         self.0[1]
     }
 
+    /// Interval from `l` (included) to `r` (excluded). Bounds that touch or
+    /// cross (`r <= l`) give [`EMPTY`](Self::EMPTY).
     pub fn new(l: i32, r: i32) -> Self {
-        assert!(l <= r);
-        Self([l, r])
+        if l < r {
+            Self([l, r])
+        } else {
+            Self::EMPTY
+        }
     }
 
     pub fn iter(&self) -> impl Iterator<Item = i32> {
@@ -23,20 +31,17 @@ impl Interval {
     }
 
     pub fn contains(&self, point: i32) -> bool {
-        !self.is_empty() && point >= self.0[0] && point <= self.0[1]
+        point >= self.0[0] && point < self.0[1]
     }
 
-    #[rustfmt::skip]
+    /// Points contained in both intervals. Doesn't depend on the order of
+    /// the operands.
     pub fn intersect(&self, other: &Self) -> Self {
-        if self.is_empty() || other.is_empty() { return Self::EMPTY; }
-        if self.0[0] > other.0[0] { return other.intersect(self); }
-        assert!(self.0[0] <= other.0[0]);
-        if self.0[1] <= other.0[0] { return Self::EMPTY; }
-        Self::new(other.0[0], other.0[1].min(self.0[1]))
+        Self::new(self.0[0].max(other.0[0]), self.0[1].min(other.0[1]))
     }
 
     pub fn is_empty(&self) -> bool {
-        return self.0[1] <= self.0[0];
+        self.0[1] <= self.0[0]
     }
 }
 
@@ -60,6 +65,14 @@ impl Coverage {
         }
     }
 
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     pub fn intersect(&mut self, column: usize, int: &Interval) -> Interval {
         assert!(column < self.width);
         if self.columns[column].is_empty() {
@@ -70,7 +83,7 @@ impl Coverage {
         if self.columns[column].is_empty() {
             self.empty += 1;
         }
-        return int;
+        int
     }
 
     pub fn column(&self, idx: usize) -> &Interval {
@@ -80,7 +93,24 @@ impl Coverage {
     /// Returns true if the pixel coverage is 100% i.e. there are no more pixels
     /// left to render in the window.
     pub fn is_full(&self) -> bool {
-        return self.empty == self.width;
+        self.empty == self.width
+    }
+
+    /// Fraction of the window pixels covered so far, from 0 to 1.
+    #[cfg(any(test, feature = "tracing"))]
+    pub fn filled(&self) -> f64 {
+        let open: i64 = self
+            .columns
+            .iter()
+            .map(|int| i64::from((int.right() - int.left()).max(0)))
+            .sum();
+        let total = (self.width * self.height).max(1) as f64;
+        1.0 - open as f64 / total
+    }
+
+    /// Number of fully covered columns.
+    pub fn full_columns(&self) -> usize {
+        self.empty
     }
 
     /// Reset pixel coverage to 0%
@@ -93,9 +123,55 @@ impl Coverage {
     }
 }
 
+/// Intervals each sector has been rendered over, in the current frame.
+///
+/// A bitset indexed by sector marks the sectors rendered so far, so their
+/// interval lists are only reset when first visited in a frame.
+#[derive(Debug, Default)]
+pub struct Visited {
+    bits: Vec<u64>,
+    intervals: Vec<Vec<Interval>>,
+}
+
+impl Visited {
+    /// Grows the bitset & interval lists for the given number of sectors.
+    pub fn reserve(&mut self, sectors: usize) {
+        if sectors > self.intervals.len() {
+            self.bits.resize(sectors.div_ceil(64), 0);
+            self.intervals.resize_with(sectors, Vec::new);
+        }
+    }
+
+    pub fn insert(&mut self, sector: SectorId, int: &Interval) {
+        let sector = sector as usize;
+        self.reserve(sector + 1);
+        let (word, bit) = (sector / 64, 1 << (sector % 64));
+        if self.bits[word] & bit == 0 {
+            self.bits[word] |= bit;
+            self.intervals[sector].clear();
+        }
+        self.intervals[sector].push(*int);
+    }
+
+    /// Returns true if the sector has been rendered over the given interval.
+    pub fn contains(&self, sector: SectorId, int: &Interval) -> bool {
+        let sector = sector as usize;
+        self.bits
+            .get(sector / 64)
+            .is_some_and(|word| word & (1 << (sector % 64)) != 0)
+            && self.intervals[sector].contains(int)
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+    }
+}
+
 #[cfg(test)]
 mod tests2 {
-    use super::{Coverage, Interval};
+    use super::{Coverage, Interval, Visited};
+    use proptest::prelude::*;
+
     #[test]
     fn intersect_nonempty() {
         assert_eq!(
@@ -146,5 +222,88 @@ mod tests2 {
             cov.intersect(i, &Interval::EMPTY);
         }
         assert!(cov.is_full());
+        assert_eq!(1.0, cov.filled());
+        cov.clear();
+        assert_eq!(0.0, cov.filled());
+        cov.intersect(0, &Interval::new(0, 16));
+        assert_eq!(16.0 / 1024.0, cov.filled());
+    }
+
+    #[test]
+    fn new_empty() {
+        assert_eq!(Interval::EMPTY, Interval::new(3, 3));
+        assert_eq!(Interval::EMPTY, Interval::new(4, -4));
+        assert_eq!(
+            Interval::EMPTY,
+            Interval::new(0, 2).intersect(&Interval::new(2, 4))
+        );
+    }
+
+    #[test]
+    fn visited() {
+        let mut visited = Visited::default();
+        assert!(!visited.contains(70, &Interval::new(0, 4)));
+        visited.insert(70, &Interval::new(0, 4));
+        visited.insert(70, &Interval::new(8, 16));
+        assert!(visited.contains(70, &Interval::new(0, 4)));
+        assert!(visited.contains(70, &Interval::new(8, 16)));
+        assert!(!visited.contains(70, &Interval::new(0, 16)));
+        assert!(!visited.contains(6, &Interval::new(0, 4)));
+        visited.clear();
+        assert!(!visited.contains(70, &Interval::new(0, 4)));
+        // intervals from previous frames are forgotten.
+        visited.insert(70, &Interval::new(8, 16));
+        assert!(!visited.contains(70, &Interval::new(0, 4)));
+    }
+
+    fn interval() -> impl Strategy<Value = Interval> {
+        (-64..64, -64..64).prop_map(|(l, r)| Interval::new(l, r))
+    }
+
+    proptest! {
+        #[test]
+        fn intersect_commutative(a in interval(), b in interval()) {
+            prop_assert_eq!(a.intersect(&b), b.intersect(&a));
+        }
+
+        #[test]
+        fn intersect_associative(a in interval(), b in interval(), c in interval()) {
+            prop_assert_eq!(a.intersect(&b).intersect(&c), a.intersect(&b.intersect(&c)));
+        }
+
+        #[test]
+        fn intersect_points(a in interval(), b in interval(), point in -80..80) {
+            let int = a.intersect(&b);
+            prop_assert_eq!(int.contains(point), a.contains(point) && b.contains(point));
+            prop_assert_eq!(a, a.intersect(&a));
+        }
+
+        #[test]
+        fn empty(l in -64..64, r in -64..64, point in -80..80) {
+            let int = Interval::new(l, r);
+            prop_assert_eq!(int.is_empty(), int == Interval::EMPTY);
+            prop_assert_eq!(int.is_empty(), int.iter().next().is_none());
+            prop_assert!(!Interval::EMPTY.contains(point));
+            prop_assert_eq!(Interval::EMPTY, int.intersect(&Interval::EMPTY));
+        }
+
+        // columns only ever shrink, and full columns stay full.
+        #[test]
+        fn coverage_monotonic(ops in prop::collection::vec((0..8usize, interval()), 0..64)) {
+            let mut cov = Coverage::new(8, 32);
+            for (column, int) in ops {
+                let (before, filled, full) = (*cov.column(column), cov.filled(), cov.full_columns());
+                let result = cov.intersect(column, &int);
+                let after = *cov.column(column);
+                prop_assert_eq!(after, result);
+                prop_assert_eq!(after, before.intersect(&int));
+                prop_assert_eq!(after, after.intersect(&before));
+                prop_assert!(cov.filled() >= filled);
+                prop_assert!(cov.full_columns() >= full);
+                let empty = (0..8).filter(|c| cov.column(*c).is_empty()).count();
+                prop_assert_eq!(empty, cov.full_columns());
+                prop_assert_eq!(empty == 8, cov.is_full());
+            }
+        }
     }
 }
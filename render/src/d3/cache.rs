@@ -0,0 +1,186 @@
+//! Static geometry precomputed from a MAP.
+//!
+//! The [`Renderer`](super::Renderer) converts wall vertices and sector
+//! heights from the integer MAP data on every frame. A
+//! [`RenderCache`](RenderCache) holds them already converted, and is kept up
+//! to date with the edits made through a
+//! [`TrackedMap`](map::tracked::TrackedMap):
+//!
+//! ```
+//! use map::{tracked::TrackedMap, Map};
+//! use render::{camera::Camera, d3, frame};
+//!
+//! let map = Map::from_slice(include_bytes!("../../../map/tests/maps/E1L1.MAP")).unwrap();
+//! let mut tracked = TrackedMap::new(map);
+//! let cache = d3::cache::RenderCache::new(tracked.map());
+//! tracked.subscribe(cache.observer());
+//!
+//! let mut renderer = d3::Renderer::new();
+//! renderer.set_cache(Some(cache));
+//! let sector = tracked.map().player.sector;
+//! tracked.modify_sector(sector, |s| s.ceiling_z -= 1024);
+//!
+//! let mut frame = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+//! let camera = Camera::from(&tracked.map().player);
+//! renderer.render(tracked.map(), &camera, &mut frame);
+//! ```
+use map::{
+    analysis,
+    sector::{Plane, SectorId, WallId},
+    tracked::Event,
+    Map,
+};
+use std::{cell::RefCell, convert::TryFrom, rc::Rc};
+
+/// Geometry of a MAP, precomputed for rendering.
+#[derive(Debug)]
+pub struct RenderCache {
+    walls: Vec<[f64; 2]>,
+    heights: Vec<[f64; 2]>,
+    planes: Vec<[Plane; 2]>,
+    adjacency: Vec<Vec<SectorId>>,
+    // events received by observers, applied on the next refresh.
+    pending: Rc<RefCell<Vec<Event>>>,
+}
+
+impl RenderCache {
+    pub fn new(map: &Map) -> Self {
+        let mut cache = Self {
+            walls: Vec::new(),
+            heights: Vec::new(),
+            planes: Vec::new(),
+            adjacency: Vec::new(),
+            pending: Rc::default(),
+        };
+        cache.rebuild(map);
+        cache
+    }
+
+    /// Position of the left vertex of a wall.
+    pub fn wall(&self, id: WallId) -> Option<[f64; 2]> {
+        self.walls.get(usize::try_from(id).ok()?).copied()
+    }
+
+    /// Ceiling & floor Z of a sector (ignoring slopes).
+    pub fn heights(&self, id: SectorId) -> Option<[f64; 2]> {
+        self.heights.get(usize::try_from(id).ok()?).copied()
+    }
+
+    /// Ceiling & floor planes of a sector (see
+    /// [`Sector::floor_plane`](map::sector::Sector::floor_plane)).
+    pub fn planes(&self, id: SectorId) -> Option<[Plane; 2]> {
+        self.planes.get(usize::try_from(id).ok()?).copied()
+    }
+
+    /// Sectors connected to a sector through portals (see
+    /// [`analysis::adjacency`](analysis::adjacency)).
+    pub fn adjacency(&self, id: SectorId) -> &[SectorId] {
+        usize::try_from(id)
+            .ok()
+            .and_then(|id| self.adjacency.get(id))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns an observer (see
+    /// [`TrackedMap::subscribe`](map::tracked::TrackedMap::subscribe)) that
+    /// invalidates the parts of the cache affected by each edit. They are
+    /// recomputed by [`refresh`](RenderCache::refresh), which the renderer
+    /// calls before rendering every frame.
+    pub fn observer(&self) -> impl FnMut(&Event) + 'static {
+        let pending = Rc::clone(&self.pending);
+        move |event| pending.borrow_mut().push(*event)
+    }
+
+    /// Invalidates the parts of the cache affected by an edit.
+    pub fn invalidate(&mut self, event: &Event) {
+        self.pending.borrow_mut().push(*event);
+    }
+
+    /// Recomputes the invalidated parts of the cache. The whole cache is
+    /// rebuilt if the number of sectors or walls of the MAP changed.
+    pub fn refresh(&mut self, map: &Map) {
+        let pending = std::mem::take(&mut *self.pending.borrow_mut());
+        let sectors = map.sectors.sectors().len();
+        if self.walls.len() != map.sectors.walls().len() || self.heights.len() != sectors {
+            return self.rebuild(map);
+        }
+        for event in pending {
+            match event {
+                Event::SectorChanged(id) => self.update_sector(map, id),
+                // portals may have been added or removed.
+                Event::WallChanged(_) => self.adjacency = analysis::adjacency(map),
+                Event::WallMoved { wall, x, y } => {
+                    if let Some(w) = self.walls.get_mut(wall as usize) {
+                        *w = [f64::from(x), f64::from(y)];
+                    }
+                    // slopes are relative to the first wall of the sector.
+                    if let Some(sector) = map.sectors.sector_of_wall(wall) {
+                        self.update_sector(map, sector);
+                    }
+                }
+                Event::MapChanged => return self.rebuild(map),
+                Event::SpriteAdded(_)
+                | Event::SpriteChanged(_)
+                | Event::SpriteRemoved(_)
+                | Event::PlayerChanged => {}
+            }
+        }
+    }
+
+    fn rebuild(&mut self, map: &Map) {
+        self.walls = map
+            .sectors
+            .walls()
+            .iter()
+            .map(|w| [f64::from(w.x), f64::from(w.y)])
+            .collect();
+        let sectors = map.sectors.sectors().len();
+        self.heights = vec![[0.0; 2]; sectors];
+        self.planes = Vec::with_capacity(sectors);
+        for id in 0..sectors {
+            let sector = &map.sectors.sectors()[id];
+            self.heights[id] = [f64::from(sector.ceiling_z), f64::from(sector.floor_z)];
+            self.planes.push([
+                sector.ceiling_plane(&map.sectors),
+                sector.floor_plane(&map.sectors),
+            ]);
+        }
+        self.adjacency = analysis::adjacency(map);
+    }
+
+    fn update_sector(&mut self, map: &Map, id: SectorId) {
+        let sector = match map.sectors.sectors().get(id as usize) {
+            Some(sector) if id >= 0 => sector,
+            _ => return,
+        };
+        let id = id as usize;
+        self.heights[id] = [f64::from(sector.ceiling_z), f64::from(sector.floor_z)];
+        self.planes[id] = [
+            sector.ceiling_plane(&map.sectors),
+            sector.floor_plane(&map.sectors),
+        ];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RenderCache;
+    use map::{tracked::TrackedMap, Map};
+
+    #[test]
+    fn tracked_edits() {
+        let map = Map::from_slice(include_bytes!("../../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut tracked = TrackedMap::new(map);
+        let mut cache = RenderCache::new(tracked.map());
+        tracked.subscribe(cache.observer());
+        let sector = tracked.map().player.sector;
+        tracked.modify_sector(sector, |s| s.floor_z += 1024);
+        tracked.move_wall(0, 123, 456);
+        cache.refresh(tracked.map());
+        let fresh = RenderCache::new(tracked.map());
+        assert_eq!(fresh.heights(sector), cache.heights(sector));
+        assert_eq!(Some([123.0, 456.0]), cache.wall(0));
+        assert_eq!(fresh.walls, cache.walls);
+        assert_eq!(fresh.adjacency, cache.adjacency);
+    }
+}
@@ -0,0 +1,280 @@
+//! Color schemes of the untextured renderer.
+//!
+//! Surfaces are painted with a single color each. Besides debug colors,
+//! [`PaletteColors`](PaletteColors) (`art` feature) takes them from the game
+//! palette, applying palette swaps and shade tables.
+use map::{
+    sector::{Sector, Wall},
+    sprite::Sprite,
+};
+use std::fmt::Debug;
+
+/// Colors used to paint walls, floors & ceilings when rendering without
+/// textures.
+pub trait ColorScheme: Debug {
+    /// Color of a solid wall.
+    fn wall(&self, sector: &Sector, wall: &Wall) -> u32;
+
+    fn ceiling(&self, sector: &Sector) -> u32;
+    fn floor(&self, sector: &Sector) -> u32;
+
+    /// Color of the wall above a portal.
+    fn portal_top(&self, sector: &Sector, wall: &Wall) -> u32 {
+        self.wall(sector, wall)
+    }
+
+    /// Color of the wall below a portal.
+    fn portal_bottom(&self, sector: &Sector, wall: &Wall) -> u32 {
+        self.wall(sector, wall)
+    }
+
+    /// Color of a face sprite. Defaults to the color of its picnum (see
+    /// [`picnum_color`](picnum_color)), darkened by its shade.
+    fn sprite(&self, sprite: &Sprite) -> u32 {
+        shade(picnum_color(sprite.picnum), sprite.shade)
+    }
+}
+
+/// Same color for every wall, floor & ceiling.
+#[derive(Debug, Clone, Copy)]
+pub struct FlatColors {
+    pub wall: u32,
+    pub ceiling: u32,
+    pub floor: u32,
+    pub portal_top: u32,
+    pub portal_bottom: u32,
+}
+
+impl Default for FlatColors {
+    fn default() -> Self {
+        Self {
+            wall: 0x888888,
+            ceiling: 0x444444,
+            floor: 0x2222ff,
+            portal_top: 0x666666,
+            portal_bottom: 0xaa33aa,
+        }
+    }
+}
+
+impl ColorScheme for FlatColors {
+    fn wall(&self, _: &Sector, _: &Wall) -> u32 {
+        self.wall
+    }
+
+    fn ceiling(&self, _: &Sector) -> u32 {
+        self.ceiling
+    }
+
+    fn floor(&self, _: &Sector) -> u32 {
+        self.floor
+    }
+
+    fn portal_top(&self, _: &Sector, _: &Wall) -> u32 {
+        self.portal_top
+    }
+
+    fn portal_bottom(&self, _: &Sector, _: &Wall) -> u32 {
+        self.portal_bottom
+    }
+}
+
+/// Colors derived from the picnum of each surface, darkened by its shade, so
+/// different textures can be told apart.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PicnumColors;
+
+impl ColorScheme for PicnumColors {
+    fn wall(&self, _: &Sector, wall: &Wall) -> u32 {
+        shade(picnum_color(wall.picnum), wall.shade)
+    }
+
+    fn ceiling(&self, sector: &Sector) -> u32 {
+        shade(picnum_color(sector.ceiling_picnum), sector.ceiling_shade)
+    }
+
+    fn floor(&self, sector: &Sector) -> u32 {
+        shade(picnum_color(sector.floor_picnum), sector.floor_shade)
+    }
+}
+
+/// Colors computed in palette space, as the original engine does: each
+/// surface is painted with the most common color of its tile, remapped by its
+/// palette swap (`pal`) and shade table, then converted to RGB.
+#[cfg(feature = "art")]
+#[derive(Debug, Clone)]
+pub struct PaletteColors {
+    palette: art::palette::Palette,
+    lookups: art::palette::Lookups,
+    colors: [u32; 256],
+
+    // most common palette index of each tile, by picnum.
+    indices: Vec<u8>,
+}
+
+#[cfg(feature = "art")]
+impl PaletteColors {
+    /// Takes the colors of the indexed tiles of `tiles` (`PALETTE.DAT`
+    /// tables, without palette swaps).
+    pub fn new<T: crate::tile::TileSource>(palette: art::palette::Palette, tiles: &T) -> Self {
+        let indices = (0..=i16::MAX)
+            .map(|picnum| tiles.tile(picnum).map_or(0, |tile| dominant_index(&tile)))
+            .collect();
+        let mut colors = [0; 256];
+        for (color, [r, g, b]) in colors.iter_mut().zip(palette.colors()) {
+            *color = u32::from(*r) << 16 | u32::from(*g) << 8 | u32::from(*b);
+        }
+        Self {
+            palette,
+            lookups: Default::default(),
+            colors,
+            indices,
+        }
+    }
+
+    /// Sets the palette swap tables (`LOOKUP.DAT`).
+    pub fn set_lookups(&mut self, lookups: art::palette::Lookups) {
+        self.lookups = lookups;
+    }
+
+    /// Palette index of a surface, before converting it to RGB.
+    pub fn index(&self, picnum: i16, pal: u8, shade: i8) -> u8 {
+        let index = self.indices.get(picnum as usize).copied().unwrap_or(0);
+        let index = self.lookups.swap(pal, index);
+        self.palette.shade(index, i32::from(shade))
+    }
+
+    fn color(&self, picnum: i16, pal: u8, shade: i8) -> u32 {
+        self.colors[usize::from(self.index(picnum, pal, shade))]
+    }
+}
+
+// most common opaque (not 255) palette index of an indexed tile.
+#[cfg(feature = "art")]
+fn dominant_index(tile: &crate::tile::TileRef<'_>) -> u8 {
+    let pixels = match tile.pixels {
+        crate::tile::Pixels::Indexed(pixels) => pixels,
+        crate::tile::Pixels::Rgba(_) => return 0,
+    };
+    let mut counts = [0u32; 256];
+    pixels.iter().for_each(|i| counts[usize::from(*i)] += 1);
+    counts[255] = 0;
+    (0..=255u8)
+        .max_by_key(|i| (counts[usize::from(*i)], core::cmp::Reverse(*i)))
+        .unwrap_or(0)
+}
+
+#[cfg(feature = "art")]
+impl ColorScheme for PaletteColors {
+    fn wall(&self, _: &Sector, wall: &Wall) -> u32 {
+        self.color(wall.picnum, wall.pal, wall.shade)
+    }
+
+    fn ceiling(&self, sector: &Sector) -> u32 {
+        self.color(
+            sector.ceiling_picnum,
+            sector.ceiling_pal,
+            sector.ceiling_shade,
+        )
+    }
+
+    fn floor(&self, sector: &Sector) -> u32 {
+        self.color(sector.floor_picnum, sector.floor_pal, sector.floor_shade)
+    }
+
+    fn sprite(&self, sprite: &Sprite) -> u32 {
+        self.color(sprite.picnum, sprite.pal, sprite.shade)
+    }
+}
+
+/// Returns a deterministic color (`0xRRGGBB`) for the given picnum.
+pub fn picnum_color(picnum: i16) -> u32 {
+    // integer hash (lowbias32) so that consecutive picnums get unrelated
+    // colors.
+    let mut h = picnum as u16 as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    // keep channels away from black so shading remains visible.
+    let channel = |c: u32| 0x40 + (c & 0xff) * 0xbf / 0xff;
+    channel(h >> 16) << 16 | channel(h >> 8) << 8 | channel(h)
+}
+
+/// Darkens a color by the given Build shade, linearly from fully lit (0) to
+/// 1/32 of the color (31, the darkest). Shades outside `0..=31` are clamped.
+pub fn shade(color: u32, shade: i8) -> u32 {
+    let light = 32 - i32::from(shade).clamp(0, 31) as u32;
+    let channel = |c: u32| (c & 0xff) * light / 32;
+    channel(color >> 16) << 16 | channel(color >> 8) << 8 | channel(color)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{picnum_color, shade};
+    #[cfg(feature = "art")]
+    use super::{ColorScheme, PaletteColors};
+
+    #[test]
+    fn picnum_colors() {
+        assert_eq!(picnum_color(123), picnum_color(123));
+        assert_ne!(picnum_color(0), picnum_color(1));
+        assert_ne!(picnum_color(1), picnum_color(2));
+    }
+
+    #[test]
+    fn shading() {
+        assert_eq!(0x804020, shade(0x804020, 0));
+        assert_eq!(0x804020, shade(0x804020, -8));
+        assert_eq!(0x402010, shade(0x804020, 16));
+        // the darkest shade isn't black
+        assert_eq!(0x040201, shade(0x804020, 31));
+        assert_eq!(0x040201, shade(0x804020, 127));
+    }
+
+    #[cfg(feature = "art")]
+    #[test]
+    fn palette_colors() {
+        use crate::tile::{Pixels, TileRef, TileSource};
+        use art::palette::{Lookups, Palette};
+
+        // single 2x2 tile (picnum 0), mostly palette index 1.
+        struct Tiles;
+        impl TileSource for Tiles {
+            fn tile(&self, picnum: i16) -> Option<TileRef<'_>> {
+                Some(TileRef {
+                    width: 2,
+                    height: 2,
+                    pixels: Pixels::Indexed(&[1, 1, 2, 255]),
+                    scale: [1.0, 1.0],
+                })
+                .filter(|_| picnum == 0)
+            }
+        }
+
+        // index i is rgb (i, i, i) (6-bit), shade 1 darkens by one index.
+        let mut data: Vec<u8> = (0..768).map(|i| (i / 3) as u8 & 63).collect();
+        data.extend_from_slice(&[2, 0]);
+        data.extend(0..=255);
+        data.extend((0..=255).map(|i: u8| i.saturating_sub(1)));
+        let palette = Palette::from_slice(&data).unwrap();
+        let mut colors = PaletteColors::new(palette, &Tiles);
+        assert_eq!(1, colors.index(0, 0, 0));
+        assert_eq!(0, colors.index(0, 0, 1));
+
+        // pal 3 maps index 1 to 2.
+        let mut lookups = vec![1, 3];
+        lookups.extend((0..=255).map(|i: u8| i.saturating_add(1)));
+        colors.set_lookups(Lookups::from_slice(&lookups).unwrap());
+        assert_eq!(2, colors.index(0, 3, 0));
+        assert_eq!(1, colors.index(0, 3, 1));
+
+        let map = map::Map::from_slice(include_bytes!("../../../map/tests/maps/SIMPLE0.MAP"));
+        let mut sector = map.unwrap().sectors.sectors()[0].clone();
+        sector.floor_picnum = 0;
+        sector.floor_pal = 3;
+        sector.floor_shade = 0;
+        assert_eq!(0x080808, colors.floor(&sector));
+    }
+}
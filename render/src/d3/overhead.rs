@@ -0,0 +1,86 @@
+//! Top-down orthographic projection.
+//!
+//! Floors are painted in order of increasing height, so upper floors cover
+//! the ones below them, and darkened the lower they are. Solid walls are
+//! outlined on top.
+use super::{color, PickResult, Renderer, Surface};
+use crate::{
+    camera::Camera,
+    frame,
+    frame::Frame,
+    raster::{draw_line, fill_triangle},
+};
+use map::{sector::SectorId, triangulate::triangulate, Map};
+
+// shade of the lowest floor of the MAP (the highest one is fully lit).
+const MAX_HEIGHT_SHADE: f64 = 24.0;
+
+pub(super) fn render(
+    renderer: &mut Renderer,
+    map: &Map,
+    camera: &Camera,
+    units_per_pixel: f32,
+    frame: &mut Frame,
+) {
+    let scale = f64::from(units_per_pixel.max(f32::EPSILON));
+    let project = |x: i32, y: i32| {
+        [
+            f64::from(x - camera.x) / scale + frame::WIDTH as f64 / 2.0,
+            f64::from(y - camera.y) / scale + frame::HEIGHT as f64 / 2.0,
+        ]
+    };
+    let sectors = map.sectors.sectors();
+    let walls = map.sectors.walls();
+    let lowest = sectors.iter().map(|s| s.floor_z).max().unwrap_or(0);
+    let highest = sectors.iter().map(|s| s.floor_z).min().unwrap_or(0);
+    let range = f64::from((lowest - highest).max(1));
+
+    // Z points down, so the lowest floors come first.
+    let mut order: Vec<SectorId> = (0..sectors.len() as SectorId).collect();
+    order.sort_by_key(|id| std::cmp::Reverse(sectors[*id as usize].floor_z));
+    for id in order {
+        let sector = &sectors[id as usize];
+        let lit = renderer.lighting.sector(id, sector);
+        let depth = f64::from(sector.floor_z - highest) / range;
+        let color = color::shade(
+            renderer.colors.floor(&lit),
+            (depth * MAX_HEIGHT_SHADE) as i8,
+        );
+        let pick = PickResult {
+            sector: id,
+            surface: Surface::Floor,
+        };
+        for triangle in triangulate(map, id) {
+            let [a, b, c] = triangle.map(|w| {
+                let wall = &walls[w as usize];
+                project(wall.x, wall.y)
+            });
+            fill_triangle([a, b, c], |x, y| {
+                frame[y][x] = color;
+                renderer.picks[y * frame::WIDTH + x] = Some(pick);
+            });
+        }
+    }
+
+    for (id, sector) in sectors.iter().enumerate() {
+        let sector_walls = match map.sectors.get(id as _) {
+            Some((_, walls)) => walls,
+            None => continue,
+        };
+        for (wall_id, left, right) in sector_walls.filter(|(_, w, _)| w.next_sector == -1) {
+            let lit = renderer.lighting.wall(id as _, wall_id, left);
+            let color = renderer.colors.wall(sector, &lit);
+            draw_line(
+                project(left.x, left.y),
+                project(right.x, right.y),
+                |x, y| {
+                    frame[y][x] = color;
+                    renderer.picks[y * frame::WIDTH + x] = Some(PickResult {
+                        sector: id as _,
+                        surface: Surface::Wall(wall_id),
+                    });
+                },
+            );
+        }
+    }
+}
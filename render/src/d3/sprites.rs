@@ -0,0 +1,249 @@
+//! Face sprites, drawn as billboards once all the walls are rendered.
+//!
+//! As in Build, sprites are drawn last and back to front. Each one is clipped
+//! to the pixels its sector was seen through when the sector was rendered
+//! (i.e. the [`Coverage`](super::algo::Coverage) at that time), so sprites
+//! behind walls don't bleed through them. Animated tiles (see
+//! [`Renderer::set_animation`](super::Renderer::set_animation)) are resolved
+//! before picking the color of each sprite.
+//!
+//! Sprites with a voxel model (see `Renderer::set_voxels`, `art` feature)
+//! are drawn as one small billboard per voxel, sorted along with the rest.
+use super::{
+    algo::{Coverage, Interval},
+    to_fixed, to_pixel, Animation, PickResult, Renderer, Surface, Target,
+};
+use crate::frame;
+use map::{
+    sector::SectorId,
+    sprite::{SpriteId, SpriteStat},
+    Map,
+};
+use nalgebra_glm as glm;
+use std::ops::Range;
+
+/// Size (in pixels) of the sprite tiles. Tiles aren't known to the
+/// renderer, so every sprite is sized as if it used a tile this big.
+pub const SPRITE_TILE_SIZE: i32 = 32;
+
+/// Coverage of each rendered sector, as it was right before the sector was
+/// rendered.
+#[derive(Debug, Default)]
+pub(super) struct Snapshots {
+    // sector, first column & range into `columns`
+    sectors: Vec<(SectorId, i32, Range<usize>)>,
+    columns: Vec<Interval>,
+}
+
+impl Snapshots {
+    pub fn clear(&mut self) {
+        self.sectors.clear();
+        self.columns.clear();
+    }
+
+    /// Reserves room for the given number of sectors, without their columns
+    /// (how many columns each sector covers depends on the camera).
+    pub fn reserve(&mut self, sectors: usize) {
+        self.sectors.reserve(sectors);
+    }
+
+    pub fn push(&mut self, sector: SectorId, interval: &Interval, coverage: &Coverage) {
+        let start = self.columns.len();
+        let columns = interval.left().max(0)..interval.right().min(coverage.width() as i32);
+        self.columns
+            .extend(columns.clone().map(|x| *coverage.column(x as usize)));
+        self.sectors
+            .push((sector, columns.start, start..self.columns.len()));
+    }
+}
+
+/// Buffers reused across frames, so rendering sprites doesn't allocate once
+/// they are large enough.
+#[derive(Debug, Default)]
+pub(super) struct Buffers {
+    sectors: Vec<SectorId>,
+    billboards: Vec<Billboard>,
+}
+
+impl Buffers {
+    pub fn reserve(&mut self, map: &Map) {
+        let (sectors, sprites) = (map.sectors.sectors().len(), map.sprites.len());
+        self.sectors.reserve(sectors);
+        self.billboards.reserve(sprites);
+    }
+}
+
+// sprite on screen, in fixed-point pixels
+#[derive(Debug)]
+struct Billboard {
+    id: SpriteId,
+    depth: f64,
+    left: i64,
+    right: i64,
+    top: i64,
+    bottom: i64,
+    color: u32,
+}
+
+// billboard of the given width (XY units), from the top to the bottom Z,
+// unless it crosses the near plane.
+fn project(
+    renderer: &Renderer,
+    id: SpriteId,
+    [x, y]: [f64; 2],
+    width: f64,
+    [top, bottom]: [f64; 2],
+    color: u32,
+) -> Option<Billboard> {
+    let (width_px, height_px) = (renderer.view.width as f64, renderer.view.height as f64);
+    let b = renderer.camera * glm::vec4(x, y, bottom, 1.0);
+    let t = renderer.camera * glm::vec4(x, y, top, 1.0);
+    if b.y < renderer.near_plane() {
+        return None;
+    }
+    let center = (b.x / b.y + 1.0) / 2.0 * width_px;
+    let half_width = width / 2.0 / renderer.scale_x / b.y / 2.0 * width_px;
+    let row = |v: &glm::DVec4| (v.z / v.y + 1.0) / 2.0 * height_px;
+    Some(Billboard {
+        id,
+        depth: b.y,
+        left: to_fixed(center - half_width),
+        right: to_fixed(center + half_width),
+        top: to_fixed(row(&t)),
+        bottom: to_fixed(row(&b)),
+        color,
+    })
+}
+
+// one billboard per voxel of the model, turned to the angle of the sprite.
+// Voxels are as big as the texels of a billboard (`x_repeat / 4` XY units
+// wide, `y_repeat * 4` Z units tall). The XY pivot of the model stands on the
+// position of the sprite, and its bottom on the Z of the sprite (like
+// billboards).
+#[cfg(feature = "art")]
+fn voxels(
+    renderer: &Renderer,
+    id: SpriteId,
+    sprite: &map::sprite::Sprite,
+    model: &art::kvx::Kvx,
+    billboards: &mut Vec<Billboard>,
+) {
+    let size = f64::from(sprite.x_repeat) / 4.0;
+    let size_z = f64::from(sprite.y_repeat) * 4.0;
+    let height = f64::from(model.size[2]) * size_z;
+    let bottom = if sprite
+        .sprite_stat
+        .contains(SpriteStat::REAL_CENTERED_CENTERING)
+    {
+        f64::from(sprite.z) + height / 2.0
+    } else {
+        f64::from(sprite.z)
+    };
+    let [cos, sin] = sprite.angle.direction();
+    let (cos, sin) = (f64::from(cos), f64::from(sin));
+    let palette = model.palette();
+    for x in 0..model.size[0] {
+        for y in 0..model.size[1] {
+            let dx = (f64::from(x) + 0.5 - f64::from(model.pivot[0])) * size;
+            let dy = (f64::from(y) + 0.5 - f64::from(model.pivot[1])) * size;
+            let position = [
+                f64::from(sprite.x) + dx * cos - dy * sin,
+                f64::from(sprite.y) + dx * sin + dy * cos,
+            ];
+            for slab in model.column(x, y).unwrap_or(&[]) {
+                for (z, index) in (u32::from(slab.z_top)..).zip(&slab.colors) {
+                    let top = bottom - height + f64::from(z) * size_z;
+                    let [r, g, b] = palette[usize::from(*index)];
+                    let color = u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b);
+                    let color = super::color::shade(color, sprite.shade);
+                    let z = [top, top + size_z];
+                    billboards.extend(project(renderer, id, position, size, z, color));
+                }
+            }
+        }
+    }
+}
+
+pub(super) fn render(renderer: &mut Renderer, map: &Map, frame: &mut impl Target) {
+    let Buffers {
+        mut sectors,
+        mut billboards,
+    } = std::mem::take(&mut renderer.sprite_buffers);
+    sectors.clear();
+    sectors.extend(renderer.snapshots.sectors.iter().map(|s| s.0));
+    sectors.sort_unstable();
+    sectors.dedup();
+    let height_px = renderer.view.height as f64;
+    billboards.clear();
+    let visible = map
+        .sprites
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| sectors.binary_search(&s.sectnum).is_ok());
+    for (id, sprite) in visible {
+        // face sprites only
+        let stat = sprite.sprite_stat;
+        if stat.contains(SpriteStat::INVISIBLE) || stat.intersects(SpriteStat::RESERVED_SPRITE_TYPE)
+        {
+            continue;
+        }
+        let picnum = match &renderer.animation {
+            Some(Animation(tiles)) => tiles.animate(sprite.picnum, renderer.clock),
+            None => sprite.picnum,
+        };
+        #[cfg(feature = "art")]
+        if let Some(model) = renderer.voxels.as_ref().and_then(|v| v.get(picnum)) {
+            voxels(renderer, id as SpriteId, sprite, model, &mut billboards);
+            continue;
+        }
+        let width = f64::from(SPRITE_TILE_SIZE * i32::from(sprite.x_repeat) / 4);
+        let height = SPRITE_TILE_SIZE * i32::from(sprite.y_repeat) * 4;
+        let bottom = if stat.contains(SpriteStat::REAL_CENTERED_CENTERING) {
+            sprite.z + height / 2
+        } else {
+            sprite.z
+        };
+        let color = if picnum == sprite.picnum {
+            renderer.colors.sprite(sprite)
+        } else {
+            let mut animated = sprite.clone();
+            animated.picnum = picnum;
+            renderer.colors.sprite(&animated)
+        };
+        let position = [f64::from(sprite.x), f64::from(sprite.y)];
+        let z = [f64::from(bottom - height), f64::from(bottom)];
+        billboards.extend(project(renderer, id as SpriteId, position, width, z, color));
+    }
+    // back to front
+    billboards.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+
+    for billboard in billboards.drain(..) {
+        let sector = map.sprites[billboard.id as usize].sectnum;
+        let pick = PickResult {
+            sector,
+            surface: Surface::Sprite(billboard.id),
+        };
+        let columns = to_pixel(billboard.left)..to_pixel(billboard.right);
+        let rows = Interval::new(
+            to_pixel(billboard.top).clamp(0, height_px as i64) as i32,
+            to_pixel(billboard.bottom).clamp(0, height_px as i64) as i32,
+        );
+        let (view, snapshots) = (renderer.view, &renderer.snapshots);
+        for (_, first, range) in snapshots.sectors.iter().filter(|s| s.0 == sector) {
+            let first = i64::from(*first);
+            let end = first + range.len() as i64;
+            for x in columns.start.max(first)..columns.end.min(end) {
+                let window = &snapshots.columns[range.start + (x - first) as usize];
+                for y in window.intersect(&rows).iter() {
+                    let (x, y) = (x as usize, y as usize);
+                    frame.set(x, y, billboard.color);
+                    renderer.picks[(view.y + y) * frame::WIDTH + view.x + x] = Some(pick);
+                }
+            }
+        }
+    }
+    renderer.sprite_buffers = Buffers {
+        sectors,
+        billboards,
+    };
+}
@@ -0,0 +1,111 @@
+//! Side-by-side stereo rendering.
+//!
+//! Each eye is rendered with its own camera, offset sideways from the
+//! original one by half the interpupillary distance (IPD), into one half of a
+//! double-width frame: the left eye on the left half, as expected by phone VR
+//! viewers.
+use super::{RenderStats, Renderer};
+use crate::{
+    camera::Camera,
+    frame::{self, Frame},
+};
+use map::Map;
+
+/// Default interpupillary distance, in map XY units.
+pub const DEFAULT_IPD: i32 = 64;
+
+/// Side-by-side frame, holding the left & right eye views.
+pub type StereoFrame = [[u32; frame::WIDTH * 2]; frame::HEIGHT];
+
+/// Cameras of the left & right eyes, `ipd` map units apart. Each eye keeps
+/// the sector of the camera if it's still inside of it.
+pub fn eyes(map: &Map, camera: &Camera, ipd: i32) -> [Camera; 2] {
+    let [cos, sin] = camera.angle.direction();
+    // same as a sideways movement of the controller.
+    let half = ipd as f32 / 2.0;
+    let eye = |side: f32| {
+        let x = camera.x + (-sin * half * side).round() as i32;
+        let y = camera.y + (cos * half * side).round() as i32;
+        let sector = if map.sectors.inside(camera.sector, x, y) {
+            camera.sector
+        } else {
+            map.sectors.sector_at(x, y).unwrap_or(camera.sector)
+        };
+        Camera {
+            x,
+            y,
+            sector,
+            ..*camera
+        }
+    };
+    [eye(-1.0), eye(1.0)]
+}
+
+impl Renderer {
+    /// Renders the left & right eye views (see [`eyes`](eyes)) side by side.
+    /// Returns the statistics of each eye. Uncovered pixels keep the
+    /// contents of the frame.
+    pub fn render_stereo(
+        &mut self,
+        map: &Map,
+        camera: &Camera,
+        ipd: i32,
+        frame: &mut StereoFrame,
+    ) -> [RenderStats; 2] {
+        let mut eye_frame: Box<Frame> = self
+            .eye_frame
+            .take()
+            .unwrap_or_else(|| Box::new([[0; frame::WIDTH]; frame::HEIGHT]));
+        let mut stats = [RenderStats::default(); 2];
+        for (i, eye) in eyes(map, camera, ipd).iter().enumerate() {
+            let columns = i * frame::WIDTH..(i + 1) * frame::WIDTH;
+            for (src, dst) in frame.iter().zip(eye_frame.iter_mut()) {
+                dst.copy_from_slice(&src[columns.clone()]);
+            }
+            stats[i] = self.render(map, eye, &mut eye_frame);
+            for (src, dst) in eye_frame.iter().zip(frame.iter_mut()) {
+                dst[columns.clone()].copy_from_slice(src);
+            }
+        }
+        self.eye_frame = Some(eye_frame);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{eyes, StereoFrame};
+    use crate::{
+        camera::Camera,
+        d3::Renderer,
+        frame::{self, Frame},
+    };
+    use map::Map;
+
+    #[test]
+    fn zero_ipd() {
+        let map = Map::from_slice(include_bytes!("../../../map/tests/maps/E1L1.MAP")).unwrap();
+        let camera = Camera::from(&map.player);
+        assert_eq!([camera; 2], eyes(&map, &camera, 0));
+        let mut mono: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut stereo: Box<StereoFrame> = Box::new([[0; frame::WIDTH * 2]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        renderer.render(&map, &camera, &mut mono);
+        renderer.render_stereo(&map, &camera, 0, &mut stereo);
+        for (mono, stereo) in mono.iter().zip(stereo.iter()) {
+            assert_eq!(&mono[..], &stereo[..frame::WIDTH]);
+            assert_eq!(&mono[..], &stereo[frame::WIDTH..]);
+        }
+    }
+
+    #[test]
+    fn eye_separation() {
+        let map = Map::from_slice(include_bytes!("../../../map/tests/maps/E1L1.MAP")).unwrap();
+        let camera = Camera::from(&map.player);
+        let [left, right] = eyes(&map, &camera, 64);
+        let distance = f64::from(left.x - right.x).hypot(f64::from(left.y - right.y));
+        assert!((distance - 64.0).abs() <= 2.0);
+        assert_eq!(camera.angle, left.angle);
+        assert_eq!(camera.z, right.z);
+    }
+}
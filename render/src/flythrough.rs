@@ -0,0 +1,209 @@
+//! Keyframed camera paths, for cinematic previews of MAPs.
+//!
+//! Cameras are interpolated between keyframes with (uniform) Catmull-Rom
+//! splines, so the path goes through every keyframe smoothly. Angles are
+//! interpolated along the shortest arc.
+//!
+//! ```
+//! # #[cfg(feature = "d3")] {
+//! use render::{camera::Camera, flythrough::{self, CameraPath}};
+//! use std::time::Duration;
+//!
+//! let map = map::Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+//! let start = Camera::from(&map.player);
+//! let mut path = CameraPath::new();
+//! path.push(Duration::from_secs(0), start);
+//! path.push(Duration::from_secs(1), Camera { x: start.x + 512, ..start });
+//!
+//! let mut renderer = render::d3::Renderer::new();
+//! let mut frames = 0;
+//! flythrough::render(&path, &map, &mut renderer, 10, |_, _frame| frames += 1);
+//! assert_eq!(11, frames);
+//! # }
+//! ```
+use crate::{
+    camera::Camera,
+    frame::{self, Frame},
+    Renderer,
+};
+use map::{player::Angle, Map};
+use std::time::Duration;
+
+/// Camera at a point in time.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Keyframe {
+    pub time: Duration,
+    pub camera: Camera,
+}
+
+/// Sequence of keyframes, sorted by time.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a keyframe, replacing the one at the same time (if any).
+    pub fn push(&mut self, time: Duration, camera: Camera) {
+        let keyframe = Keyframe { time, camera };
+        match self.keyframes.binary_search_by_key(&time, |k| k.time) {
+            Ok(i) => self.keyframes[i] = keyframe,
+            Err(i) => self.keyframes.insert(i, keyframe),
+        }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> Duration {
+        self.keyframes.last().map_or(Duration::ZERO, |k| k.time)
+    }
+
+    /// Camera at the given time, or `None` if the path is empty. The camera
+    /// stays at the first (last) keyframe before (after) the path. Its
+    /// sector is looked up in the MAP, as the interpolated position may be
+    /// outside of the sectors of the surrounding keyframes.
+    pub fn camera_at(&self, map: &Map, time: Duration) -> Option<Camera> {
+        let keyframes = &self.keyframes;
+        let next = keyframes.partition_point(|k| k.time <= time);
+        if next == 0 || next == keyframes.len() {
+            let keyframe = keyframes.get(next.saturating_sub(1))?;
+            return Some(keyframe.camera);
+        }
+        let (i1, i2) = (next - 1, next);
+        let (k1, k2) = (&keyframes[i1], &keyframes[i2]);
+        let k0 = &keyframes[i1.saturating_sub(1)];
+        let k3 = &keyframes[(i2 + 1).min(keyframes.len() - 1)];
+        let t = (time - k1.time).as_secs_f64() / (k2.time - k1.time).as_secs_f64();
+        let spline = |f: &dyn Fn(&Camera) -> f64| {
+            catmull_rom(
+                [f(&k0.camera), f(&k1.camera), f(&k2.camera), f(&k3.camera)],
+                t,
+            )
+        };
+        let x = spline(&|c| f64::from(c.x)).round() as i32;
+        let y = spline(&|c| f64::from(c.y)).round() as i32;
+        let z = spline(&|c| f64::from(c.z)).round() as i32;
+        // unwrap the angles around k1, so they are interpolated along the
+        // shortest arcs.
+        let revolution = f64::from(Angle::REVOLUTION);
+        let unwrap = |from: f64, to: Angle| {
+            let mut delta = f64::from((to - Angle(from.rem_euclid(revolution) as i16)).0);
+            if delta >= revolution / 2.0 {
+                delta -= revolution;
+            }
+            from + delta
+        };
+        let a1 = f64::from(k1.camera.angle.normalized().0);
+        let a0 = unwrap(a1, k0.camera.angle);
+        let a2 = unwrap(a1, k2.camera.angle);
+        let a3 = unwrap(a2, k3.camera.angle);
+        let angle = catmull_rom([a0, a1, a2, a3], t).round() as i64;
+        let sector = [k1.camera.sector, k2.camera.sector]
+            .iter()
+            .copied()
+            .find(|s| map.sectors.inside(*s, x, y))
+            .or_else(|| map.sectors.sector_at(x, y))
+            .unwrap_or(if t < 0.5 {
+                k1.camera.sector
+            } else {
+                k2.camera.sector
+            });
+        Some(Camera {
+            x,
+            y,
+            z,
+            angle: Angle(angle.rem_euclid(i64::from(Angle::REVOLUTION)) as i16),
+            sector,
+        })
+    }
+}
+
+/// Renders the path at `fps` frames per second, from the first keyframe to
+/// the last one (both included), calling `f` with the index and contents of
+/// every frame. Frames are cleared (to black) before rendering.
+pub fn render<R, F>(path: &CameraPath, map: &Map, renderer: &mut R, fps: u32, mut f: F)
+where
+    R: Renderer + ?Sized,
+    F: FnMut(usize, &Frame),
+{
+    let start = match path.keyframes().first() {
+        Some(keyframe) => keyframe.time,
+        None => return,
+    };
+    let fps = fps.max(1);
+    let length = path.duration() - start;
+    let count = (length.as_secs_f64() * f64::from(fps)).floor() as usize + 1;
+    let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+    for index in 0..count {
+        let time = start + Duration::from_secs_f64(index as f64 / f64::from(fps));
+        let camera = path.camera_at(map, time).unwrap();
+        *frame = [[0; frame::WIDTH]; frame::HEIGHT];
+        renderer.render(map, &camera, &mut frame);
+        f(index, &frame);
+    }
+}
+
+// uniform Catmull-Rom spline through p1 (t = 0) and p2 (t = 1).
+fn catmull_rom([p0, p1, p2, p3]: [f64; 4], t: f64) -> f64 {
+    let (t2, t3) = (t * t, t * t * t);
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+#[cfg(test)]
+mod test {
+    use super::CameraPath;
+    use crate::camera::Camera;
+    use map::{player::Angle, Map};
+    use std::time::Duration;
+
+    #[test]
+    fn interpolate() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        let start = Camera::from(&map.player);
+        let mut path = CameraPath::new();
+        assert_eq!(None, path.camera_at(&map, Duration::ZERO));
+        let end = Camera {
+            x: start.x + 1000,
+            angle: start.angle + Angle(100),
+            ..start
+        };
+        path.push(Duration::from_secs(2), end);
+        path.push(Duration::from_secs(1), start);
+        assert_eq!(Some(start), path.camera_at(&map, Duration::ZERO));
+        assert_eq!(Some(start), path.camera_at(&map, Duration::from_secs(1)));
+        assert_eq!(Some(end), path.camera_at(&map, Duration::from_secs(2)));
+        assert_eq!(Some(end), path.camera_at(&map, Duration::from_secs(3)));
+        let mid = path.camera_at(&map, Duration::from_millis(1500)).unwrap();
+        assert_eq!(start.x + 500, mid.x);
+        assert_eq!(start.angle + Angle(50), mid.angle);
+    }
+
+    // angles wrap around along the shortest arc.
+    #[test]
+    fn wrap_angle() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        let start = Camera {
+            angle: Angle(2000),
+            ..Camera::from(&map.player)
+        };
+        let mut path = CameraPath::new();
+        path.push(Duration::ZERO, start);
+        let end = Camera {
+            angle: Angle(100),
+            ..start
+        };
+        path.push(Duration::from_secs(1), end);
+        let mid = path.camera_at(&map, Duration::from_millis(500)).unwrap();
+        assert_eq!(Angle(26), mid.angle);
+    }
+}
@@ -0,0 +1,224 @@
+//! Hightile (PNG) texture packs.
+//!
+//! Packs such as the eduke32 HRP replace 8-bit ART tiles with true-color
+//! textures, usually of a higher resolution than the tiles they replace.
+//! Textures are assigned to tiles either by file name (a directory of
+//! `<picnum>.png` files) or by the `texture` & `tilefromtexture` commands of a
+//! DEF script.
+use crate::tile::{Pixels, TileRef, TileSource};
+use art::def::Definitions;
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+/// True-color texture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+
+    /// `0xAARRGGBB` colors, in row-major order.
+    pub pixels: Vec<u32>,
+
+    /// Texture scale relative to the tile it replaces.
+    pub scale: [f32; 2],
+
+    /// Drawing offset of the tile center.
+    pub offset: [i32; 2],
+}
+
+impl Texture {
+    /// Decode a PNG image.
+    pub fn from_reader<R: Read>(reader: R) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let buf = &buf[..info.buffer_size()];
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buf
+                .chunks_exact(4)
+                .map(|p| argb(p[0], p[1], p[2], p[3]))
+                .collect(),
+            png::ColorType::Rgb => buf
+                .chunks_exact(3)
+                .map(|p| argb(p[0], p[1], p[2], 0xff))
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buf
+                .chunks_exact(2)
+                .map(|p| argb(p[0], p[0], p[0], p[1]))
+                .collect(),
+            png::ColorType::Grayscale => buf.iter().map(|l| argb(*l, *l, *l, 0xff)).collect(),
+            png::ColorType::Indexed => {
+                let message = "Unexpected indexed PNG output.";
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        };
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+            scale: [1.0, 1.0],
+            offset: [0, 0],
+        })
+    }
+
+    /// Decode a PNG image from the native filesystem.
+    pub fn from_file<P: AsRef<Path>>(file: P) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(file)?))
+    }
+
+    /// Make pixels with an alpha below the given threshold (in the `[0, 1]`
+    /// range) fully transparent, and the rest fully opaque.
+    pub fn alpha_cut(&mut self, cut: f32) {
+        let cut = (cut.clamp(0.0, 1.0) * 255.0) as u32;
+        for pixel in &mut self.pixels {
+            if *pixel >> 24 < cut {
+                *pixel = 0;
+            } else {
+                *pixel |= 0xff000000;
+            }
+        }
+    }
+}
+
+fn argb(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    u32::from_be_bytes([a, r, g, b])
+}
+
+/// Hightile texture pack, indexed by picnum.
+#[derive(Debug, Default, Clone)]
+pub struct Hightile {
+    textures: BTreeMap<i16, Texture>,
+}
+
+impl Hightile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every PNG file of a directory whose name ends in a picnum (e.g.
+    /// `0123.png` or `tile0123.png`). Other files are ignored.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut pack = Self::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_png = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+            let picnum = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| {
+                    let digits = stem.trim_start_matches(|c: char| !c.is_ascii_digit());
+                    digits.parse().ok()
+                });
+            if let (true, Some(picnum)) = (is_png, picnum) {
+                pack.insert(picnum, Texture::from_file(&path)?);
+            }
+        }
+        Ok(pack)
+    }
+
+    /// Load the base palette textures defined in a DEF script. Texture files
+    /// are resolved relative to `dir` (usually the directory of the script).
+    pub fn from_definitions<P: AsRef<Path>>(defs: &Definitions, dir: P) -> io::Result<Self> {
+        let mut pack = Self::new();
+        for ((picnum, _), def) in defs.textures().filter(|((_, pal), _)| *pal == 0) {
+            let mut texture = Texture::from_file(dir.as_ref().join(&def.file))?;
+            if let Some(cut) = def.alpha_cut {
+                texture.alpha_cut(cut);
+            }
+            texture.scale = def.scale;
+            texture.offset = def.offset;
+            pack.insert(picnum, texture);
+        }
+        Ok(pack)
+    }
+
+    /// Assign a texture to a tile, returning the previous one (if any).
+    pub fn insert(&mut self, picnum: i16, texture: Texture) -> Option<Texture> {
+        self.textures.insert(picnum, texture)
+    }
+
+    /// Returns the texture of a tile.
+    pub fn get(&self, picnum: i16) -> Option<&Texture> {
+        self.textures.get(&picnum)
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+}
+
+impl TileSource for Hightile {
+    fn tile(&self, picnum: i16) -> Option<TileRef<'_>> {
+        self.get(picnum).map(|texture| TileRef {
+            width: texture.width,
+            height: texture.height,
+            pixels: Pixels::Rgba(&texture.pixels),
+            scale: texture.scale,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Hightile, Texture};
+    use crate::tile::{Texel, TileSource};
+    use art::def::Definitions;
+
+    // 2x1 RGBA image: opaque red, half-transparent blue.
+    fn png() -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, 2, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&[0xff, 0, 0, 0xff, 0, 0, 0xff, 0x40])
+            .unwrap();
+        writer.finish().unwrap();
+        data
+    }
+
+    #[test]
+    fn decode() {
+        let mut texture = Texture::from_reader(&png()[..]).unwrap();
+        assert_eq!([2, 1], [texture.width, texture.height]);
+        assert_eq!(vec![0xffff0000, 0x400000ff], texture.pixels);
+        texture.alpha_cut(0.5);
+        assert_eq!(vec![0xffff0000, 0], texture.pixels);
+    }
+
+    #[test]
+    fn from_definitions() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir(dir.join("textures")).unwrap();
+        std::fs::write(dir.join("textures/tile0042.png"), png()).unwrap();
+
+        let defs: Definitions = r#"
+            texture 42 { pal 0 { file "textures/tile0042.png" xscale 2 yscale 4 } }
+        "#
+        .parse()
+        .unwrap();
+        let pack = Hightile::from_definitions(&defs, dir).unwrap();
+        let tile = pack.tile(42).unwrap();
+        assert_eq!([2.0, 4.0], tile.scale);
+        assert_eq!(Some(Texel::Rgba(0xffff0000)), tile.texel(0, 0));
+
+        let pack = Hightile::from_dir(dir.join("textures")).unwrap();
+        assert_eq!(1, pack.len());
+        assert!(pack.tile(42).is_some());
+    }
+}
@@ -0,0 +1,238 @@
+//! Isometric "cutaway" renderer.
+//!
+//! Sectors are drawn as solid volumes: each floor is extruded down to the
+//! lowest floor of the MAP, and ceilings are left out so the inside of every
+//! room is visible. Volumes are sorted back to front (painter's algorithm).
+use crate::{
+    camera::Camera,
+    d3::color::{self, ColorScheme, PicnumColors},
+    frame::{self, Frame},
+    lighting::Lighting,
+    raster::{draw_line, fill_triangle},
+};
+use map::{
+    player::Angle,
+    sector::{SectorId, Wall},
+    triangulate::triangulate,
+    units, Map,
+};
+
+// shade of the lowest floor of the MAP (the highest one is fully lit).
+const MAX_HEIGHT_SHADE: f64 = 16.0;
+
+/// Isometric MAP renderer.
+///
+/// The view is centered on the camera, looking 45 degrees to the right of
+/// the camera angle, from above.
+#[derive(Debug)]
+pub struct Renderer {
+    /// Map XY units per frame pixel.
+    pub units_per_pixel: f32,
+
+    /// Draw the outline of the walls.
+    pub outline: bool,
+
+    /// Shade offsets applied on top of the MAP shades.
+    pub lighting: Lighting,
+    colors: Box<dyn ColorScheme>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// View space of the renderer.
+struct View {
+    origin: [f64; 3],
+    forward: [f64; 2],
+    right: [f64; 2],
+    scale: f64,
+}
+
+impl View {
+    fn new(camera: &Camera, units_per_pixel: f32) -> Self {
+        let [fx, fy] = (camera.angle + Angle(256)).direction();
+        let (fx, fy) = (f64::from(fx), f64::from(fy));
+        Self {
+            origin: [
+                f64::from(camera.x),
+                f64::from(camera.y),
+                f64::from(units::z_to_xy(camera.z)),
+            ],
+            forward: [fx, fy],
+            right: [-fy, fx],
+            scale: f64::from(units_per_pixel.max(f32::EPSILON)),
+        }
+    }
+
+    /// Distance along the view direction (larger is farther).
+    fn depth(&self, x: i32, y: i32) -> f64 {
+        let [fx, fy] = self.forward;
+        (f64::from(x) - self.origin[0]) * fx + (f64::from(y) - self.origin[1]) * fy
+    }
+
+    /// Projects a point (Z in Z units) to frame coordinates.
+    fn project(&self, x: i32, y: i32, z: i32) -> [f64; 2] {
+        let dx = f64::from(x) - self.origin[0];
+        let dy = f64::from(y) - self.origin[1];
+        let [rx, ry] = self.right;
+        let right = dx * rx + dy * ry;
+        let height = self.origin[2] - f64::from(units::z_to_xy(z));
+        [
+            right / self.scale + frame::WIDTH as f64 / 2.0,
+            (-self.depth(x, y) / 2.0 - height) / self.scale + frame::HEIGHT as f64 / 2.0,
+        ]
+    }
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self {
+            units_per_pixel: 32.0,
+            outline: true,
+            lighting: Lighting::new(),
+            colors: Box::new(PicnumColors),
+        }
+    }
+
+    /// Sets the colors used to paint the geometry. Defaults to
+    /// [`PicnumColors`](crate::d3::color::PicnumColors).
+    pub fn set_color_scheme<C: ColorScheme + 'static>(&mut self, colors: C) {
+        self.colors = Box::new(colors);
+    }
+
+    fn render_sector(
+        &self,
+        map: &Map,
+        view: &View,
+        id: SectorId,
+        base: [i32; 2],
+        frame: &mut Frame,
+    ) {
+        let (sector, walls) = match map.sectors.get(id) {
+            Some(sector) => sector,
+            None => return,
+        };
+        let lit = self.lighting.sector(id, sector);
+        let [lowest, highest] = base;
+        let sectors = map.sectors.sectors();
+
+        // visible sides: solid walls down to the base of the MAP, and steps
+        // down to lower neighbor floors.
+        let mut sides: Vec<_> = walls
+            .filter_map(|(wall_id, left, right)| {
+                let bottom = match sectors.get(left.next_sector as usize) {
+                    _ if left.next_sector < 0 => lowest,
+                    Some(next) if next.floor_z > sector.floor_z => next.floor_z,
+                    _ => return None,
+                };
+                let [nx, ny] = left.normal(right);
+                let [fx, fy] = view.forward;
+                // the outside of the wall must face the viewer
+                if nx * fx + ny * fy <= 0.0 {
+                    return None;
+                }
+                let depth = view.depth(left.x, left.y).min(view.depth(right.x, right.y));
+                Some((depth, wall_id, left, right, bottom))
+            })
+            .collect();
+        sides.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (_, wall_id, left, right, bottom) in sides {
+            let wall = self.lighting.wall(id, wall_id, left);
+            let [nx, ny] = left.normal(right);
+            let [rx, ry] = view.right;
+            let facing = nx * rx + ny * ry;
+            let color = color::shade(self.colors.wall(&lit, &wall), (6.0 + 6.0 * facing) as i8);
+            let tl = view.project(left.x, left.y, sector.floor_z);
+            let tr = view.project(right.x, right.y, sector.floor_z);
+            let bl = view.project(left.x, left.y, bottom);
+            let br = view.project(right.x, right.y, bottom);
+            let mut plot = |x: usize, y: usize| frame[y][x] = color;
+            fill_triangle([tl, tr, br], &mut plot);
+            fill_triangle([tl, br, bl], &mut plot);
+            if self.outline {
+                self.outline(left, tl, bl, frame);
+            }
+        }
+
+        let range = f64::from((lowest - highest).max(1));
+        let depth = f64::from(sector.floor_z - highest) / range;
+        let color = color::shade(self.colors.floor(&lit), (depth * MAX_HEIGHT_SHADE) as i8);
+        let walls = map.sectors.walls();
+        for triangle in triangulate(map, id) {
+            let points = triangle.map(|w| {
+                let wall = &walls[w as usize];
+                view.project(wall.x, wall.y, sector.floor_z)
+            });
+            fill_triangle(points, |x, y| frame[y][x] = color);
+        }
+        if self.outline {
+            if let Some((_, walls)) = map.sectors.get(id) {
+                for (_, left, right) in walls.filter(|(_, w, _)| w.next_sector == -1) {
+                    let a = view.project(left.x, left.y, sector.floor_z);
+                    let b = view.project(right.x, right.y, sector.floor_z);
+                    self.outline(left, a, b, frame);
+                }
+            }
+        }
+    }
+
+    fn outline(&self, wall: &Wall, a: [f64; 2], b: [f64; 2], frame: &mut Frame) {
+        let color = color::shade(color::picnum_color(wall.picnum), 24);
+        draw_line(a, b, |x, y| frame[y][x] = color);
+    }
+}
+
+impl crate::Renderer for Renderer {
+    fn render(&mut self, map: &Map, camera: &Camera, frame: &mut Frame) {
+        let view = View::new(camera, self.units_per_pixel);
+        let sectors = map.sectors.sectors();
+        let lowest = sectors.iter().map(|s| s.floor_z).max().unwrap_or(0);
+        let highest = sectors.iter().map(|s| s.floor_z).min().unwrap_or(0);
+
+        // back to front, by the nearest wall of each sector.
+        let mut order: Vec<(f64, SectorId)> = (0..sectors.len() as SectorId)
+            .filter_map(|id| {
+                let (_, walls) = map.sectors.get(id)?;
+                let depth = walls
+                    .map(|(_, w, _)| view.depth(w.x, w.y))
+                    .fold(f64::INFINITY, f64::min);
+                Some((depth, id))
+            })
+            .collect();
+        order.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (_, id) in order {
+            self.render_sector(map, &view, id, [lowest, highest], frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Renderer;
+    use crate::{
+        camera::Camera,
+        frame::{self, Frame},
+        Renderer as _,
+    };
+    use map::Map;
+
+    #[test]
+    fn render() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/DX-LIBRARY.MAP")).unwrap();
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut renderer = Renderer::new();
+        let camera = Camera::from(&map.player);
+        renderer.render(&map, &camera, &mut frame);
+        let painted = frame.iter().flatten().filter(|p| **p != 0).count();
+        assert!(painted > frame::WIDTH * frame::HEIGHT / 4);
+
+        // same view, different zoom
+        let mut zoomed: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        renderer.units_per_pixel *= 2.0;
+        renderer.render(&map, &camera, &mut zoomed);
+        assert_ne!(frame, zoomed);
+    }
+}
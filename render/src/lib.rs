@@ -1,9 +1,23 @@
+pub use render_core::{camera, frame, Renderer};
+
+#[cfg(any(feature = "d2", feature = "d3"))]
+pub mod clip;
+pub mod compositor;
 #[cfg(feature = "controller")]
 pub mod controller;
 #[cfg(feature = "d2")]
 pub mod d2;
 #[cfg(feature = "d3")]
 pub mod d3;
-pub mod frame;
-#[cfg(any(feature = "d2", feature = "d3"))]
-mod util;
+pub mod flythrough;
+#[cfg(feature = "hightile")]
+pub mod hightile;
+#[cfg(feature = "iso")]
+pub mod iso;
+pub mod lighting;
+#[cfg(feature = "overlay")]
+pub mod overlay;
+#[cfg(any(feature = "d3", feature = "iso"))]
+mod raster;
+pub mod sky;
+pub mod tile;
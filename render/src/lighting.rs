@@ -0,0 +1,128 @@
+//! Dynamic lighting.
+//!
+//! Shade offsets are applied at render time, on top of the shades stored in
+//! the MAP, so effects such as flickering or pulsating lights (or previewing
+//! lighting changes in an editor) don't need to modify the MAP itself.
+use map::sector::{Sector, SectorId, Wall, WallId};
+use std::{borrow::Cow, collections::HashMap};
+
+/// Per-sector & per-wall shade offsets.
+///
+/// Offsets follow Build shade semantics: positive values darken and negative
+/// values brighten. Sector offsets apply to the floor, ceiling and walls of
+/// the sector.
+///
+/// ```
+/// use render::lighting::Lighting;
+///
+/// let mut lighting = Lighting::new();
+/// lighting.set_sector_shade_offset(3, 8);
+/// assert_eq!(8, lighting.sector_shade_offset(3));
+/// assert_eq!(0, lighting.sector_shade_offset(4));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Lighting {
+    sectors: HashMap<SectorId, i8>,
+    walls: HashMap<WallId, i8>,
+}
+
+impl Lighting {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the shade offset of a sector. An offset of 0 removes it.
+    pub fn set_sector_shade_offset(&mut self, id: SectorId, delta: i8) {
+        if delta == 0 {
+            self.sectors.remove(&id);
+        } else {
+            self.sectors.insert(id, delta);
+        }
+    }
+
+    /// Sets the shade offset of a wall. An offset of 0 removes it.
+    pub fn set_wall_shade_offset(&mut self, id: WallId, delta: i8) {
+        if delta == 0 {
+            self.walls.remove(&id);
+        } else {
+            self.walls.insert(id, delta);
+        }
+    }
+
+    pub fn sector_shade_offset(&self, id: SectorId) -> i8 {
+        self.sectors.get(&id).copied().unwrap_or(0)
+    }
+
+    pub fn wall_shade_offset(&self, id: WallId) -> i8 {
+        self.walls.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Removes all the offsets.
+    pub fn clear(&mut self) {
+        self.sectors.clear();
+        self.walls.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sectors.is_empty() && self.walls.is_empty()
+    }
+
+    /// Returns the sector with its floor & ceiling shade offset applied.
+    pub fn sector<'a>(&self, id: SectorId, sector: &'a Sector) -> Cow<'a, Sector> {
+        match self.sector_shade_offset(id) {
+            0 => Cow::Borrowed(sector),
+            delta => {
+                let mut sector = sector.clone();
+                sector.ceiling_shade = sector.ceiling_shade.saturating_add(delta);
+                sector.floor_shade = sector.floor_shade.saturating_add(delta);
+                Cow::Owned(sector)
+            }
+        }
+    }
+
+    /// Returns the wall (of the given sector) with its shade offset applied.
+    pub fn wall<'a>(&self, sector: SectorId, id: WallId, wall: &'a Wall) -> Cow<'a, Wall> {
+        let delta = self
+            .sector_shade_offset(sector)
+            .saturating_add(self.wall_shade_offset(id));
+        match delta {
+            0 => Cow::Borrowed(wall),
+            delta => {
+                let mut wall = wall.clone();
+                wall.shade = wall.shade.saturating_add(delta);
+                Cow::Owned(wall)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Lighting;
+    use map::Map;
+
+    #[test]
+    fn apply() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let mut sector = map.sectors.sectors()[0].clone();
+        let mut wall = map.sectors.walls()[0].clone();
+        sector.floor_shade = 0;
+        sector.ceiling_shade = 16;
+        wall.shade = 8;
+        let mut lighting = Lighting::new();
+        lighting.set_sector_shade_offset(0, 4);
+        lighting.set_wall_shade_offset(0, -10);
+
+        let lit = lighting.sector(0, &sector);
+        assert_eq!(4, lit.floor_shade);
+        assert_eq!(20, lit.ceiling_shade);
+        assert_eq!(2, lighting.wall(0, 0, &wall).shade);
+        // other sectors are unaffected
+        assert_eq!(16, lighting.sector(1, &sector).ceiling_shade);
+        assert_eq!(-2, lighting.wall(1, 0, &wall).shade);
+
+        lighting.set_sector_shade_offset(0, 0);
+        lighting.set_wall_shade_offset(0, 0);
+        assert!(lighting.is_empty());
+    }
+}
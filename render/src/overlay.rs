@@ -0,0 +1,129 @@
+//! Drawing of text & shapes on top of rendered frames (HUDs, FPS counters,
+//! debug information...).
+//!
+//! Independent of the renderers, so it can be used with any of them (or none).
+//! Colors are given in the frame format (`0xRRGGBB`).
+//!
+//! ```
+//! use render::{frame, overlay::Canvas};
+//!
+//! let mut frame = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+//! let mut canvas = Canvas::new(&mut frame);
+//! canvas.text("60 FPS", [2, 2], 0xffffff);
+//! canvas.crosshair([160, 120], 4, 0x00ff00);
+//! ```
+use crate::frame::{Frame, HEIGHT, WIDTH};
+use embedded_graphics::{
+    fonts::{Font6x6, Text},
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{Circle, Line, Rectangle},
+    style::{PrimitiveStyle, TextStyle},
+};
+
+/// Width & height of a character of text, in pixels. Lines are separated by
+/// `\n`.
+pub const CHAR_SIZE: [i32; 2] = [6, 6];
+
+/// Draws on a frame. Pixels out of the frame are discarded.
+pub struct Canvas<'a>(&'a mut Frame);
+
+impl<'a> Canvas<'a> {
+    pub fn new(frame: &'a mut Frame) -> Self {
+        Self(frame)
+    }
+
+    /// Draws text with its top-left corner at `position`.
+    pub fn text(&mut self, text: &str, position: [i32; 2], color: u32) {
+        Text::new(text, point(position))
+            .into_styled(TextStyle::new(Font6x6, rgb(color)))
+            .draw(self)
+            .unwrap();
+    }
+
+    /// Draws a line `width` pixels wide.
+    pub fn line(&mut self, from: [i32; 2], to: [i32; 2], color: u32, width: u32) {
+        Line::new(point(from), point(to))
+            .into_styled(PrimitiveStyle::with_stroke(rgb(color), width))
+            .draw(self)
+            .unwrap();
+    }
+
+    /// Draws a rectangle, given two opposite corners (inclusive), either
+    /// filled or outlined.
+    pub fn rect(&mut self, from: [i32; 2], to: [i32; 2], color: u32, fill: bool) {
+        Rectangle::new(point(from), point(to))
+            .into_styled(style(color, fill))
+            .draw(self)
+            .unwrap();
+    }
+
+    /// Draws a circle, either filled or outlined.
+    pub fn circle(&mut self, center: [i32; 2], radius: u32, color: u32, fill: bool) {
+        Circle::new(point(center), radius)
+            .into_styled(style(color, fill))
+            .draw(self)
+            .unwrap();
+    }
+
+    /// Draws a cross centered at `center`, with arms `size` pixels long.
+    pub fn crosshair(&mut self, [x, y]: [i32; 2], size: i32, color: u32) {
+        self.line([x - size, y], [x + size, y], color, 1);
+        self.line([x, y - size], [x, y + size], color, 1);
+    }
+}
+
+impl DrawTarget<Rgb888> for Canvas<'_> {
+    type Error = std::convert::Infallible;
+
+    fn draw_pixel(&mut self, Pixel(point, color): Pixel<Rgb888>) -> Result<(), Self::Error> {
+        if point.x >= 0 && point.x < (WIDTH as i32) && point.y >= 0 && point.y < (HEIGHT as i32) {
+            self.0[point.y as usize][point.x as usize] =
+                (color.r() as u32) << 16 | (color.g() as u32) << 8 | (color.b() as u32);
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> Size {
+        Size::new(WIDTH as _, HEIGHT as _)
+    }
+}
+
+fn point([x, y]: [i32; 2]) -> Point {
+    Point::new(x, y)
+}
+
+fn rgb(color: u32) -> Rgb888 {
+    Rgb888::new((color >> 16) as u8, (color >> 8) as u8, color as u8)
+}
+
+fn style(color: u32, fill: bool) -> PrimitiveStyle<Rgb888> {
+    if fill {
+        PrimitiveStyle::with_fill(rgb(color))
+    } else {
+        PrimitiveStyle::with_stroke(rgb(color), 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Canvas;
+    use crate::frame::{self, Frame};
+
+    #[test]
+    fn shapes() {
+        let mut frame: Box<Frame> = Box::new([[0; frame::WIDTH]; frame::HEIGHT]);
+        let mut canvas = Canvas::new(&mut frame);
+        canvas.rect([0, 0], [3, 3], 0x123456, true);
+        canvas.crosshair([100, 100], 2, 0xff0000);
+        canvas.line([-10, -10], [-1, -1], 0xffffff, 1);
+        canvas.text("A", [200, 200], 0x00ff00);
+        assert_eq!(0x123456, frame[3][3]);
+        assert_eq!(0, frame[4][4]);
+        assert_eq!(0xff0000, frame[100][98]);
+        assert_eq!(0xff0000, frame[102][100]);
+        assert!(frame[200..206]
+            .iter()
+            .any(|row| row[200..206].contains(&0x00ff00)));
+    }
+}
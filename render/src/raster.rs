@@ -0,0 +1,67 @@
+//! Rasterization of screen-space primitives.
+use crate::frame;
+
+/// Calls `plot` for the frame pixels whose centers are inside the triangle.
+pub(crate) fn fill_triangle<F: FnMut(usize, usize)>([a, b, c]: [[f64; 2]; 3], mut plot: F) {
+    let edge = |p: [f64; 2], q: [f64; 2], x: f64, y: f64| {
+        (q[0] - p[0]) * (y - p[1]) - (q[1] - p[1]) * (x - p[0])
+    };
+    let area = edge(a, b, c[0], c[1]);
+    if area == 0.0 {
+        return;
+    }
+    let min_x = a[0].min(b[0]).min(c[0]).floor().max(0.0) as usize;
+    let min_y = a[1].min(b[1]).min(c[1]).floor().max(0.0) as usize;
+    let max_x = (a[0].max(b[0]).max(c[0]).ceil().max(0.0) as usize).min(frame::WIDTH);
+    let max_y = (a[1].max(b[1]).max(c[1]).ceil().max(0.0) as usize).min(frame::HEIGHT);
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+            let w = [edge(b, c, px, py), edge(c, a, px, py), edge(a, b, px, py)];
+            if w.iter().all(|w| w * area >= 0.0) {
+                plot(x, y);
+            }
+        }
+    }
+}
+
+pub(crate) fn draw_line<F: FnMut(usize, usize)>(a: [f64; 2], b: [f64; 2], mut plot: F) {
+    let steps = (b[0] - a[0]).abs().max((b[1] - a[1]).abs()).ceil().max(1.0);
+    if !steps.is_finite() || steps > 4.0 * frame::WIDTH.max(frame::HEIGHT) as f64 {
+        // way off screen (or zoomed in too much): clip with a coarser step.
+        return draw_line_clipped(a, b, plot);
+    }
+    for i in 0..=steps as usize {
+        let t = i as f64 / steps;
+        let x = a[0] + (b[0] - a[0]) * t;
+        let y = a[1] + (b[1] - a[1]) * t;
+        if x >= 0.0 && y >= 0.0 && (x as usize) < frame::WIDTH && (y as usize) < frame::HEIGHT {
+            plot(x as usize, y as usize);
+        }
+    }
+}
+
+// Draws the part of a long line that crosses the frame.
+fn draw_line_clipped<F: FnMut(usize, usize)>(a: [f64; 2], b: [f64; 2], plot: F) {
+    let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let bounds = [frame::WIDTH as f64, frame::HEIGHT as f64];
+    for axis in 0..2 {
+        if d[axis] == 0.0 {
+            if a[axis] < 0.0 || a[axis] >= bounds[axis] {
+                return;
+            }
+            continue;
+        }
+        let (mut lo, mut hi) = (-a[axis] / d[axis], (bounds[axis] - a[axis]) / d[axis]);
+        if lo > hi {
+            std::mem::swap(&mut lo, &mut hi);
+        }
+        t0 = t0.max(lo);
+        t1 = t1.min(hi);
+    }
+    if t0 < t1 {
+        let at = |t: f64| [a[0] + d[0] * t, a[1] + d[1] * t];
+        draw_line(at(t0), at(t1), plot);
+    }
+}
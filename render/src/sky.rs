@@ -0,0 +1,91 @@
+//! Parallax skies.
+//!
+//! Build skies are made of several tiles laid side by side around the
+//! horizon. A full revolution is split into `2^bits` segments (`pskybits` in
+//! the original engine), each one covered by a tile whose picnum is given
+//! relative to the base tile of the sky (`pskyoff`).
+
+/// Number of angle units in a full revolution.
+const REVOLUTION: f64 = 2048.0;
+
+/// Multi-tile parallax sky configuration.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Sky {
+    /// Base tile of the sky.
+    pub picnum: i16,
+
+    /// Tile of each segment, relative to `picnum`. If there are fewer offsets
+    /// than segments, they are repeated horizontally.
+    pub offsets: Vec<i16>,
+
+    /// The sky is split into `2^bits` segments across a full revolution.
+    pub bits: u8,
+}
+
+impl Sky {
+    /// Sky made of a single tile, repeated `2^bits` times across a full
+    /// revolution.
+    pub fn new(picnum: i16, bits: u8) -> Self {
+        Self {
+            picnum,
+            offsets: vec![0],
+            bits,
+        }
+    }
+
+    /// Sky made of a list of tiles, spread evenly across a full revolution.
+    /// The number of tiles is rounded up to a power of two.
+    pub fn tiled(picnum: i16, offsets: Vec<i16>) -> Self {
+        let bits = offsets.len().max(1).next_power_of_two().trailing_zeros() as u8;
+        Self {
+            picnum,
+            offsets,
+            bits,
+        }
+    }
+
+    /// Number of segments across a full revolution.
+    pub fn segments(&self) -> u32 {
+        1 << u32::from(self.bits.min(15))
+    }
+
+    /// Returns the tile seen at the given yaw (in Build angle units, 2048
+    /// per revolution), along with the horizontal texture coordinate within
+    /// that tile, in the `[0, 1)` range.
+    pub fn sample(&self, yaw: f64) -> (i16, f64) {
+        let segments = f64::from(self.segments());
+        let s = yaw.rem_euclid(REVOLUTION) / REVOLUTION * segments;
+        let segment = (s as usize).min(segments as usize - 1);
+        let offset = match self.offsets.len() {
+            0 => 0,
+            len => self.offsets[segment % len],
+        };
+        (self.picnum.wrapping_add(offset), s.fract())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Sky;
+
+    #[test]
+    fn sample() {
+        let sky = Sky::tiled(80, vec![0, 1, 2]);
+        assert_eq!(4, sky.segments());
+        assert_eq!((80, 0.0), sky.sample(0.0));
+        assert_eq!((81, 0.5), sky.sample(768.0));
+        assert_eq!((82, 0.0), sky.sample(1024.0));
+        // offsets repeat
+        assert_eq!((80, 0.0), sky.sample(1536.0));
+        // wrap around
+        assert_eq!((80, 0.5), sky.sample(-1792.0));
+        assert_eq!((81, 0.0), sky.sample(2048.0 + 512.0));
+    }
+
+    #[test]
+    fn single_tile() {
+        let sky = Sky::new(100, 3);
+        assert_eq!(8, sky.segments());
+        assert_eq!((100, 0.25), sky.sample(256.0 + 64.0));
+    }
+}
@@ -0,0 +1,148 @@
+//! Tile (texture) sources.
+//!
+//! Renderers fetch tiles by picnum through the [`TileSource`](TileSource)
+//! trait, so textures can come from ART files (`art` feature), hightile
+//! texture packs or be generated procedurally.
+
+/// Tile pixel data.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Pixels<'a> {
+    /// Palette indices, in column-major order (as stored in ART files).
+    Indexed(&'a [u8]),
+
+    /// `0xAARRGGBB` colors, in row-major order.
+    Rgba(&'a [u32]),
+}
+
+/// Single pixel of a tile.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Texel {
+    Indexed(u8),
+    Rgba(u32),
+}
+
+/// Borrowed tile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileRef<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Pixels<'a>,
+
+    /// Scale of the pixels relative to the tile they replace (`[1.0, 1.0]`
+    /// for ART tiles).
+    pub scale: [f32; 2],
+}
+
+impl TileRef<'_> {
+    /// Returns the pixel at the given tile coordinates.
+    pub fn texel(&self, x: u32, y: u32) -> Option<Texel> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        match self.pixels {
+            Pixels::Indexed(p) => p
+                .get((x * self.height + y) as usize)
+                .map(|i| Texel::Indexed(*i)),
+            Pixels::Rgba(p) => p
+                .get((y * self.width + x) as usize)
+                .map(|c| Texel::Rgba(*c)),
+        }
+    }
+}
+
+/// Source of tiles, indexed by picnum.
+pub trait TileSource {
+    /// Returns the tile with the given picnum, if it exists.
+    fn tile(&self, picnum: i16) -> Option<TileRef<'_>>;
+
+    /// Returns the picnum of the animation frame shown at the given clock
+    /// time (in Build clock ticks). Tiles aren't animated by default.
+    fn animate(&self, picnum: i16, _clock: u32) -> i16 {
+        picnum
+    }
+}
+
+impl<T: TileSource + ?Sized> TileSource for &T {
+    fn tile(&self, picnum: i16) -> Option<TileRef<'_>> {
+        (**self).tile(picnum)
+    }
+
+    fn animate(&self, picnum: i16, clock: u32) -> i16 {
+        (**self).animate(picnum, clock)
+    }
+}
+
+#[cfg(feature = "art")]
+impl TileSource for art::Art {
+    fn tile(&self, picnum: i16) -> Option<TileRef<'_>> {
+        self.get(picnum)
+            .filter(|tile| !tile.is_empty())
+            .map(|tile| TileRef {
+                width: u32::from(tile.width),
+                height: u32::from(tile.height),
+                pixels: Pixels::Indexed(tile.pixels()),
+                scale: [1.0, 1.0],
+            })
+    }
+
+    fn animate(&self, picnum: i16, clock: u32) -> i16 {
+        art::Art::animate(self, picnum, clock)
+    }
+}
+
+/// Procedural checkerboard tiles, useful to visualize texture mapping without
+/// any game assets.
+#[derive(Debug, Clone)]
+pub struct Checkerboard {
+    pixels: Vec<u32>,
+    size: u32,
+}
+
+impl Checkerboard {
+    /// Square tiles of `size` pixels, with cells of `cell` pixels.
+    pub fn new(size: u32, cell: u32, colors: [u32; 2]) -> Self {
+        let cell = cell.max(1);
+        let pixels = (0..size * size)
+            .map(|i| colors[(((i % size) / cell + (i / size) / cell) % 2) as usize])
+            .collect();
+        Self { pixels, size }
+    }
+}
+
+impl TileSource for Checkerboard {
+    fn tile(&self, _: i16) -> Option<TileRef<'_>> {
+        Some(TileRef {
+            width: self.size,
+            height: self.size,
+            pixels: Pixels::Rgba(&self.pixels),
+            scale: [1.0, 1.0],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Checkerboard, Pixels, Texel, TileRef, TileSource};
+
+    #[test]
+    fn texel() {
+        let tile = TileRef {
+            width: 2,
+            height: 3,
+            pixels: Pixels::Indexed(&[0, 1, 2, 3, 4, 5]),
+            scale: [1.0, 1.0],
+        };
+        assert_eq!(Some(Texel::Indexed(1)), tile.texel(0, 1));
+        assert_eq!(Some(Texel::Indexed(3)), tile.texel(1, 0));
+        assert_eq!(None, tile.texel(2, 0));
+    }
+
+    #[test]
+    fn checkerboard() {
+        let source = Checkerboard::new(4, 2, [0xff000000, 0xffffffff]);
+        let tile = source.tile(123).unwrap();
+        assert_eq!(Some(Texel::Rgba(0xff000000)), tile.texel(1, 1));
+        assert_eq!(Some(Texel::Rgba(0xffffffff)), tile.texel(2, 1));
+        assert_eq!(Some(Texel::Rgba(0xff000000)), tile.texel(3, 3));
+    }
+}
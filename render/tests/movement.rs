@@ -0,0 +1,78 @@
+use map::Map;
+use render::controller::{script::Script, Input, InputController};
+
+// Moves & turns, then undoes every movement. Velocities ramp up and down
+// symmetrically, so the player ends up where it started (unless something
+// clips the movement).
+fn round_trip() -> Script {
+    Script::new()
+        .hold(Input::FORWARDS | Input::RIGHT, 20)
+        .wait(40)
+        .hold(Input::LOOK_RIGHT, 30)
+        .wait(20)
+        .hold(Input::LOOK_LEFT, 30)
+        .wait(20)
+        .hold(Input::BACKWARDS | Input::LEFT, 20)
+        .wait(40)
+}
+
+// Walks around, turning every now and then.
+fn explore() -> Script {
+    (0..8).fold(Script::new(), |script, _| {
+        script
+            .hold(Input::FORWARDS, 60)
+            .hold(Input::FORWARDS | Input::LOOK_RIGHT, 15)
+            .hold(Input::LEFT, 20)
+    })
+}
+
+fn player(map: &Map) -> (i32, i32, i16, i16) {
+    let player = &map.player;
+    (player.pos_x, player.pos_y, player.angle.0, player.sector)
+}
+
+macro_rules! tests {
+    ($($test:ident => $file:expr,)+) => {
+        $(
+            mod $test {
+                use super::*;
+
+                #[test]
+                fn round_trip() {
+                    let mut map = Map::from_slice(include_bytes!($file)).unwrap();
+                    let start = player(&map);
+                    let mut controller = InputController::new(&map);
+                    super::round_trip().run(&mut controller, &mut map);
+                    assert_eq!(start, player(&map));
+                }
+
+                #[test]
+                fn collision() {
+                    let mut map = Map::from_slice(include_bytes!($file)).unwrap();
+                    let mut controller = InputController::new(&map);
+                    controller.collision = true;
+                    explore().run_with(&mut controller, &mut map, |tick, map| {
+                        let sector = map.player.sector;
+                        assert!(
+                            map.sectors.get(sector).is_some(),
+                            "invalid sector {} after tick {}",
+                            sector,
+                            tick
+                        );
+                    });
+                }
+            }
+        )+
+    }
+}
+
+tests! {
+    simple0 => "../../map/tests/maps/SIMPLE0.MAP",
+    e1l1 => "../../map/tests/maps/E1L1.MAP",
+    heights => "../../map/tests/maps/HEIGHTS.MAP",
+    dx_library => "../../map/tests/maps/DX-LIBRARY.MAP",
+    dx_oldhouse => "../../map/tests/maps/DX-OLDHOUSE.MAP",
+    ll_sewer => "../../map/tests/maps/LL-SEWER.MAP",
+    dukedc1 => "../../map/tests/maps/DUKEDC1.MAP",
+    vaca1 => "../../map/tests/maps/VACA1.MAP",
+}
@@ -0,0 +1,203 @@
+//! Sector effects: doors & platforms.
+//!
+//! Duke Nukem 3D ceiling doors (sector lotag 20) and platforms (lotags 16 &
+//! 17) open when activated, and close when activated again. Sectors are
+//! moved through a [`TrackedMap`](TrackedMap), so anything observing the
+//! MAP (e.g. a render cache) stays up to date.
+//!
+//! ```
+//! use map::{tracked::TrackedMap, Map};
+//! use sim::fx::Effects;
+//!
+//! let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+//! let mut map = TrackedMap::new(map);
+//! let mut effects = Effects::new();
+//! let sector = map.map().player.sector;
+//! effects.activate(map.map(), sector);
+//! while effects.is_animating() {
+//!     effects.update(&mut map);
+//! }
+//! ```
+use map::{
+    sector::{Sector, SectorId},
+    tags::duke::{ST_16_PLATFORM_DOWN, ST_17_PLATFORM_UP, ST_20_CEILING_DOOR},
+    tracked::TrackedMap,
+    Map,
+};
+use std::collections::HashMap;
+
+/// Z units doors & platforms move per tick.
+pub const SPEED: i32 = 256;
+
+/// Moving sector.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct Animation {
+    sector: SectorId,
+
+    /// Moves the ceiling (doors) instead of the floor (platforms).
+    ceiling: bool,
+    target: i32,
+}
+
+/// Doors & platforms of a MAP, and their animations.
+#[derive(Debug, Default, Clone)]
+pub struct Effects {
+    animations: Vec<Animation>,
+
+    // resting heights of the doors & platforms activated so far.
+    closed: HashMap<SectorId, i32>,
+}
+
+impl Effects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Toggles the doors & platforms of a sector and its neighbours (e.g.
+    /// the sector of the player). Doors & platforms still moving are left
+    /// alone. Returns the number of sectors set in motion.
+    pub fn activate(&mut self, map: &Map, sector: SectorId) -> usize {
+        let mut candidates = vec![sector];
+        candidates.extend(neighbours(map, sector).map(|(id, _)| id));
+        candidates.sort_unstable();
+        candidates.dedup();
+        let mut activated = 0;
+        for id in candidates {
+            let sector = match map.sectors.sectors().get(id as usize) {
+                Some(sector) => sector,
+                None => continue,
+            };
+            let ceiling = match sector.lotag {
+                ST_20_CEILING_DOOR => true,
+                ST_16_PLATFORM_DOWN | ST_17_PLATFORM_UP => false,
+                _ => continue,
+            };
+            if self.is_moving(id) {
+                continue;
+            }
+            let current = height(sector, ceiling);
+            let closed = *self.closed.entry(id).or_insert(current);
+            let target = if current != closed {
+                Some(closed)
+            } else {
+                open_height(map, id)
+            };
+            if let Some(target) = target {
+                self.animations.push(Animation {
+                    sector: id,
+                    ceiling,
+                    target,
+                });
+                activated += 1;
+            }
+        }
+        activated
+    }
+
+    /// Returns true if the door or platform of the sector is moving.
+    pub fn is_moving(&self, sector: SectorId) -> bool {
+        self.animations.iter().any(|a| a.sector == sector)
+    }
+
+    /// Returns true if any door or platform is moving.
+    pub fn is_animating(&self) -> bool {
+        !self.animations.is_empty()
+    }
+
+    /// Moves the doors & platforms one tick closer to their targets.
+    pub fn update(&mut self, map: &mut TrackedMap) {
+        for animation in &self.animations {
+            map.modify_sector(animation.sector, |sector| {
+                let z = if animation.ceiling {
+                    &mut sector.ceiling_z
+                } else {
+                    &mut sector.floor_z
+                };
+                *z += (animation.target - *z).clamp(-SPEED, SPEED);
+            });
+        }
+        let sectors = map.map().sectors.sectors();
+        self.animations
+            .retain(|a| height(&sectors[a.sector as usize], a.ceiling) != a.target);
+    }
+}
+
+fn height(sector: &Sector, ceiling: bool) -> i32 {
+    if ceiling {
+        sector.ceiling_z
+    } else {
+        sector.floor_z
+    }
+}
+
+// sectors behind the portals of a sector (every loop of it).
+fn neighbours(map: &Map, sector: SectorId) -> impl Iterator<Item = (SectorId, &Sector)> {
+    let sectors = map.sectors.sectors();
+    let range = sectors
+        .get(sector as usize)
+        .map_or(0..0, |sector| sector.wall_range());
+    map.sectors
+        .walls()
+        .get(range)
+        .unwrap_or(&[])
+        .iter()
+        .filter_map(move |w| Some((w.next_sector, sectors.get(w.next_sector as usize)?)))
+        .filter(|(id, _)| *id >= 0)
+}
+
+// height of the nearest neighbour ceiling above (doors) or floor below or
+// above (platforms).
+fn open_height(map: &Map, id: SectorId) -> Option<i32> {
+    let sector = map.sectors.sectors().get(id as usize)?;
+    let neighbours = neighbours(map, id).map(|(_, s)| s);
+    // Z grows downwards
+    match sector.lotag {
+        ST_20_CEILING_DOOR => neighbours
+            .map(|s| s.ceiling_z)
+            .filter(|z| *z < sector.floor_z)
+            .max(),
+        ST_16_PLATFORM_DOWN => neighbours
+            .map(|s| s.floor_z)
+            .filter(|z| *z > sector.floor_z)
+            .min(),
+        ST_17_PLATFORM_UP => neighbours
+            .map(|s| s.floor_z)
+            .filter(|z| *z < sector.floor_z)
+            .max(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Effects;
+    use map::{tags::duke::ST_20_CEILING_DOOR, tracked::TrackedMap, Map};
+
+    #[test]
+    fn door() {
+        let map = Map::from_slice(include_bytes!("../../map/tests/maps/E1L1.MAP")).unwrap();
+        let door = map
+            .sectors
+            .sectors()
+            .iter()
+            .position(|s| s.lotag == ST_20_CEILING_DOOR)
+            .unwrap() as i16;
+        let mut map = TrackedMap::new(map);
+        let closed = map.map().sectors.sectors()[door as usize].ceiling_z;
+        let mut effects = Effects::new();
+        assert!(effects.activate(map.map(), door) > 0);
+        assert!(effects.is_moving(door));
+        // moving doors can't be activated
+        assert_eq!(0, effects.activate(map.map(), door));
+        while effects.is_animating() {
+            effects.update(&mut map);
+        }
+        let open = map.map().sectors.sectors()[door as usize].ceiling_z;
+        assert!(open < closed);
+        effects.activate(map.map(), door);
+        while effects.is_animating() {
+            effects.update(&mut map);
+        }
+        assert_eq!(closed, map.map().sectors.sectors()[door as usize].ceiling_z);
+    }
+}
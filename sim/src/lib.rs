@@ -0,0 +1,69 @@
+//! Headless game logic.
+//!
+//! A [`Simulation`](Simulation) advances the state of a MAP (player movement,
+//! moving sectors, AI...) one tick at a time, independently of any renderer
+//! or windowing frontend. `render::controller::InputController` is the
+//! reference implementation.
+pub mod fx;
+pub mod net;
+pub mod script;
+pub mod trigger;
+
+use map::Map;
+
+bitflags::bitflags! {
+    /// Player input held down during a tick.
+    pub struct Input: u16 {
+        const FORWARDS   = 0b0000_0000_0001;
+        const BACKWARDS  = 0b0000_0000_0010;
+        const RIGHT      = 0b0000_0000_0100;
+        const LEFT       = 0b0000_0000_1000;
+        const UP         = 0b0000_0001_0000;
+        const DOWN       = 0b0000_0010_0000;
+        const LOOK_RIGHT = 0b0000_0100_0000;
+        const LOOK_LEFT  = 0b0000_1000_0000;
+        const CROUCH     = 0b0001_0000_0000;
+    }
+}
+
+/// Game logic, advanced one tick at a time.
+pub trait Simulation {
+    /// Advance the simulation by one tick, updating the MAP. Frontends decide
+    /// how often to tick (usually once per frame).
+    fn tick(&mut self, map: &mut Map, input: &Input);
+}
+
+impl<S: Simulation + ?Sized> Simulation for Box<S> {
+    fn tick(&mut self, map: &mut Map, input: &Input) {
+        (**self).tick(map, input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Input, Simulation};
+    use map::Map;
+
+    // walks forwards along the X axis.
+    struct Walk;
+
+    impl Simulation for Walk {
+        fn tick(&mut self, map: &mut Map, input: &Input) {
+            if input.contains(Input::FORWARDS) {
+                map.player.pos_x += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn boxed() {
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        let x = map.player.pos_x;
+        let mut sims: Vec<Box<dyn Simulation>> = vec![Box::new(Walk), Box::new(Walk)];
+        for sim in &mut sims {
+            sim.tick(&mut map, &Input::FORWARDS);
+            sim.tick(&mut map, &Input::empty());
+        }
+        assert_eq!(x + 2, map.player.pos_x);
+    }
+}
@@ -0,0 +1,205 @@
+//! Compact player state updates, for views of the same MAP shared over the
+//! network (each client sends its own player, and renders the others).
+//!
+//! States are sent once per tick as [`PlayerState::SIZE`](PlayerState::SIZE)
+//! byte packets. Receivers [`interpolate`](PlayerState::interpolate) between
+//! the last two states they got, or [`extrapolate`](PlayerState::extrapolate)
+//! the last one while waiting for the next.
+use map::{
+    player::{Angle, Player},
+    sector::SectorId,
+};
+
+/// Player position, orientation & velocity at a given tick.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct PlayerState {
+    /// Tick the state was sampled at (wraps around).
+    pub tick: u16,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub angle: Angle,
+    pub sector: SectorId,
+
+    /// Displacement (XYZ) since the previous tick.
+    pub velocity: [i16; 3],
+}
+
+impl PlayerState {
+    /// Size of an encoded state, in bytes.
+    pub const SIZE: usize = 24;
+
+    /// Samples the state of a player. The velocity is the displacement from
+    /// its position on the `previous` tick (if any).
+    pub fn new(tick: u16, player: &Player, previous: Option<&Player>) -> Self {
+        let velocity = match previous {
+            Some(prev) => [
+                clamp_i16(player.pos_x - prev.pos_x),
+                clamp_i16(player.pos_y - prev.pos_y),
+                clamp_i16(player.pos_z - prev.pos_z),
+            ],
+            None => [0; 3],
+        };
+        Self {
+            tick,
+            x: player.pos_x,
+            y: player.pos_y,
+            z: player.pos_z,
+            angle: player.angle,
+            sector: player.sector,
+            velocity,
+        }
+    }
+
+    /// Encodes the state (little endian).
+    pub fn encode(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        let mut cursor = &mut bytes[..];
+        put(&mut cursor, &self.tick.to_le_bytes());
+        put(&mut cursor, &self.x.to_le_bytes());
+        put(&mut cursor, &self.y.to_le_bytes());
+        put(&mut cursor, &self.z.to_le_bytes());
+        put(&mut cursor, &self.angle.0.to_le_bytes());
+        put(&mut cursor, &self.sector.to_le_bytes());
+        for v in &self.velocity {
+            put(&mut cursor, &v.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes a state encoded with [`encode`](Self::encode). Returns `None`
+    /// if `bytes` is shorter than [`SIZE`](Self::SIZE).
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::SIZE {
+            return None;
+        }
+        let mut cursor = bytes;
+        Some(Self {
+            tick: u16::from_le_bytes(take(&mut cursor)),
+            x: i32::from_le_bytes(take(&mut cursor)),
+            y: i32::from_le_bytes(take(&mut cursor)),
+            z: i32::from_le_bytes(take(&mut cursor)),
+            angle: Angle(i16::from_le_bytes(take(&mut cursor))).normalized(),
+            sector: i16::from_le_bytes(take(&mut cursor)),
+            velocity: [
+                i16::from_le_bytes(take(&mut cursor)),
+                i16::from_le_bytes(take(&mut cursor)),
+                i16::from_le_bytes(take(&mut cursor)),
+            ],
+        })
+    }
+
+    /// True if the state was sampled after `other` (taking tick wrap around
+    /// into account), so out of order packets can be dropped.
+    pub fn is_newer(&self, other: &Self) -> bool {
+        (self.tick.wrapping_sub(other.tick) as i16) > 0
+    }
+
+    /// Moves the player to the state.
+    pub fn apply(&self, player: &mut Player) {
+        player.pos_x = self.x;
+        player.pos_y = self.y;
+        player.pos_z = self.z;
+        player.angle = self.angle;
+        player.sector = self.sector;
+    }
+
+    /// State in between `self` (`t = 0`) and `next` (`t = 1`). The angle is
+    /// interpolated along the shortest arc, and the sector switches halfway.
+    pub fn interpolate(&self, next: &Self, t: f32) -> Self {
+        let lerp = |a: i32, b: i32| a + (f64::from(b - a) * f64::from(t)).round() as i32;
+        let lerp16 = |a: i16, b: i16| lerp(i32::from(a), i32::from(b)) as i16;
+        Self {
+            tick: if t < 0.5 { self.tick } else { next.tick },
+            x: lerp(self.x, next.x),
+            y: lerp(self.y, next.y),
+            z: lerp(self.z, next.z),
+            angle: self.angle.lerp(next.angle, t),
+            sector: if t < 0.5 { self.sector } else { next.sector },
+            velocity: [
+                lerp16(self.velocity[0], next.velocity[0]),
+                lerp16(self.velocity[1], next.velocity[1]),
+                lerp16(self.velocity[2], next.velocity[2]),
+            ],
+        }
+    }
+
+    /// Predicts the state a number of ticks later, assuming the velocity
+    /// stays constant. The sector isn't updated.
+    pub fn extrapolate(&self, ticks: u16) -> Self {
+        let step = |p: i32, v: i16| p.saturating_add(i32::from(v) * i32::from(ticks));
+        Self {
+            tick: self.tick.wrapping_add(ticks),
+            x: step(self.x, self.velocity[0]),
+            y: step(self.y, self.velocity[1]),
+            z: step(self.z, self.velocity[2]),
+            ..*self
+        }
+    }
+}
+
+fn put(cursor: &mut &mut [u8], bytes: &[u8]) {
+    let (head, tail) = std::mem::take(cursor).split_at_mut(bytes.len());
+    head.copy_from_slice(bytes);
+    *cursor = tail;
+}
+
+fn take<const N: usize>(cursor: &mut &[u8]) -> [u8; N] {
+    let (head, tail) = cursor.split_at(N);
+    *cursor = tail;
+    let mut bytes = [0; N];
+    bytes.copy_from_slice(head);
+    bytes
+}
+
+fn clamp_i16(v: i32) -> i16 {
+    v.clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16
+}
+
+#[cfg(test)]
+mod test {
+    use super::PlayerState;
+    use map::player::{Angle, Player};
+
+    fn player(x: i32, angle: i16) -> Player {
+        Player {
+            pos_x: x,
+            pos_y: -x,
+            pos_z: 1 << 16,
+            angle: Angle(angle),
+            sector: 3,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let state = PlayerState::new(65535, &player(1000, 2000), Some(&player(990, 0)));
+        assert_eq!([10, -10, 0], state.velocity);
+        let bytes = state.encode();
+        assert_eq!(Some(state), PlayerState::decode(&bytes));
+        assert_eq!(None, PlayerState::decode(&bytes[1..]));
+
+        let mut target = player(0, 0);
+        state.apply(&mut target);
+        assert_eq!(Angle(2000), target.angle);
+        assert_eq!(
+            (1000, -1000, 3),
+            (target.pos_x, target.pos_y, target.sector)
+        );
+    }
+
+    #[test]
+    fn interpolate() {
+        let a = PlayerState::new(65535, &player(0, 2000), None);
+        let b = PlayerState::new(0, &player(100, 100), Some(&player(0, 2000)));
+        assert!(b.is_newer(&a));
+        assert!(!a.is_newer(&b));
+        let half = a.interpolate(&b, 0.5);
+        assert_eq!((50, -50), (half.x, half.y));
+        assert_eq!(Angle(26), half.angle);
+        assert_eq!(a, a.interpolate(&b, 0.0));
+        assert_eq!(b, a.interpolate(&b, 1.0));
+        let next = b.extrapolate(2);
+        assert_eq!((300, -300, 2), (next.x, next.y, next.tick));
+    }
+}
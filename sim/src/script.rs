@@ -0,0 +1,114 @@
+//! Scripted input, for headless tests of movement & collision.
+//!
+//! ```
+//! use sim::{script::Script, Input};
+//!
+//! let script = Script::new()
+//!     .hold(Input::FORWARDS, 30)
+//!     .wait(40)
+//!     .hold(Input::BACKWARDS, 30);
+//! assert_eq!(100, script.ticks());
+//! ```
+use crate::{Input, Simulation};
+use map::Map;
+
+/// Sequence of inputs, each held down for a number of ticks.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Script {
+    steps: Vec<(Input, u32)>,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Holds `input` down for `ticks` ticks, after the previous steps.
+    pub fn hold(mut self, input: Input, ticks: u32) -> Self {
+        self.steps.push((input, ticks));
+        self
+    }
+
+    /// Releases all input for `ticks` ticks.
+    pub fn wait(self, ticks: u32) -> Self {
+        self.hold(Input::empty(), ticks)
+    }
+
+    pub fn steps(&self) -> &[(Input, u32)] {
+        &self.steps
+    }
+
+    /// Total number of ticks.
+    pub fn ticks(&self) -> u32 {
+        self.steps.iter().map(|(_, ticks)| ticks).sum()
+    }
+
+    /// Input held down during the given tick (empty after the script ends).
+    pub fn input_at(&self, mut tick: u32) -> Input {
+        for (input, ticks) in &self.steps {
+            if tick < *ticks {
+                return *input;
+            }
+            tick -= ticks;
+        }
+        Input::empty()
+    }
+
+    /// Runs the whole script, one tick at a time.
+    pub fn run<S: Simulation + ?Sized>(&self, simulation: &mut S, map: &mut Map) {
+        self.run_with(simulation, map, |_, _| {});
+    }
+
+    /// Same as [`run`](Self::run), calling `f` with the index of the tick
+    /// and the MAP after every tick.
+    pub fn run_with<S, F>(&self, simulation: &mut S, map: &mut Map, mut f: F)
+    where
+        S: Simulation + ?Sized,
+        F: FnMut(u32, &Map),
+    {
+        let mut tick = 0;
+        for (input, ticks) in &self.steps {
+            for _ in 0..*ticks {
+                simulation.tick(map, input);
+                f(tick, map);
+                tick += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Script;
+    use crate::{Input, Simulation};
+    use map::Map;
+
+    // walks forwards along the X axis.
+    struct Walk;
+
+    impl Simulation for Walk {
+        fn tick(&mut self, map: &mut Map, input: &Input) {
+            if input.contains(Input::FORWARDS) {
+                map.player.pos_x += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn run() {
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        let x = map.player.pos_x;
+        let script = Script::new()
+            .hold(Input::FORWARDS, 3)
+            .wait(2)
+            .hold(Input::FORWARDS | Input::LEFT, 4);
+        assert_eq!(9, script.ticks());
+        assert_eq!(Input::empty(), script.input_at(4));
+        assert_eq!(Input::FORWARDS | Input::LEFT, script.input_at(5));
+        assert_eq!(Input::empty(), script.input_at(9));
+        let mut ticks = Vec::new();
+        script.run_with(&mut Walk, &mut map, |tick, _| ticks.push(tick));
+        assert_eq!((0..9).collect::<Vec<_>>(), ticks);
+        assert_eq!(x + 7, map.player.pos_x);
+    }
+}
@@ -0,0 +1,192 @@
+//! Player triggers (entering sectors, touching walls), for frontends that
+//! play sounds or update UI in response, and simple scripted game logic.
+//!
+//! ```
+//! use sim::{trigger::{Trigger, Triggers}, Input, Simulation};
+//! use std::{cell::RefCell, rc::Rc};
+//!
+//! struct Idle;
+//!
+//! impl Simulation for Idle {
+//!     fn tick(&mut self, _: &mut map::Map, _: &Input) {}
+//! }
+//!
+//! let bytes = include_bytes!("../../map/tests/maps/SIMPLE0.MAP");
+//! let mut map = map::Map::from_slice(bytes).unwrap();
+//! let entered = Rc::new(RefCell::new(Vec::new()));
+//! let mut sim = Triggers::new(Idle);
+//! let log = entered.clone();
+//! sim.subscribe(move |trigger| {
+//!     if let Trigger::EnteredSector { sector, .. } = trigger {
+//!         log.borrow_mut().push(*sector);
+//!     }
+//! });
+//! map.player.sector = 1;
+//! sim.tick(&mut map, &Input::empty());
+//! assert_eq!(vec![1], *entered.borrow());
+//! ```
+use crate::{Input, Simulation};
+use map::{
+    sector::{SectorId, Wall, WallId},
+    Map,
+};
+
+/// Distance (in map XY units) from the player to a wall it touches.
+pub const TOUCH_DISTANCE: f64 = 128.0;
+
+/// Player trigger.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Trigger {
+    /// The player moved into a sector.
+    EnteredSector {
+        sector: SectorId,
+        lotag: i16,
+        hitag: i16,
+    },
+
+    /// The player got within [`TOUCH_DISTANCE`](TOUCH_DISTANCE) of a wall of
+    /// its sector. Reported once, until the player moves away again.
+    TouchedWall {
+        wall: WallId,
+        lotag: i16,
+        hitag: i16,
+    },
+}
+
+type Observer = Box<dyn FnMut(&Trigger)>;
+
+/// [`Simulation`](Simulation) wrapper reporting a [`Trigger`](Trigger) to its
+/// observers when the player enters a sector or touches a wall.
+pub struct Triggers<S> {
+    simulation: S,
+    observers: Vec<Observer>,
+    sector: Option<SectorId>,
+    touching: Vec<WallId>,
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for Triggers<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Triggers")
+            .field("simulation", &self.simulation)
+            .field("observers", &self.observers.len())
+            .finish()
+    }
+}
+
+impl<S: Simulation> Triggers<S> {
+    pub fn new(simulation: S) -> Self {
+        Self {
+            simulation,
+            observers: Vec::new(),
+            sector: None,
+            touching: Vec::new(),
+        }
+    }
+
+    /// Returns the wrapped simulation.
+    pub fn simulation(&self) -> &S {
+        &self.simulation
+    }
+
+    pub fn simulation_mut(&mut self) -> &mut S {
+        &mut self.simulation
+    }
+
+    /// Registers an observer called for every trigger, after the tick that
+    /// caused it.
+    pub fn subscribe<F: FnMut(&Trigger) + 'static>(&mut self, observer: F) {
+        self.observers.push(Box::new(observer));
+    }
+
+    fn emit(&mut self, trigger: Trigger) {
+        self.observers.iter_mut().for_each(|o| o(&trigger));
+    }
+
+    fn check(&mut self, map: &Map) {
+        let player = &map.player;
+        let (sector, walls) = match map.sectors.get(player.sector) {
+            Some(sector) => sector,
+            None => return,
+        };
+        if self.sector != Some(player.sector) {
+            self.sector = Some(player.sector);
+            self.emit(Trigger::EnteredSector {
+                sector: player.sector,
+                lotag: sector.lotag,
+                hitag: sector.hitag,
+            });
+        }
+        let p = [f64::from(player.pos_x), f64::from(player.pos_y)];
+        let touching: Vec<_> = walls
+            .filter(|(_, left, right)| distance_to_wall(p, left, right) <= TOUCH_DISTANCE)
+            .map(|(id, left, _)| (id, left.lotag, left.hitag))
+            .collect();
+        for &(wall, lotag, hitag) in &touching {
+            if !self.touching.contains(&wall) {
+                self.emit(Trigger::TouchedWall { wall, lotag, hitag });
+            }
+        }
+        self.touching = touching.into_iter().map(|(id, _, _)| id).collect();
+    }
+}
+
+impl<S: Simulation> Simulation for Triggers<S> {
+    fn tick(&mut self, map: &mut Map, input: &Input) {
+        self.simulation.tick(map, input);
+        self.check(map);
+    }
+}
+
+// distance from a point to a wall segment.
+fn distance_to_wall([px, py]: [f64; 2], left: &Wall, right: &Wall) -> f64 {
+    let (lx, ly) = (f64::from(left.x), f64::from(left.y));
+    let (wx, wy) = (f64::from(right.x) - lx, f64::from(right.y) - ly);
+    let len2 = wx * wx + wy * wy;
+    let t = if len2 == 0.0 {
+        0.0
+    } else {
+        (((px - lx) * wx + (py - ly) * wy) / len2).clamp(0.0, 1.0)
+    };
+    (px - lx - t * wx).hypot(py - ly - t * wy)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Trigger, Triggers};
+    use crate::{Input, Simulation};
+    use map::Map;
+    use std::{cell::RefCell, rc::Rc};
+
+    // teleports the player to the first wall of its sector.
+    struct Teleport;
+
+    impl Simulation for Teleport {
+        fn tick(&mut self, map: &mut Map, _: &Input) {
+            let (_, mut walls) = map.sectors.get(map.player.sector).unwrap();
+            let (_, left, _) = walls.next().unwrap();
+            map.player.pos_x = left.x;
+            map.player.pos_y = left.y;
+        }
+    }
+
+    #[test]
+    fn touch_once() {
+        let mut map = Map::from_slice(include_bytes!("../../map/tests/maps/SIMPLE0.MAP")).unwrap();
+        let triggers = Rc::new(RefCell::new(Vec::new()));
+        let mut sim = Triggers::new(Teleport);
+        let log = triggers.clone();
+        sim.subscribe(move |t| log.borrow_mut().push(*t));
+        for _ in 0..3 {
+            sim.tick(&mut map, &Input::empty());
+        }
+        let triggers = triggers.borrow();
+        assert!(matches!(triggers[0], Trigger::EnteredSector { .. }));
+        let touched = triggers
+            .iter()
+            .filter(|t| matches!(t, Trigger::TouchedWall { .. }))
+            .count();
+        // standing on a vertex touches the walls at both sides of it.
+        assert!(touched >= 2);
+        assert_eq!(1 + touched, triggers.len());
+    }
+}
@@ -3,6 +3,25 @@ use wasm_bindgen::prelude::*;
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 static MAP: &[u8] = include_bytes!("../../map/tests/maps/SIMPLE0.MAP");
+// byte layout of the pixel buffer shared with JS (what `ImageData` expects).
+const PIXEL_FORMAT: render::frame::PixelFormat = render::frame::PixelFormat::Rgba;
+
+// DOM key codes (`KeyboardEvent.keyCode`) of the keys of `Input`.
+mod key {
+    pub const W: u32 = 87;
+    pub const A: u32 = 65;
+    pub const S: u32 = 83;
+    pub const D: u32 = 68;
+    pub const C: u32 = 67;
+    pub const E: u32 = 69;
+    pub const Q: u32 = 81;
+    pub const UP: u32 = 38;
+    pub const LEFT: u32 = 37;
+    pub const DOWN: u32 = 40;
+    pub const RIGHT: u32 = 39;
+    pub const SPACE: u32 = 32;
+    pub const SHIFT: u32 = 16;
+}
 
 #[wasm_bindgen]
 pub fn set_panic_hook() {
@@ -12,9 +31,52 @@ pub fn set_panic_hook() {
 #[wasm_bindgen]
 pub struct Demo {
     map: map::Map,
-    controller: render::controller::InputController,
-    frame: Box<render::frame::Frame>,
+    // player movement, advanced on every update.
+    controller: sim::trigger::Triggers<render::controller::InputController>,
+    frames: render::frame::FramePair,
+    pixels: Vec<u8>,
     d3: render::d3::Renderer,
+    // 2D map drawn on top of the 3D view, if enabled.
+    #[cfg(feature = "d2")]
+    d2: Option<render::d2::Renderer>,
+    stats: render::d3::RenderStats,
+    keys: render::controller::bindings::KeyMap,
+    // keys held down (see `key_down`), on top of those of `Input`.
+    pressed: std::collections::BTreeSet<u32>,
+}
+
+/// Work done rendering the last frame (see
+/// [`RenderStats`](render::d3::RenderStats)).
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stats {
+    pub sectors_visited: usize,
+    pub walls_drawn: usize,
+    pub columns_filled: usize,
+    pub portals_traversed: usize,
+    /// Always zero, there is no clock in `std` on `wasm32`.
+    pub duration_ms: f64,
+}
+
+impl From<render::d3::RenderStats> for Stats {
+    fn from(stats: render::d3::RenderStats) -> Self {
+        Self {
+            sectors_visited: stats.sectors_visited,
+            walls_drawn: stats.walls_drawn,
+            columns_filled: stats.columns_filled,
+            portals_traversed: stats.portals_traversed,
+            duration_ms: stats.duration.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Byte layout of the pixel buffer (see
+/// [`PixelFormat`](render::frame::PixelFormat)).
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PixelFormat {
+    Rgba,
+    Bgra,
 }
 
 #[wasm_bindgen]
@@ -33,22 +95,45 @@ pub struct Input {
     pub right: bool,
     pub space: bool,
     pub left_shift: bool,
+
+    /// On-screen joystick, from `-1.0` to `1.0` (Y grows downwards, so
+    /// pushing the stick up moves forwards).
+    pub joystick_x: f32,
+    pub joystick_y: f32,
+
+    /// Rotation speed, from `-1.0` (left) to `1.0` (right).
+    pub look_dx: f32,
 }
 
 impl Input {
-    #[rustfmt::skip]
-    fn to_controller_input(&self) -> render::controller::Input {
-        let mut input = render::controller::Input::empty();
-        if self.w || self.up { input |= render::controller::Input::FORWARDS; }
-        if self.s || self.down { input |= render::controller::Input::BACKWARDS; }
-        if self.d { input |= render::controller::Input::RIGHT; }
-        if self.a { input |= render::controller::Input::LEFT; }
-        if self.right || self.w { input |= render::controller::Input::LOOK_RIGHT; }
-        if self.left || self.q { input |= render::controller::Input::LOOK_LEFT; }
-        if self.c { input |= render::controller::Input::CROUCH; }
-        if self.space { input |= render::controller::Input::UP; }
-        if self.left_shift { input |= render::controller::Input::DOWN; }
-        input
+    // key codes of the keys held down.
+    fn pressed(&self) -> impl Iterator<Item = u32> {
+        let keys = [
+            (self.w, key::W),
+            (self.a, key::A),
+            (self.s, key::S),
+            (self.d, key::D),
+            (self.c, key::C),
+            (self.e, key::E),
+            (self.q, key::Q),
+            (self.up, key::UP),
+            (self.left, key::LEFT),
+            (self.down, key::DOWN),
+            (self.right, key::RIGHT),
+            (self.space, key::SPACE),
+            (self.left_shift, key::SHIFT),
+        ];
+        IntoIterator::into_iter(keys)
+            .filter(|(down, _)| *down)
+            .map(|(_, key)| key)
+    }
+
+    fn to_controller_analog(&self) -> render::controller::Analog {
+        render::controller::Analog {
+            forwards: -self.joystick_y,
+            sideways: self.joystick_x,
+            look: self.look_dx,
+        }
     }
 }
 
@@ -57,6 +142,45 @@ impl Input {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Analog input from touchscreen controls (see
+    /// [`joystick_x`](Input::joystick_x) and [`look_dx`](Input::look_dx)).
+    pub fn from_touch(joystick_x: f32, joystick_y: f32, look_dx: f32) -> Self {
+        Self {
+            joystick_x,
+            joystick_y,
+            look_dx,
+            ..Self::default()
+        }
+    }
+}
+
+/// Default controls, using DOM key codes.
+fn default_key_map() -> render::controller::bindings::KeyMap {
+    use sim::Input;
+    vec![
+        (key::W, Input::FORWARDS),
+        (key::UP, Input::FORWARDS),
+        (key::S, Input::BACKWARDS),
+        (key::DOWN, Input::BACKWARDS),
+        (key::D, Input::RIGHT),
+        (key::A, Input::LEFT),
+        (key::E, Input::LOOK_RIGHT),
+        (key::RIGHT, Input::LOOK_RIGHT),
+        (key::Q, Input::LOOK_LEFT),
+        (key::LEFT, Input::LOOK_LEFT),
+        (key::C, Input::CROUCH),
+        (key::SPACE, Input::UP),
+        (key::SHIFT, Input::DOWN),
+    ]
+    .into_iter()
+    .collect()
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
@@ -64,41 +188,195 @@ impl Demo {
     pub fn new() -> Self {
         let map = map::Map::from_slice(MAP).unwrap();
         let controller = render::controller::InputController::new(&map);
+        let controller = sim::trigger::Triggers::new(controller);
+        let frames = render::frame::FramePair::new();
+        let mut pixels = Vec::new();
+        render::frame::encode(frames.front(), PIXEL_FORMAT, &mut pixels);
         Self {
             map,
             controller,
-            frame: Box::new([[0; render::frame::WIDTH]; render::frame::HEIGHT]),
+            frames,
+            pixels,
             d3: render::d3::Renderer::new(),
+            #[cfg(feature = "d2")]
+            d2: None,
+            stats: render::d3::RenderStats::default(),
+            keys: default_key_map(),
+            pressed: std::collections::BTreeSet::new(),
         }
     }
 
-    pub fn render(&mut self, ctx: &web_sys::CanvasRenderingContext2d) {
-        self.d3.render(&self.map, &mut self.frame);
-        // black frame to hide edge artifacts :P
-        for i in 0..render::frame::WIDTH {
-            self.frame[0][i] = 0;
-            self.frame[render::frame::HEIGHT - 1][i] = 0;
+    /// Draws the 2D map on top of the 3D view.
+    #[cfg(feature = "d2")]
+    pub fn set_d2_overlay(&mut self, enabled: bool) {
+        self.d2 = if enabled {
+            let mut d2 = render::d2::Renderer::new();
+            d2.flags = render::d2::Flags::SECTOR | render::d2::Flags::PLAYER;
+            Some(d2)
+        } else {
+            None
+        };
+    }
+
+    #[cfg(feature = "d2")]
+    pub fn d2_overlay(&self) -> bool {
+        self.d2.is_some()
+    }
+
+    /// Moves freely, ignoring floors and ceilings.
+    pub fn set_fly(&mut self, fly: bool) {
+        self.controller.simulation_mut().fly = fly;
+    }
+
+    pub fn fly(&self) -> bool {
+        self.controller.simulation().fly
+    }
+
+    pub fn set_max_speed(&mut self, max_speed: i32) {
+        self.controller.simulation_mut().max_speed = max_speed;
+    }
+
+    pub fn max_speed(&self) -> i32 {
+        self.controller.simulation().max_speed
+    }
+
+    /// Registers a function called with `(kind, id, lotag, hitag)` when the
+    /// player enters a sector (`kind` is `"sector"`) or touches a wall
+    /// (`"wall"`), during [`update`](Demo::update).
+    pub fn on_trigger(&mut self, callback: js_sys::Function) {
+        self.controller.subscribe(move |trigger| {
+            let (kind, id, lotag, hitag) = match *trigger {
+                sim::trigger::Trigger::EnteredSector {
+                    sector,
+                    lotag,
+                    hitag,
+                } => ("sector", sector, lotag, hitag),
+                sim::trigger::Trigger::TouchedWall { wall, lotag, hitag } => {
+                    ("wall", wall, lotag, hitag)
+                }
+            };
+            let args = js_sys::Array::of4(&kind.into(), &id.into(), &lotag.into(), &hitag.into());
+            // errors thrown by the callback are ignored.
+            let _ = callback.apply(&JsValue::NULL, &args);
+        });
+    }
+
+    /// Replaces the controls with a JSON key map of DOM key codes to input
+    /// names, e.g. `{"87": ["FORWARDS"], "32": ["UP"]}` (see
+    /// [`KeyMap`](render::controller::bindings::KeyMap)).
+    pub fn set_key_map(&mut self, json: &str) -> Result<(), JsValue> {
+        self.keys = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    /// Current controls, as JSON (see [`set_key_map`](Demo::set_key_map)).
+    pub fn key_map(&self) -> String {
+        serde_json::to_string(&self.keys).unwrap()
+    }
+
+    /// Holds a key down (by DOM key code), until [`key_up`](Demo::key_up).
+    pub fn key_down(&mut self, code: u32) {
+        self.pressed.insert(code);
+    }
+
+    pub fn key_up(&mut self, code: u32) {
+        self.pressed.remove(&code);
+    }
+
+    /// Statistics of the last rendered frame.
+    pub fn stats(&self) -> Stats {
+        self.stats.into()
+    }
+
+    /// Renders the next frame into the pixel buffer (see
+    /// [`pixels_ptr`](Demo::pixels_ptr)). Returns the regions that changed
+    /// since the last frame, as `[x, y, width, height]` quadruples (empty if
+    /// nothing changed).
+    pub fn render_to_buffer(&mut self) -> Box<[u32]> {
+        let camera = render::camera::Camera::from(&self.map.player);
+        self.stats = self.d3.render(&self.map, &camera, self.frames.back_mut());
+        #[cfg(feature = "d2")]
+        if let Some(d2) = &mut self.d2 {
+            render::Renderer::render(d2, &self.map, &camera, self.frames.back_mut());
         }
-        for i in 0..render::frame::HEIGHT {
-            self.frame[i][0] = 0;
-            self.frame[i][render::frame::WIDTH - 1] = 0;
+        let player = &self.map.player;
+        let hud = format!(
+            "x={} y={} z={}\nsector={}",
+            player.pos_x, player.pos_y, player.pos_z, player.sector
+        );
+        render::overlay::Canvas::new(self.frames.back_mut()).text(&hud, [2, 2], 0xffffff);
+        let dirty: Box<[u32]> = self
+            .frames
+            .present()
+            .iter()
+            .flat_map(|rect| [rect.x, rect.y, rect.width, rect.height])
+            .map(|v| v as u32)
+            .collect();
+        if !dirty.is_empty() {
+            // the buffer never grows past its initial capacity, so it isn't
+            // reallocated (and its pointer stays valid).
+            render::frame::encode(self.frames.front(), PIXEL_FORMAT, &mut self.pixels);
         }
-        let clamped = wasm_bindgen::Clamped(unsafe {
-            std::slice::from_raw_parts(
-                self.frame.as_ptr() as *const u8,
-                (render::frame::WIDTH * render::frame::HEIGHT * 4) as _,
-            )
-        });
+        dirty
+    }
+
+    /// Renders the next frame and draws the regions that changed to the
+    /// canvas.
+    pub fn render(&mut self, ctx: &web_sys::CanvasRenderingContext2d) {
+        let dirty = self.render_to_buffer();
+        if dirty.is_empty() {
+            return;
+        }
+        let clamped = wasm_bindgen::Clamped(&self.pixels[..]);
         let image_data =
             web_sys::ImageData::new_with_u8_clamped_array(clamped, (render::frame::WIDTH) as _)
                 .expect("Error creating image data");
-        ctx.put_image_data(&image_data, 0.0, 0.0)
+        for rect in dirty.chunks(4) {
+            ctx.put_image_data_with_dirty_x_and_dirty_y_and_dirty_width_and_dirty_height(
+                &image_data,
+                0.0,
+                0.0,
+                rect[0] as f64,
+                rect[1] as f64,
+                rect[2] as f64,
+                rect[3] as f64,
+            )
             .expect("Error writing image to canvas");
+        }
+    }
+
+    /// Address of the pixel buffer in the WebAssembly memory. It stays the
+    /// same for the lifetime of the demo, but views created from it must be
+    /// recreated whenever the memory grows (detaching its `ArrayBuffer`).
+    pub fn pixels_ptr(&self) -> *const u8 {
+        self.pixels.as_ptr()
+    }
+
+    /// Size of the pixel buffer, in bytes.
+    pub fn pixels_len(&self) -> usize {
+        self.pixels.len()
+    }
+
+    /// Byte layout of the pixel buffer.
+    pub fn pixel_format(&self) -> PixelFormat {
+        match PIXEL_FORMAT {
+            render::frame::PixelFormat::Rgba => PixelFormat::Rgba,
+            render::frame::PixelFormat::Bgra => PixelFormat::Bgra,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        render::frame::WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        render::frame::HEIGHT
     }
 
     pub fn update(&mut self, input: &Input) {
-        let delta = std::time::Duration::from_micros(16600);
-        let input = input.to_controller_input();
-        self.controller.update(&input, delta, &mut self.map);
+        self.controller.simulation_mut().analog = input.to_controller_analog();
+        let pressed = input.pressed().chain(self.pressed.iter().copied());
+        let input = self.keys.input(pressed);
+        sim::Simulation::tick(&mut self.controller, &mut self.map, &input);
     }
 }